@@ -1,6 +1,7 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 // use std::hint::black_box;
 
+use libgo::game::array_board::Board19;
 use libgo::game::board::{Board, Move, State};
 use libgo::game::matrix::Matrix;
 use libgo::game::player::Player;
@@ -62,6 +63,17 @@ fn bench_first_move_play_on_board(c: &mut Criterion) {
     });
 }
 
+fn bench_first_move_play_on_array_board(c: &mut Criterion) {
+    let empty_board = Board19::new();
+    let center = Vertex { x: 9, y: 9 };
+    c.bench_function("bench_first_move_play_on_array_board", |b| {
+        b.iter(|| {
+            let mut board = empty_board;
+            board.place_stone(Player::Black, center);
+        });
+    });
+}
+
 fn bench_is_vacant(c: &mut Criterion) {
     let game = Game::new();
     let center = game.board().center_point().unwrap();
@@ -104,6 +116,7 @@ criterion_group!(
     bench_first_move_all_legal_moves,
     bench_first_move_play_in_game,
     bench_first_move_play_on_board,
+    bench_first_move_play_on_array_board,
     bench_is_vacant,
     bench_not_black_regions_on_empty_board,
     bench_not_black_regions_on_black_checkered_board,
@@ -0,0 +1,37 @@
+//! Records what the library was built with, for reporting by the `dlc-build_info` GTP command.
+
+use std::env;
+use std::process::Command;
+
+fn main() {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    if let Some(version) = output(&rustc, &["--version"]) {
+        println!("cargo:rustc-env=LIBGO_RUSTC_VERSION={version}");
+    }
+
+    let mut features: Vec<_> = env::vars()
+        .filter_map(|(name, _)| name.strip_prefix("CARGO_FEATURE_").map(str::to_lowercase))
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=LIBGO_FEATURES={}", features.join(" "));
+
+    if let Some(hash) = output("git", &["rev-parse", "--short", "HEAD"]) {
+        println!("cargo:rustc-env=LIBGO_GIT_HASH={hash}");
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        println!("cargo:rerun-if-changed=.git/index");
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
+
+/// Returns the first line the program writes when run with the arguments, if it succeeds.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout)
+        .ok()?
+        .lines()
+        .next()
+        .map(str::to_owned)
+}
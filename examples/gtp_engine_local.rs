@@ -4,9 +4,7 @@ extern crate libgo;
 
 use std::io;
 
-use libgo::game::Game;
-use libgo::gtp::command::Commands;
-use libgo::gtp::engine::Engine;
+use libgo::prelude::*;
 
 fn main() {
     let mut gtp = Engine::new();
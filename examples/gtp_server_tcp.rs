@@ -1,9 +1,10 @@
-use std::io::prelude::*;
-use std::io::BufReader;
-use std::net::{Shutdown, TcpListener, TcpStream};
+use std::net::TcpListener;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 
 use clap::{self, Parser};
+use libgo::game::Game;
+use libgo::gtp::net::{Broadcast, Outcome, Server};
 
 /// A Go Server
 ///
@@ -16,104 +17,70 @@ struct Args {
     #[arg(default_value = "127.0.0.1:8000", index = 1, value_name = "host:port")]
     host_port: String,
 
-    /// Send 'boardsize `BOARD_SIZE`' to clients
+    /// Play games on a board of size `BOARD_SIZE`
     #[arg(long)]
     board_size: Option<u8>,
+
+    /// Listen for spectators of the latest game on host and port
+    #[arg(long, value_name = "host:port")]
+    spectators: Option<String>,
+
+    /// Write the record of each game to `DIR` as SGF
+    #[arg(long, value_name = "DIR")]
+    sgf_dir: Option<String>,
 }
 
 fn main() {
     let args = Args::parse();
 
-    let mut setup_commands = Vec::new();
-    if let Some(size) = args.board_size {
-        setup_commands.push(format!("boardsize {size}\n"));
+    let game = match args.board_size {
+        Some(size) => Game::with_board_size(usize::from(size)).unwrap(),
+        None => Game::new(),
+    };
+    let mut server = Server::bind(&args.host_port, game).unwrap();
+    if let Some(dir) = args.sgf_dir {
+        server.set_record_dir(dir);
     }
-
-    start(&args.host_port, &setup_commands);
-}
-
-struct Game {
-    black_connection: TcpStream,
-    white_connection: TcpStream,
-}
-
-fn send_command(
-    command: &str,
-    writer: &mut TcpStream,
-    reader: &mut BufReader<TcpStream>,
-) -> String {
-    print!("-> {command}");
-    writer.write_all(command.as_bytes()).unwrap();
-
-    let mut reply = String::new();
-    reader.read_line(&mut reply).unwrap();
-    reader.read_line(&mut reply).unwrap();
-    print!("<- {}", &reply);
-    reply
-}
-
-impl Game {
-    fn start(&mut self, setup_commands: Vec<String>) {
-        let mut black_reader = BufReader::new(self.black_connection.try_clone().unwrap());
-        let mut white_reader = BufReader::new(self.white_connection.try_clone().unwrap());
-
-        for command in setup_commands {
-            send_command(&command, &mut self.black_connection, &mut black_reader);
-            send_command(&command, &mut self.white_connection, &mut white_reader);
-        }
-
-        for i in 1..362 {
-            println!("*** turn {:04} ***", 2 * i - 1);
-            let black_move =
-                send_command("genmove b\n", &mut self.black_connection, &mut black_reader);
-            send_command(
-                &black_move.replace('=', "play b").replace("\n\n", "\n"),
-                &mut self.white_connection,
-                &mut white_reader,
-            );
-
-            println!("*** turn {:04} ***", 2 * i);
-            let white_move =
-                send_command("genmove w\n", &mut self.white_connection, &mut white_reader);
-            send_command(
-                &white_move.replace('=', "play w").replace("\n\n", "\n"),
-                &mut self.black_connection,
-                &mut black_reader,
-            );
-
-            if black_move == "= pass\n\n" && white_move == "= pass\n\n" {
-                break;
+    println!("listening on {} ...", args.host_port);
+
+    // One loop accepts every spectator and hands them to the latest match.
+    let latest = Arc::new(Mutex::new(None::<Broadcast>));
+    if let Some(address) = args.spectators {
+        let listener = TcpListener::bind(address).unwrap();
+        let latest = Arc::clone(&latest);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Some(broadcast) = latest.lock().unwrap().as_ref() {
+                    broadcast.spectate(stream);
+                }
             }
-        }
-
-        self.black_connection.shutdown(Shutdown::Both).unwrap();
-        self.white_connection.shutdown(Shutdown::Both).unwrap();
+        });
     }
-}
-
-fn start(address: &str, setup_commands: &[String]) {
-    let listener = TcpListener::bind(address).unwrap();
-    println!("listening on {address} ...");
 
-    let mut players = Vec::new();
-
-    for stream in listener.incoming() {
-        match stream {
-            Ok(stream) => {
-                if players.is_empty() {
-                    players.push(stream);
-                } else {
-                    let mut game = Game {
-                        black_connection: players.pop().unwrap(),
-                        white_connection: stream,
-                    };
-                    let setup_commands = setup_commands.to_owned();
-                    thread::spawn(move || {
-                        game.start(setup_commands.clone());
-                    });
-                }
-            }
-            Err(_e) => { /* connection failed */ }
+    let (outcomes, finished) = mpsc::channel::<(usize, Outcome)>();
+    thread::spawn(move || {
+        for (number, outcome) in finished {
+            let result = outcome
+                .result()
+                .map_or("none".to_owned(), |r| r.to_string());
+            println!(
+                "*** match {number} ended: {:?}, result {result} ***",
+                outcome.end
+            );
         }
-    }
+    });
+
+    server.run(
+        |_number, game_match| {
+            let events = game_match.broadcast().subscribe();
+            thread::spawn(move || {
+                for event in events {
+                    print!("{event}");
+                }
+            });
+
+            *latest.lock().unwrap() = Some(game_match.broadcast().clone());
+        },
+        &outcomes,
+    );
 }
@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use libgo::gtp::command::Command;
+use libgo::gtp::engine::{GtpEngine, Session};
+
+// Parses each line as a command and runs it, so the command handlers are fuzzed too.
+fuzz_target!(|data: &[u8]| {
+    let mut session = Session::default();
+    session.engine.register_all_commands();
+    for line in data.split(|&byte| byte == b'\n') {
+        if let Some(command) = Command::from_bytes(line) {
+            let _ = session.exec(&command);
+        }
+    }
+});
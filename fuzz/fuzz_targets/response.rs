@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use libgo::gtp::response::Response;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(response) = Response::from_bytes(data) {
+        // Anything we can read, we can write and read back the same.
+        assert_eq!(response.to_string().parse::<Response>(), Ok(response));
+    }
+});
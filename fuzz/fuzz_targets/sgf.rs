@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use libgo::game::problem::Problem;
+use libgo::game::sgf;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(trees) = sgf::parse_bytes(data) {
+        for tree in &trees {
+            if let Ok(size) = tree.board_size() {
+                for node in &tree.nodes {
+                    let _ = node.mov(size);
+                }
+            }
+        }
+        let _ = Problem::from_sgf(&String::from_utf8_lossy(data));
+    }
+});
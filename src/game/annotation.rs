@@ -0,0 +1,46 @@
+use std::fmt;
+
+use crate::game::vertex::Vertex;
+
+/// Metadata attached to a move of the game record, e.g. by a reviewer or an engine analysing the
+/// game. It is written out with the move by `sgf::write`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Annotation {
+    /// A comment on the move, written as SGF `C`.
+    Comment(String),
+    /// A label on a vertex, written as SGF `LB`.
+    Label(Vertex, String),
+    /// A triangle on a vertex, written as SGF `TR`.
+    Triangle(Vertex),
+    /// A square on a vertex, written as SGF `SQ`.
+    Square(Vertex),
+    /// An evaluation of the position after the move, e.g. Black's estimated lead in points,
+    /// written as SGF `V`.
+    Evaluation(f64),
+}
+
+impl Annotation {
+    /// The vertex the annotation marks, if any.
+    #[must_use]
+    pub fn vertex(&self) -> Option<Vertex> {
+        match self {
+            Annotation::Label(vertex, _)
+            | Annotation::Triangle(vertex)
+            | Annotation::Square(vertex) => Some(*vertex),
+            Annotation::Comment(_) | Annotation::Evaluation(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Annotation::Comment(comment) => write!(f, "comment: {comment}"),
+            Annotation::Label(vertex, label) => write!(f, "label {vertex}: {label}"),
+            Annotation::Triangle(vertex) => write!(f, "triangle {vertex}"),
+            Annotation::Square(vertex) => write!(f, "square {vertex}"),
+            Annotation::Evaluation(value) => write!(f, "evaluation: {value}"),
+        }
+    }
+}
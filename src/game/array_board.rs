@@ -0,0 +1,287 @@
+use std::fmt;
+
+use crate::game::board::{Board, State, BOARD_MAX_SIZE, BOARD_MIN_SIZE};
+use crate::game::player::Player;
+use crate::game::vertex::Vertex;
+
+/// A 9x9 `ArrayBoard`.
+pub type Board9 = ArrayBoard<9>;
+/// A 13x13 `ArrayBoard`.
+pub type Board13 = ArrayBoard<13>;
+/// A 19x19 `ArrayBoard`.
+pub type Board19 = ArrayBoard<19>;
+
+/// The points next to each point of an N by N board, indexed by y and then x.
+type Neighbors<const N: usize> = [[[Option<(usize, usize)>; 4]; N]; N];
+
+const fn neighbors<const N: usize>() -> Neighbors<N> {
+    let mut table = [[[None; 4]; N]; N];
+    let mut y = 0;
+    while y < N {
+        let mut x = 0;
+        while x < N {
+            if y + 1 < N {
+                table[y][x][0] = Some((x, y + 1));
+            }
+            if y > 0 {
+                table[y][x][1] = Some((x, y - 1));
+            }
+            if x > 0 {
+                table[y][x][2] = Some((x - 1, y));
+            }
+            if x + 1 < N {
+                table[y][x][3] = Some((x + 1, y));
+            }
+            x += 1;
+        }
+        y += 1;
+    }
+    table
+}
+
+/// A board whose size is fixed when compiling, for programs that play out many games on one size
+/// of board. The points are held in an array and the neighbors of each point are looked up in a
+/// table worked out at compile time, so placing stones doesn't allocate. Chains aren't kept
+/// between moves, so it lacks most of what `Board` offers; convert to a `Board` for that.
+///
+/// Like a `Board`, it can be from 1x1 to 25x25, and other sizes don't compile:
+///
+/// ```compile_fail
+/// use libgo::game::array_board::ArrayBoard;
+///
+/// let board = ArrayBoard::<26>::new();
+/// ```
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct ArrayBoard<const N: usize> {
+    points: [[State; N]; N],
+}
+
+impl<const N: usize> Default for ArrayBoard<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> ArrayBoard<N> {
+    const NEIGHBORS: Neighbors<N> = neighbors::<N>();
+    const VALID_SIZE: () = assert!(
+        BOARD_MIN_SIZE <= N && N <= BOARD_MAX_SIZE,
+        "array boards must be from 1x1 to 25x25"
+    );
+
+    /// Returns an empty board.
+    #[must_use]
+    pub fn new() -> Self {
+        // Every board starts here, so no board of an invalid size can be made.
+        let () = Self::VALID_SIZE;
+        ArrayBoard {
+            points: [[State::Empty; N]; N],
+        }
+    }
+
+    /// Returns the size of the board.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        N
+    }
+
+    /// Returns the player with a stone on the vertex, or None if it's empty or off the board.
+    #[must_use]
+    pub fn stone(&self, vertex: Vertex) -> Option<Player> {
+        match self.state(vertex) {
+            Some(State::Black) => Some(Player::Black),
+            Some(State::White) => Some(Player::White),
+            Some(State::Empty) | None => None,
+        }
+    }
+
+    /// Returns true if the vertex is on the board and empty.
+    #[must_use]
+    pub fn is_vacant(&self, vertex: Vertex) -> bool {
+        self.state(vertex) == Some(State::Empty)
+    }
+
+    /// Returns the number of liberties of the chain holding the stone on the vertex, or 0 if the
+    /// vertex is empty.
+    #[must_use]
+    pub fn liberties(&self, vertex: Vertex) -> usize {
+        let Some(state) = self.state(vertex).filter(|&state| state != State::Empty) else {
+            return 0;
+        };
+        let mut visited = [[false; N]; N];
+        let mut counted = [[false; N]; N];
+        self.count_liberties(vertex.x, vertex.y, state, &mut visited, &mut counted)
+    }
+
+    /// Places a stone on the board, removing the enemy chains it leaves without liberties and then
+    /// its own chain if that has none, as `Board::place_stone` does. Returns the number of enemy
+    /// stones captured.
+    ///
+    /// # Panics
+    ///
+    /// If the vertex is off the board.
+    pub fn place_stone(&mut self, player: Player, vertex: Vertex) -> usize {
+        assert!(vertex.x < N && vertex.y < N, "invalid vertex");
+        let (x, y) = (vertex.x, vertex.y);
+        let (own, enemy) = (State::from(player), State::from(player.enemy()));
+        self.points[y][x] = own;
+
+        let mut captured = 0;
+        for &(nx, ny) in Self::NEIGHBORS[y][x].iter().flatten() {
+            if self.points[ny][nx] == enemy && !self.has_liberty(nx, ny) {
+                captured += self.remove_chain(nx, ny, enemy);
+            }
+        }
+        // Remove suicides.
+        if !self.has_liberty(x, y) {
+            self.remove_chain(x, y, own);
+        }
+        captured
+    }
+
+    fn state(&self, vertex: Vertex) -> Option<State> {
+        self.points.get(vertex.y)?.get(vertex.x).copied()
+    }
+
+    fn has_liberty(&self, x: usize, y: usize) -> bool {
+        let mut visited = [[false; N]; N];
+        self.reaches_liberty(x, y, self.points[y][x], &mut visited)
+    }
+
+    fn reaches_liberty(
+        &self,
+        x: usize,
+        y: usize,
+        state: State,
+        visited: &mut [[bool; N]; N],
+    ) -> bool {
+        visited[y][x] = true;
+        for &(nx, ny) in Self::NEIGHBORS[y][x].iter().flatten() {
+            let neighbor = self.points[ny][nx];
+            if neighbor == State::Empty
+                || (neighbor == state
+                    && !visited[ny][nx]
+                    && self.reaches_liberty(nx, ny, state, visited))
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn count_liberties(
+        &self,
+        x: usize,
+        y: usize,
+        state: State,
+        visited: &mut [[bool; N]; N],
+        counted: &mut [[bool; N]; N],
+    ) -> usize {
+        visited[y][x] = true;
+        let mut liberties = 0;
+        for &(nx, ny) in Self::NEIGHBORS[y][x].iter().flatten() {
+            let neighbor = self.points[ny][nx];
+            if neighbor == State::Empty && !counted[ny][nx] {
+                counted[ny][nx] = true;
+                liberties += 1;
+            } else if neighbor == state && !visited[ny][nx] {
+                liberties += self.count_liberties(nx, ny, state, visited, counted);
+            }
+        }
+        liberties
+    }
+
+    /// Removes the chain of stones in the state that holds the point, returning its size.
+    fn remove_chain(&mut self, x: usize, y: usize, state: State) -> usize {
+        self.points[y][x] = State::Empty;
+        let mut removed = 1;
+        for &(nx, ny) in Self::NEIGHBORS[y][x].iter().flatten() {
+            if self.points[ny][nx] == state {
+                removed += self.remove_chain(nx, ny, state);
+            }
+        }
+        removed
+    }
+}
+
+impl<const N: usize> From<&ArrayBoard<N>> for Board {
+    fn from(array_board: &ArrayBoard<N>) -> Self {
+        let mut board = Board::with_size(N).expect("array boards have a valid size");
+        for (y, row) in array_board.points.iter().enumerate() {
+            for (x, &state) in row.iter().enumerate() {
+                let player = match state {
+                    State::Black => Player::Black,
+                    State::White => Player::White,
+                    State::Empty => continue,
+                };
+                board.place_stone(player, Vertex { x, y });
+            }
+        }
+        board
+    }
+}
+
+impl<const N: usize> TryFrom<&Board> for ArrayBoard<N> {
+    type Error = String;
+
+    fn try_from(board: &Board) -> Result<Self, Self::Error> {
+        if board.size() != N {
+            return Err(format!(
+                "expected a board of size {N}, but it is {}",
+                board.size()
+            ));
+        }
+        let mut array_board = ArrayBoard::new();
+        for player in [Player::Black, Player::White] {
+            for vertex in board.stones(player) {
+                array_board.points[vertex.y][vertex.x] = State::from(player);
+            }
+        }
+        Ok(array_board)
+    }
+}
+
+impl<const N: usize> fmt::Debug for ArrayBoard<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Board::from(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn matches_board() {
+        let mut rng = rand::thread_rng();
+        let mut array_board = Board9::new();
+        let mut board = Board::with_size(9).unwrap();
+        for turn in 0..300 {
+            let player = if turn % 2 == 0 {
+                Player::Black
+            } else {
+                Player::White
+            };
+            let vertex = Vertex {
+                x: rng.gen_range(0..9),
+                y: rng.gen_range(0..9),
+            };
+            if !board.is_vacant(vertex) {
+                continue;
+            }
+            let captured = board.place_stone(player, vertex);
+            assert_eq!(array_board.place_stone(player, vertex), captured.len());
+            assert_eq!(Board::from(&array_board), board);
+            if !board.is_vacant(vertex) {
+                assert_eq!(Some(array_board.liberties(vertex)), board.liberties(vertex));
+            }
+        }
+        assert_eq!(
+            Board19::try_from(&board),
+            Err("expected a board of size 19, but it is 9".to_owned())
+        );
+        assert_eq!(Board9::try_from(&board), Ok(array_board));
+    }
+}
@@ -0,0 +1,136 @@
+//! Benchmarks of the speed of the rules, with results that can be written out as JSON for
+//! tracking performance across releases.
+
+use std::fs;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::game::numeric;
+use crate::game::search;
+use crate::game::Game;
+
+/// The results of a benchmark run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BenchmarkResult {
+    /// The name of the benchmark.
+    pub name: &'static str,
+    /// The size of the board played on.
+    pub board_size: usize,
+    /// The number of games played out.
+    pub games: usize,
+    /// The number of positions reached, one for each move played.
+    pub positions: usize,
+    /// How long the games took to play out.
+    pub elapsed: Duration,
+    /// The peak resident memory of the process in bytes, where the operating system reports it.
+    pub peak_memory: Option<u64>,
+}
+
+impl BenchmarkResult {
+    /// Returns the number of positions reached each second.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn positions_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.positions as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the results as a JSON object on one line, with the version of the library, the
+    /// elapsed time in seconds and the peak memory or null.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let peak_memory = self
+            .peak_memory
+            .map_or("null".to_owned(), |bytes| bytes.to_string());
+        format!(
+            concat!(
+                r#"{{"name":"{}","version":"{}","board_size":{},"games":{},"positions":{},"#,
+                r#""elapsed":{},"positions_per_sec":{:.1},"peak_memory":{}}}"#
+            ),
+            self.name,
+            env!("CARGO_PKG_VERSION"),
+            self.board_size,
+            self.games,
+            self.positions,
+            numeric::format_seconds(self.elapsed),
+            self.positions_per_sec(),
+            peak_memory,
+        )
+    }
+
+    /// Writes the results to the writer as a line of JSON, so the results of several runs can be
+    /// appended to one file.
+    ///
+    /// # Errors
+    ///
+    /// Writing failed.
+    pub fn write_json<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "{}", self.to_json())
+    }
+}
+
+/// Plays out random games on an empty board of the size, as the search does, and measures how
+/// many positions it reaches a second.
+///
+/// # Errors
+///
+/// The board size is invalid.
+pub fn playouts(board_size: usize, games: usize) -> Result<BenchmarkResult, String> {
+    let empty = Game::with_board_size(board_size)?;
+    let mut rng = rand::thread_rng();
+    let mut positions = 0;
+    let start = Instant::now();
+    for _ in 0..games {
+        let mut game = empty.clone();
+        while !game.is_over() {
+            search::play_random(&mut game, &mut rng);
+            positions += 1;
+        }
+    }
+    Ok(BenchmarkResult {
+        name: "playouts",
+        board_size,
+        games,
+        positions,
+        elapsed: start.elapsed(),
+        peak_memory: peak_memory(),
+    })
+}
+
+/// Returns the peak resident memory of the process in bytes, read from `/proc` on Linux.
+fn peak_memory() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: u64 = line
+        .trim_start_matches("VmHWM:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn playouts() {
+        let result = super::playouts(5, 2).unwrap();
+        assert_eq!(result.games, 2);
+        assert!(result.positions >= 2);
+        assert!(result.positions_per_sec() > 0.0);
+
+        let mut json = Vec::new();
+        result.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with(r#"{"name":"playouts","version":""#));
+        assert!(json.contains(r#""board_size":5,"games":2,"#));
+        assert!(json.ends_with("}\n"));
+
+        assert!(super::playouts(0, 1).is_err());
+    }
+}
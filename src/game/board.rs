@@ -1,13 +1,61 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
-use crate::game::matrix::{Matrix, Node};
+use crate::game::matrix::{Matrix, Node, Region};
 use crate::game::player::Player;
+use crate::game::regions::EmptyRegions;
+use crate::game::render::{self, BoardOverlay};
 use crate::game::vertex::Vertex;
 
-const BOARD_MAX_SIZE: usize = 19;
-const BOARD_MIN_SIZE: usize = 1;
-const BOARD_LETTERS: &str = "ABCDEFGHJKLMNOPQRST";
+pub(crate) const BOARD_MAX_SIZE: usize = 25;
+pub(crate) const BOARD_MIN_SIZE: usize = 1;
+
+/// Empty regions bordered only by one player's stones with at least this many points are counted
+/// as two eyes, since there's room to make them.
+const TWO_EYE_SPACE: usize = 4;
+
+/// Whether a stone would survive if the game were played out.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Status {
+    /// The stone can't be captured.
+    Alive,
+    /// The stone can be captured, so it's counted as a prisoner.
+    Dead,
+    /// The stone lives by sharing liberties with enemy stones that neither player can fill.
+    Seki,
+}
+
+/// How safe a chain is as the position stands, judged statically by `Board::safeties` without
+/// playing anything out.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Safety {
+    /// The chain is pass alive by Benson's algorithm.
+    Alive,
+    /// The chain has two real eyes, lives in seki, or looks alive to `Board::statuses`.
+    ProbablyAlive,
+    /// The chain could live or die.
+    Unsettled,
+    /// The chain is in atari or looks dead to `Board::statuses`.
+    ProbablyDead,
+    /// The chain is in the opponent's unconditional territory.
+    Dead,
+}
+
+impl fmt::Display for Safety {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Safety::Alive => "alive",
+            Safety::ProbablyAlive => "probably_alive",
+            Safety::Unsettled => "unsettled",
+            Safety::ProbablyDead => "probably_dead",
+            Safety::Dead => "dead",
+        };
+        write!(f, "{name}")
+    }
+}
 
 /// A representation of the board state.
 #[derive(Clone)]
@@ -15,9 +63,47 @@ pub struct Board {
     /// A matrix holding the state of each vertex on the board.
     matrix: Matrix<State>,
     chains: Chains,
+    regions: EmptyRegions,
+    /// The `zobrist_hash` of the stones, updated as each stone is placed or removed.
+    hash: u64,
+}
+
+/// The chains are shared between clones of a board, and a chain is only copied when a move on one
+/// of the boards changes it, so a board can be cloned cheaply, e.g. for each node of a search.
+type Chains = Vec<Arc<Chain>>;
+
+/// Boards are serialized as the matrix of their points, and the chains and empty regions are
+/// found again when they're deserialized.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Board {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut board = serializer.serialize_struct("Board", 1)?;
+        board.serialize_field("matrix", &self.matrix)?;
+        board.end()
+    }
 }
 
-type Chains = Vec<Chain>;
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Board {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "Board")]
+        struct Fields {
+            matrix: Matrix<State>,
+        }
+
+        let Fields { matrix } = Fields::deserialize(deserializer)?;
+        let mut board = Board::with_size(matrix.size()).map_err(serde::de::Error::custom)?;
+        board.matrix = matrix;
+        board.hash = board.full_hash();
+        board.rebuild_chains();
+        board.regions.rebuild(&board.matrix);
+        board.is_reachable().map_err(serde::de::Error::custom)?;
+        Ok(board)
+    }
+}
 
 impl PartialEq for Board {
     fn eq(&self, other: &Board) -> bool {
@@ -25,6 +111,14 @@ impl PartialEq for Board {
     }
 }
 
+impl Eq for Board {}
+
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.matrix.hash(state);
+    }
+}
+
 impl Board {
     /// Returns the center point (天元 tengen) of the board. Note that even size boards don't have a
     /// center point.
@@ -43,6 +137,16 @@ impl Board {
         }
     }
 
+    /// Returns the point symmetric to the vertex through the center of the board.
+    #[must_use]
+    pub fn mirror(&self, vertex: Vertex) -> Vertex {
+        let last = self.size() - 1;
+        Vertex {
+            x: last - vertex.x,
+            y: last - vertex.y,
+        }
+    }
+
     /// Returns the edge star points (星 hoshi), which are traditionally marked with a small dot on
     /// the board.
     #[must_use]
@@ -121,6 +225,106 @@ impl Board {
         handicaps
     }
 
+    /// Returns where to place a free handicap of the given number of stones, spread out so that
+    /// together they cover as much of the board as they can. The stones start on the points of
+    /// `fixed_handicaps`, and each stone beyond those goes on the point between the third lines
+    /// farthest from the stones already placed, ties going to the point farthest from the center.
+    /// Once there is no room left between the third lines the rest of the board is used. Fewer
+    /// stones are returned than requested only if the board is full.
+    #[must_use]
+    pub fn free_handicaps(&self, stones: usize) -> Vec<Vertex> {
+        let board_size = self.size();
+        let line = match board_size {
+            9.. => 2,
+            5..=8 => 1,
+            _ => 0,
+        };
+        let band = line..board_size - line;
+        let center = board_size - 1;
+        let distance = |a: Vertex, b: Vertex| {
+            let (dx, dy) = (a.x.abs_diff(b.x), a.y.abs_diff(b.y));
+            dx * dx + dy * dy
+        };
+
+        let mut handicaps = self.fixed_handicaps(stones);
+        handicaps.truncate(stones);
+        while handicaps.len() < stones {
+            let free = |vertex: &Vertex| self.is_vacant(*vertex) && !handicaps.contains(vertex);
+            let mut candidates: Vec<Vertex> = self
+                .empty_verts()
+                .into_iter()
+                .filter(|vertex| band.contains(&vertex.x) && band.contains(&vertex.y))
+                .filter(free)
+                .collect();
+            if candidates.is_empty() {
+                candidates = self.empty_verts().into_iter().filter(free).collect();
+            }
+            // Coordinates are doubled so the distance to the center is a whole number.
+            let spread = |vertex: Vertex| {
+                let nearest = handicaps
+                    .iter()
+                    .map(|&handicap| distance(vertex, handicap))
+                    .min()
+                    .unwrap_or(usize::MAX);
+                let doubled = Vertex {
+                    x: 2 * vertex.x,
+                    y: 2 * vertex.y,
+                };
+                let from_center = distance(
+                    doubled,
+                    Vertex {
+                        x: center,
+                        y: center,
+                    },
+                );
+                (nearest, from_center)
+            };
+            let Some(best) = candidates
+                .into_iter()
+                .rev()
+                .max_by_key(|&vertex| spread(vertex))
+            else {
+                break;
+            };
+            handicaps.push(best);
+        }
+        handicaps
+    }
+
+    /// Checks that the board could have arisen in a game, e.g. before trusting a position set up
+    /// by an SGF record or sent by a client. Every chain must have a liberty, as a chain without
+    /// one would have been captured; this also rules out a full board. The numbers of stones
+    /// can't make a board unreachable, since passes and captures let either player have any
+    /// number more than the other.
+    ///
+    /// # Errors
+    ///
+    /// Lists the chains without liberties, each by its stone nearest the bottom left.
+    pub fn is_reachable(&self) -> Result<(), String> {
+        let mut captured: Vec<(Vertex, Player)> = self
+            .chains
+            .iter()
+            .filter(|chain| chain.libs.is_empty())
+            .filter_map(|chain| {
+                chain
+                    .verts
+                    .iter()
+                    .map(|&node| self.matrix.vertex_from_node(node))
+                    .min_by_key(|vertex| (vertex.x, vertex.y))
+                    .map(|vertex| (vertex, chain.player))
+            })
+            .collect();
+        if captured.is_empty() {
+            return Ok(());
+        }
+        captured.sort_by_key(|(vertex, _)| (vertex.x, vertex.y));
+        let chains: Vec<String> = captured
+            .iter()
+            .map(|(vertex, player)| format!("{player} chain at {vertex} has no liberties"))
+            .collect();
+        Err(chains.join(", "))
+    }
+
     /// Returns true if there are no stones on the board.
     #[must_use]
     pub fn is_empty(&self) -> bool {
@@ -136,12 +340,300 @@ impl Board {
         }
     }
 
+    /// Returns the player whose stone is on the vertex, if any.
+    #[must_use]
+    pub fn stone(&self, vertex: Vertex) -> Option<Player> {
+        match self.matrix.get(vertex) {
+            Some(State::Black) => Some(Player::Black),
+            Some(State::White) => Some(Player::White),
+            Some(State::Empty) | None => None,
+        }
+    }
+
+    /// Returns the stones that differ between this board and the other, as they would change to
+    /// turn this board into the other, in order of their vertices from the bottom left.
+    ///
+    /// # Panics
+    ///
+    /// If the boards are of different sizes.
+    #[must_use]
+    pub fn diff(&self, other: &Board) -> BoardDiff {
+        assert_eq!(self.size(), other.size(), "boards of different sizes");
+        let mut diff = BoardDiff::default();
+        for node in self.matrix.nodes() {
+            let vertex = self.matrix.vertex_from_node(node);
+            match (self.stone(vertex), other.stone(vertex)) {
+                (None, Some(player)) => diff.added.push((player, vertex)),
+                (Some(player), None) => diff.removed.push((player, vertex)),
+                (Some(from), Some(to)) if from != to => diff.changed.push((vertex, to)),
+                _ => {}
+            }
+        }
+        diff
+    }
+
+    /// Returns the node of the vertex, as returned by analysis such as `pass_alive_chains`, or None
+    /// if the vertex isn't on the board.
+    #[must_use]
+    pub fn node_of(&self, vertex: Vertex) -> Option<Node> {
+        self.matrix.node_from_vertex(vertex)
+    }
+
+    /// Returns the vertex of a node, or None if the node isn't on the board, e.g. because it came
+    /// from a board of another size.
+    #[must_use]
+    pub fn vertex_of(&self, node: Node) -> Option<Vertex> {
+        let vertex = self.matrix.vertex_from_node(node);
+        self.matrix.node_from_vertex(vertex).map(|_| vertex)
+    }
+
+    /// Returns the points next to the vertex: up to four, fewer on the edge, or none if the vertex
+    /// isn't on the board.
+    #[must_use]
+    pub fn neighbors(&self, vertex: Vertex) -> Vec<Vertex> {
+        self.matrix
+            .node_from_vertex(vertex)
+            .map_or(&[][..], |node| self.matrix.adjacencies(node))
+            .iter()
+            .map(|&node| self.matrix.vertex_from_node(node))
+            .collect()
+    }
+
+    /// Returns the points diagonally next to the vertex: up to four, fewer on the edge, or none if
+    /// the vertex isn't on the board.
+    #[must_use]
+    pub fn diagonals(&self, vertex: Vertex) -> Vec<Vertex> {
+        let size = self.size();
+        if vertex.x >= size || vertex.y >= size {
+            return Vec::new();
+        }
+        let Vertex { x, y } = vertex;
+        let (left, right, down, up) =
+            (x.checked_sub(1), Some(x + 1), y.checked_sub(1), Some(y + 1));
+        [(left, down), (left, up), (right, down), (right, up)]
+            .into_iter()
+            .filter_map(|(x, y)| match (x, y) {
+                (Some(x), Some(y)) if x < size && y < size => Some(Vertex { x, y }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns true if the vertex is a real eye of the player: an empty point surrounded by the
+    /// player's stones that the opponent can't make into a false eye. In the middle of the board
+    /// that takes the opponent's stones on two of the diagonal points, but on the edge or in the
+    /// corner one is enough.
+    #[must_use]
+    pub fn is_eye(&self, vertex: Vertex, player: Player) -> bool {
+        if !self.is_vacant(vertex) {
+            return false;
+        }
+        let surrounded = self
+            .neighbors(vertex)
+            .into_iter()
+            .all(|neighbor| self.stone(neighbor) == Some(player));
+        if !surrounded {
+            return false;
+        }
+        let diagonals = self.diagonals(vertex);
+        let taken = diagonals
+            .iter()
+            .filter(|&&diagonal| self.stone(diagonal) == Some(player.enemy()))
+            .count();
+        if diagonals.len() == 4 {
+            taken < 2
+        } else {
+            taken == 0
+        }
+    }
+
+    /// Returns the real eyes of the player, as decided by `is_eye`.
+    #[must_use]
+    pub fn real_eyes(&self, player: Player) -> Vec<Vertex> {
+        self.empty_verts()
+            .into_iter()
+            .filter(|&vertex| self.is_eye(vertex, player))
+            .collect()
+    }
+
+    /// Returns the number of liberties of the chain the stone on the vertex belongs to, or None if
+    /// there is no stone there. The liberties are kept up to date as stones are placed, so this
+    /// doesn't search the board.
+    #[must_use]
+    pub fn liberties(&self, vertex: Vertex) -> Option<usize> {
+        self.chain_at(vertex).map(|chain| chain.liberty_count())
+    }
+
+    /// Returns the liberties of the chain the stone on the vertex belongs to, sorted, or none if
+    /// there is no stone there.
+    #[must_use]
+    pub fn liberty_vertices(&self, vertex: Vertex) -> Vec<Vertex> {
+        self.chain_at(vertex)
+            .map_or_else(Vec::new, |chain| chain.liberties())
+    }
+
+    /// Returns a view of the chain the stone on the vertex belongs to, or None if there is no
+    /// stone there.
+    #[must_use]
+    pub fn chain_at(&self, vertex: Vertex) -> Option<ChainView<'_>> {
+        let node = self.matrix.node_from_vertex(vertex)?;
+        self.chains
+            .iter()
+            .find(|chain| chain.verts.contains(&node))
+            .map(|chain| ChainView { board: self, chain })
+    }
+
+    /// Returns views of all the chains on the board, in no particular order.
+    pub fn chains(&self) -> impl Iterator<Item = ChainView<'_>> {
+        self.chains
+            .iter()
+            .map(move |chain| ChainView { board: self, chain })
+    }
+
+    /// Returns the stones of each of the player's chains in atari, i.e. left with one liberty.
+    #[must_use]
+    pub fn chains_in_atari(&self, player: Player) -> Vec<Vec<Vertex>> {
+        let mut chains: Vec<Vec<Vertex>> = self
+            .chains
+            .iter()
+            .filter(|chain| chain.player == player && chain.libs.len() == 1)
+            .map(|chain| {
+                let mut stones: Vec<_> = chain
+                    .verts
+                    .iter()
+                    .map(|&node| self.matrix.vertex_from_node(node))
+                    .collect();
+                stones.sort_by_key(|vertex| (vertex.x, vertex.y));
+                stones
+            })
+            .collect();
+        chains.sort_by_key(|stones| (stones[0].x, stones[0].y));
+        chains
+    }
+
+    /// Returns true if the player playing on the empty vertex would leave the chain it joins in
+    /// atari, or with no liberties at all, after any captures it makes.
+    #[must_use]
+    pub fn is_self_atari(&self, player: Player, vertex: Vertex) -> bool {
+        if !self.is_vacant(vertex) {
+            return false;
+        }
+        let mut board = self.clone();
+        board.place_stone(player, vertex);
+        board
+            .liberties(vertex)
+            .map_or(true, |liberties| liberties <= 1)
+    }
+
+    /// Works out what the player playing on the vertex would do from the liberties of the chains
+    /// next to it, without changing or copying the board. Returns None if the vertex isn't on the
+    /// board or isn't empty.
+    pub(crate) fn preview(&self, player: Player, vertex: Vertex) -> Option<Placement> {
+        let node = self.matrix.node_from_vertex(vertex)?;
+        if self.matrix[node] != State::Empty {
+            return None;
+        }
+        let touching: Vec<&Chain> = self
+            .chains
+            .iter()
+            .filter(|chain| chain.libs.contains(&node))
+            .map(Arc::as_ref)
+            .collect();
+        Some(self.placement(player, node, &touching))
+    }
+
+    /// Works out what the player playing on each empty point would do, like `preview`, finding
+    /// the chains next to every point in one pass over the chains.
+    pub(crate) fn previews(&self, player: Player) -> Vec<(Vertex, Placement)> {
+        let mut touching: HashMap<Node, Vec<&Chain>> = HashMap::new();
+        for chain in &self.chains {
+            for &liberty in &chain.libs {
+                touching.entry(liberty).or_default().push(chain);
+            }
+        }
+        self.matrix
+            .nodes()
+            .filter(|&node| self.matrix[node] == State::Empty)
+            .map(|node| {
+                let chains = touching.get(&node).map_or(&[][..], Vec::as_slice);
+                let placement = self.placement(player, node, chains);
+                (self.matrix.vertex_from_node(node), placement)
+            })
+            .collect()
+    }
+
+    /// Works out what the player playing on the empty node would do, given the chains it's a
+    /// liberty of.
+    fn placement(&self, player: Player, node: Node, touching: &[&Chain]) -> Placement {
+        let vertex = self.matrix.vertex_from_node(node);
+        let mut hash = self.zobrist_hash() ^ zobrist_key(vertex, player);
+        let mut liberty = self
+            .matrix
+            .adjacencies(node)
+            .iter()
+            .any(|&neighbor| self.matrix[neighbor] == State::Empty);
+        for chain in touching {
+            if chain.player == player {
+                liberty |= chain.libs.len() > 1;
+            } else if chain.libs.len() == 1 {
+                hash ^= self.chain_hash(chain);
+                liberty = true;
+            }
+        }
+        if liberty {
+            return Placement { hash, suicided: 0 };
+        }
+
+        // The stone and the chains it joins are removed.
+        hash ^= zobrist_key(vertex, player);
+        let mut suicided = 1;
+        for chain in touching.iter().filter(|chain| chain.player == player) {
+            hash ^= self.chain_hash(chain);
+            suicided += chain.verts.len();
+        }
+        Placement { hash, suicided }
+    }
+
+    /// Returns a hash of the stones on the board. The hash of each stone is combined with xor, so
+    /// the hash of a board a move leads to can be worked out from the stones it adds and removes,
+    /// and the board keeps it up to date that way.
+    pub(crate) fn zobrist_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Works out the `zobrist_hash` from every stone on the board.
+    fn full_hash(&self) -> u64 {
+        self.matrix
+            .nodes()
+            .filter_map(|node| {
+                let player = match self.matrix[node] {
+                    State::Black => Player::Black,
+                    State::White => Player::White,
+                    State::Empty => return None,
+                };
+                Some(zobrist_key(self.matrix.vertex_from_node(node), player))
+            })
+            .fold(0, |hash, key| hash ^ key)
+    }
+
+    fn chain_hash(&self, chain: &Chain) -> u64 {
+        chain.verts.iter().fold(0, |hash, &node| {
+            hash ^ zobrist_key(self.matrix.vertex_from_node(node), chain.player)
+        })
+    }
+
     /// Returns a list of all the empty vertices.
     #[must_use]
     pub fn empty_verts(&self) -> Vec<Vertex> {
         self.matrix.verts_in_state(&State::Empty)
     }
 
+    /// Returns a list of the vertices holding a player's stones.
+    #[must_use]
+    pub fn stones(&self, player: Player) -> Vec<Vertex> {
+        self.matrix.verts_in_state(&State::from(player))
+    }
+
     /// Returns a list of all the **unconditionally alive** chains on the board.
     ///
     /// A chain on stones is **alive** when there is no seqeunce of
@@ -149,15 +641,167 @@ impl Board {
     ///
     /// A chain is **unconditionally alive** or **pass alive** if there is no sequence of moves
     /// solely from the opponent that can capture the chain.
+    ///
+    /// The chains are found by Benson's algorithm and each is returned as one of its stones, whose
+    /// vertex `vertex_of` gives.
     #[must_use]
     pub fn pass_alive_chains(&self) -> Vec<Node> {
-        unimplemented!();
+        [Player::Black, Player::White]
+            .into_iter()
+            .flat_map(|player| self.benson(player).0)
+            .filter_map(|index| self.chains[index].verts.iter().next().copied())
+            .collect()
+    }
+
+    /// Returns the player who owns the vertex unconditionally, if any: the vertex holds a stone of
+    /// one of their pass alive chains, or is in a region enclosed by their pass alive chains that
+    /// the opponent can't live in, such as an eye.
+    #[must_use]
+    pub fn unconditional_owner(&self, vertex: Vertex) -> Option<Player> {
+        let node = self.matrix.node_from_vertex(vertex)?;
+        [Player::Black, Player::White].into_iter().find(|&player| {
+            let (chains, regions) = self.benson(player);
+            chains
+                .iter()
+                .any(|&index| self.chains[index].verts.contains(&node))
+                || regions.iter().any(|region| region.contains(&node))
+        })
+    }
+
+    /// Returns the safety of every stone on the board, judged by chain. Pass alive chains are
+    /// alive and chains in the opponent's unconditional territory dead, as in
+    /// `unconditional_owner`. Of the rest, chains with two real eyes are probably alive, and
+    /// chains in atari probably dead. Otherwise chains `Board::statuses` puts in seki are probably
+    /// alive, as are those it says are alive that have an eye, while those it says are dead with
+    /// at most two liberties are probably dead. The rest are unsettled.
+    #[must_use]
+    pub fn safeties(&self) -> HashMap<Vertex, Safety> {
+        let mut owners = HashMap::new();
+        for player in [Player::Black, Player::White] {
+            let (chains, regions) = self.benson(player);
+            let nodes = chains
+                .iter()
+                .flat_map(|&index| self.chains[index].verts.iter())
+                .chain(regions.iter().flatten());
+            owners.extend(nodes.map(|&node| (node, player)));
+        }
+        let statuses = self.statuses();
+
+        let mut safeties = HashMap::new();
+        for chain in &self.chains {
+            let Some(&node) = chain.verts.iter().next() else {
+                continue;
+            };
+            let vertex = self.matrix.vertex_from_node(node);
+            let eyes = chain
+                .libs
+                .iter()
+                .filter(|&&lib| self.is_eye(self.matrix.vertex_from_node(lib), chain.player))
+                .count();
+            let safety = match owners.get(&node) {
+                Some(&owner) if owner == chain.player => Safety::Alive,
+                Some(_) => Safety::Dead,
+                None if eyes >= 2 => Safety::ProbablyAlive,
+                None if chain.libs.len() == 1 => Safety::ProbablyDead,
+                None => match statuses.get(&vertex) {
+                    Some(Status::Seki) => Safety::ProbablyAlive,
+                    Some(Status::Alive) if eyes >= 1 => Safety::ProbablyAlive,
+                    Some(Status::Dead) if chain.libs.len() <= 2 => Safety::ProbablyDead,
+                    _ => Safety::Unsettled,
+                },
+            };
+            safeties.extend(
+                chain
+                    .verts
+                    .iter()
+                    .map(|&node| (self.matrix.vertex_from_node(node), safety)),
+            );
+        }
+        safeties
+    }
+
+    /// Benson's algorithm: returns the indices of the player's pass alive chains and the regions
+    /// of empty points and enemy stones they enclose that are vital to them, meaning every empty
+    /// point of the region is a liberty of one of the chains. A chain is pass alive if it borders
+    /// two vital regions, each enclosed by pass alive chains.
+    fn benson(&self, player: Player) -> (Vec<usize>, Vec<Vec<Node>>) {
+        let mut chain_of = HashMap::new();
+        for (index, chain) in self.chains.iter().enumerate() {
+            if chain.player == player {
+                chain_of.extend(chain.verts.iter().map(|&node| (node, index)));
+            }
+        }
+        let (regions, _) = self.components(|node| self.matrix[node] != State::from(player));
+        let bordering: Vec<HashSet<usize>> = regions
+            .iter()
+            .map(|region| {
+                region
+                    .iter()
+                    .flat_map(|&node| self.matrix.adjacencies(node))
+                    .filter_map(|adjacent| chain_of.get(adjacent).copied())
+                    .collect()
+            })
+            .collect();
+        let vital: Vec<Vec<usize>> = regions
+            .iter()
+            .zip(&bordering)
+            .map(|(region, bordering)| {
+                bordering
+                    .iter()
+                    .copied()
+                    .filter(|&index| {
+                        region.iter().all(|node| {
+                            self.matrix[*node] != State::Empty
+                                || self.chains[index].libs.contains(node)
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut alive: HashSet<usize> = chain_of.values().copied().collect();
+        let mut enclosed = vec![true; regions.len()];
+        loop {
+            let dead: Vec<usize> = alive
+                .iter()
+                .copied()
+                .filter(|&index| {
+                    let vital_regions = (0..regions.len())
+                        .filter(|&region| enclosed[region] && vital[region].contains(&index))
+                        .count();
+                    vital_regions < 2
+                })
+                .collect();
+            if dead.is_empty() {
+                break;
+            }
+            for index in dead {
+                alive.remove(&index);
+            }
+            for (region, bordering) in bordering.iter().enumerate() {
+                if bordering.iter().any(|index| !alive.contains(index)) {
+                    enclosed[region] = false;
+                }
+            }
+        }
+
+        let territory = regions
+            .into_iter()
+            .enumerate()
+            .filter(|&(region, _)| enclosed[region] && !vital[region].is_empty())
+            .map(|(_, region)| region)
+            .collect();
+        let mut alive: Vec<usize> = alive.into_iter().collect();
+        alive.sort_unstable();
+        (alive, territory)
     }
 
     /// Removes all of the stones from the board.
     pub fn clear(&mut self) {
         self.matrix.reset();
+        self.hash = 0;
         self.chains.clear();
+        self.regions.rebuild(&self.matrix);
     }
 
     /// Creates a new board with the given size. A full size game is 19, but 13 and 9 are also
@@ -165,12 +809,15 @@ impl Board {
     ///
     /// # Errors
     ///
-    /// Returns an error if the board size is not between 1 and 19 inclusive.
+    /// Returns an error if the board size is not between 1 and 25 inclusive.
     pub fn with_size(size: usize) -> Result<Self, String> {
         if (BOARD_MIN_SIZE..=BOARD_MAX_SIZE).contains(&size) {
+            let matrix = Matrix::with_size(size);
             Ok(Board {
-                matrix: Matrix::with_size(size),
+                regions: EmptyRegions::new(&matrix),
+                matrix,
                 chains: Vec::new(),
+                hash: 0,
             })
         } else {
             Err(format!(
@@ -179,47 +826,161 @@ impl Board {
         }
     }
 
-    /// Updates the board with a move. The move is assumed to be valid and legal.
+    /// Updates the board with a move and returns the vertices of the enemy stones it captured.
+    /// The move is assumed to be valid and legal.
     ///
     /// # Panics
     ///
     /// If the vertex is illegal.
-    pub fn place_stone(&mut self, player: Player, vertex: Vertex) {
+    pub fn place_stone(&mut self, player: Player, vertex: Vertex) -> Vec<Vertex> {
         let node = self
             .matrix
             .node_from_vertex(vertex)
             .expect("invalid vertex");
-        self.matrix[node] = State::from(player);
+        self.add_stone(player, node);
+
+        let captures = self.remove_captures(player);
+        // Remove suicides.
+        let suicides = self.remove_captures(player.enemy());
+        self.regions.stones_removed(&self.matrix, &captures);
+        self.regions.stones_removed(&self.matrix, &suicides);
+
+        captures
+            .into_iter()
+            .map(|node| self.matrix.vertex_from_node(node))
+            .collect()
+    }
+
+    /// Reverses `place_stone`: removes the player's stone from the vertex and puts back the enemy
+    /// stones it captured and the player's own stones it removed by suicide.
+    ///
+    /// # Panics
+    ///
+    /// If a vertex is illegal.
+    pub(crate) fn unplace_stone(
+        &mut self,
+        player: Player,
+        vertex: Vertex,
+        captured: &[Vertex],
+        suicided: &[Vertex],
+    ) {
+        let node_of = |vertex| {
+            self.matrix
+                .node_from_vertex(vertex)
+                .expect("invalid vertex")
+        };
+        let node = node_of(vertex);
+        let restored: Vec<_> = suicided
+            .iter()
+            .map(|&vertex| (player, node_of(vertex)))
+            .chain(
+                captured
+                    .iter()
+                    .map(|&vertex| (player.enemy(), node_of(vertex))),
+            )
+            .collect();
+        // A suicide removed the stone itself, so it's put back with the rest of its chain.
+        for (player, node) in restored {
+            self.add_stone(player, node);
+        }
+        self.remove_stone(node);
+    }
+
+    /// Puts the player's stone on the empty node, joining it to the chains next to it, without
+    /// removing any chain it leaves without liberties.
+    fn add_stone(&mut self, player: Player, node: Node) {
+        self.set_state(node, State::from(player));
+        self.regions.stone_placed(&self.matrix, node);
 
         // Remove the liberty from chains on the board.
         for chain in &mut self.chains {
-            if chain.libs.remove(&node) && chain.player != player {
-                chain.filled_libs.insert(node);
+            if chain.libs.contains(&node) {
+                let chain = Arc::make_mut(chain);
+                chain.libs.remove(&node);
+                if chain.player != player {
+                    chain.filled_libs.insert(node);
+                }
             }
         }
 
         self.add_chain(player, node);
+    }
 
-        self.remove_captures(player);
-        // Remove suicides.
-        self.remove_captures(player.enemy());
+    /// Takes the stone off the node, splitting what is left of its chain into the chains that are
+    /// still connected.
+    fn remove_stone(&mut self, node: Node) {
+        let chain = self.remove_chain(node).expect("a stone on the node");
+        self.set_state(node, State::Empty);
+        for other in &mut self.chains {
+            if other.player != chain.player && other.filled_libs.contains(&node) {
+                let other = Arc::make_mut(other);
+                other.filled_libs.remove(&node);
+                other.libs.insert(node);
+            }
+        }
+        for &stone in &chain.verts {
+            if stone != node {
+                self.add_chain(chain.player, stone);
+            }
+        }
+        self.regions.stones_removed(&self.matrix, &[node]);
     }
 
-    /// Removes all enemy Chains from the board that have 0 liberties.
-    fn remove_captures(&mut self, capturer: Player) {
-        let empty_nodes = self.remove_dead_chains(capturer.enemy());
-        for n in empty_nodes {
-            self.matrix[n] = State::Empty;
+    /// Replaces the stones on the board with the given ones without capturing any, so chains
+    /// without liberties are left on the board.
+    ///
+    /// # Errors
+    ///
+    /// A vertex is off the board or given twice.
+    pub(crate) fn set_stones(&mut self, stones: &[(Player, Vertex)]) -> Result<(), String> {
+        let mut matrix = Matrix::with_size(self.size());
+        for &(player, vertex) in stones {
+            let node = matrix
+                .node_from_vertex(vertex)
+                .ok_or_else(|| format!("{vertex} is not on the board"))?;
+            if matrix[node] != State::Empty {
+                return Err(format!("{vertex} is set up twice"));
+            }
+            matrix[node] = State::from(player);
+        }
+        self.matrix = matrix;
+        self.hash = self.full_hash();
+        self.rebuild_chains();
+        self.regions.rebuild(&self.matrix);
+        Ok(())
+    }
+
+    /// Puts the point in the state, keeping the hash up to date.
+    fn set_state(&mut self, node: Node, state: State) {
+        let vertex = self.matrix.vertex_from_node(node);
+        for old_or_new in [self.matrix[node], state] {
+            match old_or_new {
+                State::Black => self.hash ^= zobrist_key(vertex, Player::Black),
+                State::White => self.hash ^= zobrist_key(vertex, Player::White),
+                State::Empty => {}
+            }
         }
+        self.matrix[node] = state;
     }
 
-    fn push_letters(&self, board: &mut String) {
-        board.push_str("  ");
-        for letter in BOARD_LETTERS.chars().take(self.matrix.size()) {
-            board.push(' ');
-            board.push(letter);
+    /// Rebuilds the chains from the stones, joining each to the chains next to it.
+    fn rebuild_chains(&mut self) {
+        self.chains.clear();
+        for player in [Player::Black, Player::White] {
+            for vertex in self.stones(player) {
+                let node = self.matrix.node_from_vertex(vertex).expect("valid vertex");
+                self.add_chain(player, node);
+            }
         }
-        board.push_str("   ");
+    }
+
+    /// Removes all enemy Chains from the board that have 0 liberties and returns their nodes.
+    fn remove_captures(&mut self, capturer: Player) -> Vec<Node> {
+        let empty_nodes = self.remove_dead_chains(capturer.enemy());
+        for &n in &empty_nodes {
+            self.set_state(n, State::Empty);
+        }
+        empty_nodes
     }
 
     /// Returns the current size of the board.
@@ -238,36 +999,454 @@ impl Board {
         })
     }
 
+    /// The score according to area rules (stones plus empty points surrounded only by that
+    /// player's stones, Black's minus White's).
+    #[must_use]
+    pub fn score_area(&self) -> i32 {
+        self.score_ancient() + self.regions.territory()
+    }
+
+    /// Returns the player whose stones alone surround the empty region holding the vertex, as
+    /// counted by `score_area`, or None if the vertex isn't empty, the region touches both
+    /// players' stones or neither, or the vertex is off the board. This is kept up to date as
+    /// stones are placed, so it's cheap to call after every move.
+    #[must_use]
+    pub fn territory_owner(&self, vertex: Vertex) -> Option<Player> {
+        let node = self.matrix.node_from_vertex(vertex)?;
+        match self.regions.owner(node) {
+            State::Black => Some(Player::Black),
+            State::White => Some(Player::White),
+            State::Empty => None,
+        }
+    }
+
+    /// The score according to area rules after removing the given dead stones from the board.
+    #[must_use]
+    pub fn score_area_removing(&self, dead_stones: &[Vertex]) -> i32 {
+        let mut matrix = self.matrix.clone();
+        for &vertex in dead_stones {
+            if let Some(node) = matrix.node_from_vertex(vertex) {
+                matrix[node] = State::Empty;
+            }
+        }
+        score_area(&matrix)
+    }
+
+    /// The score according to territory rules after removing the given dead stones from the
+    /// board: the empty points each player surrounds plus the dead stones they take as prisoners.
+    /// Prisoners captured during the game are not included, and neither are the eyes of stones in
+    /// seki.
+    #[must_use]
+    pub fn score_territory_removing(&self, dead_stones: &[Vertex]) -> i32 {
+        let seki = self.seki_stones();
+        let mut matrix = self.matrix.clone();
+        let mut prisoners = 0;
+        for &vertex in dead_stones {
+            if let Some(node) = matrix.node_from_vertex(vertex) {
+                match matrix[node] {
+                    State::Empty => {}
+                    State::Black => prisoners -= 1,
+                    State::White => prisoners += 1,
+                }
+                matrix[node] = State::Empty;
+            }
+        }
+        territory(&matrix, &seki) + prisoners
+    }
+
+    /// Returns the stones that are dead under Japanese rules because their only eye space is a
+    /// bent four in the corner.
+    ///
+    /// The defender can only live in this shape by winning a ko that the attacker is free to start
+    /// after removing all ko threats, so the Japanese rules rule the chains dead as they stand.
+    #[must_use]
+    pub fn bent_four_in_corner(&self) -> Vec<Vertex> {
+        let max = self.size() - 1;
+        let corners = [(false, false), (true, false), (false, true), (true, true)];
+        // The eye space in the bottom left corner, with the bend on the corner point.
+        let shapes = [
+            [(0, 0), (1, 0), (2, 0), (0, 1)],
+            [(0, 0), (0, 1), (0, 2), (1, 0)],
+        ];
+
+        let mut dead = Vec::new();
+        for (flip_x, flip_y) in corners {
+            for shape in shapes {
+                let eye_space: Option<HashSet<Node>> = shape
+                    .iter()
+                    .map(|&(x, y)| {
+                        let x = if flip_x { max.checked_sub(x)? } else { x };
+                        let y = if flip_y { max.checked_sub(y)? } else { y };
+                        self.matrix.node_from_vertex(Vertex { x, y })
+                    })
+                    .collect();
+                if let Some(eye_space) = eye_space.filter(|nodes| nodes.len() == shape.len()) {
+                    for chain in self.bent_four_defenders(&eye_space) {
+                        dead.extend(
+                            chain
+                                .verts
+                                .iter()
+                                .map(|&node| self.matrix.vertex_from_node(node)),
+                        );
+                    }
+                }
+            }
+        }
+        dead
+    }
+
+    /// Returns the chains enclosing the eye space if it is their only source of liberties. The
+    /// eye space must hold at least one empty point and no stones of the enclosing color.
+    fn bent_four_defenders(&self, eye_space: &HashSet<Node>) -> Vec<&Chain> {
+        let mut defender = State::Empty;
+        let mut has_empty = false;
+        let mut chains: Vec<&Chain> = Vec::new();
+
+        for &node in eye_space {
+            has_empty |= self.matrix[node] == State::Empty;
+            for &adjacent in self.matrix.adjacencies(node) {
+                if eye_space.contains(&adjacent) {
+                    continue;
+                }
+                let state = self.matrix[adjacent];
+                if state == State::Empty || (defender != State::Empty && state != defender) {
+                    return Vec::new();
+                }
+                defender = state;
+                if !chains.iter().any(|chain| chain.verts.contains(&adjacent)) {
+                    if let Some(chain) = self.chains.iter().find(|c| c.verts.contains(&adjacent)) {
+                        chains.push(chain);
+                    }
+                }
+            }
+        }
+
+        let inside_ok = eye_space.iter().all(|&node| self.matrix[node] != defender);
+        let no_outside_libs = chains
+            .iter()
+            .all(|chain| chain.libs.iter().all(|lib| eye_space.contains(lib)));
+        if has_empty && defender != State::Empty && inside_ok && no_outside_libs {
+            chains
+        } else {
+            Vec::new()
+        }
+    }
+
     /// Returns a human readable ASCII representation of the board.
     #[must_use]
     pub fn to_ascii(&self) -> String {
-        let size = self.size();
-        let star_points = self.star_points();
-        let mut board = String::new();
-        self.push_letters(&mut board);
-        board.push_str("\r\n");
-        for y in (0..size).rev() {
-            board.push_str(&format!("{:02}", y + 1));
-            for x in 0..size {
-                board.push(' ');
-                let vertex = Vertex { x, y };
-                let c = match self.matrix[&vertex] {
-                    State::Empty => {
-                        if star_points.contains(&vertex) {
-                            '+'
-                        } else {
-                            '.'
-                        }
-                    }
-                    State::Black => 'x',
-                    State::White => 'o',
+        render::ascii(self, &BoardOverlay::default())
+    }
+
+    /// Reads a board from the output of a `showboard` command. Besides our own `to_ascii` format,
+    /// this accepts the common layouts where each row starts with its number followed by one
+    /// character per point: `.`, `+`, `,` or `-` for empty points, `X`, `x`, `#` or `@` for Black
+    /// and `O` or `o` for White. Markers such as `|`, `(` and `)` between points and any text
+    /// after the last point of a row are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a row is missing, repeated or too short, or the position has a chain without
+    /// liberties.
+    pub fn from_ascii(text: &str) -> Result<Self, String> {
+        let mut rows = Vec::new();
+        for line in text.lines() {
+            let line = line.trim_start();
+            let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits == 0 {
+                continue;
+            }
+            let number = line[..digits]
+                .parse::<usize>()
+                .map_err(|_| format!("invalid row number in {line:?}"))?;
+            rows.push((number, &line[digits..]));
+        }
+
+        let size = rows.iter().map(|&(number, _)| number).max().unwrap_or(0);
+        let mut board = Board::with_size(size)?;
+        let mut seen = vec![false; size];
+        let mut stones = 0;
+        for (number, points) in rows {
+            if number == 0 || seen[number - 1] {
+                return Err(format!("row {number} is repeated or out of range"));
+            }
+            seen[number - 1] = true;
+
+            let mut x = 0;
+            for c in points.chars() {
+                if x == size {
+                    break;
+                }
+                let player = match c {
+                    '.' | '+' | ',' | '-' => None,
+                    'X' | 'x' | '#' | '@' => Some(Player::Black),
+                    'O' | 'o' => Some(Player::White),
+                    '|' | '(' | ')' | '[' | ']' | '<' | '>' => continue,
+                    c if c.is_whitespace() => continue,
+                    c => return Err(format!("unexpected character {c:?} in row {number}")),
                 };
-                board.push(c);
+                let vertex = Vertex { x, y: number - 1 };
+                if let Some(player) = player {
+                    board.place_stone(player, vertex);
+                    stones += 1;
+                }
+                x += 1;
+            }
+            if x < size {
+                return Err(format!("row {number} is too short"));
             }
-            board.push_str(&format!(" {:02}\r\n", y + 1));
         }
-        self.push_letters(&mut board);
-        board
+        if let Some(missing) = seen.iter().position(|&seen| !seen) {
+            return Err(format!("row {} is missing", missing + 1));
+        }
+
+        // Placing a stone removes any chains left without liberties.
+        if board.stones(Player::Black).len() + board.stones(Player::White).len() != stones {
+            return Err("a chain has no liberties".to_owned());
+        }
+        Ok(board)
+    }
+
+    // Statuses //
+
+    /// Estimates the status of every stone, assuming the game has been played out. This is a
+    /// heuristic and unsettled or unusual shapes may be misjudged.
+    ///
+    /// Two chains of different colors are in seki as `Board::seki_groups` describes. The rest of
+    /// the stones are divided into groups by the points each player can reach without crossing a
+    /// living enemy stone. A group with two eyes lives, an eye being an empty region bordered by
+    /// the group alone, or two eyes if it has room for them. A group without two eyes is dead if
+    /// every enemy group next to it can reach more points. Dead stones count as empty points from
+    /// then on, which can give the enemy its eyes, so this is repeated until no more stones die.
+    #[must_use]
+    pub fn statuses(&self) -> HashMap<Vertex, Status> {
+        let seki = self.seki_stones();
+        let mut dead = HashSet::new();
+        loop {
+            let mut died = false;
+            for player in [Player::Black, Player::White] {
+                let doomed = self.dead_groups(player, &dead, &seki);
+                died |= !doomed.is_empty();
+                dead.extend(doomed);
+            }
+            if !died {
+                break;
+            }
+        }
+
+        let mut statuses = HashMap::new();
+        for node in self.matrix.nodes() {
+            if self.matrix[node] == State::Empty {
+                continue;
+            }
+            let status = if seki.contains(&node) {
+                Status::Seki
+            } else if dead.contains(&node) {
+                Status::Dead
+            } else {
+                Status::Alive
+            };
+            statuses.insert(self.matrix.vertex_from_node(node), status);
+        }
+        statuses
+    }
+
+    /// Returns the groups of stones living in seki, each sorted. A group is made of the chains
+    /// of both players that share liberties neither can fill: chains with just two liberties that
+    /// share some with an enemy chain with two liberties, where whoever fills a shared liberty
+    /// puts themselves in atari and any other liberty is an eye the enemy can't play in. With a
+    /// liberty the enemy can fill the chains are in a capturing race instead. Eyes of stones in
+    /// seki aren't counted as territory.
+    #[must_use]
+    pub fn seki_groups(&self) -> Vec<Vec<Vertex>> {
+        let seki = self.seki_chains();
+        let mut grouped = vec![false; seki.len()];
+        let mut groups = Vec::new();
+        for start in 0..seki.len() {
+            if grouped[start] {
+                continue;
+            }
+            grouped[start] = true;
+            let mut members = vec![start];
+            let mut next = 0;
+            while next < members.len() {
+                let chain = seki[members[next]];
+                for (index, other) in seki.iter().enumerate() {
+                    if !grouped[index] && !other.libs.is_disjoint(&chain.libs) {
+                        grouped[index] = true;
+                        members.push(index);
+                    }
+                }
+                next += 1;
+            }
+            let mut group: Vec<Vertex> = members
+                .iter()
+                .flat_map(|&member| &seki[member].verts)
+                .map(|&node| self.matrix.vertex_from_node(node))
+                .collect();
+            group.sort_by_key(|vertex| (vertex.x, vertex.y));
+            groups.push(group);
+        }
+        groups
+    }
+
+    /// Returns the chains in seki.
+    fn seki_chains(&self) -> Vec<&Chain> {
+        self.chains
+            .iter()
+            .filter(|chain| self.chains.iter().any(|other| self.is_seki(chain, other)))
+            .map(Arc::as_ref)
+            .collect()
+    }
+
+    /// Whether the chains of different colors with two liberties each hold each other in seki:
+    /// filling a shared liberty puts either player in atari, and every other liberty of each chain
+    /// is an eye the enemy can't play in.
+    fn is_seki(&self, chain: &Chain, other: &Chain) -> bool {
+        if chain.player == other.player
+            || chain.libs.len() != 2
+            || other.libs.len() != 2
+            || chain.libs.is_disjoint(&other.libs)
+        {
+            return false;
+        }
+        let fills_to = |player: Player, node: Node, most: usize| {
+            self.liberties_after(player, node)
+                .map_or(false, |liberties| liberties <= most)
+        };
+        [(chain, other), (other, chain)].iter().all(|(own, enemy)| {
+            own.libs.iter().all(|&node| {
+                if enemy.libs.contains(&node) {
+                    fills_to(own.player, node, 1) && fills_to(enemy.player, node, 1)
+                } else {
+                    fills_to(enemy.player, node, 0)
+                }
+            })
+        })
+    }
+
+    /// Returns the liberties the player's chain would have after playing on the empty node, or
+    /// None if the stone would capture.
+    fn liberties_after(&self, player: Player, node: Node) -> Option<usize> {
+        let mut libs: HashSet<Node> = self
+            .matrix
+            .adjacencies(node)
+            .iter()
+            .copied()
+            .filter(|&adjacent| self.matrix[adjacent] == State::Empty)
+            .collect();
+        for chain in self
+            .chains
+            .iter()
+            .filter(|chain| chain.libs.contains(&node))
+        {
+            if chain.player == player {
+                libs.extend(chain.libs.iter().copied().filter(|&lib| lib != node));
+            } else if chain.libs.len() == 1 {
+                return None;
+            }
+        }
+        Some(libs.len())
+    }
+
+    /// Returns the stones of the chains in seki.
+    fn seki_stones(&self) -> HashSet<Node> {
+        self.seki_chains()
+            .into_iter()
+            .flat_map(|chain| chain.verts.iter().copied())
+            .collect()
+    }
+
+    /// Returns the stones of the player's groups that die, given the stones already dead.
+    fn dead_groups(
+        &self,
+        player: Player,
+        dead: &HashSet<Node>,
+        seki: &HashSet<Node>,
+    ) -> HashSet<Node> {
+        let alive = |node: Node, player: Player| {
+            self.matrix[node] == State::from(player) && !dead.contains(&node)
+        };
+        let (domains, domain_of) = self.components(|node| !alive(node, player.enemy()));
+        let (enemy_domains, enemy_domain_of) = self.components(|node| !alive(node, player));
+        // Eyes are regions of empty points and dead stones.
+        let (eyes, _) =
+            self.components(|node| self.matrix[node] == State::Empty || dead.contains(&node));
+
+        let mut eye_count = vec![0; domains.len()];
+        for eye in &eyes {
+            let bordered_by_player = eye.iter().all(|&node| {
+                self.matrix
+                    .adjacencies(node)
+                    .iter()
+                    .all(|&adjacent| !alive(adjacent, player.enemy()))
+            });
+            let borders_player = eye.iter().any(|&node| {
+                self.matrix
+                    .adjacencies(node)
+                    .iter()
+                    .any(|&adjacent| alive(adjacent, player))
+            });
+            if bordered_by_player && borders_player {
+                eye_count[domain_of[&eye[0]]] += if eye.len() >= TWO_EYE_SPACE { 2 } else { 1 };
+            }
+        }
+
+        let mut doomed = HashSet::new();
+        for (index, domain) in domains.iter().enumerate() {
+            let stones: Vec<Node> = domain
+                .iter()
+                .copied()
+                .filter(|&node| alive(node, player))
+                .collect();
+            if stones.is_empty()
+                || eye_count[index] >= 2
+                || stones.iter().any(|node| seki.contains(node))
+            {
+                continue;
+            }
+            let enemies: HashSet<usize> = domain
+                .iter()
+                .flat_map(|&node| self.matrix.adjacencies(node).iter().copied())
+                .filter(|&adjacent| alive(adjacent, player.enemy()))
+                .map(|adjacent| enemy_domain_of[&adjacent])
+                .collect();
+            if !enemies.is_empty()
+                && enemies
+                    .iter()
+                    .all(|&enemy| enemy_domains[enemy].len() > domain.len())
+            {
+                doomed.extend(stones);
+            }
+        }
+        doomed
+    }
+
+    /// Returns the connected regions of the nodes that pass the test and the index of the region
+    /// each of those nodes is in.
+    fn components<F: Fn(Node) -> bool>(&self, test: F) -> (Vec<Vec<Node>>, HashMap<Node, usize>) {
+        let mut components = Vec::new();
+        let mut component_of = HashMap::new();
+        for start in self.matrix.nodes() {
+            if !test(start) || component_of.contains_key(&start) {
+                continue;
+            }
+            let index = components.len();
+            let mut component = vec![start];
+            component_of.insert(start, index);
+            let mut next = 0;
+            while next < component.len() {
+                for &adjacent in self.matrix.adjacencies(component[next]) {
+                    if test(adjacent) && !component_of.contains_key(&adjacent) {
+                        component_of.insert(adjacent, index);
+                        component.push(adjacent);
+                    }
+                }
+                next += 1;
+            }
+            components.push(component);
+        }
+        (components, component_of)
     }
 
     // Chains //
@@ -280,7 +1459,7 @@ impl Board {
         let mut adjacent_chains = Vec::new();
 
         verts.insert(node);
-        for node in self.matrix.adjacencies(node) {
+        for &node in self.matrix.adjacencies(node) {
             let state = self.matrix[node];
             if state == State::Empty {
                 libs.insert(node);
@@ -300,14 +1479,14 @@ impl Board {
 
         for node in adjacent_chains {
             if let Some(old_chain) = self.remove_chain(node) {
-                chain.eat(old_chain);
+                chain.eat(&old_chain);
             }
         }
-        self.chains.push(chain);
+        self.chains.push(Arc::new(chain));
     }
 
     /// Removes the chain that contains node from the set of chains.
-    fn remove_chain(&mut self, node: Node) -> Option<Chain> {
+    fn remove_chain(&mut self, node: Node) -> Option<Arc<Chain>> {
         let mut idx = None;
         for (i, chain) in self.chains.iter().enumerate() {
             if chain.verts.contains(&node) {
@@ -335,7 +1514,9 @@ impl Board {
             .retain(|chain| chain.player != player || !chain.libs.is_empty());
         for node in &empty_nodes {
             for chain in &mut self.chains {
-                if chain.player != player && chain.filled_libs.remove(node) {
+                if chain.player != player && chain.filled_libs.contains(node) {
+                    let chain = Arc::make_mut(chain);
+                    chain.filled_libs.remove(node);
                     chain.libs.insert(*node);
                 }
             }
@@ -344,6 +1525,53 @@ impl Board {
     }
 }
 
+/// The score according to area rules of a matrix of board states.
+fn score_area(matrix: &Matrix<State>) -> i32 {
+    let stones = matrix.values().fold(0, |acc, &state| match state {
+        State::Empty => acc,
+        State::Black => acc + 1,
+        State::White => acc - 1,
+    });
+    stones + territory(matrix, &HashSet::new())
+}
+
+/// The empty points surrounded by Black less those surrounded by White, leaving out the regions
+/// next to the neutral stones.
+fn territory(matrix: &Matrix<State>, neutral: &HashSet<Node>) -> i32 {
+    matrix
+        .get_regions(|&state| state == State::Empty)
+        .iter()
+        .fold(0, |acc, region| {
+            let size = i32::try_from(region.nodes().len()).expect("region too large");
+            let borders_neutral = region
+                .adjacencies()
+                .any(|node| neutral.contains(node) && matrix[*node] != State::Empty);
+            if borders_neutral {
+                return acc;
+            }
+            match region_owner(matrix, region) {
+                State::Empty => acc,
+                State::Black => acc + size,
+                State::White => acc - size,
+            }
+        })
+}
+
+/// Returns the color of the stones bordering a region of empty points, or Empty if it borders
+/// both colors or none.
+fn region_owner(matrix: &Matrix<State>, region: &Region) -> State {
+    let mut owner = State::Empty;
+    for &node in region.adjacencies() {
+        match (owner, matrix[node]) {
+            (_, State::Empty) => {}
+            (State::Empty, state) => owner = state,
+            (owner, state) if owner != state => return State::Empty,
+            _ => {}
+        }
+    }
+    owner
+}
+
 impl fmt::Debug for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}\r\nChains = {:?}", self, self.chains)
@@ -356,8 +1584,108 @@ impl fmt::Display for Board {
     }
 }
 
+/// A read-only view of a chain of stones on a board, from `Board::chain_at` or `Board::chains`.
+#[derive(Clone, Copy)]
+pub struct ChainView<'a> {
+    board: &'a Board,
+    chain: &'a Chain,
+}
+
+impl ChainView<'_> {
+    /// Returns the player whose stones make up the chain.
+    #[must_use]
+    pub fn player(&self) -> Player {
+        self.chain.player
+    }
+
+    /// Returns the number of stones in the chain.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.chain.verts.len()
+    }
+
+    /// Always false, as a chain has at least one stone.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.chain.verts.is_empty()
+    }
+
+    /// Returns the stones of the chain, sorted.
+    #[must_use]
+    pub fn stones(&self) -> Vec<Vertex> {
+        self.vertices(&self.chain.verts)
+    }
+
+    /// Returns the number of liberties of the chain.
+    #[must_use]
+    pub fn liberty_count(&self) -> usize {
+        self.chain.libs.len()
+    }
+
+    /// Returns the liberties of the chain, sorted.
+    #[must_use]
+    pub fn liberties(&self) -> Vec<Vertex> {
+        self.vertices(&self.chain.libs)
+    }
+
+    /// Whether the chain holds the stone on the vertex.
+    #[must_use]
+    pub fn contains(&self, vertex: Vertex) -> bool {
+        self.board
+            .matrix
+            .node_from_vertex(vertex)
+            .map_or(false, |node| self.chain.verts.contains(&node))
+    }
+
+    fn vertices(&self, nodes: &HashSet<Node>) -> Vec<Vertex> {
+        let mut vertices: Vec<_> = nodes
+            .iter()
+            .map(|&node| self.board.matrix.vertex_from_node(node))
+            .collect();
+        vertices.sort_by_key(|vertex| (vertex.x, vertex.y));
+        vertices
+    }
+}
+
+impl fmt::Debug for ChainView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChainView")
+            .field("player", &self.player())
+            .field("stones", &self.stones())
+            .field("liberties", &self.liberties())
+            .finish()
+    }
+}
+
+/// The stones that differ between two boards, from `Board::diff`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BoardDiff {
+    /// The stones on empty points of the first board.
+    pub added: Vec<(Player, Vertex)>,
+    /// The stones of the first board on empty points of the second.
+    pub removed: Vec<(Player, Vertex)>,
+    /// The points with a stone of one color on the first board and the other on the second, with
+    /// the color on the second.
+    pub changed: Vec<(Vertex, Player)>,
+}
+
+impl BoardDiff {
+    /// Whether the boards are the same.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Returns the number of points that differ.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.added.len() + self.removed.len() + self.changed.len()
+    }
+}
+
 /// Includes a player and a location on the board, or None for pass.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Move {
     /// The player taking the move.
     pub player: Player,
@@ -367,6 +1695,7 @@ pub struct Move {
 
 /// The possible board states.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum State {
     /// A stone from second player.
     White = -1,
@@ -386,6 +1715,27 @@ impl From<Player> for State {
     }
 }
 
+/// What playing a stone would do to the board, from `Board::preview`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Placement {
+    /// The `Board::zobrist_hash` of the board after the move.
+    pub(crate) hash: u64,
+    /// The number of the player's stones the move would remove for having no liberties, counting
+    /// the one played, or 0 if it isn't suicide.
+    pub(crate) suicided: usize,
+}
+
+/// Returns a random looking key for a stone of the player on the vertex, mixing them with
+/// `SplitMix64` so that no table of keys needs to be kept.
+fn zobrist_key(vertex: Vertex, player: Player) -> u64 {
+    let color = u64::from(player == Player::White);
+    let seed = (vertex.x as u64) << 33 | (vertex.y as u64) << 1 | color;
+    let mut key = seed.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    key = (key ^ (key >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    key = (key ^ (key >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    key ^ (key >> 31)
+}
+
 /// A connected set of stones of the same color.
 #[derive(Clone, Debug)]
 struct Chain {
@@ -400,10 +1750,550 @@ struct Chain {
 }
 
 impl Chain {
-    /// Update a chain with the consumed union of another.
-    fn eat(&mut self, chain: Chain) {
-        self.verts.extend(chain.verts);
-        self.libs.extend(chain.libs);
-        self.filled_libs.extend(chain.filled_libs);
+    /// Update a chain with the union of another.
+    fn eat(&mut self, chain: &Chain) {
+        self.verts.extend(&chain.verts);
+        self.libs.extend(&chain.libs);
+        self.filled_libs.extend(&chain.filled_libs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stones_with_status(board: &Board, status: Status) -> Vec<String> {
+        let mut stones: Vec<String> = board
+            .statuses()
+            .into_iter()
+            .filter(|&(_, s)| s == status)
+            .map(|(vertex, _)| vertex.to_string())
+            .collect();
+        stones.sort();
+        stones
+    }
+
+    #[test]
+    fn statuses() {
+        let board = Board::from_ascii(
+            "5 . x o . .
+             4 . x o . .
+             3 . x o x .
+             2 . x o . .
+             1 . x o . .",
+        )
+        .unwrap();
+        assert_eq!(stones_with_status(&board, Status::Dead), ["D3"]);
+        assert_eq!(stones_with_status(&board, Status::Alive).len(), 10);
+
+        let board = Board::from_ascii(
+            "7 . x . o . o x
+             6 x x x o o o x
+             5 o o o x x x x
+             4 . . o x . . .
+             3 . . o x . . .
+             2 . . o x . . .
+             1 . . o x . . .",
+        )
+        .unwrap();
+        assert_eq!(
+            stones_with_status(&board, Status::Seki),
+            ["A6", "B6", "B7", "C6", "D6", "D7", "E6", "F6", "F7"]
+        );
+        assert!(stones_with_status(&board, Status::Dead).is_empty());
+        let groups = board.seki_groups();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].len(), 9);
+        // Only the outer territory counts.
+        assert_eq!(board.score_territory_removing(&[]), 12 - 8);
+        assert_eq!(board.score_area(), 14 + 13 - (12 + 9));
+    }
+
+    #[test]
+    fn capturing_race_is_not_seki() {
+        // Black and White have two liberties each and share C1, but White's other liberty is an
+        // eye and Black's isn't, so White wins whoever moves first.
+        let board = Board::from_ascii(
+            "6 . . . . . .
+             5 . . . . . .
+             4 o o o . . .
+             3 x x x o . .
+             2 o o x . . .
+             1 . o . o . .",
+        )
+        .unwrap();
+        let (black, white) = (Vertex { x: 0, y: 2 }, Vertex { x: 0, y: 1 });
+        assert_eq!(board.liberties(black), Some(2));
+        assert_eq!(board.liberties(white), Some(2));
+        for to_move in [Player::Black, Player::White] {
+            let race = crate::game::semeai::solve(&board, black, white, to_move).unwrap();
+            assert_eq!(
+                race.outcome,
+                crate::game::semeai::Outcome::Won(Player::White)
+            );
+        }
+        assert!(board.seki_groups().is_empty());
+        assert!(stones_with_status(&board, Status::Seki).is_empty());
+        // The eye at A1 is White's territory.
+        assert!(board.score_territory_removing(&[]) < 0);
+    }
+
+    #[test]
+    fn zobrist_hash() {
+        let mut board = Board::from_ascii(
+            "5 . . . . .
+             4 . . . . .
+             3 . x o . .
+             2 x o . o .
+             1 . x o . .",
+        )
+        .unwrap();
+        let before = board.clone();
+        let c2 = Vertex { x: 2, y: 1 };
+        let placement = board.preview(Player::Black, c2).unwrap();
+        let captured = board.place_stone(Player::Black, c2);
+        assert_eq!(captured.len(), 1);
+        assert_eq!(board.zobrist_hash(), placement.hash);
+        assert_eq!(board.zobrist_hash(), board.full_hash());
+        board.unplace_stone(Player::Black, c2, &captured, &[]);
+        assert_eq!(board.zobrist_hash(), before.zobrist_hash());
+        board.clear();
+        assert_eq!(board.zobrist_hash(), 0);
+    }
+
+    #[test]
+    fn eyes() {
+        let mut board = Board::from_ascii(
+            "7 . x . . . . .
+             6 x x . . . . .
+             5 . . . . . . .
+             4 . . x x . . .
+             3 . . x . x o x
+             2 . . o x x x .
+             1 . . . . . x x",
+        )
+        .unwrap();
+        let d3 = Vertex { x: 3, y: 2 };
+        // The corner eye and the eye with one White diagonal are real, but the edge eye isn't.
+        assert_eq!(board.real_eyes(Player::Black), [d3, Vertex { x: 0, y: 6 }]);
+        assert!(!board.is_eye(Vertex { x: 6, y: 1 }, Player::Black));
+        assert!(!board.is_eye(Vertex { x: 2, y: 3 }, Player::Black));
+        assert!(board.real_eyes(Player::White).is_empty());
+
+        board.place_stone(Player::White, Vertex { x: 4, y: 3 });
+        assert!(!board.is_eye(d3, Player::Black));
+    }
+
+    #[test]
+    fn atari() {
+        let board = Board::from_ascii(
+            "5 . . . . .
+             4 . . . . .
+             3 o o . . .
+             2 o x o . .
+             1 x . x o .",
+        )
+        .unwrap();
+        let atari = board.chains_in_atari(Player::Black);
+        assert_eq!(atari.len(), 3);
+        assert!(atari.contains(&vec![Vertex { x: 1, y: 1 }]));
+        assert!(board.chains_in_atari(Player::White).is_empty());
+
+        // Black connecting at B1 is left with no liberties, but White capturing there is safe.
+        assert!(board.is_self_atari(Player::Black, Vertex { x: 1, y: 0 }));
+        assert!(!board.is_self_atari(Player::White, Vertex { x: 1, y: 0 }));
+        assert!(board.is_self_atari(Player::Black, Vertex { x: 4, y: 0 }));
+        assert!(!board.is_self_atari(Player::Black, Vertex { x: 3, y: 3 }));
+        assert!(!board.is_self_atari(Player::Black, Vertex { x: 1, y: 1 }));
+    }
+
+    #[test]
+    fn neighbors() {
+        let board = Board::with_size(9).unwrap();
+        let sorted = |mut vertices: Vec<Vertex>| {
+            vertices.sort_by_key(|vertex| (vertex.x, vertex.y));
+            vertices
+        };
+        let parse = |vertices: &[&str]| -> Vec<Vertex> {
+            vertices
+                .iter()
+                .map(|vertex| vertex.parse().unwrap())
+                .collect()
+        };
+        let center = "E5".parse().unwrap();
+        assert_eq!(
+            sorted(board.neighbors(center)),
+            parse(&["D5", "E4", "E6", "F5"])
+        );
+        assert_eq!(
+            sorted(board.diagonals(center)),
+            parse(&["D4", "D6", "F4", "F6"])
+        );
+        let corner = "A9".parse().unwrap();
+        assert_eq!(sorted(board.neighbors(corner)), parse(&["A8", "B9"]));
+        assert_eq!(board.diagonals(corner), parse(&["B8"]));
+        assert!(board.neighbors("K10".parse().unwrap()).is_empty());
+        assert!(board.diagonals("K10".parse().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn benson() {
+        let board = Board::from_ascii(
+            "7 . X . X O . .
+             6 X X X X O . .
+             5 . O . X O O O
+             4 X X X X O . .
+             3 O O O O O . .
+             2 . . . . . . .
+             1 . . . . . . .",
+        )
+        .unwrap();
+        let alive = board.pass_alive_chains();
+        assert_eq!(alive.len(), 1);
+        let stone = board.vertex_of(alive[0]).unwrap();
+        assert_eq!(board.stone(stone), Some(Player::Black));
+        assert_eq!(board.node_of(stone), Some(alive[0]));
+        let corner = "G7".parse().unwrap();
+        let small = Board::with_size(5).unwrap();
+        assert_eq!(small.node_of(corner), None);
+        assert_eq!(small.vertex_of(board.node_of(corner).unwrap()), None);
+        for vertex in ["A7", "C7", "A5", "B5", "D4"] {
+            assert_eq!(
+                board.unconditional_owner(vertex.parse().unwrap()),
+                Some(Player::Black),
+                "{vertex}"
+            );
+        }
+        for vertex in ["E5", "F6", "A1"] {
+            assert_eq!(
+                board.unconditional_owner(vertex.parse().unwrap()),
+                None,
+                "{vertex}"
+            );
+        }
+    }
+
+    #[test]
+    fn free_handicaps() {
+        let board = Board::with_size(19).unwrap();
+        for stones in [2, 6, 9] {
+            assert_eq!(board.free_handicaps(stones), board.fixed_handicaps(stones));
+        }
+        for stones in [11, 15, 30] {
+            let handicaps = board.free_handicaps(stones);
+            assert_eq!(handicaps.len(), stones);
+            assert_eq!(handicaps[..9], board.fixed_handicaps(9));
+            let distinct: HashSet<_> = handicaps.iter().collect();
+            assert_eq!(distinct.len(), stones);
+            assert!(handicaps
+                .iter()
+                .all(|vertex| (2..17).contains(&vertex.x) && (2..17).contains(&vertex.y)));
+        }
+        assert_eq!(board.free_handicaps(10)[9], Vertex { x: 6, y: 6 });
+
+        let small = Board::with_size(5).unwrap();
+        let vertices = |vertices: &[(usize, usize)]| -> Vec<Vertex> {
+            vertices.iter().map(|&(x, y)| Vertex { x, y }).collect()
+        };
+        assert_eq!(small.free_handicaps(3), vertices(&[(1, 1), (3, 3), (3, 1)]));
+        assert_eq!(small.free_handicaps(24).len(), 24);
+        assert_eq!(small.free_handicaps(30).len(), 25);
+    }
+
+    #[test]
+    fn is_reachable() {
+        let mut board = Board::with_size(5).unwrap();
+        board.place_stone(Player::White, Vertex { x: 0, y: 0 });
+        assert_eq!(board.is_reachable(), Ok(()));
+        board
+            .set_stones(&[
+                (Player::White, Vertex { x: 0, y: 0 }),
+                (Player::Black, Vertex { x: 1, y: 0 }),
+                (Player::Black, Vertex { x: 0, y: 1 }),
+                (Player::White, Vertex { x: 4, y: 4 }),
+                (Player::White, Vertex { x: 3, y: 4 }),
+                (Player::Black, Vertex { x: 3, y: 3 }),
+                (Player::Black, Vertex { x: 2, y: 4 }),
+                (Player::Black, Vertex { x: 4, y: 3 }),
+            ])
+            .unwrap();
+        assert_eq!(
+            board.is_reachable(),
+            Err(
+                "white chain at A1 has no liberties, white chain at D5 has no liberties".to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn safeties() {
+        let board = Board::from_ascii(
+            "7 . X . X O . .
+             6 X X X X O . .
+             5 . O . X O O O
+             4 X X X X O . O
+             3 O O O O O . X
+             2 . . . . . O .
+             1 . . . . . . .",
+        )
+        .unwrap();
+        let safeties = board.safeties();
+        assert_eq!(safeties.len(), 26);
+        let safety = |vertex: &str| safeties.get(&vertex.parse().unwrap()).copied();
+        assert_eq!(safety("B7"), Some(Safety::Alive));
+        assert_eq!(safety("B5"), Some(Safety::Dead));
+        assert_eq!(safety("A1"), None);
+        assert_eq!(safety("G3"), Some(Safety::ProbablyDead));
+        assert_eq!(Safety::ProbablyAlive.to_string(), "probably_alive");
+    }
+
+    #[test]
+    fn from_ascii() {
+        let mut board = Board::with_size(9).unwrap();
+        board.place_stone(Player::Black, Vertex { x: 2, y: 2 });
+        board.place_stone(Player::White, Vertex { x: 3, y: 8 });
+        assert_eq!(Board::from_ascii(&board.to_ascii()), Ok(board.clone()));
+
+        // GNU Go style, with the last move marked and text after the rows.
+        let gnugo = "   A B C D E F G H J
+ 9 . . . O . . . . . 9
+ 8 . . . . . . . . . 8     WHITE (O) has captured 0 stones
+ 7 . . + . . . + . . 7     BLACK (X) has captured 0 stones
+ 6 . . . . . . . . . 6
+ 5 . . . . + . . . . 5
+ 4 . . . . . . . . . 4
+ 3 . .(X). . . + . . 3
+ 2 . . . . . . . . . 2
+ 1 . . . . . . . . . 1
+   A B C D E F G H J";
+        assert_eq!(Board::from_ascii(gnugo), Ok(board));
+
+        assert!(Board::from_ascii("2 .\n1 . .").is_err());
+        assert!(Board::from_ascii("2 X O\n1 O .").is_err());
+        assert_eq!(
+            Board::from_ascii("3 . . .\n1 . . ."),
+            Err("row 2 is missing".to_owned())
+        );
+    }
+
+    #[test]
+    fn large_board() {
+        assert!(Board::with_size(26).is_err());
+        let mut board = Board::with_size(25).unwrap();
+        let corner: Vertex = "Z25".parse().unwrap();
+        assert_eq!(corner, Vertex { x: 24, y: 24 });
+        assert_eq!(corner.to_string(), "Z25");
+        board.place_stone(Player::Black, corner);
+        board.place_stone(Player::White, "Y1".parse().unwrap());
+        assert!(board.to_ascii().contains('Z'));
+        assert_eq!(Board::from_ascii(&board.to_ascii()), Ok(board.clone()));
+        assert_eq!(board.fixed_handicaps(9).len(), 9);
+    }
+
+    #[test]
+    fn chain_view() {
+        let board = Board::from_ascii(
+            "3 . X .
+             2 X X O
+             1 . O .",
+        )
+        .unwrap();
+        let chain = board.chain_at("B2".parse().unwrap()).unwrap();
+        assert_eq!(chain.player(), Player::Black);
+        assert_eq!(chain.len(), 3);
+        assert_eq!(
+            chain.stones(),
+            ["A2", "B2", "B3"].map(|vertex| vertex.parse::<Vertex>().unwrap())
+        );
+        assert_eq!(chain.liberty_count(), 3);
+        assert_eq!(
+            chain.liberties(),
+            board.liberty_vertices("B3".parse().unwrap())
+        );
+        assert_eq!(board.liberties("A2".parse().unwrap()), Some(3));
+        assert_eq!(board.liberties("A1".parse().unwrap()), None);
+        assert!(chain.contains("A2".parse().unwrap()));
+        assert!(!chain.contains("B1".parse().unwrap()));
+        assert!(board.chain_at("A1".parse().unwrap()).is_none());
+        assert!(board.chain_at("Z25".parse().unwrap()).is_none());
+        assert_eq!(board.chains().count(), 3);
+    }
+
+    #[test]
+    fn diff() {
+        let mut board = Board::with_size(5).unwrap();
+        board.place_stone(Player::White, Vertex { x: 0, y: 0 });
+        board.place_stone(Player::White, Vertex { x: 2, y: 2 });
+        let mut after = board.clone();
+        after.place_stone(Player::Black, Vertex { x: 1, y: 0 });
+        after.place_stone(Player::Black, Vertex { x: 0, y: 1 });
+        assert!(after.is_vacant(Vertex { x: 0, y: 0 }));
+        after
+            .set_stones(&[
+                (Player::Black, Vertex { x: 1, y: 0 }),
+                (Player::Black, Vertex { x: 0, y: 1 }),
+                (Player::Black, Vertex { x: 2, y: 2 }),
+            ])
+            .unwrap();
+
+        let diff = board.diff(&after);
+        assert_eq!(
+            diff,
+            BoardDiff {
+                added: vec![
+                    (Player::Black, Vertex { x: 1, y: 0 }),
+                    (Player::Black, Vertex { x: 0, y: 1 })
+                ],
+                removed: vec![(Player::White, Vertex { x: 0, y: 0 })],
+                changed: vec![(Vertex { x: 2, y: 2 }, Player::Black)],
+            }
+        );
+        assert_eq!(diff.len(), 4);
+        assert!(board.diff(&board).is_empty());
+    }
+
+    #[test]
+    fn clones_share_chains() {
+        let mut board = Board::with_size(9).unwrap();
+        board.place_stone(Player::Black, Vertex { x: 0, y: 0 });
+        board.place_stone(Player::White, Vertex { x: 8, y: 8 });
+        let mut branch = board.clone();
+        branch.place_stone(Player::Black, Vertex { x: 1, y: 0 });
+        branch.place_stone(Player::Black, Vertex { x: 0, y: 1 });
+
+        // The white chain is untouched, so it's still shared, while the black one was copied.
+        let chain_at = |board: &Board, x, y| {
+            let node = board.matrix.node_from_vertex(Vertex { x, y }).unwrap();
+            Arc::clone(
+                board
+                    .chains
+                    .iter()
+                    .find(|chain| chain.verts.contains(&node))
+                    .unwrap(),
+            )
+        };
+        assert!(Arc::ptr_eq(
+            &chain_at(&board, 8, 8),
+            &chain_at(&branch, 8, 8)
+        ));
+        assert!(!Arc::ptr_eq(
+            &chain_at(&board, 0, 0),
+            &chain_at(&branch, 0, 0)
+        ));
+        assert_eq!(board.liberties(Vertex { x: 0, y: 0 }), Some(2));
+        assert_eq!(branch.liberties(Vertex { x: 0, y: 0 }), Some(3));
+        assert_eq!(board.stones(Player::Black).len(), 1);
+    }
+
+    #[test]
+    fn unplace_stone_reverses_place_stone() {
+        use rand::Rng;
+
+        // The chains and regions, as they'd be found from scratch.
+        let summary = |board: &Board| {
+            let sorted = |nodes: &HashSet<Node>| {
+                let mut vertices: Vec<_> = nodes
+                    .iter()
+                    .map(|&node| board.matrix.vertex_from_node(node))
+                    .collect();
+                vertices.sort_unstable_by_key(|vertex| (vertex.x, vertex.y));
+                vertices
+            };
+            let mut chains: Vec<_> = board
+                .chains
+                .iter()
+                .map(|chain| {
+                    (
+                        sorted(&chain.verts),
+                        sorted(&chain.libs),
+                        sorted(&chain.filled_libs),
+                    )
+                })
+                .collect();
+            chains.sort_unstable_by_key(|chain| (chain.0[0].x, chain.0[0].y));
+            let owners: Vec<_> = board
+                .matrix
+                .nodes()
+                .map(|node| board.regions.owner(node))
+                .collect();
+            (board.hash, chains, owners, board.regions.territory())
+        };
+
+        let mut rng = rand::thread_rng();
+        for size in [3, 5, 9] {
+            let mut board = Board::with_size(size).unwrap();
+            let mut history = Vec::new();
+            for turn in 0..4 * size * size {
+                let player = if turn % 2 == 0 {
+                    Player::Black
+                } else {
+                    Player::White
+                };
+                let vertex = Vertex {
+                    x: rng.gen_range(0..size),
+                    y: rng.gen_range(0..size),
+                };
+                if !board.is_vacant(vertex) {
+                    continue;
+                }
+                let before = board.clone();
+                let captured = board.place_stone(player, vertex);
+                let suicided: Vec<_> = before
+                    .stones(player)
+                    .into_iter()
+                    .chain([vertex])
+                    .filter(|&stone| board.stone(stone) != Some(player))
+                    .collect();
+                history.push((before, player, vertex, captured, suicided));
+            }
+            for (before, player, vertex, captured, suicided) in history.into_iter().rev() {
+                board.unplace_stone(player, vertex, &captured, &suicided);
+                assert_eq!(board, before, "{vertex}");
+                assert_eq!(summary(&board), summary(&before), "{vertex}\n{board}");
+            }
+        }
+    }
+
+    #[test]
+    fn territory_tracking() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        for size in [3, 5, 9] {
+            let mut board = Board::with_size(size).unwrap();
+            for turn in 0..4 * size * size {
+                let player = if turn % 2 == 0 {
+                    Player::Black
+                } else {
+                    Player::White
+                };
+                let vertex = Vertex {
+                    x: rng.gen_range(0..size),
+                    y: rng.gen_range(0..size),
+                };
+                if !board.is_vacant(vertex) {
+                    continue;
+                }
+                board.place_stone(player, vertex);
+                assert_eq!(board.score_area(), score_area(&board.matrix), "{board}");
+
+                let mut owners = HashMap::new();
+                for region in board.matrix.get_regions(|&state| state == State::Empty) {
+                    let owner = region_owner(&board.matrix, &region);
+                    for &node in region.nodes() {
+                        owners.insert(node, owner);
+                    }
+                }
+                for node in board.matrix.nodes() {
+                    let expected = match owners.get(&node) {
+                        Some(State::Black) => Some(Player::Black),
+                        Some(State::White) => Some(Player::White),
+                        _ => None,
+                    };
+                    let vertex = board.matrix.vertex_from_node(node);
+                    assert_eq!(board.territory_owner(vertex), expected, "{vertex}\n{board}");
+                }
+            }
+        }
     }
 }
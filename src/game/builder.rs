@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::game::clock::Clock;
+use crate::game::{Game, Handicap, RuleSet, CHINESE_KOMI, DEFAULT_BOARD_SIZE};
+
+/// Configures a new game one setting at a time, e.g. from user input, and checks the settings
+/// together when the game is built:
+///
+/// ```
+/// use libgo::game::{Game, RuleSet};
+///
+/// let game = Game::builder()
+///     .board_size(9)
+///     .komi(6.5)
+///     .rules(RuleSet::Japanese)
+///     .handicap(2)
+///     .build()
+///     .unwrap();
+/// assert_eq!(game.handicap(), 2);
+/// ```
+#[derive(Clone, Copy, Debug)]
+#[must_use]
+pub struct GameBuilder {
+    board_size: usize,
+    komi: f64,
+    rule_set: RuleSet,
+    handicap: usize,
+    clock: Clock,
+    seed: Option<u64>,
+}
+
+impl Default for GameBuilder {
+    fn default() -> Self {
+        GameBuilder {
+            board_size: DEFAULT_BOARD_SIZE,
+            komi: CHINESE_KOMI,
+            rule_set: RuleSet::Chinese,
+            handicap: 0,
+            clock: Clock::Unlimited,
+            seed: None,
+        }
+    }
+}
+
+impl GameBuilder {
+    /// Returns a builder for a game on the default board with Chinese rules and komi.
+    pub fn new() -> Self {
+        GameBuilder::default()
+    }
+
+    /// Sets the size of the board.
+    pub fn board_size(mut self, board_size: usize) -> Self {
+        self.board_size = board_size;
+        self
+    }
+
+    /// Sets the komi.
+    pub fn komi(mut self, komi: f64) -> Self {
+        self.komi = komi;
+        self
+    }
+
+    /// Sets the rules.
+    pub fn rules(mut self, rule_set: RuleSet) -> Self {
+        self.rule_set = rule_set;
+        self
+    }
+
+    /// Sets the number of handicap stones placed on the star points, or 0 for none.
+    pub fn handicap(mut self, stones: usize) -> Self {
+        self.handicap = stones;
+        self
+    }
+
+    /// Sets the time settings.
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Seeds the generator random moves are drawn from, so the same moves are generated each
+    /// time the game is played.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Builds the game.
+    ///
+    /// # Errors
+    ///
+    /// The board size isn't supported, the komi isn't a number, the handicap doesn't fit on the
+    /// board's star points, byo-yomi has no periods or periods of no time, or Canadian overtime
+    /// has blocks of no stones or no time.
+    pub fn build(self) -> Result<Game, String> {
+        let mut game = Game::with_board_size(self.board_size)?;
+        if !self.komi.is_finite() {
+            return Err(format!("invalid komi: {}", self.komi));
+        }
+        match self.clock {
+            Clock::ByoYomi {
+                period_time,
+                periods,
+                ..
+            } if periods == 0 || period_time == Duration::ZERO => {
+                return Err("byo-yomi needs at least one period of some time".to_owned());
+            }
+            Clock::Canadian {
+                period_time,
+                stones,
+                ..
+            } if stones == 0 || period_time == Duration::ZERO => {
+                return Err("Canadian overtime needs at least one stone in some time".to_owned());
+            }
+            _ => {}
+        }
+
+        game.komi = self.komi;
+        game.rule_set = self.rule_set;
+        game.set_time_settings(self.clock);
+        game.rng = self.seed.map(StdRng::seed_from_u64);
+        if self.handicap > 0 {
+            game.place_handicap(self.handicap, Handicap::Fixed)?;
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::player::Player;
+
+    #[test]
+    fn build() {
+        let game = Game::builder().build().unwrap();
+        assert_eq!(game.board().size(), DEFAULT_BOARD_SIZE);
+        assert_eq!(game.player_turn(), Player::Black);
+
+        let game = Game::builder().board_size(9).handicap(3).build().unwrap();
+        assert_eq!(game.handicap(), 3);
+        assert_eq!(game.player_turn(), Player::White);
+
+        assert!(Game::builder().board_size(0).build().is_err());
+        assert!(Game::builder().komi(f64::NAN).build().is_err());
+        assert!(Game::builder().board_size(9).handicap(1).build().is_err());
+        assert!(Game::builder().board_size(9).handicap(10).build().is_err());
+        let clock = Clock::ByoYomi {
+            main_time: Duration::from_secs(60),
+            period_time: Duration::from_secs(30),
+            periods: 0,
+        };
+        assert!(Game::builder().clock(clock).build().is_err());
+        let clock = Clock::Canadian {
+            main_time: Duration::from_secs(60),
+            period_time: Duration::from_secs(300),
+            stones: 0,
+        };
+        assert!(Game::builder().clock(clock).build().is_err());
+    }
+
+    #[test]
+    fn seed() {
+        let genmoves = |seed| {
+            let mut game = Game::builder().board_size(9).seed(seed).build().unwrap();
+            (0..20)
+                .map(|_| game.genmove_random(game.player_turn()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(genmoves(1), genmoves(1));
+        assert_ne!(genmoves(1), genmoves(2));
+    }
+}
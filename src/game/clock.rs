@@ -0,0 +1,473 @@
+use std::time::{Duration, Instant};
+
+/// The number of moves a player is assumed to still make in main time, which `Clock::move_time`
+/// spreads it over.
+const MAIN_TIME_MOVES: u32 = 30;
+
+/// The time settings for a game.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Clock {
+    /// Neither player can lose on time.
+    Unlimited,
+    /// Absolute time, or sudden death. Each player has `main_time` for the whole game and loses
+    /// on time when it runs out.
+    Absolute {
+        /// The time each player has for the game.
+        main_time: Duration,
+    },
+    /// Japanese byo-yomi. After the main time runs out each move must be made within
+    /// `period_time`. Taking longer uses up a period and the player loses on time when the last
+    /// period is exceeded.
+    ByoYomi {
+        /// The time each player has before byo-yomi starts.
+        main_time: Duration,
+        /// The length of one byo-yomi period.
+        period_time: Duration,
+        /// The number of byo-yomi periods each player has.
+        periods: u32,
+    },
+    /// Canadian overtime. After the main time runs out each block of `stones` moves must be made
+    /// within `period_time`, and the player loses on time when a block isn't.
+    Canadian {
+        /// The time each player has before overtime starts.
+        main_time: Duration,
+        /// The time to make a block of moves in.
+        period_time: Duration,
+        /// The number of moves in a block.
+        stones: u32,
+    },
+}
+
+impl Clock {
+    /// Returns the clock of the GTP `time_settings` command. Byo-yomi of no time means absolute
+    /// time, and byo-yomi with time but no stones means there is no time limit. Otherwise the
+    /// byo-yomi is Canadian, as GTP has no other kind.
+    #[must_use]
+    pub fn from_gtp(main_time: Duration, byo_yomi_time: Duration, byo_yomi_stones: u32) -> Clock {
+        if byo_yomi_time.is_zero() {
+            Clock::Absolute { main_time }
+        } else if byo_yomi_stones == 0 {
+            Clock::Unlimited
+        } else {
+            Clock::Canadian {
+                main_time,
+                period_time: byo_yomi_time,
+                stones: byo_yomi_stones,
+            }
+        }
+    }
+
+    /// Returns how long a player should think about their next move, or None if they can take as
+    /// long as they like. Main time is spread over the moves a player is expected to still make,
+    /// a byo-yomi period can be used in full and a block of Canadian overtime is shared out
+    /// evenly between the stones left to play in it.
+    #[must_use]
+    pub fn move_time(&self, time_left: &TimeLeft) -> Option<Duration> {
+        if *self == Clock::Unlimited {
+            return None;
+        }
+        if !time_left.main_time.is_zero() {
+            return Some(time_left.main_time / MAIN_TIME_MOVES);
+        }
+        Some(match *self {
+            Clock::Unlimited | Clock::Absolute { .. } => Duration::ZERO,
+            Clock::ByoYomi { period_time, .. } => period_time,
+            Clock::Canadian {
+                period_time,
+                stones,
+                ..
+            } => {
+                if time_left.stones_left == 0 {
+                    // A block of no stones, which `from_gtp` never gives, is taken as one.
+                    period_time / stones.max(1)
+                } else {
+                    time_left.period_time_left / time_left.stones_left
+                }
+            }
+        })
+    }
+
+    /// Returns the time each player starts the game with.
+    #[must_use]
+    pub fn initial_time_left(&self) -> TimeLeft {
+        match *self {
+            Clock::Unlimited => TimeLeft::default(),
+            Clock::Absolute { main_time } | Clock::Canadian { main_time, .. } => TimeLeft {
+                main_time,
+                ..TimeLeft::default()
+            },
+            Clock::ByoYomi {
+                main_time, periods, ..
+            } => TimeLeft {
+                main_time,
+                periods_left: periods,
+                ..TimeLeft::default()
+            },
+        }
+    }
+
+    /// Charges a player for a move that took `elapsed` time. Returns false if the player ran out
+    /// of time.
+    pub fn spend(&self, time_left: &mut TimeLeft, elapsed: Duration) -> bool {
+        match *self {
+            Clock::Unlimited => true,
+            Clock::Absolute { .. } => {
+                if elapsed <= time_left.main_time {
+                    time_left.main_time -= elapsed;
+                    return true;
+                }
+                time_left.main_time = Duration::ZERO;
+                false
+            }
+            Clock::ByoYomi { period_time, .. } => {
+                if elapsed <= time_left.main_time {
+                    time_left.main_time -= elapsed;
+                    return true;
+                }
+                let overtime = elapsed.saturating_sub(time_left.main_time);
+                time_left.main_time = Duration::ZERO;
+
+                // The first period is reset by the move, every other one started is used up.
+                let started = periods_started(overtime, period_time);
+                if started > time_left.periods_left {
+                    time_left.periods_used += time_left.periods_left;
+                    time_left.periods_left = 0;
+                    return false;
+                }
+                time_left.periods_left -= started - 1;
+                time_left.periods_used += started - 1;
+                true
+            }
+            Clock::Canadian {
+                period_time,
+                stones,
+                ..
+            } => {
+                let mut overtime = elapsed;
+                if time_left.stones_left == 0 {
+                    if elapsed <= time_left.main_time {
+                        time_left.main_time -= elapsed;
+                        return true;
+                    }
+                    overtime = elapsed.saturating_sub(time_left.main_time);
+                    time_left.main_time = Duration::ZERO;
+                    time_left.period_time_left = period_time;
+                    time_left.stones_left = stones;
+                }
+                if overtime > time_left.period_time_left {
+                    time_left.period_time_left = Duration::ZERO;
+                    return false;
+                }
+                time_left.period_time_left -= overtime;
+                time_left.stones_left = time_left.stones_left.saturating_sub(1);
+                if time_left.stones_left == 0 {
+                    time_left.period_time_left = period_time;
+                    time_left.stones_left = stones;
+                }
+                true
+            }
+        }
+    }
+
+    /// Returns how long the player has for their current move before their flag falls, or None
+    /// if they can't lose on time.
+    #[must_use]
+    pub fn time_until_flag(&self, time_left: &TimeLeft) -> Option<Duration> {
+        match *self {
+            Clock::Unlimited => None,
+            Clock::Absolute { .. } => Some(time_left.main_time),
+            Clock::ByoYomi { period_time, .. } => Some(
+                time_left
+                    .main_time
+                    .saturating_add(period_time.saturating_mul(time_left.periods_left)),
+            ),
+            Clock::Canadian { period_time, .. } => Some(if time_left.stones_left == 0 {
+                time_left.main_time.saturating_add(period_time)
+            } else {
+                time_left.period_time_left
+            }),
+        }
+    }
+
+    /// Starts timing a player's turn at `now`.
+    pub fn start_turn(&self, time_left: &mut TimeLeft, now: Instant) {
+        time_left.turn_started = Some(now);
+    }
+
+    /// Ends the player's turn at `now`, charging them for the time since `start_turn` as `spend`
+    /// does. A turn that wasn't started costs nothing. Returns false if the player ran out of
+    /// time.
+    pub fn end_turn(&self, time_left: &mut TimeLeft, now: Instant) -> bool {
+        let elapsed = time_left
+            .turn_started
+            .take()
+            .map_or(Duration::ZERO, |started| {
+                now.saturating_duration_since(started)
+            });
+        self.spend(time_left, elapsed)
+    }
+
+    /// Whether the player's flag has fallen at `now`: their turn has taken longer than
+    /// `time_until_flag` allows, so they have lost on time without moving.
+    #[must_use]
+    pub fn is_flag_down(&self, time_left: &TimeLeft, now: Instant) -> bool {
+        let elapsed = time_left.turn_started.map_or(Duration::ZERO, |started| {
+            now.saturating_duration_since(started)
+        });
+        self.time_until_flag(time_left)
+            .map_or(false, |limit| elapsed > limit)
+    }
+
+    /// Returns the `time` and `stones` arguments a controller sends with `time_left`. In main
+    /// time `stones` is 0, in byo-yomi it is the number of periods left and in Canadian overtime
+    /// the number of stones left to play in the block.
+    #[must_use]
+    pub fn gtp_time_left(&self, time_left: &TimeLeft) -> (Duration, u32) {
+        match *self {
+            Clock::Unlimited => (Duration::ZERO, 0),
+            Clock::Absolute { .. } => (time_left.main_time, 0),
+            Clock::Canadian { .. } => {
+                if time_left.stones_left == 0 {
+                    (time_left.main_time, 0)
+                } else {
+                    (time_left.period_time_left, time_left.stones_left)
+                }
+            }
+            Clock::ByoYomi { period_time, .. } => {
+                if time_left.main_time > Duration::ZERO {
+                    (time_left.main_time, 0)
+                } else {
+                    (period_time, time_left.periods_left)
+                }
+            }
+        }
+    }
+
+    /// The inverse of `gtp_time_left`, updates `time_left` from the arguments of a `time_left`
+    /// command.
+    pub fn set_gtp_time_left(&self, time_left: &mut TimeLeft, time: Duration, stones: u32) {
+        match *self {
+            Clock::Unlimited => {}
+            Clock::Absolute { .. } => time_left.main_time = time,
+            Clock::Canadian { .. } => {
+                if stones == 0 {
+                    time_left.main_time = time;
+                    time_left.period_time_left = Duration::ZERO;
+                } else {
+                    time_left.main_time = Duration::ZERO;
+                    time_left.period_time_left = time;
+                }
+                time_left.stones_left = stones;
+            }
+            Clock::ByoYomi { periods, .. } => {
+                if stones == 0 {
+                    time_left.main_time = time;
+                    time_left.periods_left = periods;
+                    time_left.periods_used = 0;
+                } else {
+                    time_left.main_time = Duration::ZERO;
+                    time_left.periods_left = stones.min(periods);
+                    time_left.periods_used = periods - time_left.periods_left;
+                }
+            }
+        }
+    }
+}
+
+/// The number of periods of length `period` that `overtime` reaches into.
+fn periods_started(overtime: Duration, period: Duration) -> u32 {
+    if period.is_zero() {
+        return u32::MAX;
+    }
+    let (overtime, period) = (overtime.as_nanos(), period.as_nanos());
+    let started = ((overtime + period - 1) / period).max(1);
+    u32::try_from(started).unwrap_or(u32::MAX)
+}
+
+/// The time a player has left.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeLeft {
+    /// The main time remaining.
+    pub main_time: Duration,
+    /// The number of byo-yomi periods remaining.
+    pub periods_left: u32,
+    /// The number of byo-yomi periods used up so far.
+    pub periods_used: u32,
+    /// The time left for the current block of Canadian overtime.
+    pub period_time_left: Duration,
+    /// The stones left to play in the current block of Canadian overtime, or 0 before overtime.
+    pub stones_left: u32,
+    /// When the player's turn started, if it's being timed with `Clock::start_turn`. It isn't
+    /// serialized, as an instant only means something in the process that took it.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub turn_started: Option<Instant>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn byo_yomi() -> Clock {
+        Clock::ByoYomi {
+            main_time: Duration::from_secs(60),
+            period_time: Duration::from_secs(30),
+            periods: 3,
+        }
+    }
+
+    #[test]
+    fn spend_main_time() {
+        let clock = byo_yomi();
+        let mut time_left = clock.initial_time_left();
+        assert!(clock.spend(&mut time_left, Duration::from_secs(20)));
+        assert_eq!(time_left.main_time, Duration::from_secs(40));
+        assert_eq!(time_left.periods_left, 3);
+        assert_eq!(
+            clock.gtp_time_left(&time_left),
+            (Duration::from_secs(40), 0)
+        );
+    }
+
+    #[test]
+    fn spend_periods() {
+        let clock = byo_yomi();
+        let mut time_left = clock.initial_time_left();
+
+        // Finishing within the first period does not use it up.
+        assert!(clock.spend(&mut time_left, Duration::from_secs(80)));
+        assert_eq!(time_left.main_time, Duration::ZERO);
+        assert_eq!(time_left.periods_used, 0);
+
+        assert!(clock.spend(&mut time_left, Duration::from_secs(30)));
+        assert_eq!(time_left.periods_used, 0);
+
+        assert!(clock.spend(&mut time_left, Duration::from_secs(61)));
+        assert_eq!(time_left.periods_used, 2);
+        assert_eq!(
+            clock.gtp_time_left(&time_left),
+            (Duration::from_secs(30), 1)
+        );
+
+        assert!(!clock.spend(&mut time_left, Duration::from_secs(31)));
+        assert_eq!(time_left.periods_left, 0);
+        assert_eq!(time_left.periods_used, 3);
+    }
+
+    #[test]
+    fn set_gtp_time_left() {
+        let clock = byo_yomi();
+        let mut time_left = clock.initial_time_left();
+        clock.set_gtp_time_left(&mut time_left, Duration::from_secs(30), 2);
+        assert_eq!(
+            time_left,
+            TimeLeft {
+                main_time: Duration::ZERO,
+                periods_left: 2,
+                periods_used: 1,
+                ..TimeLeft::default()
+            }
+        );
+    }
+
+    #[test]
+    fn absolute() {
+        let clock = Clock::Absolute {
+            main_time: Duration::from_secs(60),
+        };
+        let mut time_left = clock.initial_time_left();
+        assert!(clock.spend(&mut time_left, Duration::from_secs(45)));
+        assert_eq!(
+            clock.gtp_time_left(&time_left),
+            (Duration::from_secs(15), 0)
+        );
+        assert!(!clock.spend(&mut time_left, Duration::from_secs(16)));
+        assert_eq!(time_left.main_time, Duration::ZERO);
+    }
+
+    #[test]
+    fn canadian() {
+        let clock = Clock::Canadian {
+            main_time: Duration::from_secs(60),
+            period_time: Duration::from_secs(300),
+            stones: 3,
+        };
+        let mut time_left = clock.initial_time_left();
+        assert_eq!(clock.move_time(&time_left), Some(Duration::from_secs(2)));
+
+        // The move that runs out of main time is the first of the block.
+        assert!(clock.spend(&mut time_left, Duration::from_secs(100)));
+        assert_eq!(
+            clock.gtp_time_left(&time_left),
+            (Duration::from_secs(260), 2)
+        );
+        assert_eq!(clock.move_time(&time_left), Some(Duration::from_secs(130)));
+        assert!(clock.spend(&mut time_left, Duration::from_secs(200)));
+        // Finishing the block starts a new one.
+        assert!(clock.spend(&mut time_left, Duration::from_secs(60)));
+        assert_eq!(
+            clock.gtp_time_left(&time_left),
+            (Duration::from_secs(300), 3)
+        );
+        assert!(!clock.spend(&mut time_left, Duration::from_secs(301)));
+
+        let mut reported = clock.initial_time_left();
+        clock.set_gtp_time_left(&mut reported, Duration::from_secs(120), 2);
+        assert_eq!(
+            clock.gtp_time_left(&reported),
+            (Duration::from_secs(120), 2)
+        );
+
+        let no_stones = Clock::Canadian {
+            main_time: Duration::ZERO,
+            period_time: Duration::from_secs(30),
+            stones: 0,
+        };
+        assert_eq!(
+            no_stones.move_time(&no_stones.initial_time_left()),
+            Some(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn from_gtp() {
+        let minute = Duration::from_secs(60);
+        assert_eq!(
+            Clock::from_gtp(minute, Duration::ZERO, 0),
+            Clock::Absolute { main_time: minute }
+        );
+        assert_eq!(Clock::from_gtp(minute, minute, 0), Clock::Unlimited);
+        assert_eq!(
+            Clock::from_gtp(minute, minute, 25),
+            Clock::Canadian {
+                main_time: minute,
+                period_time: minute,
+                stones: 25
+            }
+        );
+        assert_eq!(Clock::Unlimited.move_time(&TimeLeft::default()), None);
+    }
+
+    #[test]
+    fn turns() {
+        let clock = byo_yomi();
+        let mut time_left = clock.initial_time_left();
+        assert_eq!(
+            clock.time_until_flag(&time_left),
+            Some(Duration::from_secs(150))
+        );
+        assert_eq!(Clock::Unlimited.time_until_flag(&time_left), None);
+
+        let start = Instant::now();
+        clock.start_turn(&mut time_left, start);
+        assert!(!clock.is_flag_down(&time_left, start + Duration::from_secs(150)));
+        assert!(clock.is_flag_down(&time_left, start + Duration::from_secs(151)));
+        assert!(clock.end_turn(&mut time_left, start + Duration::from_secs(20)));
+        assert_eq!(time_left.main_time, Duration::from_secs(40));
+        assert_eq!(time_left.turn_started, None);
+        // Without a turn started, nothing is charged.
+        assert!(clock.end_turn(&mut time_left, start + Duration::from_secs(500)));
+        assert_eq!(time_left.main_time, Duration::from_secs(40));
+    }
+}
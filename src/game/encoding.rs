@@ -0,0 +1,613 @@
+//! A compact binary encoding of a game record, two bytes per move, for syncing games over the
+//! network.
+//!
+//! The encoding starts with a header:
+//!
+//! | bytes | contents                           |
+//! |-------|------------------------------------|
+//! | 1     | format version                     |
+//! | 1     | board size                         |
+//! | 1     | rule set                           |
+//! | 2     | komi in half points, big endian    |
+//! | 2     | number of setup stones, big endian |
+//!
+//! followed by the setup stones (e.g. handicaps) and then the moves, each as a big endian `u16`.
+//! The high bit is set for White and the rest is the vertex index `y * size + x`, or `0x7fff`
+//! for a pass.
+//!
+//! `GameRecord` has a denser format for storing many games, such as those of self-play. It has the
+//! same header, except that the number of setup stones and of moves follow it as LEB128 varints.
+//! The number of setup stones is shifted left two bits, with the low bits giving whose turn it is
+//! after the moves, if it was set with `Game::set_to_move`: 1 for Black and 2 for White. Version 2
+//! records, which are still read, have no turn. Each setup stone is then a varint of its vertex
+//! index shifted left one bit, with the low bit set for White. Each move is a varint of the zigzag
+//! encoded difference between its vertex index plus one, or zero for a pass, and that of the move
+//! before it, shifted left one bit, with the low bit set when the move isn't by the opponent of the
+//! player who moved before it. Most moves take one or two bytes. With the `zstd` feature the
+//! records can also be compressed.
+
+use crate::game::board::Move;
+use crate::game::player::Player;
+use crate::game::vertex::{self, Symmetry, Vertex};
+use crate::game::{Game, RuleSet};
+
+/// The version of the encoding written by `encode`.
+pub const VERSION: u8 = 1;
+
+/// The version of the encoding written by `GameRecord::to_bytes`.
+pub const RECORD_VERSION: u8 = 3;
+
+const HEADER_LEN: usize = 7;
+const WHITE: u16 = 0x8000;
+const PASS: u16 = 0x7fff;
+
+/// Encodes the starting position and moves of a game.
+///
+/// # Panics
+///
+/// If the game has more than 65535 setup stones.
+#[must_use]
+pub fn encode(game: &Game) -> Vec<u8> {
+    let size = game.board().size();
+    let initial = game.initial_board();
+    let setup: Vec<Move> = [Player::Black, Player::White]
+        .into_iter()
+        .flat_map(|player| {
+            initial.stones(player).into_iter().map(move |vertex| Move {
+                player,
+                vertex: Some(vertex),
+            })
+        })
+        .collect();
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + 2 * (setup.len() + game.move_history.len()));
+    bytes.push(VERSION);
+    bytes.push(u8::try_from(size).expect("board size fits in a byte"));
+    bytes.push(rule_set_byte(game.rule_set));
+    bytes.extend(half_points(game.komi).to_be_bytes());
+    bytes.extend(
+        u16::try_from(setup.len())
+            .expect("too many setup stones")
+            .to_be_bytes(),
+    );
+    for mov in setup
+        .iter()
+        .chain(game.move_history.iter().map(|record| &record.mov))
+    {
+        bytes.extend(encode_move(mov, size).to_be_bytes());
+    }
+    bytes
+}
+
+fn rule_set_byte(rule_set: RuleSet) -> u8 {
+    match rule_set {
+        RuleSet::Chinese => 0,
+        RuleSet::AGA => 1,
+        RuleSet::Japanese => 2,
+        RuleSet::TrompTaylor => 3,
+    }
+}
+
+fn rule_set_from_byte(byte: u8) -> Result<RuleSet, String> {
+    match byte {
+        0 => Ok(RuleSet::Chinese),
+        1 => Ok(RuleSet::AGA),
+        2 => Ok(RuleSet::Japanese),
+        3 => Ok(RuleSet::TrompTaylor),
+        rules => Err(format!("unknown rule set {rules}")),
+    }
+}
+
+/// Komi rounded to the nearest half point.
+#[allow(clippy::cast_possible_truncation)]
+fn half_points(komi: f64) -> i16 {
+    (komi * 2.0)
+        .round()
+        .clamp(f64::from(i16::MIN), f64::from(i16::MAX)) as i16
+}
+
+fn encode_move(mov: &Move, size: usize) -> u16 {
+    let color = match mov.player {
+        Player::Black => 0,
+        Player::White => WHITE,
+    };
+    let vertex = mov.vertex.map_or(PASS, |vertex| {
+        u16::try_from(vertex::index(vertex, size)).expect("board too large to encode")
+    });
+    color | vertex
+}
+
+fn decode_move(value: u16, size: usize) -> Result<Move, String> {
+    let player = if value & WHITE == 0 {
+        Player::Black
+    } else {
+        Player::White
+    };
+    let index = usize::from(value & !WHITE);
+    let vertex = if value & !WHITE == PASS {
+        None
+    } else if index < size * size {
+        Some(vertex::from_index(index, size))
+    } else {
+        return Err(format!("vertex index {index} is off the board"));
+    };
+    Ok(Move { player, vertex })
+}
+
+/// Decodes a game, replaying every move so the record is checked for legality.
+///
+/// # Errors
+///
+/// Fails if the bytes are truncated, use an unknown version or rule set, or contain an illegal
+/// move.
+pub fn decode(bytes: &[u8]) -> Result<Game, String> {
+    if bytes.len() < HEADER_LEN || (bytes.len() - HEADER_LEN) % 2 != 0 {
+        return Err("truncated game record".to_owned());
+    }
+    if bytes[0] != VERSION {
+        return Err(format!("unsupported version {}", bytes[0]));
+    }
+
+    let board_size = usize::from(bytes[1]);
+    let rule_set = rule_set_from_byte(bytes[2])?;
+    let komi = f64::from(i16::from_be_bytes([bytes[3], bytes[4]])) / 2.0;
+    let setup = usize::from(u16::from_be_bytes([bytes[5], bytes[6]]));
+
+    let mut setup_stones = Vec::with_capacity(setup);
+    let mut moves = Vec::new();
+    for (index, pair) in bytes[HEADER_LEN..].chunks_exact(2).enumerate() {
+        let mov = decode_move(u16::from_be_bytes([pair[0], pair[1]]), board_size)?;
+        if index < setup {
+            let vertex = mov.vertex.ok_or("pass in the setup stones")?;
+            setup_stones.push((mov.player, vertex));
+        } else {
+            moves.push(mov);
+        }
+    }
+    GameRecord {
+        board_size,
+        rule_set,
+        komi,
+        setup: setup_stones,
+        moves,
+        to_move: None,
+    }
+    .to_game()
+}
+
+/// A game record as stored: the board size, rules and komi, the stones set up before the first
+/// move, such as handicap stones, the moves and whose turn it is. It holds less than a `Game`, so
+/// it's cheap to read and write records in bulk, and is only replayed into a `Game` with
+/// `to_game`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRecord {
+    /// The size of the board.
+    pub board_size: usize,
+    /// The rule set the game is played under.
+    pub rule_set: RuleSet,
+    /// The komi, stored rounded to the nearest half point.
+    pub komi: f64,
+    /// The stones on the board before the first move.
+    pub setup: Vec<(Player, Vertex)>,
+    /// The moves played.
+    pub moves: Vec<Move>,
+    /// Whose turn it is after the moves, if it was set with `Game::set_to_move` rather than
+    /// following from them.
+    pub to_move: Option<Player>,
+}
+
+impl From<&Game> for GameRecord {
+    fn from(game: &Game) -> Self {
+        let initial = game.initial_board();
+        GameRecord {
+            board_size: game.board().size(),
+            rule_set: game.rule_set,
+            komi: game.komi,
+            setup: [Player::Black, Player::White]
+                .into_iter()
+                .flat_map(|player| {
+                    initial
+                        .stones(player)
+                        .into_iter()
+                        .map(move |vertex| (player, vertex))
+                })
+                .collect(),
+            moves: game.moves().copied().collect(),
+            to_move: game.turn_set(),
+        }
+    }
+}
+
+impl GameRecord {
+    /// Encodes the record in the format described in the module documentation.
+    ///
+    /// # Panics
+    ///
+    /// If the board is larger than 255x255.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let size = self.board_size;
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.setup.len() + 2 * self.moves.len());
+        bytes.push(RECORD_VERSION);
+        bytes.push(u8::try_from(size).expect("board size fits in a byte"));
+        bytes.push(rule_set_byte(self.rule_set));
+        bytes.extend(half_points(self.komi).to_be_bytes());
+        let to_move = match self.to_move {
+            None => 0,
+            Some(Player::Black) => 1,
+            Some(Player::White) => 2,
+        };
+        write_varint(&mut bytes, (self.setup.len() as u64) << 2 | to_move);
+        write_varint(&mut bytes, self.moves.len() as u64);
+        for &(player, vertex) in &self.setup {
+            let index = vertex::index(vertex, size) as u64;
+            write_varint(&mut bytes, index << 1 | u64::from(player == Player::White));
+        }
+
+        let mut previous: Option<&Move> = None;
+        for mov in &self.moves {
+            let value = move_value(mov, size);
+            let delta = value - previous.map_or(0, |previous| move_value(previous, size));
+            let on_turn = previous.map_or(Player::Black, |previous| previous.player.enemy());
+            write_varint(
+                &mut bytes,
+                zigzag(delta) << 1 | u64::from(mov.player != on_turn),
+            );
+            previous = Some(mov);
+        }
+        bytes
+    }
+
+    /// Decodes a record written by `to_bytes`, or by the version before, which didn't store the
+    /// turn. The moves aren't checked for legality until the record is replayed with `to_game`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the bytes are truncated or have bytes left over, use an unknown version or rule
+    /// set, or a vertex is off the board.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let truncated = || "truncated game record".to_owned();
+        let header = bytes.get(..5).ok_or_else(truncated)?;
+        if header[0] != RECORD_VERSION && header[0] != 2 {
+            return Err(format!("unsupported version {}", header[0]));
+        }
+        let board_size = usize::from(header[1]);
+        let rule_set = rule_set_from_byte(header[2])?;
+        let komi = f64::from(i16::from_be_bytes([header[3], header[4]])) / 2.0;
+
+        let mut rest = &bytes[5..];
+        let mut setup_len = read_varint(&mut rest).ok_or_else(truncated)?;
+        let mut to_move = None;
+        if header[0] == RECORD_VERSION {
+            to_move = match setup_len & 3 {
+                0 => None,
+                1 => Some(Player::Black),
+                2 => Some(Player::White),
+                _ => return Err("invalid turn".to_owned()),
+            };
+            setup_len >>= 2;
+        }
+        let moves_len = read_varint(&mut rest).ok_or_else(truncated)?;
+        let vertex = |index: u64| match usize::try_from(index) {
+            Ok(index) if index < board_size * board_size => {
+                Ok(vertex::from_index(index, board_size))
+            }
+            _ => Err(format!("vertex index {index} is off the board")),
+        };
+
+        let mut setup = Vec::new();
+        for _ in 0..setup_len {
+            let value = read_varint(&mut rest).ok_or_else(truncated)?;
+            let player = if value & 1 == 0 {
+                Player::Black
+            } else {
+                Player::White
+            };
+            setup.push((player, vertex(value >> 1)?));
+        }
+
+        let mut moves: Vec<Move> = Vec::new();
+        let mut value = 0;
+        for _ in 0..moves_len {
+            let encoded = read_varint(&mut rest).ok_or_else(truncated)?;
+            value = unzigzag(encoded >> 1).wrapping_add(value);
+            let on_turn = moves
+                .last()
+                .map_or(Player::Black, |last| last.player.enemy());
+            let player = if encoded & 1 == 0 {
+                on_turn
+            } else {
+                on_turn.enemy()
+            };
+            let vertex = match value {
+                0 => None,
+                value => {
+                    let index = value
+                        .checked_sub(1)
+                        .and_then(|index| u64::try_from(index).ok())
+                        .ok_or_else(|| format!("move value {value} is off the board"))?;
+                    Some(vertex(index)?)
+                }
+            };
+            moves.push(Move { player, vertex });
+        }
+        if !rest.is_empty() {
+            return Err(format!("{} bytes left over", rest.len()));
+        }
+
+        Ok(GameRecord {
+            board_size,
+            rule_set,
+            komi,
+            setup,
+            moves,
+            to_move,
+        })
+    }
+
+    /// Encodes the record with `to_bytes` and compresses it with zstd at the level, where 0 is
+    /// zstd's default.
+    ///
+    /// # Errors
+    ///
+    /// Fails if zstd fails, e.g. on an invalid level.
+    #[cfg(feature = "zstd")]
+    pub fn to_compressed_bytes(&self, level: i32) -> Result<Vec<u8>, String> {
+        zstd::encode_all(self.to_bytes().as_slice(), level).map_err(|err| err.to_string())
+    }
+
+    /// Decompresses and decodes a record written by `to_compressed_bytes`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the bytes aren't zstd compressed or `from_bytes` fails.
+    #[cfg(feature = "zstd")]
+    pub fn from_compressed_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let bytes = zstd::decode_all(bytes).map_err(|err| err.to_string())?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Returns the record with the symmetry applied to every setup stone and move, e.g. to augment
+    /// training data or to store equivalent games the same way.
+    ///
+    /// # Panics
+    ///
+    /// If a vertex is off the board.
+    #[must_use]
+    pub fn transform(&self, symmetry: Symmetry) -> GameRecord {
+        let size = self.board_size;
+        GameRecord {
+            setup: self
+                .setup
+                .iter()
+                .map(|&(player, vertex)| (player, vertex.transform(symmetry, size)))
+                .collect(),
+            moves: self
+                .moves
+                .iter()
+                .map(|mov| Move {
+                    player: mov.player,
+                    vertex: mov.vertex.map(|vertex| vertex.transform(symmetry, size)),
+                })
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the record with the colors swapped, every setup stone and move being the other
+    /// player's, and the komi negated so the swapped game is scored the same for the other side.
+    #[must_use]
+    pub fn invert_colors(&self) -> GameRecord {
+        GameRecord {
+            komi: -self.komi,
+            setup: self
+                .setup
+                .iter()
+                .map(|&(player, vertex)| (player.enemy(), vertex))
+                .collect(),
+            moves: self
+                .moves
+                .iter()
+                .map(|mov| Move {
+                    player: mov.player.enemy(),
+                    vertex: mov.vertex,
+                })
+                .collect(),
+            to_move: self.to_move.map(|player| player.enemy()),
+            ..self.clone()
+        }
+    }
+
+    /// Replays the record into a game, checking that every move is legal, and sets whose turn it
+    /// is if the record says.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the board size is invalid, a setup stone is off the board or given twice, the
+    /// setup leaves a chain without liberties (see `Board::is_reachable`), or a move is illegal.
+    pub fn to_game(&self) -> Result<Game, String> {
+        let mut game = Game::with_board_size(self.board_size)?;
+        game.rule_set = self.rule_set;
+        game.komi = self.komi;
+        game.board.set_stones(&self.setup)?;
+        game.board
+            .is_reachable()
+            .map_err(|err| format!("invalid setup: {err}"))?;
+        for (index, mov) in self.moves.iter().enumerate() {
+            game.play(mov)
+                .map_err(|err| format!("move {}: {err}", index + 1))?;
+        }
+        if let Some(player) = self.to_move {
+            game.set_to_move(player);
+        }
+        Ok(game)
+    }
+}
+
+/// A move's vertex index plus one, or zero for a pass.
+fn move_value(mov: &Move, size: usize) -> i64 {
+    mov.vertex.map_or(0, |vertex| {
+        i64::try_from(vertex::index(vertex, size) + 1).expect("board too large to encode")
+    })
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+#[allow(clippy::cast_possible_wrap)]
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Reads a varint from the front of the bytes, or returns None if they end first.
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Handicap;
+
+    #[test]
+    fn round_trip() {
+        let mut game = Game::with_board_size(9).unwrap();
+        game.rule_set = RuleSet::Japanese;
+        game.komi = 6.5;
+        game.place_handicap(2, Handicap::Fixed).unwrap();
+        let moves = [
+            Move {
+                player: Player::White,
+                vertex: Some(Vertex { x: 4, y: 4 }),
+            },
+            Move {
+                player: Player::Black,
+                vertex: None,
+            },
+        ];
+        for mov in &moves {
+            game.play(mov).unwrap();
+        }
+
+        let bytes = encode(&game);
+        assert_eq!(bytes.len(), HEADER_LEN + 2 * 4);
+
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded.board(), game.board());
+        assert_eq!(decoded.rule_set, RuleSet::Japanese);
+        assert!((decoded.komi - 6.5).abs() < f64::EPSILON);
+        assert_eq!(encode(&decoded), bytes);
+
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn game_record() {
+        let mut game = Game::with_board_size(19).unwrap();
+        game.komi = 0.5;
+        game.place_handicap(4, Handicap::Fixed).unwrap();
+        let moves = ["Q10", "C3", "pass", "T19", "A1"];
+        for (index, vertex) in moves.into_iter().enumerate() {
+            let player = if index % 2 == 0 {
+                Player::White
+            } else {
+                Player::Black
+            };
+            let vertex = (vertex != "pass").then(|| vertex.parse().unwrap());
+            game.play(&Move { player, vertex }).unwrap();
+        }
+        // Two moves in a row by Black.
+        game.set_to_move(Player::Black);
+        game.play(&Move {
+            player: Player::Black,
+            vertex: Some("K10".parse().unwrap()),
+        })
+        .unwrap();
+
+        let record = GameRecord::from(&game);
+        assert_eq!(record.setup.len(), 4);
+        assert_eq!(record.moves.len(), 6);
+        let bytes = record.to_bytes();
+        assert!(bytes.len() < encode(&game).len());
+        assert_eq!(GameRecord::from_bytes(&bytes), Ok(record.clone()));
+        let replayed = record.to_game().unwrap();
+        assert_eq!(replayed.board(), game.board());
+        assert_eq!(replayed.moves().count(), 6);
+
+        assert!(GameRecord::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert!(GameRecord::from_bytes(&extra).is_err());
+        assert!(GameRecord::from_bytes(&encode(&game)).is_err());
+        // A single move as far below the first vertex as a varint can reach.
+        let mut negative = bytes[..5].to_vec();
+        write_varint(&mut negative, 0);
+        write_varint(&mut negative, 1);
+        write_varint(&mut negative, u64::MAX - 1);
+        assert!(GameRecord::from_bytes(&negative)
+            .unwrap_err()
+            .contains("off the board"));
+
+        for symmetry in Symmetry::ALL {
+            let transformed = record.transform(symmetry);
+            assert_eq!(transformed.transform(symmetry.inverse()), record);
+            assert_eq!(transformed.to_game().unwrap().moves().count(), 6);
+        }
+        let mut surrounded = record.clone();
+        surrounded.setup = vec![
+            (Player::White, Vertex { x: 0, y: 0 }),
+            (Player::Black, Vertex { x: 1, y: 0 }),
+            (Player::Black, Vertex { x: 0, y: 1 }),
+        ];
+        assert!(surrounded
+            .to_game()
+            .unwrap_err()
+            .contains("A1 has no liberties"));
+
+        let mut turned = record.clone();
+        turned.to_move = Some(Player::White);
+        let bytes = turned.to_bytes();
+        assert_eq!(GameRecord::from_bytes(&bytes), Ok(turned.clone()));
+        assert_eq!(turned.to_game().unwrap().player_turn(), Player::White);
+        assert_eq!(turned.invert_colors().to_move, Some(Player::Black));
+
+        let inverted = record.invert_colors();
+        assert!((inverted.komi + 0.5).abs() < f64::EPSILON);
+        assert_eq!(inverted.setup[0].0, Player::White);
+        assert_eq!(inverted.invert_colors(), record);
+        assert_eq!(
+            inverted.to_game().unwrap().board(),
+            game.swap_colors().board()
+        );
+        let flipped = record.transform(Symmetry::FlipVertical);
+        assert_eq!(flipped.moves[0].vertex, Some("Q10".parse().unwrap()));
+        assert_eq!(flipped.moves[1].vertex, Some("C17".parse().unwrap()));
+        assert_eq!(flipped.moves[2].vertex, None);
+
+        #[cfg(feature = "zstd")]
+        {
+            let compressed = record.to_compressed_bytes(0).unwrap();
+            assert_eq!(GameRecord::from_compressed_bytes(&compressed), Ok(record));
+        }
+    }
+}
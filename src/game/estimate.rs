@@ -0,0 +1,140 @@
+//! Estimates who owns each point with Bouzy's 5/21 algorithm: every stone radiates influence that
+//! is spread by 5 dilations and then trimmed back by 21 erosions, the mathematical morphology
+//! operators of image processing. What's left marks the points each player is likely to end up
+//! with; points where the players' influence meets stay neutral.
+
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+use crate::game::board::Board;
+use crate::game::matrix::Matrix;
+use crate::game::player::Player;
+use crate::game::vertex::Vertex;
+
+/// The influence of a stone before it is spread.
+const STONE: i32 = 128;
+const DILATIONS: usize = 5;
+const EROSIONS: usize = 21;
+
+/// Returns how strongly each point on the board belongs to a player, from 1 for Black to -1 for
+/// White, with 0 for neutral points. Stones keep most of their influence, while empty points are
+/// left with much less, so compare the values of empty points with each other rather than with
+/// those of stones. Dead stones are counted as though they were alive, so remove them from the
+/// board first for a better estimate.
+#[must_use]
+pub fn ownership(board: &Board) -> HashMap<Vertex, f64> {
+    let mut influence: Matrix<i32> = Matrix::with_size(board.size());
+    for node in influence.nodes() {
+        influence[node] = match board.stone(influence.vertex_from_node(node)) {
+            Some(Player::Black) => STONE,
+            Some(Player::White) => -STONE,
+            None => 0,
+        };
+    }
+    for _ in 0..DILATIONS {
+        influence = dilate(&influence);
+    }
+    for _ in 0..EROSIONS {
+        influence = erode(&influence);
+    }
+
+    influence
+        .nodes()
+        .map(|node| {
+            let value = f64::from(influence[node]) / f64::from(STONE);
+            (influence.vertex_from_node(node), value.clamp(-1.0, 1.0))
+        })
+        .collect()
+}
+
+/// Returns the player each point belongs to in the estimate, leaving out the neutral points.
+#[must_use]
+pub fn owners<S: BuildHasher>(ownership: &HashMap<Vertex, f64, S>) -> HashMap<Vertex, Player> {
+    ownership
+        .iter()
+        .filter_map(|(&vertex, &value)| {
+            if value > 0.0 {
+                Some((vertex, Player::Black))
+            } else if value < 0.0 {
+                Some((vertex, Player::White))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Adds to each point untouched by the other player's influence the number of its neighbors
+/// under its own player's influence.
+fn dilate(influence: &Matrix<i32>) -> Matrix<i32> {
+    let mut dilated = influence.clone();
+    for node in influence.nodes() {
+        let value = influence[node];
+        let neighbors: Vec<_> = influence
+            .adjacencies(node)
+            .iter()
+            .map(|&neighbor| influence[neighbor])
+            .collect();
+        let count = |test: fn(&i32) -> bool| {
+            i32::try_from(neighbors.iter().filter(|&value| test(value)).count()).unwrap_or(0)
+        };
+        if value >= 0 && count(|&value| value < 0) == 0 {
+            dilated[node] += count(|&value| value > 0);
+        }
+        if value <= 0 && count(|&value| value > 0) == 0 {
+            dilated[node] -= count(|&value| value < 0);
+        }
+    }
+    dilated
+}
+
+/// Takes from each point under a player's influence the number of its neighbors that aren't,
+/// stopping at 0.
+fn erode(influence: &Matrix<i32>) -> Matrix<i32> {
+    let mut eroded = influence.clone();
+    for node in influence.nodes() {
+        let value = influence[node];
+        let neighbors = influence.adjacencies(node).iter();
+        if value > 0 {
+            let outside = neighbors
+                .filter(|&&neighbor| influence[neighbor] <= 0)
+                .count();
+            eroded[node] = (value - i32::try_from(outside).unwrap_or(0)).max(0);
+        } else if value < 0 {
+            let outside = neighbors
+                .filter(|&&neighbor| influence[neighbor] >= 0)
+                .count();
+            eroded[node] = (value + i32::try_from(outside).unwrap_or(0)).min(0);
+        }
+    }
+    eroded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ownership() {
+        let board = Board::from_ascii(
+            "7 . . x o . . .
+             6 . . x o . . .
+             5 . . x o . . .
+             4 . . x o . . .
+             3 . . x o . . .
+             2 . . x o . . .
+             1 . . x o . . .",
+        )
+        .unwrap();
+        let ownership = super::ownership(&board);
+        assert_eq!(ownership.len(), 49);
+        assert!(ownership[&Vertex { x: 2, y: 3 }] > 0.5);
+        assert!(ownership[&Vertex { x: 1, y: 3 }] > 0.0);
+        assert!(ownership[&Vertex { x: 5, y: 3 }] < 0.0);
+
+        let owners = owners(&ownership);
+        assert_eq!(owners[&Vertex { x: 0, y: 0 }], Player::Black);
+        assert_eq!(owners[&Vertex { x: 6, y: 6 }], Player::White);
+        assert_eq!(owners.len(), 49);
+    }
+}
@@ -0,0 +1,131 @@
+//! Importing collections of SGF files, such as the public game archives, as game records. With
+//! the `rayon` feature the files are read and parsed in parallel.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::game::encoding::GameRecord;
+use crate::game::sgf;
+
+/// The number of files parsed together before their records are handed out, which bounds the
+/// memory held by an import in parallel.
+#[cfg(feature = "rayon")]
+const BATCH_SIZE: usize = 256;
+
+/// The games read from one SGF file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedFile {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// A record of the main line of each game tree in the file, or why the file couldn't be read.
+    pub records: Result<Vec<GameRecord>, String>,
+}
+
+/// Reads every `.sgf` file in the directory and its subdirectories, in order of their paths.
+/// A file that can't be read or parsed is reported with its error and the import goes on.
+/// Symbolic links to directories aren't followed, so a link cycle can't recurse without end.
+///
+/// # Errors
+///
+/// Fails if the directory or one of its subdirectories can't be listed.
+pub fn import_dir<P: AsRef<Path>>(dir: P) -> io::Result<impl Iterator<Item = ImportedFile>> {
+    let mut paths = Vec::new();
+    sgf_files(dir.as_ref(), &mut paths)?;
+    paths.sort();
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+
+        let batches: Vec<Vec<PathBuf>> = paths.chunks(BATCH_SIZE).map(<[_]>::to_vec).collect();
+        Ok(batches
+            .into_iter()
+            .flat_map(|batch| batch.into_par_iter().map(import_file).collect::<Vec<_>>()))
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        Ok(paths.into_iter().map(import_file))
+    }
+}
+
+/// Reads the game records of one SGF file.
+#[must_use]
+pub fn import_file(path: PathBuf) -> ImportedFile {
+    let records = fs::read(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| sgf::parse_bytes(&bytes))
+        .and_then(|trees| trees.iter().map(sgf::GameTree::record).collect());
+    ImportedFile { path, records }
+}
+
+fn sgf_files(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        // The entry's own type, unlike `Path::is_dir`, doesn't follow a link.
+        if entry.file_type()?.is_dir() {
+            sgf_files(&path, paths)?;
+        } else if !path.is_dir()
+            && path
+                .extension()
+                .map_or(false, |extension| extension.eq_ignore_ascii_case("sgf"))
+        {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::board::Move;
+    use crate::game::player::Player;
+    use crate::game::RuleSet;
+
+    #[test]
+    fn import() {
+        let dir = std::env::temp_dir().join(format!("libgo-import-{}", std::process::id()));
+        fs::create_dir_all(dir.join("pro")).unwrap();
+        fs::write(
+            dir.join("pro/1.SGF"),
+            "(;SZ[9]KM[6.5]RU[Japanese]AB[cc];W[ee];B[])(;B[dd])",
+        )
+        .unwrap();
+        fs::write(dir.join("2.sgf"), "(;B[dd]").unwrap();
+        fs::write(dir.join("notes.txt"), "not a game").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("pro/cycle.sgf")).unwrap();
+
+        let imported: Vec<_> = import_dir(&dir).unwrap().collect();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].path, dir.join("2.sgf"));
+        assert!(imported[0].records.is_err());
+
+        let records = imported[1].records.as_ref().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].board_size, 9);
+        assert_eq!(records[0].rule_set, RuleSet::Japanese);
+        assert!((records[0].komi - 6.5).abs() < f64::EPSILON);
+        assert_eq!(records[0].setup.len(), 1);
+        assert_eq!(
+            records[0].moves,
+            [
+                Move {
+                    player: Player::White,
+                    vertex: Some("E5".parse().unwrap()),
+                },
+                Move {
+                    player: Player::Black,
+                    vertex: None,
+                },
+            ]
+        );
+        assert!(records[0].to_game().is_ok());
+        assert_eq!(records[1].board_size, 19);
+
+        assert!(import_dir(dir.join("missing")).is_err());
+    }
+}
@@ -1,31 +1,192 @@
 //! A generic Matrix module specilized for holding Go Board state.
 
+use std::cell::RefCell;
 use std::collections::{hash_set, HashSet};
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
 use std::ops::{Index, IndexMut};
 use std::slice;
+use std::sync::{Arc, Mutex, PoisonError};
 
-use crate::game::vertex::Vertex;
+use crate::game::vertex::{self, Vertex};
 
 /// A matrix holding the state of type T for each vertex on the board.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone)]
 pub struct Matrix<T: Clone + Debug + Default + PartialEq> {
     size: usize,
     vec: Vec<T>,
+    /// The nodes adjacent to each node, worked out once for the size and shared by clones.
+    neighbors: NeighborTable,
+}
+
+impl<T: Clone + Debug + Default + PartialEq> PartialEq for Matrix<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.vec == other.vec
+    }
+}
+
+impl<T: Clone + Debug + Default + Eq> Eq for Matrix<T> {}
+
+impl<T: Clone + Debug + Default + Hash + PartialEq> Hash for Matrix<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.size.hash(state);
+        self.vec.hash(state);
+    }
+}
+
+impl<T: Clone + Debug + Default + PartialEq> Debug for Matrix<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Matrix")
+            .field("size", &self.size)
+            .field("vec", &self.vec)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Matrices are serialized as their size and values, and the neighbor table is built again when
+/// they're deserialized.
+#[cfg(feature = "serde")]
+impl<T> serde::Serialize for Matrix<T>
+where
+    T: Clone + Debug + Default + PartialEq + serde::Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut matrix = serializer.serialize_struct("Matrix", 2)?;
+        matrix.serialize_field("size", &self.size)?;
+        matrix.serialize_field("values", &self.vec)?;
+        matrix.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> serde::Deserialize<'de> for Matrix<T>
+where
+    T: Clone + Debug + Default + PartialEq + serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "Matrix")]
+        struct Fields<T> {
+            size: usize,
+            values: Vec<T>,
+        }
+
+        let Fields { size, values } = Fields::deserialize(deserializer)?;
+        if size.checked_mul(size) != Some(values.len()) {
+            return Err(serde::de::Error::custom(format!(
+                "a matrix of size {size} needs {} values, but has {}",
+                size.saturating_mul(size),
+                values.len()
+            )));
+        }
+        Ok(Matrix {
+            size,
+            vec: values,
+            neighbors: neighbor_table(size),
+        })
+    }
 }
 
 /// A reference to a location in a Matrix.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Node(usize);
 
-fn vertex_from_index(index: usize, board_size: usize) -> Vertex {
-    let x = index % board_size;
-    let y = index / board_size;
-    Vertex { x, y }
+/// Marks on the nodes of a matrix that can be cleared without touching them, by starting a new
+/// generation of marks.
+#[derive(Debug, Default)]
+struct Marks {
+    generations: Vec<u32>,
+    generation: u32,
 }
 
-fn index_from_vertex(vertex: Vertex, board_size: usize) -> usize {
-    vertex.y * board_size + vertex.x
+impl Marks {
+    /// Clears the marks and makes room for `len` nodes.
+    fn clear(&mut self, len: usize) {
+        if self.generations.len() < len {
+            self.generations.resize(len, 0);
+        }
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            self.generations.fill(0);
+            self.generation = 1;
+        }
+    }
+
+    /// Marks the node, returning false if it was already marked.
+    fn mark(&mut self, node: Node) -> bool {
+        let marked = self.generations[node.0] == self.generation;
+        self.generations[node.0] = self.generation;
+        !marked
+    }
+
+    fn is_marked(&self, node: Node) -> bool {
+        self.generations[node.0] == self.generation
+    }
+}
+
+/// The buffers a flood fill works in, kept between region queries so they don't allocate them.
+#[derive(Debug, Default)]
+struct Scratch {
+    /// The nodes visited by the flood fill of the current region.
+    visited: Marks,
+    /// The nodes already in a region found by the current query.
+    in_region: Marks,
+    queue: Vec<Node>,
+}
+
+thread_local! {
+    static SCRATCH: RefCell<Scratch> = RefCell::default();
+}
+
+/// The nodes adjacent to each node of a matrix.
+type NeighborTable = Arc<Vec<Vec<Node>>>;
+
+/// The neighbor tables worked out so far, by matrix size.
+static NEIGHBOR_TABLES: Mutex<Vec<Option<NeighborTable>>> = Mutex::new(Vec::new());
+
+/// Returns the nodes adjacent to each node of a matrix of the size, worked out the first time
+/// the size is used.
+fn neighbor_table(size: usize) -> NeighborTable {
+    let mut tables = NEIGHBOR_TABLES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if tables.len() <= size {
+        tables.resize(size + 1, None);
+    }
+    let table = tables[size].get_or_insert_with(|| {
+        let matrix = Matrix::<()> {
+            size,
+            vec: Vec::new(),
+            neighbors: Arc::default(),
+        };
+        let table = (0..size * size)
+            .map(Node)
+            .map(|node| {
+                [
+                    matrix.left_of(node),
+                    matrix.below(node),
+                    matrix.right_of(node),
+                    matrix.above(node),
+                ]
+                .into_iter()
+                .flatten()
+                .collect()
+            })
+            .collect();
+        Arc::new(table)
+    });
+    Arc::clone(table)
+}
+
+/// Runs `f` with this thread's scratch buffers, or fresh ones if they're in use further up the
+/// stack.
+fn with_scratch<R>(f: impl FnOnce(&mut Scratch) -> R) -> R {
+    SCRATCH.with(|scratch| match scratch.try_borrow_mut() {
+        Ok(mut scratch) => f(&mut scratch),
+        Err(_) => f(&mut Scratch::default()),
+    })
 }
 
 impl<T: Clone + Debug + Default + PartialEq> Matrix<T> {
@@ -73,7 +234,7 @@ impl<T: Clone + Debug + Default + PartialEq> Matrix<T> {
     #[must_use]
     pub fn node_from_vertex(&self, vertex: Vertex) -> Option<Node> {
         if vertex.x < self.size && vertex.y < self.size {
-            Some(Node(index_from_vertex(vertex, self.size)))
+            Some(Node(vertex::index(vertex, self.size)))
         } else {
             None
         }
@@ -82,7 +243,7 @@ impl<T: Clone + Debug + Default + PartialEq> Matrix<T> {
     /// Returns the vertex of a node.
     #[must_use]
     pub fn vertex_from_node(&self, node: Node) -> Vertex {
-        vertex_from_index(node.0, self.size)
+        vertex::from_index(node.0, self.size)
     }
 
     /// Returns a set of all of the empty vertices on the board.
@@ -92,7 +253,7 @@ impl<T: Clone + Debug + Default + PartialEq> Matrix<T> {
             .enumerate()
             .filter_map(|(index, state)| {
                 if state == in_state {
-                    Some(vertex_from_index(index, self.size))
+                    Some(vertex::from_index(index, self.size))
                 } else {
                     None
                 }
@@ -100,31 +261,21 @@ impl<T: Clone + Debug + Default + PartialEq> Matrix<T> {
             .collect()
     }
 
+    /// Returns every node in the matrix.
+    pub fn nodes(&self) -> impl Iterator<Item = Node> {
+        (0..self.vec.len()).map(Node)
+    }
+
     /// Returns all nodes adjacent to node.
     #[must_use]
-    pub fn adjacencies(&self, node: Node) -> Vec<Node> {
-        let mut adjacencies = Vec::with_capacity(4);
-
-        if let Some(node) = self.left_of(node) {
-            adjacencies.push(node);
-        }
-        if let Some(node) = self.below(node) {
-            adjacencies.push(node);
-        }
-        if let Some(node) = self.right_of(node) {
-            adjacencies.push(node);
-        }
-        if let Some(node) = self.above(node) {
-            adjacencies.push(node);
-        }
-
-        adjacencies
+    pub fn adjacencies(&self, node: Node) -> &[Node] {
+        &self.neighbors[node.0]
     }
 
     /// Returns the cell state at a given vertex or none if the vertex is not in the matrix.
     #[must_use]
     pub fn get(&self, vertex: Vertex) -> Option<&T> {
-        self.vec.get(index_from_vertex(vertex, self.size))
+        self.vec.get(vertex::index(vertex, self.size))
     }
 
     /// Returns a new empty matrix.
@@ -133,6 +284,7 @@ impl<T: Clone + Debug + Default + PartialEq> Matrix<T> {
         Matrix {
             size,
             vec: vec![T::default(); size * size],
+            neighbors: neighbor_table(size),
         }
     }
 
@@ -144,22 +296,28 @@ impl<T: Clone + Debug + Default + PartialEq> Matrix<T> {
 
     /// Returns the largest connected region of nodes for which the test function applied to
     /// each node returns true starting at `node`.
+    #[cfg(test)]
     fn get_region<F: Fn(&T) -> bool>(&self, node: Node, test: F) -> Region {
+        with_scratch(|scratch| self.flood(scratch, node, test))
+    }
+
+    /// Finds the region of `get_region` using the scratch buffers.
+    fn flood<F: Fn(&T) -> bool>(&self, scratch: &mut Scratch, node: Node, test: F) -> Region {
         let mut passed_test = HashSet::new();
         let mut adjacencies = HashSet::new();
-        let mut queue = Vec::new();
-        let mut visited = vec![false; self.size * self.size];
+        let Scratch { visited, queue, .. } = scratch;
+        visited.clear(self.vec.len());
+        queue.clear();
 
         queue.push(node);
-        visited[node.0] = true;
+        visited.mark(node);
 
         while let Some(node) = queue.pop() {
             if test(&self[node]) {
                 passed_test.insert(node);
-                for n in self.adjacencies(node) {
-                    if !visited[n.0] {
+                for &n in self.adjacencies(node) {
+                    if visited.mark(n) {
                         queue.push(n);
-                        visited[n.0] = true;
                     }
                 }
             } else {
@@ -180,45 +338,41 @@ impl<T: Clone + Debug + Default + PartialEq> Matrix<T> {
     /// Returns all of the largest connected regions of verticies for which the test function
     /// applied to each vertex returns true.
     pub fn get_regions<F: Fn(&T) -> bool>(&self, test: F) -> Vec<Region> {
-        let mut visited = vec![false; self.size * self.size];
-        let mut regions = Vec::new();
-
-        for i in 0..(self.size() * self.size()) {
-            if visited[i] {
-                continue;
-            }
-
-            let node = Node(i);
-            if test(&self[node]) {
-                let region = self.get_region(node, &test);
-                for n in &region.nodes {
-                    visited[n.0] = true;
+        with_scratch(|scratch| {
+            scratch.in_region.clear(self.vec.len());
+            let mut regions = Vec::new();
+            for node in self.nodes() {
+                if scratch.in_region.is_marked(node) || !test(&self[node]) {
+                    continue;
+                }
+                let region = self.flood(scratch, node, &test);
+                for &n in &region.nodes {
+                    scratch.in_region.mark(n);
                 }
                 regions.push(region);
             }
-        }
-        regions
+            regions
+        })
     }
 
     /// Returns all of the largest connected regions of verticies that are equal to each other.
     #[must_use]
     pub fn get_regions_by_value(&self) -> Vec<Region> {
-        let mut visited = vec![false; self.size * self.size];
-        let mut regions = Vec::new();
-
-        for i in 0..(self.size() * self.size()) {
-            if visited[i] {
-                continue;
-            }
-
-            let node = Node(i);
-            let region = self.get_region(node, |value| value == &self[node]);
-            for n in &region.nodes {
-                visited[n.0] = true;
+        with_scratch(|scratch| {
+            scratch.in_region.clear(self.vec.len());
+            let mut regions = Vec::new();
+            for node in self.nodes() {
+                if scratch.in_region.is_marked(node) {
+                    continue;
+                }
+                let region = self.flood(scratch, node, |value| value == &self[node]);
+                for &n in &region.nodes {
+                    scratch.in_region.mark(n);
+                }
+                regions.push(region);
             }
-            regions.push(region);
-        }
-        regions
+            regions
+        })
     }
 
     /// Returns the matrix to all default values.
@@ -229,16 +383,16 @@ impl<T: Clone + Debug + Default + PartialEq> Matrix<T> {
     }
 
     /// Returns all of the values stored in the Matrix.
-    pub fn values(&self) -> slice::Iter<T> {
+    pub fn values(&self) -> slice::Iter<'_, T> {
         self.vec.iter()
     }
 }
 
-impl<'a, T: Clone + Debug + Default + PartialEq> Index<&'a Vertex> for Matrix<T> {
+impl<T: Clone + Debug + Default + PartialEq> Index<&Vertex> for Matrix<T> {
     type Output = T;
     fn index(&self, vertex: &Vertex) -> &Self::Output {
         self.vec
-            .get(index_from_vertex(*vertex, self.size))
+            .get(vertex::index(*vertex, self.size))
             .expect("vertex not in the matrix")
     }
 }
@@ -250,10 +404,10 @@ impl<T: Clone + Debug + Default + PartialEq> Index<Node> for Matrix<T> {
     }
 }
 
-impl<'a, T: Clone + Debug + Default + PartialEq> IndexMut<&'a Vertex> for Matrix<T> {
+impl<T: Clone + Debug + Default + PartialEq> IndexMut<&Vertex> for Matrix<T> {
     fn index_mut(&mut self, vertex: &Vertex) -> &mut T {
         self.vec
-            .get_mut(index_from_vertex(*vertex, self.size))
+            .get_mut(vertex::index(*vertex, self.size))
             .expect("vertex not in the matrix")
     }
 }
@@ -271,7 +425,11 @@ impl<T: Clone + Debug + Default + PartialEq> From<Vec<T>> for Matrix<T> {
     #[allow(clippy::cast_precision_loss)]
     fn from(vec: Vec<T>) -> Self {
         let size = (vec.len() as f64).sqrt() as usize;
-        Matrix { size, vec }
+        Matrix {
+            size,
+            vec,
+            neighbors: neighbor_table(size),
+        }
     }
 }
 
@@ -285,9 +443,15 @@ pub struct Region {
 impl Region {
     /// Returns an iterator over all of the nodes in the region.
     #[must_use]
-    pub fn nodes(&self) -> hash_set::Iter<Node> {
+    pub fn nodes(&self) -> hash_set::Iter<'_, Node> {
         self.nodes.iter()
     }
+
+    /// Returns an iterator over all of the nodes bordering the region.
+    #[must_use]
+    pub fn adjacencies(&self) -> hash_set::Iter<'_, Node> {
+        self.adjacencies.iter()
+    }
 }
 
 #[cfg(test)]
@@ -298,6 +462,22 @@ mod tests {
 
     static TEST_MATRIX_3: [u32; 9] = [0, 0, 1, 1, 1, 0, 0, 0, 0];
 
+    #[test]
+    fn adjacencies() {
+        let matrix = Matrix::from(TEST_MATRIX_3.to_vec());
+        assert_eq!(matrix.adjacencies(Node(0)), [Node(1), Node(3)]);
+        assert_eq!(
+            matrix.adjacencies(Node(4)),
+            [Node(3), Node(1), Node(5), Node(7)]
+        );
+        assert_eq!(matrix.adjacencies(Node(8)), [Node(7), Node(5)]);
+
+        let clone = matrix.clone();
+        assert!(Arc::ptr_eq(&matrix.neighbors, &clone.neighbors));
+        let same_size = Matrix::<u32>::with_size(3);
+        assert!(Arc::ptr_eq(&matrix.neighbors, &same_size.neighbors));
+    }
+
     #[test]
     fn get_region() {
         let matrix = Matrix::from(TEST_MATRIX_3.to_vec());
@@ -334,6 +514,11 @@ mod tests {
             regions[1].nodes,
             vec![Node(3), Node(4)].into_iter().collect()
         );
+        // Both regions border node 5, and the reused buffers give the same regions again.
+        assert!(regions
+            .iter()
+            .all(|region| region.adjacencies.contains(&Node(5))));
+        assert_eq!(matrix.get_regions(|&value| value == 1), regions);
     }
 
     #[test]
@@ -1,27 +1,81 @@
 //! The core Go logic.
 
+/// Comments, marks and evaluations attached to the moves of a game record.
+pub mod annotation;
+/// Boards of a size fixed at compile time, held in arrays for speed.
+pub mod array_board;
+/// Benchmarks of the rules with results written out as JSON.
+pub mod benchmark;
 /// A structure that maintains the board's arrangement of stones and properties derived from the
 /// arrangement.
 pub mod board;
+/// A builder that configures and checks the settings of a new game.
+pub mod builder;
+/// Time settings and the time each player has left.
+pub mod clock;
+/// A compact binary encoding of game records.
+pub mod encoding;
+/// Estimating who owns each point from the influence of the stones on the board.
+pub mod estimate;
 
+/// Importing collections of SGF files as game records, in parallel with the `rayon` feature.
+pub mod import;
 /// A structure that holds the state all of the verticies of the board in a matrix.
 pub mod matrix;
+/// Writing and reading the numbers of GTP and SGF, such as komi, scores and times, the same way
+/// everywhere.
+pub mod numeric;
 /// Black or White.
 pub mod player;
+/// Positions as values: a board with the player to move, the ko and the rules, without history.
+pub mod position;
+/// Go problems with a goal and a tree of solutions.
+pub mod problem;
+/// Empty regions of a board kept up to date as stones are placed and removed.
+pub mod regions;
+/// ASCII, Unicode and SVG diagrams of boards, with overlays for analysis, and game playback.
+pub mod render;
+/// Pair Go and rengo, where teams of players alternate making their side's moves.
+pub mod rengo;
+/// Move generation by Monte Carlo tree search that can be cancelled, keeping the best move so far.
+pub mod search;
+/// Reading out capturing races between two chains.
+pub mod semeai;
+/// A game owned by its own thread and driven by messages.
+pub mod service;
+/// Reading Smart Game Format (SGF) game records.
+pub mod sgf;
+/// A game shared between threads.
+pub mod shared;
 /// A structure for storing the x and y coordinates of a board cell.
 pub mod vertex;
 
+use rand::rngs::StdRng;
 use rand::{self, Rng};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
 
-use crate::game::board::{Board, Move};
+use crate::game::annotation::Annotation;
+use crate::game::board::{Board, Move, Status};
+use crate::game::builder::GameBuilder;
+use crate::game::clock::TimeLeft;
+use crate::game::matrix::Matrix;
 use crate::game::player::Player;
+use crate::game::position::Position;
 use crate::game::vertex::Vertex;
 
+pub use crate::game::clock::Clock;
+
 /// The compensation in points White gets for going second under Chinese rules.
 pub const CHINESE_KOMI: f64 = 7.5;
-const DEFAULT_BOARD_SIZE: usize = 19;
+pub(crate) const DEFAULT_BOARD_SIZE: usize = 19;
 const MAX_MOVES: usize = 512;
+/// The number of mirrored replies in a row that make a player a mirror Go player.
+const MIRROR_MOVES: usize = 4;
+/// The number of random games played out by `Game::group_status`.
+const GROUP_STATUS_PLAYOUTS: usize = 32;
 
 /// Fixed or Free placement of the handicap stones.
 #[derive(Clone, Copy, Debug)]
@@ -32,29 +86,175 @@ pub enum Handicap {
     Free,
 }
 
-/// The time settings for a game.
-#[derive(Clone, Copy, Debug)]
-pub enum Clock {
-    /// Neither player can lose on time.
-    Unlimited,
+/// How a finished game was decided.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameResult {
+    /// The player won by the given number of points.
+    Score(Player, f64),
+    /// Neither player won.
+    Draw,
+    /// The player won because their opponent ran out of time.
+    Time(Player),
+    /// The game has no result because a position repeated in a long cycle.
+    Void,
+    /// The player won a game of capture Go by making the first capture.
+    Capture(Player),
+    /// The player won because their opponent resigned.
+    Resignation(Player),
+    /// The player won because their opponent forfeited, e.g. by breaking the rules.
+    Forfeit(Player),
+}
+
+impl fmt::Display for GameResult {
+    /// Formats the result the way SGF records it, e.g. `B+T`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GameResult::Score(winner, points) => {
+                write!(
+                    f,
+                    "{}+{}",
+                    winner_letter(winner),
+                    numeric::format_points(points)
+                )
+            }
+            GameResult::Draw => write!(f, "0"),
+            GameResult::Time(winner) => write!(f, "{}+T", winner_letter(winner)),
+            GameResult::Void => write!(f, "Void"),
+            GameResult::Capture(winner) => write!(f, "{}+", winner_letter(winner)),
+            GameResult::Resignation(winner) => write!(f, "{}+R", winner_letter(winner)),
+            GameResult::Forfeit(winner) => write!(f, "{}+F", winner_letter(winner)),
+        }
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = String;
+
+    /// Parses a result written the way SGF records it, e.g. `W+7.5`, `B+R` or `0`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s {
+            "0" | "Draw" => return Ok(GameResult::Draw),
+            "Void" => return Ok(GameResult::Void),
+            _ => {}
+        }
+        let (winner, how) = s
+            .split_once('+')
+            .ok_or_else(|| format!("invalid result {s:?}"))?;
+        let winner = match winner {
+            "B" | "b" => Player::Black,
+            "W" | "w" => Player::White,
+            _ => return Err(format!("invalid winner in {s:?}")),
+        };
+        match how {
+            "" => Ok(GameResult::Capture(winner)),
+            "R" | "Resign" => Ok(GameResult::Resignation(winner)),
+            "T" | "Time" => Ok(GameResult::Time(winner)),
+            "F" | "Forfeit" => Ok(GameResult::Forfeit(winner)),
+            points => numeric::parse_points(points)
+                .map(|points| GameResult::Score(winner, points))
+                .map_err(|_| format!("invalid result {s:?}")),
+        }
+    }
+}
+
+impl GameResult {
+    /// Returns the winner, or None if neither player won.
+    #[must_use]
+    pub fn winner(&self) -> Option<Player> {
+        match *self {
+            GameResult::Score(winner, _)
+            | GameResult::Time(winner)
+            | GameResult::Capture(winner)
+            | GameResult::Resignation(winner)
+            | GameResult::Forfeit(winner) => Some(winner),
+            GameResult::Draw | GameResult::Void => None,
+        }
+    }
+}
+
+fn hash_board(board: &Board) -> u64 {
+    board.zobrist_hash()
+}
+
+fn winner_letter(player: Player) -> char {
+    match player {
+        Player::Black => 'B',
+        Player::White => 'W',
+    }
+}
+
+/// An entry in the game record.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Record {
+    /// The move played.
+    mov: Move,
+    /// The time the player had left after the move, if the move was timed.
+    time_left: Option<TimeLeft>,
+    /// The time the player had left before the move, if the move was timed, to be given back if
+    /// it's undone.
+    time_left_before: Option<TimeLeft>,
+    /// How the move changed the board.
+    delta: Delta,
+    /// Comments, marks and evaluations attached to the move.
+    annotations: Vec<Annotation>,
+}
+
+/// The stones a move removed from the board, so it can be taken back without keeping a copy of
+/// the board before it.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Delta {
+    /// The enemy stones captured.
+    captured: Vec<Vertex>,
+    /// The player's own stones removed by suicide, including the stone played.
+    suicided: Vec<Vertex>,
 }
 
-/// This structure includes everything needed for playing real Go games.
+/// This structure includes everything needed for playing real Go games. It keeps the history of
+/// the game around the current `Position`, for the superko rules, undo and the game record.
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "GameData")
+)]
 pub struct Game {
     /// The current state of the board.
     board: Board,
-    /// All previous board states.
-    previous_boards: Vec<Board>,
+    /// The hashes of the boards each stone was played on, for finding repeated positions. The
+    /// boards themselves are recreated by taking back the stones played since.
+    positions: Vec<u64>,
     /// All moves in the game record.
-    move_history: Vec<Move>,
+    move_history: Vec<Record>,
     /// The score handicap.
     pub komi: f64,
-    _time_settings: Clock,
-    /// Has KGS told us a game just ended?
-    pub kgs_game_over: bool,
+    time_settings: Clock,
+    black_time_left: TimeLeft,
+    white_time_left: TimeLeft,
+    /// The player who ran out of time, if any.
+    timed_out: Option<Player>,
+    /// The player who resigned and the length of the move history when they did, if any.
+    resigned: Option<(usize, Player)>,
+    /// Whether a repetition cycle voided the game.
+    void: bool,
+    /// The player set to move next and the length of the move history when they were set.
+    to_move: Option<(usize, Player)>,
+    /// The player who made the first capture and the move number it was made on.
+    first_capture: Option<(usize, Player)>,
+    /// Whether the game was declared over, e.g. by KGS once the dead stones were agreed on.
+    finished: bool,
     /// The variation of Go being played.
     pub rule_set: RuleSet,
+    /// The ko rule, or None to use the rule set's.
+    pub ko_rule: Option<KoRule>,
+    /// Changes to how a game is won.
+    pub variant: Variant,
+    /// The generator random moves are drawn from if the game was seeded, or None to use the
+    /// thread's. It isn't serialized, so a deserialized game draws from the thread's.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    rng: Option<StdRng>,
 }
 
 impl Default for Game {
@@ -63,6 +263,104 @@ impl Default for Game {
     }
 }
 
+/// A game as it's serialized. It's only turned into a `Game` by replaying its moves, so a history
+/// that doesn't fit the board is rejected instead of breaking `undo` later.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "Game")]
+struct GameData {
+    board: Board,
+    positions: Vec<u64>,
+    move_history: Vec<Record>,
+    komi: f64,
+    time_settings: Clock,
+    black_time_left: TimeLeft,
+    white_time_left: TimeLeft,
+    timed_out: Option<Player>,
+    resigned: Option<(usize, Player)>,
+    to_move: Option<(usize, Player)>,
+    finished: bool,
+    rule_set: RuleSet,
+    ko_rule: Option<KoRule>,
+    variant: Variant,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<GameData> for Game {
+    type Error = String;
+
+    fn try_from(data: GameData) -> Result<Self, String> {
+        // The board the game started from is found by taking back the stones played.
+        let mut initial = data.board.clone();
+        for record in data.move_history.iter().rev() {
+            let Some(vertex) = record.mov.vertex else {
+                continue;
+            };
+            let delta = &record.delta;
+            let off_board = [vertex]
+                .iter()
+                .chain(&delta.captured)
+                .chain(&delta.suicided)
+                .copied()
+                .find(|&vertex| initial.node_of(vertex).is_none());
+            if let Some(Vertex { x, y }) = off_board {
+                // Vertices far off the board have no name.
+                return Err(format!("({x}, {y}) is not on the board"));
+            }
+            // The stone must be on the board, unless it was removed by suicide, and the stones it
+            // removed must be gone.
+            let removed: HashSet<_> = delta.captured.iter().chain(&delta.suicided).collect();
+            let fits = removed.len() == delta.captured.len() + delta.suicided.len()
+                && removed.iter().all(|&&vertex| initial.is_vacant(vertex))
+                && (removed.contains(&vertex) || initial.stone(vertex) == Some(record.mov.player));
+            if !fits {
+                return Err(format!("the move at {vertex} doesn't fit the board"));
+            }
+            initial.unplace_stone(record.mov.player, vertex, &delta.captured, &delta.suicided);
+        }
+        initial
+            .is_reachable()
+            .map_err(|err| format!("invalid initial board: {err}"))?;
+
+        let mut game = Game::with_board_size(initial.size())?;
+        game.board = initial;
+        game.komi = data.komi;
+        game.rule_set = data.rule_set;
+        game.ko_rule = data.ko_rule;
+        game.variant = data.variant;
+        for (index, record) in data.move_history.into_iter().enumerate() {
+            game.play(&record.mov)
+                .map_err(|err| format!("move {}: {err}", index + 1))?;
+            let replayed = game
+                .move_history
+                .last_mut()
+                .expect("a move was just played");
+            replayed.time_left = record.time_left;
+            replayed.annotations = record.annotations;
+        }
+        if game.board != data.board || game.positions != data.positions {
+            return Err("the board doesn't follow from the moves".to_owned());
+        }
+
+        let moves = game.move_history.len();
+        if [data.resigned, data.to_move]
+            .iter()
+            .flatten()
+            .any(|&(count, _)| count > moves)
+        {
+            return Err("a move number is past the end of the game".to_owned());
+        }
+        game.time_settings = data.time_settings;
+        game.black_time_left = data.black_time_left;
+        game.white_time_left = data.white_time_left;
+        game.timed_out = data.timed_out;
+        game.resigned = data.resigned;
+        game.to_move = data.to_move;
+        game.finished = data.finished;
+        Ok(game)
+    }
+}
+
 impl Game {
     /// Returns a shared reference to the game board.
     #[must_use]
@@ -72,9 +370,15 @@ impl Game {
 
     /// Clears all of the stones off the board and deletes the move history.
     pub fn clear_board(&mut self) {
-        self.previous_boards.clear();
+        self.positions.clear();
         self.move_history.clear();
         self.board.clear();
+        self.void = false;
+        self.to_move = None;
+        self.first_capture = None;
+        self.resigned = None;
+        self.finished = false;
+        self.set_time_settings(self.time_settings);
     }
 
     /// Picks a move uniform randomly from all the the possible legal moves.
@@ -83,10 +387,12 @@ impl Game {
     /// Failed to pass, programming error.
     pub fn genmove_random(&mut self, player: Player) -> Move {
         let mut possible_moves = self.board.empty_verts();
-        let mut rng = rand::thread_rng();
 
         while !possible_moves.is_empty() {
-            let index = rng.gen_range(0..possible_moves.len());
+            let index = match &mut self.rng {
+                Some(rng) => rng.gen_range(0..possible_moves.len()),
+                None => rand::thread_rng().gen_range(0..possible_moves.len()),
+            };
             let mov = Move {
                 player,
                 vertex: Some(possible_moves[index]),
@@ -124,6 +430,33 @@ impl Game {
         legal_moves
     }
 
+    /// Returns a matrix that is true on every point the player may play on and false elsewhere,
+    /// e.g. as the legal move plane of a neural network's input or to grey out illegal points.
+    #[must_use]
+    pub fn legal_move_mask(&self, player: Player) -> Matrix<bool> {
+        let mut mask = Matrix::with_size(self.board.size());
+        let ko = self.ko();
+        let superko = matches!(
+            self.ko_rule(),
+            KoRule::PositionalSuperko | KoRule::SituationalSuperko
+        );
+        let positions: HashSet<u64> = self.positions.iter().copied().collect();
+        for (vertex, placement) in self.board.previews(player) {
+            mask[&vertex] = position::check_placement(vertex, &placement, ko, self.rule_set)
+                .is_ok()
+                // Only a board that occurred before can break a superko rule, so the move is only
+                // played out when the hash of the board it makes matches an earlier one.
+                && (!superko
+                    || !positions.contains(&placement.hash)
+                    || self
+                        .try_place_stone(player, vertex, |board| {
+                            self.stones_played_on(board).next().is_some()
+                        })
+                        .is_some());
+        }
+        mask
+    }
+
     /// Returns the difference in moves left for each player. Positive values mean Black is ahead.
     /// This may be extended to surreal numbers and combinatorial game values to give a more precise
     /// description of the state of the game.
@@ -142,15 +475,30 @@ impl Game {
     pub fn with_board_size(board_size: usize) -> Result<Self, String> {
         Board::with_size(board_size).map(|board| Game {
             board,
-            previous_boards: Vec::new(),
+            positions: Vec::new(),
             move_history: Vec::new(),
             komi: CHINESE_KOMI,
-            _time_settings: Clock::Unlimited,
-            kgs_game_over: false,
+            time_settings: Clock::Unlimited,
+            black_time_left: TimeLeft::default(),
+            white_time_left: TimeLeft::default(),
+            timed_out: None,
+            resigned: None,
+            void: false,
+            to_move: None,
+            first_capture: None,
+            finished: false,
             rule_set: RuleSet::Chinese,
+            ko_rule: None,
+            variant: Variant::Standard,
+            rng: None,
         })
     }
 
+    /// Returns a builder for configuring a new game.
+    pub fn builder() -> GameBuilder {
+        GameBuilder::new()
+    }
+
     /// Returns a new game with the default board size.
     #[allow(clippy::missing_panics_doc)]
     #[must_use]
@@ -159,31 +507,180 @@ impl Game {
     }
 
     fn is_legal_move(&self, mov: &Move) -> bool {
-        if let Some(vertex) = mov.vertex {
-            // The vertex must exist and be empty.
-            if !self.board.is_vacant(vertex) {
-                return false;
-            }
+        let Some(vertex) = mov.vertex else {
+            return true;
+        };
+        let Ok(placement) =
+            position::check_stone(&self.board, mov.player, vertex, self.ko(), self.rule_set)
+        else {
+            return false;
+        };
+        // Only a move to a board that occurred before can break a ko rule, so the board is only
+        // copied to check the rule when its hash matches an earlier one.
+        !self.positions.contains(&placement.hash)
+            || self
+                .try_place_stone(mov.player, vertex, |board| {
+                    self.stones_played_on(board).next().is_some()
+                })
+                .is_some()
+    }
 
-            // Also, check the suicide and ko rules:
-            let mut test_board = self.board.clone();
-            test_board.place_stone(mov.player, vertex);
-            match self.rule_set {
-                RuleSet::Chinese => {
-                    // Check if the move committed suicide.
-                    if test_board.is_vacant(vertex) {
-                        return false;
-                    }
-                    // Check whether the super-ko rule was broken.
-                    for board in &self.previous_boards {
-                        if test_board == *board {
-                            return false;
-                        }
-                    }
+    /// Returns the board before the stone with the given index was played, or the current board
+    /// if there is no such stone, by taking back the stones played since.
+    fn board_before_stone(&self, index: usize) -> Board {
+        let mut board = self.board.clone();
+        let stones = self
+            .move_history
+            .iter()
+            .rev()
+            .filter_map(|record| record.mov.vertex.map(|vertex| (record, vertex)))
+            .take(self.positions.len().saturating_sub(index));
+        for (record, vertex) in stones {
+            board.unplace_stone(
+                record.mov.player,
+                vertex,
+                &record.delta.captured,
+                &record.delta.suicided,
+            );
+        }
+        board
+    }
+
+    /// Returns the board the game started from, before any stones were played.
+    pub(crate) fn initial_board(&self) -> Board {
+        self.board_before_stone(0)
+    }
+
+    /// Returns the indices of the stones that were played on the board, oldest first.
+    fn stones_played_on<'a>(&'a self, board: &'a Board) -> impl Iterator<Item = usize> + 'a {
+        let hash = hash_board(board);
+        self.positions
+            .iter()
+            .enumerate()
+            .filter(move |&(_, &position)| position == hash)
+            .map(|(index, _)| index)
+            .filter(move |&index| self.board_before_stone(index) == *board)
+    }
+
+    /// Returns the board after placing a stone and the stones it removed, or None if the move is
+    /// illegal. `occurred` says whether a board occurred earlier in the game, for superko.
+    fn try_place_stone<F>(
+        &self,
+        player: Player,
+        vertex: Vertex,
+        occurred: F,
+    ) -> Option<(Board, Delta)>
+    where
+        F: Fn(&Board) -> bool,
+    {
+        // The position checks the move is on an empty point and breaks neither the suicide rule
+        // nor the simple ko rule, which the superko rules include.
+        let (position, captured) = Position::new(self.board.clone(), player, self.rule_set)
+            .with_ko(self.ko())
+            .into_played(&Move {
+                player,
+                vertex: Some(vertex),
+            })
+            .ok()?;
+        let test_board = position.into_board();
+        match self.ko_rule() {
+            KoRule::PositionalSuperko => {
+                // Check whether the positional super-ko rule was broken.
+                if occurred(&test_board) {
+                    return None;
+                }
+            }
+            KoRule::SituationalSuperko => {
+                // Check whether the situational super-ko rule was broken.
+                if self.situation_occurred(&test_board, player.enemy()) {
+                    return None;
                 }
             }
+            KoRule::Simple | KoRule::Unrestricted => {}
+        }
+        let suicided = if test_board.is_vacant(vertex) {
+            let mut suicided = self.board.stones(player);
+            suicided.retain(|&stone| test_board.is_vacant(stone));
+            suicided.push(vertex);
+            suicided
+        } else {
+            Vec::new()
+        };
+        Some((test_board, Delta { captured, suicided }))
+    }
+
+    /// Returns the ko rule in force: the one set on the game, or else the rule set's.
+    #[must_use]
+    pub fn ko_rule(&self) -> KoRule {
+        self.ko_rule.unwrap_or_else(|| self.rule_set.ko_rule())
+    }
+
+    /// Whether the board occurred earlier in the game with the player to move, each move in the
+    /// record being made from the board before it by the player to move.
+    fn situation_occurred(&self, board: &Board, to_move: Player) -> bool {
+        // A position is kept for each stone played, so passes share the board of the next stone.
+        let hash = hash_board(board);
+        let current = hash_board(&self.board);
+        let mut stone = 0;
+        for record in &self.move_history {
+            let position = self.positions.get(stone).copied().unwrap_or(current);
+            if record.mov.player == to_move
+                && position == hash
+                && self.board_before_stone(stone) == *board
+            {
+                return true;
+            }
+            if record.mov.vertex.is_some() {
+                stone += 1;
+            }
         }
-        true
+        false
+    }
+
+    /// Checks that a sequence of moves can be played from the current position, without changing
+    /// the game. Earlier positions are looked up by hash, so long sequences are checked without
+    /// rescanning the history for every move.
+    ///
+    /// # Errors
+    ///
+    /// Returns the index of the first illegal move and why it is illegal.
+    pub fn validate_sequence(&self, moves: &[Move]) -> Result<(), (usize, String)> {
+        let mut game = self.clone();
+        let mut occurred: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (index, &position) in game.positions.iter().enumerate() {
+            occurred.entry(position).or_default().push(index);
+        }
+
+        for (index, mov) in moves.iter().enumerate() {
+            let mut delta = Delta::default();
+            if let Some(vertex) = mov.vertex {
+                let board;
+                (board, delta) = game
+                    .try_place_stone(mov.player, vertex, |board| {
+                        occurred.get(&hash_board(board)).map_or(false, |indices| {
+                            indices
+                                .iter()
+                                .any(|&i| game.board_before_stone(i) == *board)
+                        })
+                    })
+                    .ok_or_else(|| (index, format!("illegal move {vertex} by {}", mov.player)))?;
+                let position = hash_board(&game.board);
+                occurred
+                    .entry(position)
+                    .or_default()
+                    .push(game.positions.len());
+                game.positions.push(position);
+                game.board = board;
+            }
+            game.move_history.push(Record {
+                mov: *mov,
+                time_left: None,
+                time_left_before: None,
+                delta,
+                annotations: Vec::new(),
+            });
+        }
+        Ok(())
     }
 
     /// Attempts to play a move.
@@ -192,30 +689,651 @@ impl Game {
     ///
     /// The move is illegal.
     pub fn play(&mut self, mov: &Move) -> Result<(), String> {
+        let mut delta = Delta::default();
+        if let Some(vertex) = mov.vertex {
+            let board;
+            (board, delta) = self
+                .try_place_stone(mov.player, vertex, |board| {
+                    self.stones_played_on(board).next().is_some()
+                })
+                .ok_or("illegal move")?;
+            self.positions.push(hash_board(&self.board));
+            self.board = board;
+            if !delta.captured.is_empty() && self.first_capture.is_none() {
+                self.first_capture = Some((self.move_history.len() + 1, mov.player));
+            }
+        }
+
+        self.move_history.push(Record {
+            mov: *mov,
+            time_left: None,
+            time_left_before: None,
+            delta,
+            annotations: Vec::new(),
+        });
+        if mov.vertex.is_some() {
+            self.void = self.is_long_cycle();
+        }
+        Ok(())
+    }
+
+    /// Attempts to play a move that took `elapsed` time, charging the player's clock. The time
+    /// left after the move is kept in the game record.
+    ///
+    /// # Errors
+    ///
+    /// The move is illegal or the player ran out of time making it, which ends the game.
+    pub fn play_timed(&mut self, mov: &Move, elapsed: Duration) -> Result<(), String> {
+        if self.timed_out.is_some() {
+            return Err("the game is over".to_owned());
+        }
         if !self.is_legal_move(mov) {
             return Err("illegal move".to_owned());
         }
 
-        if let Some(vertex) = mov.vertex {
-            self.previous_boards.push(self.board.clone());
-            self.board.place_stone(mov.player, vertex);
+        let clock = self.time_settings;
+        let time_left_before = self.time_left(mov.player);
+        let time_left = self.time_left_mut(mov.player);
+        let in_time = clock.spend(time_left, elapsed);
+        let time_left = *time_left;
+        if !in_time {
+            self.timed_out = Some(mov.player);
+            return Err(format!("{} ran out of time", mov.player));
         }
 
-        self.move_history.push(*mov);
+        self.play(mov)?;
+        if let Some(record) = self.move_history.last_mut() {
+            record.time_left = Some(time_left);
+            record.time_left_before = Some(time_left_before);
+        }
         Ok(())
     }
 
-    /// Undo the last move.
+    /// Returns the moves played so far, passes included, in order.
+    pub fn moves(&self) -> impl Iterator<Item = &Move> {
+        self.move_history.iter().map(|record| &record.mov)
+    }
+
+    /// Returns the last move played, or None if no move has been played.
+    #[must_use]
+    pub fn last_move(&self) -> Option<&Move> {
+        self.move_history.last().map(|record| &record.mov)
+    }
+
+    /// Returns the number of moves played, passes included.
+    #[must_use]
+    pub fn move_number(&self) -> usize {
+        self.move_history.len()
+    }
+
+    /// Attaches an annotation to the given move (counting from 1).
     ///
     /// # Errors
     ///
-    /// Fails if there are no moves to undo.
+    /// There is no such move, or the annotation marks a vertex off the board.
+    pub fn annotate(&mut self, move_number: usize, annotation: Annotation) -> Result<(), String> {
+        if let Some(vertex) = annotation.vertex() {
+            if vertex.x >= self.board.size() || vertex.y >= self.board.size() {
+                return Err(format!("{vertex} is not on the board"));
+            }
+        }
+        let record = move_number
+            .checked_sub(1)
+            .and_then(|index| self.move_history.get_mut(index))
+            .ok_or_else(|| format!("there is no move {move_number}"))?;
+        record.annotations.push(annotation);
+        Ok(())
+    }
+
+    /// Returns the annotations attached to the given move (counting from 1), in the order they
+    /// were added.
+    #[must_use]
+    pub fn annotations(&self, move_number: usize) -> &[Annotation] {
+        move_number
+            .checked_sub(1)
+            .and_then(|index| self.move_history.get(index))
+            .map_or(&[], |record| &record.annotations)
+    }
+
+    /// Returns the time settings.
+    #[must_use]
+    pub fn time_settings(&self) -> Clock {
+        self.time_settings
+    }
+
+    /// Changes the time settings and gives both players their full time.
+    pub fn set_time_settings(&mut self, clock: Clock) {
+        self.time_settings = clock;
+        self.black_time_left = clock.initial_time_left();
+        self.white_time_left = clock.initial_time_left();
+        self.timed_out = None;
+    }
+
+    /// Returns the time a player has left, including the byo-yomi periods used up.
+    #[must_use]
+    pub fn time_left(&self, player: Player) -> TimeLeft {
+        match player {
+            Player::Black => self.black_time_left,
+            Player::White => self.white_time_left,
+        }
+    }
+
+    fn time_left_mut(&mut self, player: Player) -> &mut TimeLeft {
+        match player {
+            Player::Black => &mut self.black_time_left,
+            Player::White => &mut self.white_time_left,
+        }
+    }
+
+    /// Returns how long the player should think about their next move with the time they have
+    /// left, as `Clock::move_time` works it out, or None if they have no time limit.
+    #[must_use]
+    pub fn move_time(&self, player: Player) -> Option<Duration> {
+        self.time_settings.move_time(&self.time_left(player))
+    }
+
+    /// Updates a player's time from the `time` and `stones` arguments of the GTP `time_left`
+    /// command. A player reported to have no time and no stones left has run out of time.
+    pub fn set_time_left(&mut self, player: Player, time: Duration, stones: u32) {
+        let clock = self.time_settings;
+        clock.set_gtp_time_left(self.time_left_mut(player), time, stones);
+        if clock != Clock::Unlimited && time.is_zero() && stones == 0 {
+            self.timed_out = Some(player);
+        }
+    }
+
+    /// Returns the time the player had left after the given move (counting from 1), if the move
+    /// was timed.
+    #[must_use]
+    pub fn recorded_time_left(&self, move_number: usize) -> Option<TimeLeft> {
+        move_number
+            .checked_sub(1)
+            .and_then(|index| self.move_history.get(index))
+            .and_then(|record| record.time_left)
+    }
+
+    /// Whether the current position has now occurred three times. Without superko long cycles
+    /// such as triple ko are legal, so they void the game instead, unless the ko rule is
+    /// unrestricted for analysis.
+    fn is_long_cycle(&self) -> bool {
+        if self.ko_rule() == KoRule::Unrestricted {
+            return false;
+        }
+        match self.rule_set {
+            RuleSet::Chinese | RuleSet::AGA | RuleSet::TrompTaylor => false,
+            RuleSet::Japanese => self.stones_played_on(&self.board).count() >= 2,
+        }
+    }
+
+    /// Returns the result of the game if it has been decided.
+    #[must_use]
+    pub fn result(&self) -> Option<GameResult> {
+        if let Some((_, player)) = self.resigned {
+            return Some(GameResult::Resignation(player.enemy()));
+        }
+        if let Some(player) = self.timed_out {
+            return Some(GameResult::Time(player.enemy()));
+        }
+        if self.void {
+            return Some(GameResult::Void);
+        }
+        if let (Variant::CaptureGo, Some((_, player))) = (self.variant, self.first_capture) {
+            return Some(GameResult::Capture(player));
+        }
+        if !self.is_over() {
+            return None;
+        }
+        Some(self.score_result())
+    }
+
+    /// Returns how far the game has got: in progress, being scored after the players passed, or
+    /// finished with a result that stands.
+    #[must_use]
+    pub fn lifecycle(&self) -> Lifecycle {
+        if self.finished {
+            return Lifecycle::Finished;
+        }
+        match self.result() {
+            None => Lifecycle::InProgress,
+            Some(GameResult::Score(..) | GameResult::Draw) => Lifecycle::Scoring,
+            Some(_) => Lifecycle::Finished,
+        }
+    }
+
+    /// Declares the game finished, e.g. when the server says it's over after the dead stones were
+    /// agreed on. Clearing the board starts a new game in progress.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Returns the result of scoring the position as it stands, whether or not the game is over.
+    #[must_use]
+    pub fn score_result(&self) -> GameResult {
+        self.score_result_removing(&self.dead_stones())
+    }
+
+    /// Returns the result of scoring the position with the stones on the vertices removed as
+    /// dead, instead of those in `dead_stones`.
+    pub(crate) fn score_result_removing(&self, dead_stones: &[Vertex]) -> GameResult {
+        let score = self.score_removing(dead_stones);
+        if score > 0.0 {
+            GameResult::Score(Player::Black, score)
+        } else if score < 0.0 {
+            GameResult::Score(Player::White, -score)
+        } else {
+            GameResult::Draw
+        }
+    }
+
+    /// Estimates how strongly each point belongs to a player as the position stands, from 1 for
+    /// Black to -1 for White. See `estimate::ownership`.
+    #[must_use]
+    pub fn territory_estimate(&self) -> HashMap<Vertex, f64> {
+        estimate::ownership(&self.board)
+    }
+
+    /// Returns the stones that are dead as the position stands. Only the statuses settled by the
+    /// rules themselves are recognized: under Japanese rules a bent four in the corner is dead.
+    #[must_use]
+    pub fn dead_stones(&self) -> Vec<Vertex> {
+        match self.rule_set {
+            RuleSet::Chinese | RuleSet::AGA | RuleSet::TrompTaylor => Vec::new(),
+            RuleSet::Japanese => self.board.bent_four_in_corner(),
+        }
+    }
+
+    /// Returns the stones with the status, sorted, as estimated by `Board::statuses` for a game
+    /// that has been played out. The stones in `dead_stones` are always dead.
+    #[must_use]
+    pub fn stones_with_status(&self, status: Status) -> Vec<Vertex> {
+        let settled = self.dead_stones();
+        let mut stones: Vec<Vertex> = self
+            .board
+            .statuses()
+            .into_iter()
+            .filter(|&(vertex, estimated)| {
+                let actual = if settled.contains(&vertex) {
+                    Status::Dead
+                } else {
+                    estimated
+                };
+                actual == status
+            })
+            .map(|(vertex, _)| vertex)
+            .collect();
+        stones.sort_by_key(|vertex| (vertex.x, vertex.y));
+        stones
+    }
+
+    /// Judges whether the chain with a stone on the vertex lives, or returns None if there is no
+    /// stone there. A chain that is pass alive by Benson's algorithm or has two real eyes is
+    /// alive, one in the opponent's pass alive territory is dead, and one in `Board::seki_groups`
+    /// is in seki. Any other chain is judged by how often it survives random games played out
+    /// from the position, half of them with each player moving first, so the verdict may differ
+    /// between calls.
+    #[must_use]
+    pub fn group_status(&self, vertex: Vertex) -> Option<GroupStatus> {
+        let chain = self.board.chain_at(vertex)?;
+        let player = chain.player();
+        match self.board.unconditional_owner(vertex) {
+            Some(owner) if owner == player => return Some(GroupStatus::Alive),
+            Some(_) => return Some(GroupStatus::Dead),
+            None => {}
+        }
+        if self
+            .board
+            .seki_groups()
+            .iter()
+            .any(|group| group.contains(&vertex))
+        {
+            return Some(GroupStatus::Seki);
+        }
+        let eyes = chain
+            .liberties()
+            .into_iter()
+            .filter(|&liberty| self.board.is_eye(liberty, player))
+            .count();
+        if eyes >= 2 {
+            return Some(GroupStatus::Alive);
+        }
+
+        let size = self.board.size();
+        let mut rng = rand::thread_rng();
+        let mut survived = 0;
+        for playout in 0..GROUP_STATUS_PLAYOUTS {
+            let mut game = self.clone();
+            game.set_to_move(if playout % 2 == 0 {
+                player
+            } else {
+                player.enemy()
+            });
+            let mut passes = 0;
+            for _ in 0..2 * size * size {
+                search::play_random(&mut game, &mut rng);
+                passes = match game.last_move() {
+                    Some(Move { vertex: None, .. }) => passes + 1,
+                    _ => 0,
+                };
+                if passes == 2 {
+                    break;
+                }
+            }
+            if game.board.stone(vertex) == Some(player) {
+                survived += 1;
+            }
+        }
+        Some(if survived * 5 >= GROUP_STATUS_PLAYOUTS * 4 {
+            GroupStatus::Alive
+        } else if survived * 5 <= GROUP_STATUS_PLAYOUTS {
+            GroupStatus::Dead
+        } else {
+            GroupStatus::Unsettled
+        })
+    }
+
+    /// Returns the next stone the player should play to clean up a finished game before it's
+    /// counted by area, or None if there is nothing left to do. Chains in atari are connected
+    /// first by filling their last liberty, then the dame are filled: empty points next to stones
+    /// of both players. No stone is played that would capture or leave its own chain in atari, so
+    /// cleaning up never gives the opponent new points to fill.
+    #[must_use]
+    pub fn cleanup_move(&self, player: Player) -> Option<Vertex> {
+        let board = &self.board;
+        let playable = |vertex: Vertex| {
+            let captures = board.neighbors(vertex).into_iter().any(|neighbor| {
+                board.stone(neighbor) == Some(player.enemy())
+                    && board.liberties(neighbor) == Some(1)
+            });
+            !captures
+                && !board.is_self_atari(player, vertex)
+                && self.is_legal_move(&Move {
+                    player,
+                    vertex: Some(vertex),
+                })
+        };
+        let connections = board
+            .chains_in_atari(player)
+            .into_iter()
+            .filter_map(|chain| board.liberty_vertices(chain[0]).first().copied());
+        let dame = board.empty_verts().into_iter().filter(|&vertex| {
+            let neighbors = board.neighbors(vertex);
+            [Player::Black, Player::White].into_iter().all(|color| {
+                neighbors
+                    .iter()
+                    .any(|&neighbor| board.stone(neighbor) == Some(color))
+            })
+        });
+        connections.chain(dame).find(|&vertex| playable(vertex))
+    }
+
+    /// Plays the `cleanup_move`s of both players in turn, passing for a player without one, until
+    /// neither has one and the game ends with two passes. Returns the moves played, none if there
+    /// was nothing to clean up.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn fill_dame(&mut self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        if [Player::Black, Player::White]
+            .into_iter()
+            .all(|player| self.cleanup_move(player).is_none())
+        {
+            return moves;
+        }
+        let mut passes = 0;
+        while passes < 2 {
+            let player = self.player_turn();
+            let mov = Move {
+                player,
+                vertex: self.cleanup_move(player),
+            };
+            self.play(&mov).expect("cleanup moves are legal");
+            passes = if mov.vertex.is_none() { passes + 1 } else { 0 };
+            moves.push(mov);
+        }
+        moves
+    }
+
+    /// Returns the score of the current position counting every stone not in `dead_stones` as
+    /// alive. Positive values mean Black is ahead.
+    ///
+    /// Under AGA rules passing hands a prisoner to the opponent and White passes last, which makes
+    /// counting territory and prisoners give the same result as counting area. To keep it so in
+    /// handicap games, White gets a point for each handicap stone after the first.
+    ///
+    /// Under Japanese rules territory and prisoners are counted, so stones on the board are not
+    /// points.
+    #[must_use]
+    pub fn score(&self) -> f64 {
+        self.score_removing(&self.dead_stones())
+    }
+
+    /// Returns the score of the position with the stones on the vertices removed as dead, instead
+    /// of those in `dead_stones`.
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn score_removing(&self, dead_stones: &[Vertex]) -> f64 {
+        let points = match self.rule_set {
+            RuleSet::Chinese | RuleSet::TrompTaylor => {
+                f64::from(self.board.score_area_removing(dead_stones))
+            }
+            RuleSet::AGA => {
+                f64::from(self.board.score_area_removing(dead_stones))
+                    - self.handicap().saturating_sub(1) as f64
+            }
+            RuleSet::Japanese => {
+                f64::from(self.board.score_territory_removing(dead_stones))
+                    + self.prisoners(Player::Black) as f64
+                    - self.prisoners(Player::White) as f64
+            }
+        };
+        points - self.komi
+    }
+
+    /// The number of handicap stones: the black stones on the board before the first move, if
+    /// there were no white stones.
+    #[must_use]
+    pub fn handicap(&self) -> usize {
+        let initial = self.initial_board();
+        if initial.stones(Player::White).is_empty() {
+            initial.stones(Player::Black).len()
+        } else {
+            0
+        }
+    }
+
+    /// The number of the opponent's stones the player has captured during the game.
+    #[must_use]
+    pub fn prisoners(&self, player: Player) -> usize {
+        self.move_history
+            .iter()
+            .filter(|record| record.mov.player == player)
+            .map(|record| record.delta.captured.len())
+            .sum()
+    }
+
+    /// The number of the opponent's stones the player has taken off the board: the `prisoners`,
+    /// plus any the opponent removed by suicide where the rules allow it, so the two only differ
+    /// under rules that allow suicide.
+    #[must_use]
+    pub fn captures(&self, player: Player) -> usize {
+        let suicided: usize = self
+            .move_history
+            .iter()
+            .filter(|record| record.mov.player != player)
+            .map(|record| record.delta.suicided.len())
+            .sum();
+        self.prisoners(player) + suicided
+    }
+
+    /// The stones taken off the board by the last move, e.g. for a UI to show them being removed.
+    #[must_use]
+    pub fn last_captures(&self) -> Vec<Vertex> {
+        self.move_history.last().map_or_else(Vec::new, |record| {
+            let delta = &record.delta;
+            delta
+                .captured
+                .iter()
+                .chain(&delta.suicided)
+                .copied()
+                .collect()
+        })
+    }
+
+    /// The number of pass stones the player has handed to their opponent as prisoners. This is
+    /// always 0 unless the rules call for pass stones.
+    #[must_use]
+    pub fn pass_stones(&self, player: Player) -> usize {
+        match self.rule_set {
+            RuleSet::Chinese | RuleSet::Japanese | RuleSet::TrompTaylor => 0,
+            RuleSet::AGA => self
+                .move_history
+                .iter()
+                .filter(|record| record.mov.player == player && record.mov.vertex.is_none())
+                .count(),
+        }
+    }
+
+    /// Returns an independent copy of the game as it was after the given number of moves. Each
+    /// player's clock is restored to the time recorded with their last move. If `move_number` is
+    /// past the end of the game, the whole game is copied.
+    #[must_use]
+    pub fn fork_at(&self, move_number: usize) -> Game {
+        if move_number >= self.move_history.len() {
+            return self.clone();
+        }
+
+        let move_history = self.move_history[..move_number].to_vec();
+        let stones = move_history
+            .iter()
+            .filter(|record| record.mov.vertex.is_some())
+            .count();
+        let time_left = |player: Player| {
+            move_history
+                .iter()
+                .rev()
+                .filter(|record| record.mov.player == player)
+                .find_map(|record| record.time_left)
+                .unwrap_or_else(|| self.time_settings.initial_time_left())
+        };
+
+        Game {
+            board: self.board_before_stone(stones),
+            positions: self.positions[..stones].to_vec(),
+            black_time_left: time_left(Player::Black),
+            white_time_left: time_left(Player::White),
+            timed_out: None,
+            resigned: self.resigned.filter(|&(count, _)| count <= move_number),
+            void: false,
+            to_move: self.to_move.filter(|&(count, _)| count <= move_number),
+            first_capture: self
+                .first_capture
+                .filter(|&(number, _)| number <= move_number),
+            move_history,
+            ..self.clone_settings()
+        }
+    }
+
+    /// Returns a game with the same settings, but without any of the board or history.
+    fn clone_settings(&self) -> Game {
+        Game {
+            board: Board::with_size(self.board.size()).expect("valid board size"),
+            positions: Vec::new(),
+            move_history: Vec::new(),
+            komi: self.komi,
+            time_settings: self.time_settings,
+            black_time_left: self.time_settings.initial_time_left(),
+            white_time_left: self.time_settings.initial_time_left(),
+            timed_out: None,
+            resigned: None,
+            void: false,
+            to_move: None,
+            first_capture: None,
+            finished: false,
+            rule_set: self.rule_set,
+            ko_rule: self.ko_rule,
+            variant: self.variant,
+            rng: self.rng.clone(),
+        }
+    }
+
+    /// Returns the game with the colors swapped, as if every stone and move had been the other
+    /// player's. Komi changes sign, and each player's time, resignation and turn go to the other,
+    /// so a position good for Black in this game is as good for White in the swapped one.
     #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn swap_colors(&self) -> Game {
+        let mut game = self.clone();
+        let stones: Vec<(Player, Vertex)> = [Player::Black, Player::White]
+            .into_iter()
+            .flat_map(|player| {
+                self.board
+                    .stones(player)
+                    .into_iter()
+                    .map(move |vertex| (player.enemy(), vertex))
+            })
+            .collect();
+        game.board
+            .set_stones(&stones)
+            .expect("stones are from a board of the same size");
+        for record in &mut game.move_history {
+            record.mov.player = record.mov.player.enemy();
+        }
+
+        // Take the stones back again to hash the boards they were played on.
+        let mut board = game.board.clone();
+        game.positions.clear();
+        for record in game.move_history.iter().rev() {
+            if let Some(vertex) = record.mov.vertex {
+                board.unplace_stone(
+                    record.mov.player,
+                    vertex,
+                    &record.delta.captured,
+                    &record.delta.suicided,
+                );
+                game.positions.push(hash_board(&board));
+            }
+        }
+        game.positions.reverse();
+
+        game.komi = -self.komi;
+        game.black_time_left = self.white_time_left;
+        game.white_time_left = self.black_time_left;
+        game.timed_out = self.timed_out.map(|player| player.enemy());
+        game.resigned = self.resigned.map(|(count, player)| (count, player.enemy()));
+        game.to_move = self.to_move.map(|(count, player)| (count, player.enemy()));
+        game.first_capture = self
+            .first_capture
+            .map(|(number, player)| (number, player.enemy()));
+        game
+    }
+
+    /// Undo the last move. If it was timed, the player gets back the time it took.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there are no moves to undo.
     pub fn undo(&mut self) -> Result<(), String> {
         match self.move_history.pop() {
-            Some(mov) => {
-                if mov.vertex.is_some() {
-                    self.board = self.previous_boards.pop().unwrap();
+            Some(record) => {
+                if let Some(vertex) = record.mov.vertex {
+                    self.board.unplace_stone(
+                        record.mov.player,
+                        vertex,
+                        &record.delta.captured,
+                        &record.delta.suicided,
+                    );
+                    self.positions.pop();
+                }
+                self.void = false;
+                if let Some((move_number, _)) = self.first_capture {
+                    if move_number > self.move_history.len() {
+                        self.first_capture = None;
+                    }
+                }
+                self.resigned = self
+                    .resigned
+                    .filter(|&(count, _)| count <= self.move_history.len());
+                if let Some(time_left) = record.time_left_before {
+                    *self.time_left_mut(record.mov.player) = time_left;
+                    self.timed_out = None;
                 }
                 Ok(())
             }
@@ -223,8 +1341,8 @@ impl Game {
         }
     }
 
-    /// Places handicap stones in fixed locations based on the number requested and the size of
-    /// the board.
+    /// Places handicap stones for Black: fixed handicaps on the points of
+    /// `Board::fixed_handicaps`, and free handicaps on those of `Board::free_handicaps`.
     ///
     /// # Errors
     ///
@@ -250,7 +1368,10 @@ impl Game {
         if !self.board.is_empty() {
             return Err("board not empty".to_owned());
         }
-        let verts = self.board.fixed_handicaps(stones);
+        let verts = match handicap {
+            Handicap::Fixed => self.board.fixed_handicaps(stones),
+            Handicap::Free => self.board.free_handicaps(stones),
+        };
 
         if let Handicap::Fixed = handicap {
             if stones > verts.len() {
@@ -296,12 +1417,81 @@ impl Game {
         Ok(())
     }
 
+    /// Sets up a position to play from, e.g. a problem or the AB and AW stones of an SGF record:
+    /// replaces the stones on the board with the given ones and makes it the player's turn. The
+    /// stones are placed as they are, without alternating colors.
+    ///
+    /// # Errors
+    ///
+    /// A move has been played, a vertex is off the board or given twice, or a chain is left
+    /// without liberties.
+    pub fn setup_position(
+        &mut self,
+        stones: &[(Player, Vertex)],
+        to_move: Player,
+    ) -> Result<(), String> {
+        if !self.move_history.is_empty() {
+            return Err("a position can only be set up before the first move".to_owned());
+        }
+        let mut board = self.board.clone();
+        board.set_stones(stones)?;
+        board
+            .is_reachable()
+            .map_err(|err| format!("invalid setup: {err}"))?;
+        self.board = board;
+        self.set_to_move(to_move);
+        Ok(())
+    }
+
+    /// Returns the current position: the board, the player to move, the point of a ko just taken
+    /// and the rule set, without the history of the game. With the unrestricted ko rule the
+    /// position is exempt from ko.
+    #[must_use]
+    pub fn position(&self) -> Position {
+        let position = Position::new(self.board.clone(), self.player_turn(), self.rule_set);
+        if self.ko_rule() == KoRule::Unrestricted {
+            return position.ko_exempt(true);
+        }
+        position.with_ko(self.ko())
+    }
+
+    /// Returns the point of a ko the last move took, which may not be retaken at once unless the
+    /// ko rule is `KoRule::Unrestricted`.
+    fn ko(&self) -> Option<Vertex> {
+        if self.ko_rule() == KoRule::Unrestricted {
+            return None;
+        }
+        self.move_history.last().and_then(|record| {
+            let vertex = record.mov.vertex?;
+            position::ko_point(&self.board, vertex, &record.delta.captured)
+        })
+    }
+
+    /// Sets whose turn it is to play next, e.g. for games where White moves first or after the
+    /// players swap colors during setup. Play alternates from there as usual.
+    pub fn set_to_move(&mut self, player: Player) {
+        self.to_move = Some((self.move_history.len(), player));
+    }
+
+    /// Whose turn it is to play next, if it was set with `set_to_move` rather than following from
+    /// the moves.
+    pub(crate) fn turn_set(&self) -> Option<Player> {
+        self.to_move
+            .filter(|&(count, _)| count == self.move_history.len())
+            .map(|(_, player)| player)
+    }
+
     /// Whose turn it is to play next.
     #[must_use]
     pub fn player_turn(&self) -> Player {
         let len = self.move_history.len();
+        if let Some((move_count, player)) = self.to_move {
+            if move_count == len {
+                return player;
+            }
+        }
         if len > 0 {
-            self.move_history[len - 1].player.enemy()
+            self.move_history[len - 1].mov.player.enemy()
         } else if self.board.is_empty() {
             Player::Black
         } else {
@@ -309,21 +1499,865 @@ impl Game {
         }
     }
 
-    /// Whether the game has ended or not.
+    /// Ends the game with the player resigning. Undoing the last move takes the resignation back
+    /// too.
+    pub fn resign(&mut self, player: Player) {
+        self.resigned = Some((self.move_history.len(), player));
+    }
+
+    /// The player who resigned, if any.
+    #[must_use]
+    pub fn resigned(&self) -> Option<Player> {
+        self.resigned.map(|(_, player)| player)
+    }
+
+    /// Returns the player playing mirror Go, if any: each of their last few moves was played on the
+    /// point symmetric through the center of the board to the opponent's move before it.
+    #[must_use]
+    pub fn mirroring_player(&self) -> Option<Player> {
+        let last = self.move_history.last()?.mov.player;
+        let mirrored = self
+            .move_history
+            .windows(2)
+            .rev()
+            .step_by(2)
+            .take(MIRROR_MOVES)
+            .filter(|pair| {
+                let (theirs, reply) = (pair[0].mov, pair[1].mov);
+                match (theirs.vertex, reply.vertex) {
+                    (Some(vertex), Some(mirrored)) => {
+                        theirs.player != last
+                            && reply.player == last
+                            && self.board.mirror(vertex) == mirrored
+                    }
+                    _ => false,
+                }
+            })
+            .count();
+        (mirrored == MIRROR_MOVES).then_some(last)
+    }
+
+    /// Whether the game has ended or not. The game ends after two passes in a row, but under AGA
+    /// rules White must be the last to pass. Capture Go ends with the first capture. Either player
+    /// can end the game by resigning.
     #[must_use]
     pub fn is_over(&self) -> bool {
         let move_count = self.move_history.len();
 
-        move_count > MAX_MOVES
+        self.resigned.is_some()
+            || self.timed_out.is_some()
+            || self.void
+            || self.variant == Variant::CaptureGo && self.first_capture.is_some()
+            || move_count > MAX_MOVES
             || move_count > 1
-                && self.move_history[move_count - 1].vertex.is_none()
-                && self.move_history[move_count - 2].vertex.is_none()
+                && self.move_history[move_count - 1].mov.vertex.is_none()
+                && self.move_history[move_count - 2].mov.vertex.is_none()
+                && self.last_pass_allowed(self.move_history[move_count - 1].mov.player)
+    }
+
+    /// Whether the rules allow the player to make the pass that ends the game.
+    fn last_pass_allowed(&self, player: Player) -> bool {
+        match self.rule_set {
+            RuleSet::Chinese | RuleSet::Japanese | RuleSet::TrompTaylor => true,
+            RuleSet::AGA => player == Player::White,
+        }
     }
 }
 
+/// A best-effort verdict on whether a chain lives, given by `Game::group_status`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GroupStatus {
+    /// The chain can't be captured, or it survives nearly every random game played out.
+    Alive,
+    /// The chain is inside the opponent's unconditional territory, or it's captured in nearly
+    /// every random game played out.
+    Dead,
+    /// The chain lives by sharing liberties with enemy stones that neither player can fill.
+    Seki,
+    /// Whether the chain lives depends on who plays there first.
+    Unsettled,
+}
+
+/// How far a game has got.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Lifecycle {
+    /// The players are still making moves.
+    InProgress,
+    /// The players passed and the game will be decided by score, once the dead stones are agreed
+    /// on.
+    Scoring,
+    /// The game is over and its result stands.
+    Finished,
+}
+
+/// Variations on how a game is won, played with the legality rules of the rule set.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Variant {
+    /// The game is won on points.
+    Standard,
+    /// [Capture Go](https://senseis.xmp.net/?CaptureGo), the first player to capture wins.
+    CaptureGo,
+}
+
 /// One of major Go variations.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RuleSet {
     /// [Chinese ruleset](http://senseis.xmp.net/?ChineseRules)
     Chinese,
+    /// [AGA ruleset](https://www.usgo.org/aga-concise-rules-go)
+    AGA,
+    /// [Japanese ruleset](https://senseis.xmp.net/?JapaneseRules)
+    Japanese,
+    /// [Tromp-Taylor rules](https://tromp.github.io/go.html), as used in computer Go contests:
+    /// positional superko, suicide allowed, and every stone is alive when the game is scored by
+    /// area.
+    TrompTaylor,
+}
+
+impl RuleSet {
+    /// Returns the ko rule the rule set uses.
+    #[must_use]
+    pub fn ko_rule(self) -> KoRule {
+        match self {
+            RuleSet::Chinese | RuleSet::TrompTaylor => KoRule::PositionalSuperko,
+            RuleSet::AGA => KoRule::SituationalSuperko,
+            RuleSet::Japanese => KoRule::Simple,
+        }
+    }
+
+    /// Whether games under the rule set are counted by area, stones and surrounded points, rather
+    /// than by territory and prisoners. AGA rules count either way with the same result.
+    #[must_use]
+    pub fn counts_area(self) -> bool {
+        self != RuleSet::Japanese
+    }
+}
+
+/// Which repetitions of earlier positions a move may not make.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KoRule {
+    /// A ko may not be retaken immediately, i.e. a move may not recreate the board as it was
+    /// before the opponent's last move. Longer cycles are allowed.
+    Simple,
+    /// A move may not recreate any earlier board.
+    PositionalSuperko,
+    /// A move may not recreate an earlier board with the same player to move next, so a board may
+    /// recur after a pass.
+    SituationalSuperko,
+    /// Any earlier board may be recreated, even by retaking a ko at once, for solving problems and
+    /// trying out moves from a bare position. Suicide is still forbidden as the rule set says.
+    Unrestricted,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pass(player: Player) -> Move {
+        Move {
+            player,
+            vertex: None,
+        }
+    }
+
+    #[test]
+    fn aga_white_passes_last() {
+        let mut game = Game::with_board_size(5).unwrap();
+        game.rule_set = RuleSet::AGA;
+
+        game.play(&pass(Player::White)).unwrap();
+        game.play(&pass(Player::Black)).unwrap();
+        assert!(!game.is_over());
+
+        game.play(&pass(Player::White)).unwrap();
+        assert!(game.is_over());
+        assert_eq!(game.pass_stones(Player::White), 2);
+        assert_eq!(game.pass_stones(Player::Black), 1);
+        assert_eq!(game.result(), Some(GameResult::Score(Player::White, 7.5)));
+    }
+
+    fn stone(player: Player, x: usize, y: usize) -> Move {
+        Move {
+            player,
+            vertex: Some(Vertex { x, y }),
+        }
+    }
+
+    fn play(game: &mut Game, player: Player, x: usize, y: usize) {
+        game.play(&stone(player, x, y)).unwrap();
+    }
+
+    /// Sets up a ko with its bottom edge on row `y`. Black can capture at (2, y + 1) when
+    /// `black_to_take`, otherwise White can capture at (1, y + 1).
+    fn setup_ko(game: &mut Game, y: usize, black_to_take: bool) {
+        for (x, dy) in [(1, 2), (0, 1), (1, 0)] {
+            play(game, Player::Black, x, y + dy);
+        }
+        for (x, dy) in [(2, 2), (3, 1), (2, 0)] {
+            play(game, Player::White, x, y + dy);
+        }
+        if black_to_take {
+            play(game, Player::White, 1, y + 1);
+        } else {
+            play(game, Player::Black, 2, y + 1);
+        }
+    }
+
+    #[test]
+    fn parse_result() {
+        for result in [
+            GameResult::Score(Player::White, 7.5),
+            GameResult::Draw,
+            GameResult::Time(Player::Black),
+            GameResult::Void,
+            GameResult::Capture(Player::White),
+            GameResult::Resignation(Player::Black),
+            GameResult::Forfeit(Player::White),
+        ] {
+            assert_eq!(result.to_string().parse(), Ok(result));
+        }
+        assert!("B+x".parse::<GameResult>().is_err());
+        assert!("?".parse::<GameResult>().is_err());
+    }
+
+    #[test]
+    fn aga_situational_superko() {
+        let moves = [
+            stone(Player::Black, 2, 1),
+            stone(Player::White, 1, 5),
+            pass(Player::Black),
+            stone(Player::White, 1, 1),
+            // The board repeats, but now with White to move instead of Black.
+            stone(Player::Black, 2, 5),
+        ];
+        for (rule_set, legal) in [(RuleSet::AGA, true), (RuleSet::Chinese, false)] {
+            let mut game = Game::with_board_size(9).unwrap();
+            game.rule_set = rule_set;
+            setup_ko(&mut game, 0, true);
+            setup_ko(&mut game, 4, false);
+            assert_eq!(game.validate_sequence(&moves).is_ok(), legal);
+            for mov in &moves[..4] {
+                game.play(mov).unwrap();
+            }
+            assert_eq!(game.play(&moves[4]).is_ok(), legal);
+        }
+
+        // Retaking a ko immediately recreates the board with the same player to move.
+        let mut game = Game::with_board_size(9).unwrap();
+        game.rule_set = RuleSet::AGA;
+        setup_ko(&mut game, 0, true);
+        play(&mut game, Player::Black, 2, 1);
+        assert!(game.play(&stone(Player::White, 1, 1)).is_err());
+    }
+
+    #[test]
+    fn ko_rule() {
+        let moves = [
+            stone(Player::Black, 2, 1),
+            stone(Player::White, 1, 5),
+            pass(Player::Black),
+            stone(Player::White, 1, 1),
+            stone(Player::Black, 2, 5),
+        ];
+        // The ko rule overrides the rule set's, so Chinese scoring can be played with AGA ko.
+        for (ko_rule, legal) in [
+            (KoRule::SituationalSuperko, true),
+            (KoRule::PositionalSuperko, false),
+            (KoRule::Simple, true),
+            (KoRule::Unrestricted, true),
+        ] {
+            let mut game = Game::with_board_size(9).unwrap();
+            game.ko_rule = Some(ko_rule);
+            assert_eq!(game.ko_rule(), ko_rule);
+            setup_ko(&mut game, 0, true);
+            setup_ko(&mut game, 4, false);
+            assert_eq!(game.validate_sequence(&moves).is_ok(), legal);
+        }
+
+        let mut game = Game::with_board_size(9).unwrap();
+        assert_eq!(game.ko_rule(), KoRule::PositionalSuperko);
+        game.rule_set = RuleSet::Japanese;
+        assert_eq!(game.ko_rule(), KoRule::Simple);
+    }
+
+    #[test]
+    fn tromp_taylor_suicide() {
+        let mut game = Game::with_board_size(5).unwrap();
+        game.rule_set = RuleSet::TrompTaylor;
+        for (player, x, y) in [
+            (Player::Black, 0, 0),
+            (Player::White, 2, 0),
+            (Player::Black, 1, 0),
+            (Player::White, 1, 1),
+            (Player::Black, 4, 3),
+            (Player::White, 0, 2),
+            (Player::Black, 3, 4),
+        ] {
+            play(&mut game, player, x, y);
+        }
+        // A single stone killing itself would repeat the board.
+        assert!(game.play(&stone(Player::White, 4, 4)).is_err());
+        play(&mut game, Player::White, 2, 2);
+
+        // Filling the last liberty of Black's two stones kills all three.
+        play(&mut game, Player::Black, 0, 1);
+        assert_eq!(
+            game.board().stones(Player::Black),
+            [Vertex { x: 4, y: 3 }, Vertex { x: 3, y: 4 }]
+        );
+        assert_eq!(game.prisoners(Player::White), 0);
+
+        game.rule_set = RuleSet::Chinese;
+        game.undo().unwrap();
+        assert!(game.play(&stone(Player::Black, 0, 1)).is_err());
+    }
+
+    #[test]
+    fn aga_handicap_compensation() {
+        let mut game = Game::with_board_size(9).unwrap();
+        game.rule_set = RuleSet::AGA;
+        game.komi = 0.5;
+        game.place_handicap(4, Handicap::Fixed).unwrap();
+        assert_eq!(game.handicap(), 4);
+        assert_eq!(game.score_result().to_string(), "B+77.5");
+        game.play(&pass(Player::White)).unwrap();
+        game.play(&pass(Player::Black)).unwrap();
+        game.play(&pass(Player::White)).unwrap();
+        assert_eq!(
+            game.result(),
+            Some(GameResult::Score(Player::Black, 81.0 - 3.0 - 0.5))
+        );
+    }
+
+    #[test]
+    fn japanese_triple_ko_is_void() {
+        let mut game = Game::with_board_size(11).unwrap();
+        game.rule_set = RuleSet::Japanese;
+        setup_ko(&mut game, 0, true);
+        setup_ko(&mut game, 4, false);
+        setup_ko(&mut game, 8, true);
+
+        // Retaking a ko immediately is illegal.
+        play(&mut game, Player::Black, 2, 1);
+        assert!(game
+            .play(&Move {
+                player: Player::White,
+                vertex: Some(Vertex { x: 1, y: 1 }),
+            })
+            .is_err());
+
+        let cycle = [
+            (Player::White, 1, 5),
+            (Player::Black, 2, 9),
+            (Player::White, 1, 1),
+            (Player::Black, 2, 5),
+            (Player::White, 1, 9),
+            (Player::Black, 2, 1),
+        ];
+        for (player, x, y) in cycle {
+            play(&mut game, player, x, y);
+        }
+        assert!(!game.is_over());
+        for (player, x, y) in cycle {
+            play(&mut game, player, x, y);
+        }
+        assert!(game.is_over());
+        assert_eq!(game.result(), Some(GameResult::Void));
+    }
+
+    #[test]
+    fn validate_sequence() {
+        let mut game = Game::with_board_size(11).unwrap();
+        setup_ko(&mut game, 0, true);
+        let moves = [
+            stone(Player::Black, 2, 1),
+            stone(Player::White, 5, 5),
+            stone(Player::Black, 6, 6),
+            stone(Player::White, 1, 1),
+        ];
+        assert_eq!(game.validate_sequence(&moves), Ok(()));
+        // Retaking the ko immediately repeats an earlier position.
+        let moves = [moves[0], moves[3]];
+        assert_eq!(game.validate_sequence(&moves).map_err(|e| e.0), Err(1));
+        assert_eq!(game.move_history.len(), 7);
+    }
+
+    #[test]
+    fn resign() {
+        let mut game = Game::with_board_size(9).unwrap();
+        play(&mut game, Player::Black, 0, 0);
+        game.resign(Player::White);
+        assert!(game.is_over());
+        assert_eq!(game.resigned(), Some(Player::White));
+        assert_eq!(game.result(), Some(GameResult::Resignation(Player::Black)));
+
+        game.undo().unwrap();
+        assert!(!game.is_over());
+        assert_eq!(game.result(), None);
+    }
+
+    #[test]
+    fn captures() {
+        let mut game = Game::with_board_size(9).unwrap();
+        game.rule_set = RuleSet::TrompTaylor;
+        play(&mut game, Player::Black, 0, 0);
+        for (x, y) in [(1, 0), (1, 1), (0, 2)] {
+            play(&mut game, Player::White, x, y);
+        }
+        // Black's two stones in the corner commit suicide, which White counts as captures.
+        play(&mut game, Player::Black, 0, 1);
+        assert_eq!(game.last_captures().len(), 2);
+        assert_eq!(game.captures(Player::White), 2);
+        assert_eq!(game.captures(Player::Black), 0);
+        assert_eq!(game.prisoners(Player::White), 0);
+    }
+
+    #[test]
+    fn undo_restores_captures() {
+        let mut game = Game::with_board_size(9).unwrap();
+        setup_ko(&mut game, 0, true);
+        let before = game.board().clone();
+        play(&mut game, Player::Black, 2, 1);
+        assert_eq!(game.prisoners(Player::Black), 1);
+        assert_eq!(game.captures(Player::Black), 1);
+        assert_eq!(game.last_captures(), vec![Vertex { x: 1, y: 1 }]);
+
+        game.undo().unwrap();
+        assert_eq!(game.board(), &before);
+        assert_eq!(game.prisoners(Player::Black), 0);
+        assert_eq!(game.captures(Player::Black), 0);
+        assert_eq!(game.initial_board(), Board::with_size(9).unwrap());
+        // The restored chains have their liberties, so the capture can be played again.
+        play(&mut game, Player::Black, 2, 1);
+        assert_eq!(game.prisoners(Player::Black), 1);
+        assert!(game.play(&stone(Player::White, 1, 1)).is_err());
+    }
+
+    #[test]
+    fn undo_restores_time_left() {
+        let mut game = Game::with_board_size(9).unwrap();
+        game.set_time_settings(Clock::ByoYomi {
+            main_time: Duration::from_secs(60),
+            period_time: Duration::from_secs(30),
+            periods: 3,
+        });
+        let full = game.time_left(Player::Black);
+        game.play_timed(&stone(Player::Black, 2, 2), Duration::from_secs(75))
+            .unwrap();
+        assert_eq!(game.time_left(Player::Black).periods_used, 0);
+        game.play_timed(&stone(Player::White, 6, 6), Duration::from_secs(10))
+            .unwrap();
+        let white = game.time_left(Player::White);
+        game.play_timed(&stone(Player::Black, 6, 2), Duration::from_secs(40))
+            .unwrap();
+        assert_eq!(game.time_left(Player::Black).periods_used, 1);
+
+        game.undo().unwrap();
+        assert_eq!(game.time_left(Player::White), white);
+        assert_eq!(game.time_left(Player::Black).periods_used, 0);
+        game.undo().unwrap();
+        game.undo().unwrap();
+        assert_eq!(game.time_left(Player::Black), full);
+        assert_eq!(game.time_left(Player::White), full);
+    }
+
+    #[test]
+    fn legal_moves_match_placing_stones() {
+        for rule_set in [
+            RuleSet::Chinese,
+            RuleSet::Japanese,
+            RuleSet::AGA,
+            RuleSet::TrompTaylor,
+        ] {
+            let mut game = Game::with_board_size(4).unwrap();
+            game.rule_set = rule_set;
+            while !game.is_over() && game.move_history.len() < 60 {
+                for vertex in game.board().empty_verts() {
+                    for player in [Player::Black, Player::White] {
+                        let placed = game
+                            .try_place_stone(player, vertex, |board| {
+                                game.stones_played_on(board).next().is_some()
+                            })
+                            .is_some();
+                        let mov = stone(player, vertex.x, vertex.y);
+                        assert_eq!(game.is_legal_move(&mov), placed, "{rule_set:?} {mov:?}");
+                    }
+                }
+                game.genmove_random(game.player_turn());
+            }
+        }
+    }
+
+    #[test]
+    fn move_history() {
+        let mut game = Game::with_board_size(5).unwrap();
+        assert_eq!(game.last_move(), None);
+        assert_eq!(game.move_number(), 0);
+        play(&mut game, Player::Black, 2, 2);
+        game.play(&pass(Player::White)).unwrap();
+        assert_eq!(game.last_move(), Some(&pass(Player::White)));
+        assert_eq!(game.move_number(), 2);
+        assert_eq!(
+            game.moves().copied().collect::<Vec<_>>(),
+            [stone(Player::Black, 2, 2), pass(Player::White)]
+        );
+        game.undo().unwrap();
+        assert_eq!(game.last_move(), Some(&stone(Player::Black, 2, 2)));
+    }
+
+    #[test]
+    fn setup_position() {
+        let mut game = Game::with_board_size(5).unwrap();
+        let stones = [
+            (Player::White, Vertex { x: 0, y: 0 }),
+            (Player::Black, Vertex { x: 1, y: 0 }),
+            (Player::Black, Vertex { x: 0, y: 1 }),
+            (Player::White, Vertex { x: 1, y: 1 }),
+        ];
+        // The white stone in the corner would be left without liberties.
+        assert!(game.setup_position(&stones, Player::White).is_err());
+        assert!(game.board().stones(Player::Black).is_empty());
+
+        game.setup_position(&stones[1..], Player::White).unwrap();
+        assert_eq!(game.player_turn(), Player::White);
+        assert_eq!(game.initial_board(), game.board().clone());
+
+        // The black stones have their liberties counted, so White can capture them.
+        play(&mut game, Player::White, 2, 0);
+        play(&mut game, Player::Black, 4, 4);
+        play(&mut game, Player::White, 0, 2);
+        play(&mut game, Player::Black, 4, 3);
+        play(&mut game, Player::White, 0, 0);
+        assert_eq!(game.prisoners(Player::White), 2);
+        assert!(game.setup_position(&stones[1..], Player::Black).is_err());
+
+        let mut game = Game::with_board_size(5).unwrap();
+        let twice = [stones[0], stones[0]];
+        assert!(game.setup_position(&twice, Player::Black).is_err());
+        let off = [(Player::Black, Vertex { x: 5, y: 0 })];
+        assert!(game.setup_position(&off, Player::Black).is_err());
+    }
+
+    #[test]
+    fn fork_at() {
+        let mut game = Game::with_board_size(9).unwrap();
+        play(&mut game, Player::Black, 0, 0);
+        game.play(&pass(Player::White)).unwrap();
+        play(&mut game, Player::Black, 1, 0);
+
+        let mut fork = game.fork_at(2);
+        assert_eq!(fork.move_history.len(), 2);
+        assert_eq!(fork.player_turn(), Player::Black);
+        assert!(fork.board().is_vacant(Vertex { x: 1, y: 0 }));
+        assert!(!fork.board().is_vacant(Vertex { x: 0, y: 0 }));
+
+        play(&mut fork, Player::Black, 2, 0);
+        assert!(game.board().is_vacant(Vertex { x: 2, y: 0 }));
+        assert_eq!(game.fork_at(0).board(), &Board::with_size(9).unwrap());
+
+        game.play(&pass(Player::White)).unwrap();
+        game.play(&pass(Player::Black)).unwrap();
+        assert_eq!(game.fork_at(4).board(), game.board());
+    }
+
+    #[test]
+    fn capture_go() {
+        let mut game = Game::with_board_size(5).unwrap();
+        game.variant = Variant::CaptureGo;
+        play(&mut game, Player::Black, 0, 0);
+        play(&mut game, Player::White, 1, 0);
+        assert!(!game.is_over());
+        play(&mut game, Player::White, 0, 1);
+        assert!(game.is_over());
+        assert_eq!(game.result(), Some(GameResult::Capture(Player::White)));
+        assert_eq!(game.result().unwrap().to_string(), "W+");
+
+        game.undo().unwrap();
+        assert!(!game.is_over());
+    }
+
+    #[test]
+    fn group_status() {
+        let board = Board::from_ascii(
+            "7 . X . X O . .
+             6 X X X X O . .
+             5 . O . X O O O
+             4 X X X X O . .
+             3 O O O O O . .
+             2 . . . . . . .
+             1 . . . . . . .",
+        )
+        .unwrap();
+        let stones: Vec<_> = [Player::Black, Player::White]
+            .into_iter()
+            .flat_map(|player| {
+                board
+                    .stones(player)
+                    .into_iter()
+                    .map(move |vertex| (player, vertex))
+            })
+            .collect();
+        let mut game = Game::with_board_size(7).unwrap();
+        game.setup_position(&stones, Player::Black).unwrap();
+
+        let status = |vertex: &str| game.group_status(vertex.parse().unwrap());
+        assert_eq!(status("B7"), Some(GroupStatus::Alive));
+        assert_eq!(status("B5"), Some(GroupStatus::Dead));
+        assert_eq!(status("A7"), None);
+    }
+
+    #[test]
+    fn fill_dame() {
+        let mut game = Game::with_board_size(5).unwrap();
+        // Walls on the B and D files with dame between them on the C file.
+        for y in 0..5 {
+            play(&mut game, Player::Black, 1, y);
+            play(&mut game, Player::White, 3, y);
+        }
+        play(&mut game, Player::Black, 0, 1);
+        play(&mut game, Player::White, 2, 0);
+        play(&mut game, Player::Black, 0, 3);
+        assert_eq!(
+            game.cleanup_move(Player::White),
+            Some(Vertex { x: 2, y: 1 })
+        );
+
+        let moves = game.fill_dame();
+        assert!(game.is_over());
+        assert_eq!(moves.iter().filter(|mov| mov.vertex.is_some()).count(), 4);
+        for y in 1..5 {
+            assert!(!game.board().is_vacant(Vertex { x: 2, y }));
+        }
+        assert_eq!(game.cleanup_move(Player::Black), None);
+        assert_eq!(game.cleanup_move(Player::White), None);
+        assert!(game.fill_dame().is_empty());
+    }
+
+    #[test]
+    fn swap_colors() {
+        let mut game = Game::with_board_size(9).unwrap();
+        game.komi = 6.5;
+        // A ko on the edge, which Black has just taken.
+        for (player, x, y) in [
+            (Player::Black, 0, 0),
+            (Player::White, 1, 0),
+            (Player::Black, 1, 1),
+            (Player::White, 2, 1),
+            (Player::Black, 8, 8),
+            (Player::White, 3, 0),
+            (Player::Black, 2, 0),
+        ] {
+            play(&mut game, player, x, y);
+        }
+
+        let swapped = game.swap_colors();
+        assert!((swapped.komi + 6.5).abs() < f64::EPSILON);
+        assert_eq!(swapped.player_turn(), Player::Black);
+        assert_eq!(
+            swapped.board().stone(Vertex { x: 2, y: 0 }),
+            Some(Player::White)
+        );
+        assert_eq!(swapped.prisoners(Player::White), 1);
+        assert_eq!(swapped.prisoners(Player::Black), 0);
+        assert_eq!(swapped.board().score_area(), -game.board().score_area());
+        // Retaking the ko straight away is still illegal.
+        let retake = Move {
+            player: Player::Black,
+            vertex: Some(Vertex { x: 1, y: 0 }),
+        };
+        assert!(!game.is_legal_move(&Move {
+            player: Player::White,
+            ..retake
+        }));
+        assert!(!swapped.is_legal_move(&retake));
+        assert_eq!(swapped.swap_colors().board(), game.board());
+    }
+
+    #[test]
+    fn position() {
+        let mut game = Game::new();
+        setup_ko(&mut game, 0, true);
+        play(&mut game, Player::Black, 2, 1);
+        let position = game.position();
+        let retake = stone(Player::White, 1, 1);
+        assert_eq!(position.ko(), Some(Vertex { x: 1, y: 1 }));
+        assert_eq!(position.to_move(), Player::White);
+        assert!(!position.is_legal(&retake));
+        assert!(game.play(&retake).is_err());
+
+        play(&mut game, Player::White, 10, 10);
+        let position = game.position();
+        assert_eq!(position.ko(), None);
+        assert_eq!(position.board(), game.board());
+
+        // Analysis may retake the ko at once, but not commit suicide.
+        let mut game = Game::new();
+        game.ko_rule = Some(KoRule::Unrestricted);
+        setup_ko(&mut game, 0, true);
+        play(&mut game, Player::Black, 2, 1);
+        assert!(game.position().is_ko_exempt());
+        assert!(game.position().is_legal(&retake));
+        play(&mut game, Player::White, 1, 1);
+        play(&mut game, Player::Black, 2, 1);
+        assert!(!game.position().is_legal(&stone(Player::White, 0, 0)));
+        assert!(game.play(&stone(Player::White, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn legal_move_mask() {
+        let mut game = Game::with_board_size(9).unwrap();
+        setup_ko(&mut game, 0, true);
+        play(&mut game, Player::Black, 2, 1);
+        let mask = game.legal_move_mask(Player::White);
+        assert!(!mask[&Vertex { x: 1, y: 1 }]);
+        assert!(!mask[&Vertex { x: 2, y: 1 }]);
+        assert!(mask[&Vertex { x: 8, y: 8 }]);
+        let legal: Vec<_> = game
+            .board()
+            .empty_verts()
+            .into_iter()
+            .filter(|vertex| mask[vertex])
+            .collect();
+        assert_eq!(legal, game.all_legal_moves(Player::White));
+
+        for ko_rule in [KoRule::PositionalSuperko, KoRule::Unrestricted] {
+            game.ko_rule = Some(ko_rule);
+            let mask = game.legal_move_mask(Player::White);
+            for vertex in game.board().empty_verts() {
+                let mov = Move {
+                    player: Player::White,
+                    vertex: Some(vertex),
+                };
+                assert_eq!(
+                    mask[&vertex],
+                    game.is_legal_move(&mov),
+                    "{ko_rule:?} {vertex}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn lifecycle() {
+        let mut game = Game::with_board_size(9).unwrap();
+        play(&mut game, Player::Black, 4, 4);
+        assert_eq!(game.lifecycle(), Lifecycle::InProgress);
+        game.play(&pass(Player::White)).unwrap();
+        game.play(&pass(Player::Black)).unwrap();
+        assert_eq!(game.lifecycle(), Lifecycle::Scoring);
+        game.finish();
+        assert_eq!(game.lifecycle(), Lifecycle::Finished);
+        game.clear_board();
+        assert_eq!(game.lifecycle(), Lifecycle::InProgress);
+        game.resign(Player::White);
+        assert_eq!(game.lifecycle(), Lifecycle::Finished);
+    }
+
+    #[test]
+    fn set_to_move() {
+        let mut game = Game::with_board_size(9).unwrap();
+        game.set_to_move(Player::White);
+        assert_eq!(game.player_turn(), Player::White);
+        play(&mut game, Player::White, 4, 4);
+        assert_eq!(game.player_turn(), Player::Black);
+        game.undo().unwrap();
+        assert_eq!(game.player_turn(), Player::White);
+    }
+
+    #[test]
+    fn japanese_territory_scoring() {
+        let mut game = Game::with_board_size(5).unwrap();
+        game.rule_set = RuleSet::Japanese;
+        game.komi = 0.0;
+        // Walls on the C and D files split the board, then White invades and is captured.
+        for y in 0..5 {
+            play(&mut game, Player::Black, 2, y);
+            play(&mut game, Player::White, 3, y);
+        }
+        play(&mut game, Player::White, 0, 0);
+        play(&mut game, Player::Black, 1, 0);
+        game.play(&pass(Player::White)).unwrap();
+        play(&mut game, Player::Black, 0, 1);
+        assert_eq!(game.prisoners(Player::Black), 1);
+        assert_eq!(game.prisoners(Player::White), 0);
+
+        game.play(&pass(Player::White)).unwrap();
+        game.play(&pass(Player::Black)).unwrap();
+        // Black has 8 points of territory and a prisoner, White 5 points of territory.
+        assert!((game.score() - 4.0).abs() < f64::EPSILON);
+        game.rule_set = RuleSet::Chinese;
+        assert!((game.score() - 5.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn japanese_bent_four_in_corner_is_dead() {
+        let mut game = Game::with_board_size(7).unwrap();
+        game.rule_set = RuleSet::Japanese;
+        let white = [(3, 0), (3, 1), (2, 1), (1, 1), (1, 2), (0, 2)];
+        for (x, y) in white {
+            play(&mut game, Player::White, x, y);
+        }
+        assert!(game.dead_stones().is_empty());
+
+        for (x, y) in [(4, 0), (4, 1), (3, 2), (2, 2), (1, 3), (0, 3)] {
+            play(&mut game, Player::Black, x, y);
+        }
+        let dead: HashSet<_> = game.dead_stones().into_iter().collect();
+        let expected: HashSet<_> = white.iter().map(|&(x, y)| Vertex { x, y }).collect();
+        assert_eq!(dead, expected);
+
+        game.rule_set = RuleSet::Chinese;
+        assert!(game.dead_stones().is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let mut game = Game::with_board_size(5).unwrap();
+        game.rule_set = RuleSet::Japanese;
+        for (player, x, y) in [
+            (Player::Black, 1, 0),
+            (Player::White, 0, 0),
+            (Player::Black, 0, 1),
+        ] {
+            play(&mut game, player, x, y);
+        }
+        game.annotate(3, Annotation::Comment("captures".to_owned()))
+            .unwrap();
+
+        let json = serde_json::to_string(&game).unwrap();
+        let mut copy: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.board(), game.board());
+        assert_eq!(copy.rule_set, RuleSet::Japanese);
+        assert_eq!(
+            copy.moves().collect::<Vec<_>>(),
+            game.moves().collect::<Vec<_>>()
+        );
+        assert_eq!(copy.annotations(3), game.annotations(3));
+        assert_eq!(copy.captures(Player::Black), 1);
+        assert_eq!(copy.board().score_area(), game.board().score_area());
+        copy.undo().unwrap();
+        assert!(copy.board().is_vacant(Vertex { x: 0, y: 1 }));
+        assert!(!copy.board().is_vacant(Vertex { x: 0, y: 0 }));
+
+        // Histories that don't fit the board are rejected rather than breaking undo.
+        let off_board = json.replacen(r#""x":0,"y":0"#, r#""x":30,"y":0"#, 1);
+        assert_ne!(off_board, json);
+        assert!(serde_json::from_str::<Game>(&off_board).is_err());
+        let moved = json.replacen(r#""x":1,"y":0"#, r#""x":3,"y":3"#, 1);
+        assert_ne!(moved, json);
+        assert!(serde_json::from_str::<Game>(&moved).is_err());
+
+        let board: Board =
+            serde_json::from_str(&serde_json::to_string(game.board()).unwrap()).unwrap();
+        assert_eq!(&board, game.board());
+        assert!(
+            serde_json::from_str::<Board>(r#"{"matrix":{"size":2,"values":["Empty"]}}"#).is_err()
+        );
+    }
 }
@@ -0,0 +1,131 @@
+//! The numbers written and read by GTP and SGF: komi and scores in points, and times in seconds.
+//!
+//! Numbers are always written with a `.` before the decimals and never with exponents or
+//! thousands separators, whatever the locale, and parsing accepts only that form. Points are
+//! written with at most one decimal place, as komi and scores are whole or half points, and
+//! times with exactly three, to the millisecond. What `format_points` writes `parse_points`
+//! reads back to the same value, and the same goes for `format_seconds` and `parse_seconds`.
+
+use std::time::Duration;
+
+/// Writes points such as komi or a score: `6.5`, `7` or `-0.5`. Points are rounded to one decimal
+/// place. Infinite and NaN points, which no protocol allows, are written as `inf`, `-inf` and
+/// `NaN` and aren't read back by `parse_points`.
+#[must_use]
+pub fn format_points(points: f64) -> String {
+    let tenths = (points * 10.0).round();
+    if !tenths.is_finite() {
+        points.to_string()
+    } else if tenths == 0.0 {
+        "0".to_owned()
+    } else if tenths % 10.0 == 0.0 {
+        format!("{:.0}", tenths / 10.0)
+    } else {
+        format!("{:.1}", tenths / 10.0)
+    }
+}
+
+/// Reads points written as an optionally signed decimal number such as `6.5`, `7`, `+0.5` or
+/// `-3.`.
+///
+/// # Errors
+///
+/// The text isn't a decimal number, e.g. `7,5`, `1e3`, `inf` or `NaN`, or it's too large to be
+/// finite.
+pub fn parse_points(text: &str) -> Result<f64, String> {
+    let invalid = || format!("invalid number of points {text:?}");
+    let unsigned = text.strip_prefix(['+', '-']).unwrap_or(text);
+    if !is_decimal(unsigned) {
+        return Err(invalid());
+    }
+    text.parse()
+        .ok()
+        .filter(|points: &f64| points.is_finite())
+        .ok_or_else(invalid)
+}
+
+/// Writes a time in seconds with three decimal places, e.g. `10.000`.
+#[must_use]
+pub fn format_seconds(time: Duration) -> String {
+    format!("{}.{:03}", time.as_secs(), time.subsec_millis())
+}
+
+/// Reads a time in seconds written as an unsigned decimal number such as `10`, `0.5` or `9.999`.
+/// Decimals beyond the nanosecond are dropped.
+///
+/// # Errors
+///
+/// The text isn't an unsigned decimal number, or it's too large.
+pub fn parse_seconds(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid number of seconds {text:?}");
+    if !is_decimal(text) {
+        return Err(invalid());
+    }
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let secs = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| invalid())?
+    };
+    let nanos = fraction
+        .bytes()
+        .chain(std::iter::repeat(b'0'))
+        .take(9)
+        .fold(0, |nanos, digit| nanos * 10 + u32::from(digit - b'0'));
+    Ok(Duration::new(secs, nanos))
+}
+
+/// Whether the text is digits with at most one `.` among or after them.
+fn is_decimal(text: &str) -> bool {
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    !(whole.is_empty() && fraction.is_empty())
+        && whole.bytes().all(|byte| byte.is_ascii_digit())
+        && fraction.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points() {
+        for (points, text) in [
+            (6.5, "6.5"),
+            (7.0, "7"),
+            (-0.5, "-0.5"),
+            (0.0, "0"),
+            (-0.0, "0"),
+        ] {
+            assert_eq!(format_points(points), text);
+            assert!((parse_points(text).unwrap() - points).abs() < f64::EPSILON);
+        }
+        assert_eq!(format_points(0.04), "0");
+        assert_eq!(format_points(1234.56), "1234.6");
+        assert_eq!(parse_points("+.5"), Ok(0.5));
+        assert_eq!(parse_points("-3."), Ok(-3.0));
+        for text in [
+            "7,5", "1e3", "inf", "NaN", "", ".", "-", " 7", "7.5.1", "--1",
+        ] {
+            assert!(parse_points(text).is_err(), "{text}");
+        }
+        assert!(parse_points(&"9".repeat(400)).is_err());
+        assert!(parse_points(&format!("-{}", "9".repeat(400))).is_err());
+    }
+
+    #[test]
+    fn seconds() {
+        let time = Duration::from_millis(10_250);
+        assert_eq!(format_seconds(time), "10.250");
+        assert_eq!(parse_seconds("10.250"), Ok(time));
+        assert_eq!(parse_seconds("10.25"), Ok(time));
+        assert_eq!(parse_seconds("60"), Ok(Duration::from_secs(60)));
+        assert_eq!(parse_seconds(".5"), Ok(Duration::from_millis(500)));
+        assert_eq!(
+            parse_seconds("0.1234567891"),
+            Ok(Duration::from_nanos(123_456_789))
+        );
+        for text in ["-1", "1,5", "1e3", "", ".", "99999999999999999999"] {
+            assert!(parse_seconds(text).is_err(), "{text}");
+        }
+    }
+}
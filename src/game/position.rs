@@ -0,0 +1,287 @@
+use crate::game::board::{Board, Move, Placement};
+use crate::game::player::Player;
+use crate::game::vertex::Vertex;
+use crate::game::RuleSet;
+
+/// A board with the player to move, the point a ko forbids retaking and the rules, without the
+/// history of how it came about. Positions are values: playing a move returns a new position and
+/// leaves the old one as it was, so they can be shared between the nodes of a search or used as
+/// keys of an opening book or a database.
+///
+/// A position only knows the last move, so it enforces the simple ko rule. The superko rules need
+/// every earlier board, which `Game` keeps. For solving problems and trying out moves a position
+/// can be exempt from ko altogether, while suicide is still forbidden as the rule set says.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    board: Board,
+    to_move: Player,
+    ko: Option<Vertex>,
+    ko_exempt: bool,
+    rule_set: RuleSet,
+}
+
+impl Position {
+    /// Returns the position with the board and the player to move, with no ko.
+    #[must_use]
+    pub fn new(board: Board, to_move: Player, rule_set: RuleSet) -> Self {
+        Position {
+            board,
+            to_move,
+            ko: None,
+            ko_exempt: false,
+            rule_set,
+        }
+    }
+
+    /// Returns the position with a ko the player to move may not retake this turn.
+    pub(crate) fn with_ko(self, ko: Option<Vertex>) -> Self {
+        Position {
+            ko: ko.filter(|_| !self.ko_exempt),
+            ..self
+        }
+    }
+
+    /// Returns the position exempt from ko or not. Positions played from an exempt position are
+    /// exempt too, so a ko may be retaken at once.
+    #[must_use]
+    pub fn ko_exempt(self, exempt: bool) -> Self {
+        Position {
+            ko: self.ko.filter(|_| !exempt),
+            ko_exempt: exempt,
+            ..self
+        }
+    }
+
+    /// Whether the position is exempt from ko.
+    #[must_use]
+    pub fn is_ko_exempt(&self) -> bool {
+        self.ko_exempt
+    }
+
+    /// Returns the stones on the board.
+    #[must_use]
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Returns the player to move.
+    #[must_use]
+    pub fn to_move(&self) -> Player {
+        self.to_move
+    }
+
+    /// Returns the point of a ko the player to move may not retake this turn, if any.
+    #[must_use]
+    pub fn ko(&self) -> Option<Vertex> {
+        self.ko
+    }
+
+    /// Returns the rules the position is played under.
+    #[must_use]
+    pub fn rule_set(&self) -> RuleSet {
+        self.rule_set
+    }
+
+    /// Returns the position after the player to move makes the move.
+    ///
+    /// # Errors
+    ///
+    /// It isn't the player's turn, or the move is illegal.
+    pub fn play(&self, mov: &Move) -> Result<Position, String> {
+        self.check(mov)?;
+        Ok(self.clone().played(mov).0)
+    }
+
+    /// Returns the position after the player to move makes the move and the enemy stones it
+    /// captured, reusing the board.
+    ///
+    /// # Errors
+    ///
+    /// It isn't the player's turn, or the move is illegal.
+    pub(crate) fn into_played(self, mov: &Move) -> Result<(Position, Vec<Vertex>), String> {
+        self.check(mov)?;
+        Ok(self.played(mov))
+    }
+
+    /// Returns the board, dropping the rest of the position.
+    pub(crate) fn into_board(self) -> Board {
+        self.board
+    }
+
+    /// Whether the player to move may make the move.
+    #[must_use]
+    pub fn is_legal(&self, mov: &Move) -> bool {
+        self.check(mov).is_ok()
+    }
+
+    fn check(&self, mov: &Move) -> Result<(), String> {
+        if mov.player != self.to_move {
+            return Err(format!("it is {}'s turn", self.to_move));
+        }
+        match mov.vertex {
+            Some(vertex) => check_stone(&self.board, mov.player, vertex, self.ko, self.rule_set)
+                .map(|_placement| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Makes a move that has been checked.
+    fn played(self, mov: &Move) -> (Position, Vec<Vertex>) {
+        let Position {
+            mut board,
+            to_move,
+            ko_exempt,
+            rule_set,
+            ..
+        } = self;
+        let (ko, captured) = match mov.vertex {
+            Some(vertex) => {
+                let captured = board.place_stone(mov.player, vertex);
+                let ko = ko_point(&board, vertex, &captured).filter(|_| !ko_exempt);
+                (ko, captured)
+            }
+            None => (None, Vec::new()),
+        };
+        let position = Position {
+            board,
+            to_move: to_move.enemy(),
+            ko,
+            ko_exempt,
+            rule_set,
+        };
+        (position, captured)
+    }
+
+    /// Returns the points the player to move may play on.
+    #[must_use]
+    pub fn legal_moves(&self) -> Vec<Vertex> {
+        self.board
+            .empty_verts()
+            .into_iter()
+            .filter(|&vertex| {
+                self.is_legal(&Move {
+                    player: self.to_move,
+                    vertex: Some(vertex),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Checks that the player may play a stone on the vertex of the board under the rule set, when
+/// the ko point, if any, may not be retaken, and returns what the stone would do. This is the
+/// check every move of a `Position` or a `Game` goes through.
+///
+/// # Errors
+///
+/// The vertex isn't an empty point on the board, retakes the ko or is suicide.
+pub(crate) fn check_stone(
+    board: &Board,
+    player: Player,
+    vertex: Vertex,
+    ko: Option<Vertex>,
+    rule_set: RuleSet,
+) -> Result<Placement, String> {
+    let placement = board
+        .preview(player, vertex)
+        .ok_or_else(|| format!("{vertex} is not an empty point on the board"))?;
+    check_placement(vertex, &placement, ko, rule_set)?;
+    Ok(placement)
+}
+
+/// Checks that a stone on the empty vertex, which would do as the placement says, neither
+/// retakes the ko nor breaks the rule set's suicide rule.
+///
+/// # Errors
+///
+/// The stone retakes the ko or is suicide.
+pub(crate) fn check_placement(
+    vertex: Vertex,
+    placement: &Placement,
+    ko: Option<Vertex>,
+    rule_set: RuleSet,
+) -> Result<(), String> {
+    if ko == Some(vertex) {
+        return Err(format!("{vertex} retakes a ko"));
+    }
+    // Tromp-Taylor rules allow suicide, but a single stone suicide leaves the board as it was,
+    // which is no move at all.
+    if placement.suicided > 0 && (rule_set != RuleSet::TrompTaylor || placement.suicided == 1) {
+        return Err(format!("{vertex} is suicide"));
+    }
+    Ok(())
+}
+
+/// Returns the point the opponent may not retake after a stone on the vertex captured the stones,
+/// if they took a ko: a lone stone captured a lone stone and is left in atari.
+pub(crate) fn ko_point(board: &Board, vertex: Vertex, captured: &[Vertex]) -> Option<Vertex> {
+    let &[captured] = captured else {
+        return None;
+    };
+    let chain = board.chain_at(vertex)?;
+    (chain.len() == 1 && chain.liberty_count() == 1).then_some(captured)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stone(player: Player, vertex: &str) -> Move {
+        Move {
+            player,
+            vertex: Some(vertex.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn ko() {
+        let board = Board::from_ascii(
+            "5 . . . . .
+             4 . . . . .
+             3 . X O . .
+             2 X O . O .
+             1 . X O . .",
+        )
+        .unwrap();
+        let position = Position::new(board, Player::Black, RuleSet::Japanese);
+        let taken = position.play(&stone(Player::Black, "C2")).unwrap();
+        assert_eq!(taken.ko(), Some("B2".parse().unwrap()));
+        assert_eq!(taken.to_move(), Player::White);
+        assert!(!taken.is_legal(&stone(Player::White, "B2")));
+        assert!(position.board().stone("B2".parse().unwrap()).is_some());
+        let exempt = taken.clone().ko_exempt(true);
+        assert_eq!(exempt.ko(), None);
+        let retaken = exempt.play(&stone(Player::White, "B2")).unwrap();
+        assert!(retaken.is_legal(&stone(Player::Black, "C2")));
+        assert_eq!(exempt.ko_exempt(false).ko(), None);
+
+        let passed = taken
+            .play(&Move {
+                player: Player::White,
+                vertex: None,
+            })
+            .unwrap();
+        assert_eq!(passed.ko(), None);
+        assert!(passed.play(&stone(Player::White, "B2")).is_err());
+        let threat = passed.play(&stone(Player::Black, "E5")).unwrap();
+        assert!(threat.is_legal(&stone(Player::White, "B2")));
+    }
+
+    #[test]
+    fn suicide() {
+        let board = Board::from_ascii(
+            "5 . X O . .
+             4 X X O . .
+             3 O O O . .
+             2 . . . . .
+             1 . . . . .",
+        )
+        .unwrap();
+        let position = Position::new(board.clone(), Player::Black, RuleSet::Chinese);
+        assert!(!position.legal_moves().contains(&"A5".parse().unwrap()));
+        let position = Position::new(board, Player::Black, RuleSet::TrompTaylor);
+        let suicided = position.play(&stone(Player::Black, "A5")).unwrap();
+        assert!(suicided.board().stones(Player::Black).is_empty());
+    }
+}
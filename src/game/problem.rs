@@ -0,0 +1,236 @@
+use crate::game::board::Move;
+use crate::game::player::Player;
+use crate::game::sgf::{self, GameTree};
+use crate::game::vertex::Vertex;
+use crate::game::Game;
+
+/// What the player to move has to achieve to solve a problem.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Goal {
+    /// Capture the marked chain containing the vertex.
+    Capture(Vertex),
+    /// Reach the end of a correct line of the solution tree.
+    Solution,
+}
+
+/// A move in the solution tree and the moves that may follow it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variation {
+    /// The move played.
+    pub mov: Move,
+    /// Whether the move belongs to a correct line. Playing into a wrong line fails the problem.
+    pub correct: bool,
+    /// The moves that may follow.
+    pub children: Vec<Variation>,
+}
+
+/// The outcome of checking a line of play against a problem.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Verdict {
+    /// The goal was reached.
+    Solved,
+    /// The move at the index left the correct lines of the solution tree.
+    Failed(usize),
+    /// The line is correct so far but the goal has not been reached.
+    Unfinished,
+}
+
+/// A Go problem (詰碁 tsumego): a position, the player to move, a tree of solutions and a goal.
+#[derive(Clone, Debug)]
+pub struct Problem {
+    game: Game,
+    to_move: Player,
+    solutions: Vec<Variation>,
+    goal: Goal,
+}
+
+impl Problem {
+    /// Returns a new problem. The player to move is set on the game.
+    #[must_use]
+    pub fn new(mut game: Game, to_move: Player, solutions: Vec<Variation>, goal: Goal) -> Self {
+        game.set_to_move(to_move);
+        Problem {
+            game,
+            to_move,
+            solutions,
+            goal,
+        }
+    }
+
+    /// Loads a problem from SGF. The setup stones come from AB and AW, the player to move from PL
+    /// (or the first move of the solution), and the solution tree from the variations. Lines
+    /// whose comments say "wrong" are wrong answers. A stone marked with MA, TR, SQ or CR is a
+    /// chain to capture, otherwise the goal is to follow the solution.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the SGF is malformed or describes an impossible position.
+    pub fn from_sgf(text: &str) -> Result<Self, String> {
+        let trees = sgf::parse(text)?;
+        let tree = &trees[0];
+        let size = tree.board_size()?;
+        let mut game = Game::with_board_size(size)?;
+
+        let root = &tree.nodes[0];
+        let mut stones = Vec::new();
+        for (id, player) in [("AB", Player::Black), ("AW", Player::White)] {
+            for vertex in root.points(id, size)? {
+                stones.push((player, vertex));
+            }
+        }
+
+        let solutions = variations(tree, 1, size)?;
+        let to_move = match root.player_to_move()? {
+            Some(player) => player,
+            None => solutions
+                .first()
+                .map_or(Player::Black, |variation| variation.mov.player),
+        };
+        game.setup_position(&stones, to_move)?;
+
+        let mut goal = Goal::Solution;
+        for id in ["MA", "TR", "SQ", "CR"] {
+            if let Some(&vertex) = root.points(id, size)?.first() {
+                if game.board.is_vacant(vertex) {
+                    return Err(format!("marked point {vertex} is empty"));
+                }
+                goal = Goal::Capture(vertex);
+                break;
+            }
+        }
+
+        Ok(Problem::new(game, to_move, solutions, goal))
+    }
+
+    /// Returns the starting position.
+    #[must_use]
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Returns the player who has to solve the problem.
+    #[must_use]
+    pub fn to_move(&self) -> Player {
+        self.to_move
+    }
+
+    /// Returns the goal.
+    #[must_use]
+    pub fn goal(&self) -> Goal {
+        self.goal
+    }
+
+    /// Returns the first moves of the solution tree.
+    #[must_use]
+    pub fn solutions(&self) -> &[Variation] {
+        &self.solutions
+    }
+
+    /// Plays a line from the starting position and checks it against the goal and the solution
+    /// tree. Replies by the opponent that aren't in the tree are allowed, after which only the
+    /// goal is checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns the index of the first illegal move in the line.
+    pub fn check(&self, line: &[Move]) -> Result<Verdict, (usize, String)> {
+        let mut game = self.game.clone();
+        let mut variations = Some(&self.solutions);
+
+        for (index, mov) in line.iter().enumerate() {
+            game.play(mov).map_err(|err| (index, err))?;
+
+            if let Some(children) = variations.filter(|children| !children.is_empty()) {
+                match children.iter().find(|variation| variation.mov == *mov) {
+                    Some(variation) if !variation.correct => return Ok(Verdict::Failed(index)),
+                    Some(variation) => {
+                        if variation.children.is_empty() && self.goal == Goal::Solution {
+                            return Ok(Verdict::Solved);
+                        }
+                        variations = Some(&variation.children);
+                    }
+                    None if mov.player == self.to_move => return Ok(Verdict::Failed(index)),
+                    None => variations = None,
+                }
+            }
+
+            if let Goal::Capture(vertex) = self.goal {
+                if game.board().is_vacant(vertex) {
+                    return Ok(Verdict::Solved);
+                }
+            }
+        }
+        Ok(Verdict::Unfinished)
+    }
+}
+
+/// Builds the solution tree starting at node `start` of the game tree.
+fn variations(tree: &GameTree, start: usize, size: usize) -> Result<Vec<Variation>, String> {
+    let Some(node) = tree.nodes.get(start) else {
+        let mut variations = Vec::new();
+        for tree in &tree.variations {
+            variations.extend(self::variations(tree, 0, size)?);
+        }
+        return Ok(variations);
+    };
+
+    let children = variations(tree, start + 1, size)?;
+    match node.mov(size)? {
+        Some(mov) => {
+            let correct = !node
+                .value("C")
+                .map_or(false, |comment| comment.to_lowercase().contains("wrong"));
+            Ok(vec![Variation {
+                mov,
+                correct: correct && (children.is_empty() || children.iter().any(|c| c.correct)),
+                children,
+            }])
+        }
+        None => Ok(children),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stone(player: Player, x: usize, y: usize) -> Move {
+        Move {
+            player,
+            vertex: Some(Vertex { x, y }),
+        }
+    }
+
+    // A white stone in atari on the edge, captured by filling its last liberty.
+    const CAPTURE: &str = "(;SZ[5]PL[B]AB[bd][ce]AW[be]MA[be]\
+                           (;B[ad];W[ae]C[Wrong])(;B[ae]C[Right]))";
+
+    #[test]
+    fn capture_problem() {
+        let problem = Problem::from_sgf(CAPTURE).unwrap();
+        assert_eq!(problem.to_move(), Player::Black);
+        assert_eq!(problem.goal(), Goal::Capture(Vertex { x: 1, y: 0 }));
+        assert_eq!(problem.solutions().len(), 2);
+        assert!(!problem.solutions()[0].correct);
+
+        assert_eq!(
+            problem.check(&[stone(Player::Black, 0, 0)]),
+            Ok(Verdict::Solved)
+        );
+        assert_eq!(
+            problem.check(&[stone(Player::Black, 0, 1)]),
+            Ok(Verdict::Failed(0))
+        );
+        assert_eq!(
+            problem.check(&[stone(Player::Black, 4, 4)]),
+            Ok(Verdict::Failed(0))
+        );
+        assert_eq!(
+            problem
+                .check(&[stone(Player::Black, 1, 1)])
+                .map_err(|e| e.0),
+            Err(0)
+        );
+        assert_eq!(problem.check(&[]), Ok(Verdict::Unfinished));
+    }
+}
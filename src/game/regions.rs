@@ -0,0 +1,242 @@
+//! The empty regions of a board and the stones bordering them, kept up to date as stones are
+//! placed and removed so that the area score can be read after every move without a flood fill
+//! of the whole board.
+
+use crate::game::board::State;
+use crate::game::matrix::{Matrix, Node};
+use crate::game::vertex::Vertex;
+
+/// The points around a point, in order around it, with the ones next to it at even indices.
+const RING: [(isize, isize); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+/// An empty region and the stones around it.
+#[derive(Clone, Copy, Debug, Default)]
+struct Region {
+    /// The number of empty points in the region.
+    size: usize,
+    /// The number of pairs of a point in the region next to a black stone.
+    black: usize,
+    /// The number of pairs of a point in the region next to a white stone.
+    white: usize,
+}
+
+impl Region {
+    fn owner(&self) -> State {
+        match (self.black > 0, self.white > 0) {
+            (true, false) => State::Black,
+            (false, true) => State::White,
+            _ => State::Empty,
+        }
+    }
+}
+
+/// The empty regions of a board. Placing a stone only floods its region again when the stone may
+/// have split it in two, and removing stones floods the region they leave behind.
+#[derive(Clone, Debug)]
+pub(crate) struct EmptyRegions {
+    /// The region each empty point is in.
+    labels: Matrix<Option<usize>>,
+    regions: Vec<Region>,
+    /// The labels no longer in use.
+    free: Vec<usize>,
+}
+
+impl EmptyRegions {
+    /// Finds the empty regions of the board.
+    pub(crate) fn new(matrix: &Matrix<State>) -> Self {
+        let mut regions = EmptyRegions {
+            labels: Matrix::with_size(matrix.size()),
+            regions: Vec::new(),
+            free: Vec::new(),
+        };
+        regions.rebuild(matrix);
+        regions
+    }
+
+    /// Finds the empty regions of the board again from scratch.
+    pub(crate) fn rebuild(&mut self, matrix: &Matrix<State>) {
+        self.labels.reset();
+        self.regions.clear();
+        self.free.clear();
+        for node in matrix.nodes() {
+            if matrix[node] == State::Empty && self.labels[node].is_none() {
+                let label = self.allocate();
+                self.flood(matrix, node, label);
+            }
+        }
+    }
+
+    /// Updates the regions after a stone was placed on the empty node, before anything it
+    /// captures is removed.
+    pub(crate) fn stone_placed(&mut self, matrix: &Matrix<State>, node: Node) {
+        let Some(label) = self.labels[node].take() else {
+            return self.rebuild(matrix);
+        };
+        let region = &mut self.regions[label];
+        region.size -= 1;
+        for &neighbor in matrix.adjacencies(node) {
+            match matrix[neighbor] {
+                // The neighbor borders the new stone now.
+                State::Empty => match matrix[node] {
+                    State::Black => region.black += 1,
+                    State::White => region.white += 1,
+                    State::Empty => {}
+                },
+                // The node no longer borders the neighbor.
+                State::Black => region.black -= 1,
+                State::White => region.white -= 1,
+            }
+        }
+
+        if region.size > 0 && may_split(matrix, node) {
+            for &neighbor in matrix.adjacencies(node) {
+                if self.labels[neighbor] == Some(label) {
+                    let part = self.allocate();
+                    self.flood(matrix, neighbor, part);
+                }
+            }
+            self.release(label);
+        } else if region.size == 0 {
+            self.release(label);
+        }
+    }
+
+    /// Updates the regions after the stones on the nodes were removed, joining the regions next
+    /// to them.
+    pub(crate) fn stones_removed(&mut self, matrix: &Matrix<State>, nodes: &[Node]) {
+        for &node in nodes {
+            if self.labels[node].is_none() {
+                let label = self.allocate();
+                for joined in self.flood(matrix, node, label) {
+                    self.release(joined);
+                }
+            }
+        }
+    }
+
+    /// Returns the player whose stones alone border the region of the node as a state, or
+    /// `State::Empty` if both players' stones do, neither do or the node isn't empty.
+    pub(crate) fn owner(&self, node: Node) -> State {
+        self.labels[node].map_or(State::Empty, |label| self.regions[label].owner())
+    }
+
+    /// Returns the empty points bordered only by Black less those bordered only by White.
+    pub(crate) fn territory(&self) -> i32 {
+        let mut territory = 0;
+        for region in &self.regions {
+            let size = i32::try_from(region.size).expect("region too large");
+            match region.owner() {
+                State::Black => territory += size,
+                State::White => territory -= size,
+                State::Empty => {}
+            }
+        }
+        territory
+    }
+
+    fn allocate(&mut self) -> usize {
+        if let Some(label) = self.free.pop() {
+            label
+        } else {
+            self.regions.push(Region::default());
+            self.regions.len() - 1
+        }
+    }
+
+    /// Frees a label whose points have all been filled or given other labels.
+    fn release(&mut self, label: usize) {
+        self.regions[label] = Region::default();
+        self.free.push(label);
+    }
+
+    /// Labels the empty region holding the node and counts its points and borders, returning the
+    /// labels of the regions it took points from.
+    fn flood(&mut self, matrix: &Matrix<State>, node: Node, label: usize) -> Vec<usize> {
+        let mut region = Region::default();
+        let mut replaced = Vec::new();
+        let mut queue = vec![node];
+        if let Some(old) = self.labels[node].replace(label) {
+            replaced.push(old);
+        }
+        while let Some(node) = queue.pop() {
+            region.size += 1;
+            for &neighbor in matrix.adjacencies(node) {
+                match matrix[neighbor] {
+                    State::Empty => {
+                        if let Some(old) = self.labels[neighbor] {
+                            if old == label {
+                                continue;
+                            }
+                            if !replaced.contains(&old) {
+                                replaced.push(old);
+                            }
+                        }
+                        self.labels[neighbor] = Some(label);
+                        queue.push(neighbor);
+                    }
+                    State::Black => region.black += 1,
+                    State::White => region.white += 1,
+                }
+            }
+        }
+        self.regions[label] = region;
+        replaced
+    }
+}
+
+/// Whether the empty points next to the node might no longer be connected to each other: they
+/// are if they're joined by the empty points around the node.
+fn may_split(matrix: &Matrix<State>, node: Node) -> bool {
+    let Vertex { x, y } = matrix.vertex_from_node(node);
+    let empty = RING.map(|(dx, dy)| {
+        let (Some(x), Some(y)) = (offset(x, dx), offset(y, dy)) else {
+            return false;
+        };
+        matrix
+            .node_from_vertex(Vertex { x, y })
+            .map_or(false, |node| matrix[node] == State::Empty)
+    });
+    if empty.iter().all(|&empty| empty) {
+        return false;
+    }
+
+    // Count the runs of empty points around the node that hold a point next to it, starting
+    // after a point that isn't empty so no run wraps around.
+    let start = empty.iter().position(|&empty| !empty).unwrap_or(0);
+    let mut runs = 0;
+    let mut in_run = false;
+    let mut counted = false;
+    for i in 1..=RING.len() {
+        let index = (start + i) % RING.len();
+        if empty[index] {
+            if !in_run {
+                in_run = true;
+                counted = false;
+            }
+            if index % 2 == 0 && !counted {
+                counted = true;
+                runs += 1;
+            }
+        } else {
+            in_run = false;
+        }
+    }
+    runs > 1
+}
+
+fn offset(coordinate: usize, by: isize) -> Option<usize> {
+    if by < 0 {
+        coordinate.checked_sub(by.unsigned_abs())
+    } else {
+        coordinate.checked_add(by.unsigned_abs())
+    }
+}
@@ -0,0 +1,458 @@
+//! Board diagrams as ASCII, Unicode or SVG, with an optional overlay of labels, marks, numbers and
+//! territory for drawing analysis, e.g. the top moves of an engine or who owns each point.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::game::board::Board;
+use crate::game::player::Player;
+use crate::game::vertex::{Vertex, GOBAN_LETTERS};
+use crate::game::Game;
+
+/// The size in pixels of a point of an SVG diagram.
+const CELL: usize = 24;
+
+/// A shape drawn on a point.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mark {
+    /// A circle, e.g. for the last move.
+    Circle,
+    /// A square.
+    Square,
+    /// A triangle.
+    Triangle,
+    /// A cross.
+    Cross,
+}
+
+/// Annotations drawn over the points of a board. When a point has more than one, a label is
+/// drawn in preference to a number and a number in preference to a mark. Territory is shaded
+/// under the rest.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BoardOverlay {
+    /// Text drawn on points. The text diagrams only have room for the first character.
+    pub labels: HashMap<Vertex, String>,
+    /// Shapes drawn on points.
+    pub marks: HashMap<Vertex, Mark>,
+    /// Numbers drawn on points, e.g. the order of a sequence of moves.
+    pub numbers: HashMap<Vertex, usize>,
+    /// The player each point is counted for. The text diagrams only shade empty points.
+    pub territory: HashMap<Vertex, Player>,
+}
+
+/// The characters a text diagram is drawn with.
+struct Glyphs {
+    /// The separator before each point after the first of a row.
+    separator: char,
+    black: char,
+    white: char,
+    /// Returns the character for an empty point that isn't annotated.
+    empty: fn(&Board, Vertex) -> char,
+    /// Returns the character for a mark on an empty point or on a stone of the player.
+    mark: fn(Mark, Option<Player>) -> char,
+    /// Returns the character for a number.
+    number: fn(usize) -> char,
+    /// Returns the character for an empty point counted for the player.
+    territory: fn(Player) -> char,
+}
+
+const ASCII: Glyphs = Glyphs {
+    separator: ' ',
+    black: 'x',
+    white: 'o',
+    empty: |board, vertex| {
+        if board.star_points().contains(&vertex) {
+            '+'
+        } else {
+            '.'
+        }
+    },
+    mark: |_mark, player| match player {
+        Some(Player::Black) => 'X',
+        Some(Player::White) => 'O',
+        None => '*',
+    },
+    number: last_digit,
+    territory: |player| match player {
+        Player::Black => 'b',
+        Player::White => 'w',
+    },
+};
+
+const UNICODE: Glyphs = Glyphs {
+    separator: '─',
+    black: '●',
+    white: '○',
+    empty: |board, vertex| {
+        let last = board.size() - 1;
+        let (left, right) = (vertex.x == 0, vertex.x == last);
+        let (bottom, top) = (vertex.y == 0, vertex.y == last);
+        match (left, right, bottom, top) {
+            (true, _, _, true) => '┌',
+            (_, true, _, true) => '┐',
+            (true, _, true, _) => '└',
+            (_, true, true, _) => '┘',
+            (_, _, _, true) => '┬',
+            (_, _, true, _) => '┴',
+            (true, _, _, _) => '├',
+            (_, true, _, _) => '┤',
+            _ if board.star_points().contains(&vertex) => '╋',
+            _ => '┼',
+        }
+    },
+    mark: |mark, player| match (mark, player) {
+        (_, Some(Player::Black)) => '◉',
+        (_, Some(Player::White)) => '◎',
+        (Mark::Circle, None) => '◌',
+        (Mark::Square, None) => '□',
+        (Mark::Triangle, None) => '△',
+        (Mark::Cross, None) => '×',
+    },
+    number: |number| match u32::try_from(number) {
+        // The circled numbers ① to ⑳.
+        Ok(number @ 1..=20) => char::from_u32(0x245f + number).unwrap_or('?'),
+        _ => last_digit(number),
+    },
+    territory: |player| match player {
+        Player::Black => '▪',
+        Player::White => '▫',
+    },
+};
+
+fn last_digit(number: usize) -> char {
+    char::from_digit(u32::try_from(number % 10).unwrap_or(0), 10).unwrap_or('?')
+}
+
+/// Returns an ASCII diagram of the board, the same as `Board::to_ascii` without an overlay. Black
+/// stones are `x` and White's `o`, or `X` and `O` when marked. Marked empty points are `*`, a
+/// number is shown by its last digit and empty points counted for Black and White are `b` and
+/// `w`.
+#[must_use]
+pub fn ascii(board: &Board, overlay: &BoardOverlay) -> String {
+    text(board, overlay, &ASCII)
+}
+
+/// Returns a Unicode diagram of the board drawn with box drawing characters. Stones are `●` and
+/// `○`, or `◉` and `◎` when marked. Numbers up to 20 are circled, and empty points counted
+/// for Black and White are `▪` and `▫`.
+#[must_use]
+pub fn unicode(board: &Board, overlay: &BoardOverlay) -> String {
+    text(board, overlay, &UNICODE)
+}
+
+fn text(board: &Board, overlay: &BoardOverlay, glyphs: &Glyphs) -> String {
+    let size = board.size();
+    let mut diagram = String::new();
+    push_letters(&mut diagram, size);
+    diagram.push_str("\r\n");
+    for y in (0..size).rev() {
+        let _ = write!(diagram, "{:02}", y + 1);
+        for x in 0..size {
+            diagram.push(if x == 0 { ' ' } else { glyphs.separator });
+            let vertex = Vertex { x, y };
+            let stone = board.stone(vertex);
+            let c = if let Some(label) = overlay.labels.get(&vertex) {
+                label.chars().next().unwrap_or(' ')
+            } else if let Some(&number) = overlay.numbers.get(&vertex) {
+                (glyphs.number)(number)
+            } else if let Some(&mark) = overlay.marks.get(&vertex) {
+                (glyphs.mark)(mark, stone)
+            } else {
+                match (stone, overlay.territory.get(&vertex)) {
+                    (Some(Player::Black), _) => glyphs.black,
+                    (Some(Player::White), _) => glyphs.white,
+                    (None, Some(&player)) => (glyphs.territory)(player),
+                    (None, None) => (glyphs.empty)(board, vertex),
+                }
+            };
+            diagram.push(c);
+        }
+        let _ = write!(diagram, " {:02}\r\n", y + 1);
+    }
+    push_letters(&mut diagram, size);
+    diagram
+}
+
+fn push_letters(diagram: &mut String, size: usize) {
+    diagram.push_str("  ");
+    for letter in GOBAN_LETTERS.chars().take(size) {
+        diagram.push(' ');
+        diagram.push(letter);
+    }
+    diagram.push_str("   ");
+}
+
+/// Returns an SVG diagram of the board with coordinates, drawn on a wooden background.
+#[must_use]
+pub fn svg(board: &Board, overlay: &BoardOverlay) -> String {
+    let mut svg = String::new();
+    let _ = svg_grid(&mut svg, board).and_then(|()| svg_points(&mut svg, board, overlay));
+    svg_document(board.size(), &svg)
+}
+
+/// Returns an SVG diagram of the board after each move of the game, starting from the position
+/// before the first move. The last move played is marked with a circle.
+#[must_use]
+pub fn svg_frames(game: &Game) -> Vec<String> {
+    playback(game)
+        .into_iter()
+        .map(|(board, overlay)| svg(&board, &overlay))
+        .collect()
+}
+
+/// Returns a single SVG that plays back the game with SMIL animation, showing each position for
+/// `frame` and then stopping at the final position, e.g. to embed a game in a web page without a
+/// JavaScript viewer.
+#[must_use]
+pub fn animated_svg(game: &Game, frame: Duration) -> String {
+    let frames = playback(game);
+    let mut svg = String::new();
+    let _ = svg_grid(&mut svg, game.board());
+    for (number, (board, overlay)) in frames.iter().enumerate() {
+        let begin = frame.as_secs_f64() * count(number);
+        let end = if number + 1 == frames.len() {
+            "fill=\"freeze\"".to_owned()
+        } else {
+            format!("dur=\"{:.3}s\"", frame.as_secs_f64())
+        };
+        let _ = writeln!(
+            svg,
+            "<g visibility=\"hidden\">\n\
+             <set attributeName=\"visibility\" to=\"visible\" begin=\"{begin:.3}s\" {end}/>"
+        );
+        let _ = svg_points(&mut svg, board, overlay);
+        svg.push_str("</g>\n");
+    }
+    svg_document(game.board().size(), &svg)
+}
+
+/// Returns each position of the game with the move that led to it marked.
+fn playback(game: &Game) -> Vec<(Board, BoardOverlay)> {
+    let mut replay = game.fork_at(0);
+    let mut frames = vec![(replay.board().clone(), BoardOverlay::default())];
+    for mov in game.moves() {
+        // The moves were legal when they were played.
+        let _ = replay.play(mov);
+        let mut overlay = BoardOverlay::default();
+        if let Some(vertex) = mov.vertex {
+            overlay.marks.insert(vertex, Mark::Circle);
+        }
+        frames.push((replay.board().clone(), overlay));
+    }
+    frames
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn count(frames: usize) -> f64 {
+    frames as f64
+}
+
+fn svg_document(size: usize, elements: &str) -> String {
+    let width = (size + 1) * CELL;
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{width}\" \
+         viewBox=\"0 0 {width} {width}\">\n{elements}</svg>\n"
+    )
+}
+
+/// Returns the center of a point of an SVG diagram.
+fn center(size: usize, vertex: Vertex) -> (usize, usize) {
+    (CELL * (vertex.x + 1), CELL * (size - vertex.y))
+}
+
+/// Writes the background, lines, coordinates and star points of an SVG diagram.
+fn svg_grid(svg: &mut String, board: &Board) -> std::fmt::Result {
+    let size = board.size();
+    let width = (size + 1) * CELL;
+    let (first, last) = (CELL, size * CELL);
+    let center = |vertex: Vertex| center(size, vertex);
+
+    writeln!(
+        svg,
+        "<rect width=\"{width}\" height=\"{width}\" fill=\"#dcb35c\"/>"
+    )?;
+    for line in 1..=size {
+        let at = line * CELL;
+        writeln!(
+            svg,
+            "<line x1=\"{first}\" y1=\"{at}\" x2=\"{last}\" y2=\"{at}\" stroke=\"black\"/>"
+        )?;
+        writeln!(
+            svg,
+            "<line x1=\"{at}\" y1=\"{first}\" x2=\"{at}\" y2=\"{last}\" stroke=\"black\"/>"
+        )?;
+    }
+    let font = "font-family=\"sans-serif\" font-size=\"10\" text-anchor=\"middle\"";
+    for (x, letter) in GOBAN_LETTERS.chars().take(size).enumerate() {
+        let (cx, _) = center(Vertex { x, y: 0 });
+        let y = width - CELL / 4;
+        writeln!(svg, "<text x=\"{cx}\" y=\"{y}\" {font}>{letter}</text>")?;
+    }
+    for y in 0..size {
+        let (_, cy) = center(Vertex { x: 0, y });
+        let (x, cy) = (CELL / 2 - 2, cy + 3);
+        writeln!(svg, "<text x=\"{x}\" y=\"{cy}\" {font}>{}</text>", y + 1)?;
+    }
+    for vertex in board.star_points() {
+        let (cx, cy) = center(vertex);
+        writeln!(svg, "<circle cx=\"{cx}\" cy=\"{cy}\" r=\"3\"/>")?;
+    }
+    Ok(())
+}
+
+/// Writes the stones and overlay of an SVG diagram.
+fn svg_points(svg: &mut String, board: &Board, overlay: &BoardOverlay) -> std::fmt::Result {
+    let size = board.size();
+
+    for y in (0..size).rev() {
+        for x in 0..size {
+            let vertex = Vertex { x, y };
+            let (cx, cy) = center(size, vertex);
+            let stone = board.stone(vertex);
+            if let Some(player) = stone {
+                let fill = color(player);
+                writeln!(
+                    svg,
+                    "<circle cx=\"{cx}\" cy=\"{cy}\" r=\"{}\" fill=\"{fill}\" stroke=\"black\"/>",
+                    CELL / 2 - 1
+                )?;
+            }
+            if let Some(&player) = overlay.territory.get(&vertex) {
+                let (corner, shade) = (CELL / 6, CELL / 3);
+                writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{shade}\" height=\"{shade}\" fill=\"{}\" \
+                     stroke=\"gray\"/>",
+                    cx - corner,
+                    cy - corner,
+                    color(player)
+                )?;
+            }
+
+            // Annotations are drawn in the color that contrasts with what's under them.
+            let ink = color(stone.map_or(Player::Black, |player| player.enemy()));
+            let text = overlay
+                .labels
+                .get(&vertex)
+                .map(|label| escape(label))
+                .or_else(|| overlay.numbers.get(&vertex).map(ToString::to_string));
+            if let Some(text) = text {
+                if stone.is_none() {
+                    // Clear the lines under the text.
+                    writeln!(
+                        svg,
+                        "<circle cx=\"{cx}\" cy=\"{cy}\" r=\"{}\" fill=\"#dcb35c\"/>",
+                        CELL / 3
+                    )?;
+                }
+                writeln!(
+                    svg,
+                    "<text x=\"{cx}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"12\" \
+                     text-anchor=\"middle\" fill=\"{ink}\">{text}</text>",
+                    cy + 4
+                )?;
+            } else if let Some(&mark) = overlay.marks.get(&vertex) {
+                svg_mark(svg, mark, (cx, cy), ink)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes a mark centered on a point.
+fn svg_mark(svg: &mut String, mark: Mark, (cx, cy): (usize, usize), ink: &str) -> std::fmt::Result {
+    let r = CELL / 4;
+    let stroke = format!("fill=\"none\" stroke=\"{ink}\" stroke-width=\"2\"");
+    let (left, right, top, bottom) = (cx - r, cx + r, cy - r, cy + r);
+    match mark {
+        Mark::Circle => writeln!(svg, "<circle cx=\"{cx}\" cy=\"{cy}\" r=\"{r}\" {stroke}/>"),
+        Mark::Square => writeln!(
+            svg,
+            "<rect x=\"{left}\" y=\"{top}\" width=\"{}\" height=\"{}\" {stroke}/>",
+            2 * r,
+            2 * r
+        ),
+        Mark::Triangle => writeln!(
+            svg,
+            "<polygon points=\"{cx},{top} {left},{bottom} {right},{bottom}\" {stroke}/>"
+        ),
+        Mark::Cross => writeln!(
+            svg,
+            "<path d=\"M{left} {top}L{right} {bottom}M{right} {top}L{left} {bottom}\" {stroke}/>"
+        ),
+    }
+}
+
+fn color(player: Player) -> &'static str {
+    match player {
+        Player::Black => "black",
+        Player::White => "white",
+    }
+}
+
+/// Escapes text for XML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::board::Move;
+
+    #[test]
+    fn overlay() {
+        let mut board = Board::with_size(3).unwrap();
+        board.place_stone(Player::Black, Vertex { x: 0, y: 0 });
+        board.place_stone(Player::White, Vertex { x: 2, y: 2 });
+        let mut overlay = BoardOverlay::default();
+        overlay.marks.insert(Vertex { x: 0, y: 0 }, Mark::Circle);
+        overlay.labels.insert(Vertex { x: 1, y: 1 }, "A".to_owned());
+        overlay.numbers.insert(Vertex { x: 2, y: 0 }, 12);
+        overlay
+            .territory
+            .insert(Vertex { x: 0, y: 2 }, Player::White);
+
+        assert_eq!(
+            ascii(&board, &overlay),
+            "   A B C   \r\n03 w . o 03\r\n02 . A . 02\r\n01 X . 2 01\r\n   A B C   "
+        );
+        assert_eq!(
+            unicode(&board, &overlay),
+            "   A B C   \r\n03 ▫─┬─○ 03\r\n02 ├─A─┤ 02\r\n01 ◉─┴─⑫ 01\r\n   A B C   "
+        );
+        assert_eq!(ascii(&board, &BoardOverlay::default()), board.to_ascii());
+
+        let svg = svg(&board, &overlay);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"96\""));
+        assert_eq!(svg.matches("<circle").count(), 5);
+        assert!(svg.contains(">A</text>") && svg.contains(">12</text>"));
+    }
+
+    #[test]
+    fn playback() {
+        let mut game = Game::with_board_size(5).unwrap();
+        for (player, vertex) in [
+            (Player::Black, Some(Vertex { x: 2, y: 2 })),
+            (Player::White, None),
+        ] {
+            game.play(&Move { player, vertex }).unwrap();
+        }
+        let frames = svg_frames(&game);
+        assert_eq!(frames.len(), 3);
+        assert_eq!(
+            frames[0],
+            svg(&Board::with_size(5).unwrap(), &BoardOverlay::default())
+        );
+        // The stone and the circle marking it.
+        assert_eq!(frames[1].matches("<circle").count(), 2);
+
+        let animated = animated_svg(&game, Duration::from_millis(500));
+        assert_eq!(animated.matches("<set ").count(), 3);
+        assert!(animated.contains(r#"begin="1.000s" fill="freeze""#));
+        assert_eq!(animated.matches("<line").count(), 10);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::game::board::Move;
+use crate::game::player::Player;
+use crate::game::Game;
+
+/// A game between two teams whose members take turns making their team's moves, as in Pair Go
+/// and rengo. Black's first member plays the first Black move, White's first member the first
+/// White move, then each team's second member, and so on.
+#[derive(Clone, Debug)]
+pub struct Rengo {
+    game: Game,
+    black_team: Vec<String>,
+    white_team: Vec<String>,
+    /// The team and index of the member who made each move in the game record.
+    played_by: Vec<(Player, usize)>,
+}
+
+impl Rengo {
+    /// Returns a new rengo game played on `game` by the given teams.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a team has no members or moves were already played in the game.
+    pub fn new(
+        game: Game,
+        black_team: Vec<String>,
+        white_team: Vec<String>,
+    ) -> Result<Self, String> {
+        if black_team.is_empty() || white_team.is_empty() {
+            return Err("each team needs at least one member".to_owned());
+        }
+        if !game.move_history.is_empty() {
+            return Err("moves were already played in the game".to_owned());
+        }
+        Ok(Rengo {
+            game,
+            black_team,
+            white_team,
+            played_by: Vec::new(),
+        })
+    }
+
+    /// Returns a shared reference to the game being played.
+    #[must_use]
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Returns the members of a team in playing order.
+    #[must_use]
+    pub fn team(&self, player: Player) -> &[String] {
+        match player {
+            Player::Black => &self.black_team,
+            Player::White => &self.white_team,
+        }
+    }
+
+    /// Returns the member whose turn it is to play next.
+    #[must_use]
+    pub fn member_to_move(&self) -> &str {
+        let player = self.game.player_turn();
+        &self.team(player)[self.next_index(player)]
+    }
+
+    /// Index of the next member of a team to move.
+    fn next_index(&self, player: Player) -> usize {
+        let moves = self
+            .played_by
+            .iter()
+            .filter(|&&(team, _)| team == player)
+            .count();
+        moves % self.team(player).len()
+    }
+
+    /// Plays a move for the named member.
+    ///
+    /// # Errors
+    ///
+    /// Fails if it is not the member's turn, the move is for the wrong color or the move is
+    /// illegal.
+    pub fn play(&mut self, member: &str, mov: &Move) -> Result<(), String> {
+        let player = self.game.player_turn();
+        if mov.player != player {
+            return Err(format!("it is {player}'s turn"));
+        }
+        let index = self.next_index(player);
+        let expected = &self.team(player)[index];
+        if member != expected {
+            return Err(format!("it is {expected}'s turn, not {member}'s"));
+        }
+
+        self.game.play(mov)?;
+        self.played_by.push((player, index));
+        Ok(())
+    }
+
+    /// Undo the last move, handing the turn back to the member who made it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there are no moves to undo.
+    pub fn undo(&mut self) -> Result<(), String> {
+        self.game.undo()?;
+        self.played_by.pop();
+        Ok(())
+    }
+
+    /// Returns the member who made the given move (counting from 1).
+    #[must_use]
+    pub fn played_by(&self, move_number: usize) -> Option<&str> {
+        let &(player, index) = self.played_by.get(move_number.checked_sub(1)?)?;
+        Some(&self.team(player)[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::vertex::Vertex;
+
+    fn stone(player: Player, x: usize, y: usize) -> Move {
+        Move {
+            player,
+            vertex: Some(Vertex { x, y }),
+        }
+    }
+
+    #[test]
+    fn rotation() {
+        let team = |a: &str, b: &str| vec![a.to_owned(), b.to_owned()];
+        let mut rengo = Rengo::new(
+            Game::with_board_size(9).unwrap(),
+            team("b1", "b2"),
+            team("w1", "w2"),
+        )
+        .unwrap();
+
+        assert_eq!(rengo.member_to_move(), "b1");
+        assert!(rengo.play("b2", &stone(Player::Black, 0, 0)).is_err());
+        rengo.play("b1", &stone(Player::Black, 0, 0)).unwrap();
+        rengo.play("w1", &stone(Player::White, 1, 0)).unwrap();
+        assert_eq!(rengo.member_to_move(), "b2");
+        rengo.play("b2", &stone(Player::Black, 2, 0)).unwrap();
+        rengo.play("w2", &stone(Player::White, 3, 0)).unwrap();
+        assert_eq!(rengo.member_to_move(), "b1");
+        assert_eq!(rengo.played_by(3), Some("b2"));
+
+        rengo.undo().unwrap();
+        assert_eq!(rengo.member_to_move(), "w2");
+    }
+}
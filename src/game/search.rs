@@ -0,0 +1,470 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+use rand::rngs::ThreadRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::game::board::Move;
+use crate::game::player::Player;
+use crate::game::vertex::Vertex;
+use crate::game::Game;
+
+/// Tells a search to stop early. Clones share the same flag and deadline, so one can be handed to
+/// the thread searching and another kept by whoever decides when it should stop, e.g. a clock or
+/// a GTP interrupt.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<Cancel>);
+
+#[derive(Debug, Default)]
+struct Cancel {
+    cancelled: AtomicBool,
+    deadline: Mutex<Option<Instant>>,
+}
+
+impl CancelToken {
+    /// Returns a token that hasn't been cancelled.
+    #[must_use]
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Asks every search holding the token to stop.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Cancels the token once the deadline has passed, e.g. when the time for a move is up,
+    /// replacing any deadline set before.
+    pub fn cancel_at(&self, deadline: Instant) {
+        *self
+            .0
+            .deadline
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(deadline);
+    }
+
+    /// Whether the token has been cancelled or its deadline has passed.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        let Cancel {
+            cancelled,
+            deadline,
+        } = &*self.0;
+        if !cancelled.load(Ordering::Relaxed)
+            && deadline
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .map_or(false, |deadline| Instant::now() >= deadline)
+        {
+            self.cancel();
+        }
+        cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// The most a child's win rate is raised for being explored less than its siblings when choosing
+/// which move to search next.
+const EXPLORATION: f64 = 1.4;
+
+/// A move in the search tree, with the statistics of the playouts that went through it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchNode {
+    mov: Move,
+    visits: usize,
+    /// The playouts won by the player making the move, with draws counting half.
+    wins: f64,
+    children: Vec<SearchNode>,
+    /// The replies that haven't been searched yet.
+    untried: Vec<Move>,
+}
+
+impl SearchNode {
+    fn new(mov: Move, game: &Game, rng: &mut ThreadRng) -> Self {
+        SearchNode {
+            mov,
+            visits: 0,
+            wins: 0.0,
+            children: Vec::new(),
+            untried: candidates(game, mov.player.enemy(), rng),
+        }
+    }
+
+    /// The move.
+    #[must_use]
+    pub fn mov(&self) -> Move {
+        self.mov
+    }
+
+    /// The number of playouts that went through the move.
+    #[must_use]
+    pub fn visits(&self) -> usize {
+        self.visits
+    }
+
+    /// The fraction of the playouts through the move that the player making it won, if there were
+    /// any.
+    #[must_use]
+    pub fn win_rate(&self) -> Option<f64> {
+        (self.visits > 0).then(|| self.wins / count(self.visits))
+    }
+
+    /// The replies searched so far.
+    #[must_use]
+    pub fn children(&self) -> &[SearchNode] {
+        &self.children
+    }
+
+    /// Returns the most visited child, breaking ties by win rate.
+    fn most_visited(children: &[SearchNode]) -> Option<&SearchNode> {
+        children
+            .iter()
+            .max_by(|a, b| a.visits.cmp(&b.visits).then(a.wins.total_cmp(&b.wins)))
+    }
+
+    /// Plays out one game through the node, whose move has been played on the game, growing the
+    /// tree by one node. Returns the winner, if any.
+    fn playout(&mut self, game: &mut Game, rng: &mut ThreadRng) -> Option<Player> {
+        let winner = if game.is_over() {
+            winner(game)
+        } else if let Some(mov) = self.untried.pop() {
+            game.play(&mov).expect("failed to play a candidate move");
+            let mut child = SearchNode::new(mov, game, rng);
+            while !game.is_over() {
+                play_random(game, rng);
+            }
+            let winner = winner(game);
+            child.record(winner);
+            self.children.push(child);
+            winner
+        } else {
+            let child = self.select();
+            game.play(&child.mov)
+                .expect("failed to play a searched move");
+            child.playout(game, rng)
+        };
+        self.record(winner);
+        winner
+    }
+
+    /// Returns the child with the best win rate after adding a bonus for being explored less.
+    fn select(&mut self) -> &mut SearchNode {
+        let explored = count(self.visits).ln();
+        let value = |child: &SearchNode| {
+            child.wins / count(child.visits) + EXPLORATION * (explored / count(child.visits)).sqrt()
+        };
+        let best = (0..self.children.len())
+            .max_by(|&a, &b| value(&self.children[a]).total_cmp(&value(&self.children[b])))
+            .expect("a node with no untried moves has children");
+        &mut self.children[best]
+    }
+
+    fn record(&mut self, winner: Option<Player>) {
+        self.visits += 1;
+        self.wins += match winner {
+            Some(player) if player == self.mov.player => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        };
+    }
+}
+
+/// The outcome of a search, with a read-only view of the tree it searched.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    /// The move chosen.
+    pub mov: Move,
+    /// The number of random games played out to choose the move.
+    pub playouts: usize,
+    /// The fraction of the move's playouts the player won, if it had any.
+    pub win_rate: Option<f64>,
+    /// Whether the search was cancelled before using all of its playouts.
+    pub cancelled: bool,
+    /// The player's moves that were searched, with the replies searched below them.
+    pub children: Vec<SearchNode>,
+}
+
+impl SearchResult {
+    /// The sequence of moves the search expects, starting with the chosen move and following the
+    /// most searched reply at each step.
+    #[must_use]
+    pub fn principal_variation(&self) -> Vec<Move> {
+        let mut variation = vec![self.mov];
+        let mut node = self.children.iter().find(|child| child.mov == self.mov);
+        while let Some(child) = node.and_then(|node| SearchNode::most_visited(&node.children)) {
+            variation.push(child.mov);
+            node = Some(child);
+        }
+        variation
+    }
+}
+
+/// Picks a move for the player and plays it, as `search` does.
+///
+/// # Panics
+///
+/// Failed to play the chosen move, programming error.
+pub fn genmove(
+    game: &mut Game,
+    player: Player,
+    playouts: usize,
+    cancel: &CancelToken,
+) -> SearchResult {
+    let search = search(game, player, playouts, cancel);
+    game.play(&search.mov)
+        .expect("failed to play the chosen move");
+    search
+}
+
+/// Picks a move for the player by Monte Carlo tree search: up to `playouts` random games are
+/// played out, each going down the tree of moves searched so far by the best upper confidence
+/// bound and adding one move to it. The most searched move is chosen. The token is checked
+/// between playouts; once it is cancelled the best move found so far is chosen. A search stopped
+/// before its first playout chooses a random legal move. Players only pass when they have no
+/// move that doesn't fill one of their own eyes.
+///
+/// # Panics
+///
+/// Failed to play a move in the tree, programming error.
+#[must_use]
+pub fn search(game: &Game, player: Player, playouts: usize, cancel: &CancelToken) -> SearchResult {
+    let mut rng = rand::thread_rng();
+    // The root stands for the opponent's last move, so its replies are the player's candidates.
+    let mut root = SearchNode::new(
+        Move {
+            player: player.enemy(),
+            vertex: None,
+        },
+        game,
+        &mut rng,
+    );
+
+    let mut finished = 0;
+    let mut cancelled = false;
+    while finished < playouts {
+        if cancel.is_cancelled() {
+            cancelled = true;
+            break;
+        }
+        root.playout(&mut game.clone(), &mut rng);
+        finished += 1;
+    }
+
+    let best = SearchNode::most_visited(&root.children);
+    SearchResult {
+        mov: best.map_or_else(
+            || *root.untried.last().expect("there is always a move"),
+            |best| best.mov,
+        ),
+        playouts: finished,
+        win_rate: best.and_then(SearchNode::win_rate),
+        cancelled,
+        children: root.children,
+    }
+}
+
+/// Returns a move that breaks the mirror when the opponent is playing mirror Go against the
+/// player: the center of the board, which has no mirror point, or else the move nearest the
+/// center whose mirror the opponent couldn't play in reply. Returns None if the opponent isn't
+/// mirroring or no move breaks the mirror.
+#[must_use]
+pub fn anti_mirror_move(game: &Game, player: Player) -> Option<Move> {
+    if game.mirroring_player() != Some(player.enemy()) {
+        return None;
+    }
+    let board = game.board();
+    let mut candidates: Vec<_> = game
+        .all_legal_moves(player)
+        .into_iter()
+        .filter(|&vertex| !board.is_eye(vertex, player))
+        .collect();
+    let last = board.size() - 1;
+    candidates.sort_by_key(|vertex| (2 * vertex.x).abs_diff(last) + (2 * vertex.y).abs_diff(last));
+
+    let breaks_mirror = |&vertex: &Vertex| {
+        if board.center_point() == Some(vertex) {
+            return true;
+        }
+        let mut game = game.clone();
+        let mirrored = Move {
+            player: player.enemy(),
+            vertex: Some(board.mirror(vertex)),
+        };
+        game.play(&Move {
+            player,
+            vertex: Some(vertex),
+        })
+        .is_ok()
+            && !game.is_legal_move(&mirrored)
+    };
+    candidates
+        .into_iter()
+        .find(breaks_mirror)
+        .map(|vertex| Move {
+            player,
+            vertex: Some(vertex),
+        })
+}
+
+/// The player's legal moves that don't fill one of their own eyes in random order, or a pass if
+/// there are none.
+fn candidates(game: &Game, player: Player, rng: &mut ThreadRng) -> Vec<Move> {
+    let mut candidates: Vec<_> = game
+        .all_legal_moves(player)
+        .into_iter()
+        .filter(|&vertex| !game.board().is_eye(vertex, player))
+        .map(|vertex| Move {
+            player,
+            vertex: Some(vertex),
+        })
+        .collect();
+    if candidates.is_empty() {
+        candidates.push(Move {
+            player,
+            vertex: None,
+        });
+    }
+    candidates.shuffle(rng);
+    candidates
+}
+
+fn winner(game: &Game) -> Option<Player> {
+    game.result().and_then(|result| result.winner())
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn count(n: usize) -> f64 {
+    n as f64
+}
+
+/// Plays a random legal move for the player to move that doesn't fill one of their own eyes, or
+/// passes.
+pub(crate) fn play_random(game: &mut Game, rng: &mut ThreadRng) {
+    let player = game.player_turn();
+    let mut possible_moves = game.board().empty_verts();
+    while !possible_moves.is_empty() {
+        let index = rng.gen_range(0..possible_moves.len());
+        let vertex = possible_moves.swap_remove(index);
+        if !game.board().is_eye(vertex, player)
+            && game
+                .play(&Move {
+                    player,
+                    vertex: Some(vertex),
+                })
+                .is_ok()
+        {
+            return;
+        }
+    }
+    game.play(&Move {
+        player,
+        vertex: None,
+    })
+    .expect("failed to pass");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn genmove() {
+        let mut game = Game::with_board_size(5).unwrap();
+        let search = super::genmove(&mut game, Player::Black, 20, &CancelToken::new());
+        assert_eq!(search.playouts, 20);
+        assert!(!search.cancelled);
+        assert!(search.mov.vertex.is_some());
+        assert_eq!(
+            game.board().stones(Player::Black),
+            vec![search.mov.vertex.unwrap()]
+        );
+
+        // Cancelled before it starts, the search still plays a legal move.
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let search = super::genmove(&mut game, Player::White, 20, &cancel);
+        assert_eq!(search.playouts, 0);
+        assert!(search.cancelled);
+        assert_eq!(search.win_rate, None);
+        assert_eq!(game.board().stones(Player::White).len(), 1);
+
+        // Stopped from another thread, it returns the best move so far.
+        let cancel = CancelToken::new();
+        let stopper = cancel.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            stopper.cancel();
+        });
+        let search = super::genmove(&mut game, Player::Black, usize::MAX, &cancel);
+        assert!(search.cancelled);
+        assert!(search.playouts > 0);
+        assert_eq!(game.board().stones(Player::Black).len(), 2);
+
+        // Or once its deadline passes.
+        let cancel = CancelToken::new();
+        cancel.cancel_at(Instant::now() + Duration::from_millis(50));
+        assert!(!cancel.is_cancelled());
+        let search = super::genmove(&mut game, Player::White, usize::MAX, &cancel);
+        assert!(search.cancelled);
+        assert!(search.playouts > 0);
+    }
+
+    #[test]
+    fn anti_mirror() {
+        let mut game = Game::with_board_size(9).unwrap();
+        for (x, y) in [(2, 2), (3, 2), (2, 6), (6, 3)] {
+            assert_eq!(anti_mirror_move(&game, Player::Black), None);
+            for (player, vertex) in [
+                (Player::Black, Vertex { x, y }),
+                (Player::White, game.board().mirror(Vertex { x, y })),
+            ] {
+                game.play(&Move {
+                    player,
+                    vertex: Some(vertex),
+                })
+                .unwrap();
+            }
+        }
+        assert_eq!(game.mirroring_player(), Some(Player::White));
+        let center = game.board().center_point();
+        assert_eq!(
+            anti_mirror_move(&game, Player::Black).unwrap().vertex,
+            center
+        );
+    }
+
+    #[test]
+    fn search_tree() {
+        let game = Game::with_board_size(5).unwrap();
+        let search = search(&game, Player::Black, 200, &CancelToken::new());
+        let visits: usize = search.children.iter().map(SearchNode::visits).sum();
+        assert_eq!(visits, 200);
+        // Each move was tried before any was searched deeper.
+        assert_eq!(
+            search.children.len(),
+            game.all_legal_moves(Player::Black).len()
+        );
+
+        let chosen = search
+            .children
+            .iter()
+            .find(|child| child.mov() == search.mov)
+            .unwrap();
+        assert_eq!(chosen.win_rate(), search.win_rate);
+        assert!(search
+            .children
+            .iter()
+            .all(|child| child.visits() <= chosen.visits()));
+        assert!(chosen
+            .children()
+            .iter()
+            .all(|reply| reply.mov().player == Player::White));
+
+        let variation = search.principal_variation();
+        assert_eq!(variation[0], search.mov);
+        assert!(variation.len() > 1);
+    }
+}
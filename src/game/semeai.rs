@@ -0,0 +1,221 @@
+//! Reading out capturing races (攻め合い semeai) between two chains of opposite colors.
+
+use std::collections::HashMap;
+
+use crate::game::board::Board;
+use crate::game::player::Player;
+use crate::game::vertex::Vertex;
+
+/// The most moves, passes included, read ahead. Races that aren't settled by then are unknown.
+const MAX_DEPTH: usize = 12;
+/// The most positions read before giving up on a race as unknown.
+const MAX_NODES: usize = 5_000;
+
+/// How a capturing race ends.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    /// The player's chain survives and the other is captured.
+    Won(Player),
+    /// Neither chain can be captured.
+    Seki,
+    /// The race is too long or has too many liberties to read out.
+    Unknown,
+}
+
+/// The outcome of a capturing race.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Semeai {
+    /// How the race ends with best play.
+    pub outcome: Outcome,
+    /// The move the player to move should start with, or None if they should pass or tenuki.
+    pub key_move: Option<Vertex>,
+}
+
+/// Reads out the capturing race between the chains holding the stones on `a` and `b` with
+/// `to_move` playing first. Each player only fills the liberties of the other's chain, so races
+/// that are won by capturing other stones, making eyes or winning a ko aren't read correctly.
+/// Liberties outside the other chain are tried before shared ones and positions reached by
+/// different orders of moves are read once, but races that still take too long are unknown.
+///
+/// # Errors
+///
+/// The vertices don't hold stones of opposite colors.
+pub fn solve(board: &Board, a: Vertex, b: Vertex, to_move: Player) -> Result<Semeai, String> {
+    let (Some(a_player), Some(b_player)) = (board.stone(a), board.stone(b)) else {
+        return Err("expected a stone on both vertices".to_owned());
+    };
+    if a_player == b_player {
+        return Err("expected stones of opposite colors".to_owned());
+    }
+    let mut race = Race {
+        stones: [(a_player, a), (b_player, b)],
+        nodes: 0,
+        read: HashMap::new(),
+    };
+    let (value, key_move) = race.read(board, to_move, MAX_DEPTH, false);
+    Ok(Semeai {
+        outcome: match value {
+            Some(1) => Outcome::Won(to_move),
+            Some(-1) => Outcome::Won(to_move.enemy()),
+            Some(_) => Outcome::Seki,
+            None => Outcome::Unknown,
+        },
+        key_move,
+    })
+}
+
+struct Race {
+    /// A stone of each chain and its color.
+    stones: [(Player, Vertex); 2],
+    /// The positions read so far.
+    nodes: usize,
+    /// The values of the positions read out, by board hash, player to move and whether the
+    /// last move was a pass.
+    read: HashMap<(u64, Player, bool), i32>,
+}
+
+impl Race {
+    /// Returns the player whose chain has been captured, if either has.
+    fn loser(&self, board: &Board) -> Option<Player> {
+        self.stones
+            .iter()
+            .find(|&&(player, vertex)| board.stone(vertex) != Some(player))
+            .map(|&(player, _)| player)
+    }
+
+    /// Returns the liberties of the player's opponent's chain, those it doesn't share with the
+    /// player's chain first.
+    fn targets(&self, board: &Board, player: Player) -> Vec<Vertex> {
+        let liberties = |owner: Player| {
+            self.stones
+                .iter()
+                .find(|&&(stone, _)| stone == owner)
+                .map_or_else(Vec::new, |&(_, vertex)| board.liberty_vertices(vertex))
+        };
+        let own = liberties(player);
+        let mut targets = liberties(player.enemy());
+        targets.sort_by_key(|target| own.contains(target));
+        targets
+    }
+
+    /// Returns 1 if the player wins the race, -1 if they lose, 0 if neither can capture the other
+    /// and None if it couldn't be read out, and the move to play.
+    fn read(
+        &mut self,
+        board: &Board,
+        player: Player,
+        depth: usize,
+        passed: bool,
+    ) -> (Option<i32>, Option<Vertex>) {
+        if let Some(loser) = self.loser(board) {
+            return (Some(if loser == player { -1 } else { 1 }), None);
+        }
+        // Only the values are kept, which is all that is needed below the first move.
+        let key = (board.zobrist_hash(), player, passed);
+        if let Some(&value) = self.read.get(&key) {
+            return (Some(value), None);
+        }
+        if depth == 0 || self.nodes == MAX_NODES {
+            return (None, None);
+        }
+        self.nodes += 1;
+
+        // The best value found, and whether a move that might have been better couldn't be read.
+        let mut best = (-1, None);
+        let mut unknown = false;
+        for vertex in self.targets(board, player) {
+            let mut next = board.clone();
+            next.place_stone(player, vertex);
+            if next.is_vacant(vertex) {
+                // Suicide.
+                continue;
+            }
+            match self.read(&next, player.enemy(), depth - 1, false).0 {
+                Some(value) if -value > best.0 => {
+                    best = (-value, Some(vertex));
+                    if best.0 == 1 {
+                        break;
+                    }
+                }
+                Some(_) => {}
+                None => unknown = true,
+            }
+        }
+        // Passing twice in a row leaves the race as it is.
+        if best.0 < 1 {
+            let pass = if passed {
+                Some(0)
+            } else {
+                self.read(board, player.enemy(), depth - 1, true)
+                    .0
+                    .map(|value| -value)
+            };
+            match pass {
+                Some(value) if value > best.0 => best = (value, None),
+                Some(_) => {}
+                None => unknown = true,
+            }
+        }
+        if unknown && best.0 < 1 {
+            return (None, None);
+        }
+        self.read.insert(key, best.0);
+        (Some(best.0), best.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solve() {
+        let mut board = Board::from_ascii(
+            "5 . . . . .
+             4 . . x o .
+             3 . . o x .
+             2 . . o x .
+             1 . . o x .",
+        )
+        .unwrap();
+        let (white, black) = (Vertex { x: 2, y: 0 }, Vertex { x: 3, y: 0 });
+        assert_eq!(board.liberties(white), Some(3));
+
+        // With three liberties each, whoever moves first wins.
+        for player in [Player::Black, Player::White] {
+            let semeai = super::solve(&board, white, black, player).unwrap();
+            assert_eq!(semeai.outcome, Outcome::Won(player));
+            let key_move = semeai.key_move.unwrap();
+            let target = if player == Player::Black {
+                white
+            } else {
+                black
+            };
+            assert!(board.liberty_vertices(target).contains(&key_move));
+        }
+
+        // Black is a liberty short, so moving first doesn't help.
+        board.place_stone(Player::White, Vertex { x: 4, y: 2 });
+        let semeai = super::solve(&board, white, black, Player::Black).unwrap();
+        assert_eq!(semeai.outcome, Outcome::Won(Player::White));
+        let semeai = super::solve(&board, white, black, Player::White).unwrap();
+        assert_eq!(semeai.outcome, Outcome::Won(Player::White));
+
+        assert!(super::solve(&board, white, Vertex { x: 0, y: 0 }, Player::Black).is_err());
+        assert!(super::solve(&board, white, white, Player::Black).is_err());
+    }
+
+    #[test]
+    fn long_race() {
+        // Two rows of stones with a row of liberties each are far too long a race to read out.
+        let mut board = Board::with_size(19).unwrap();
+        for x in 0..19 {
+            board.place_stone(Player::Black, Vertex { x, y: 9 });
+            board.place_stone(Player::White, Vertex { x, y: 8 });
+        }
+        let (black, white) = (Vertex { x: 0, y: 9 }, Vertex { x: 0, y: 8 });
+        assert_eq!(board.liberties(black), Some(19));
+        let semeai = super::solve(&board, black, white, Player::Black).unwrap();
+        assert_eq!(semeai.outcome, Outcome::Unknown);
+    }
+}
@@ -0,0 +1,215 @@
+//! A game owned by its own thread and driven by messages.
+//!
+//! Where `SharedGame` lets many threads lock one game, `GameService` gives the game to a single
+//! thread and has everyone else send it messages, so no caller ever holds a lock. Messages are
+//! handled one at a time in the order they arrive, and every change is sent as an `Event` to each
+//! subscriber.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::game::board::{Board, Move};
+use crate::game::player::Player;
+use crate::game::Game;
+
+/// A request to the game service. Requests that have an answer carry the sender to reply on.
+#[derive(Debug)]
+pub enum Message {
+    /// Play a move.
+    Play(Move, Sender<Result<(), String>>),
+    /// Undo the last move.
+    Undo(Sender<Result<(), String>>),
+    /// Ask for the current board.
+    Query(Sender<Board>),
+    /// Generate and play a move for the player.
+    Genmove(Player, Sender<Move>),
+    /// Send every change from now on to the sender.
+    Subscribe(Sender<Event>),
+}
+
+/// A change to the game.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// A move was played, leaving the board.
+    Played(Move, Board),
+    /// The last move was undone, leaving the board.
+    Undone(Board),
+}
+
+/// A handle to a game running on its own thread. Once every handle and sender is dropped the
+/// thread ends.
+#[derive(Debug)]
+pub struct GameService {
+    sender: Sender<Message>,
+    thread: JoinHandle<Game>,
+}
+
+impl GameService {
+    /// Starts a thread that owns the game.
+    #[must_use]
+    pub fn spawn(game: Game) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        GameService {
+            sender,
+            thread: thread::spawn(move || serve(game, &receiver)),
+        }
+    }
+
+    /// Returns a sender for passing messages to the service from other threads.
+    #[must_use]
+    pub fn sender(&self) -> Sender<Message> {
+        self.sender.clone()
+    }
+
+    /// Attempts to play a move.
+    ///
+    /// # Errors
+    ///
+    /// The move is illegal or the service stopped.
+    pub fn play(&self, mov: &Move) -> Result<(), String> {
+        self.request(|reply| Message::Play(*mov, reply))?
+    }
+
+    /// Undoes the last move.
+    ///
+    /// # Errors
+    ///
+    /// There are no moves to undo or the service stopped.
+    pub fn undo(&self) -> Result<(), String> {
+        self.request(Message::Undo)?
+    }
+
+    /// Returns the current board.
+    ///
+    /// # Errors
+    ///
+    /// The service stopped.
+    pub fn board(&self) -> Result<Board, String> {
+        self.request(Message::Query)
+    }
+
+    /// Generates and plays a move for the player.
+    ///
+    /// # Errors
+    ///
+    /// The service stopped.
+    pub fn genmove(&self, player: Player) -> Result<Move, String> {
+        self.request(|reply| Message::Genmove(player, reply))
+    }
+
+    /// Returns a receiver of every change from now on.
+    ///
+    /// # Errors
+    ///
+    /// The service stopped.
+    pub fn subscribe(&self) -> Result<Receiver<Event>, String> {
+        let (sender, receiver) = mpsc::channel();
+        self.sender
+            .send(Message::Subscribe(sender))
+            .map_err(|_| STOPPED.to_owned())?;
+        Ok(receiver)
+    }
+
+    /// Waits for every sender returned by `sender` to be dropped and for all the messages to be
+    /// handled, then returns the game.
+    ///
+    /// # Errors
+    ///
+    /// The service's thread panicked.
+    pub fn stop(self) -> Result<Game, String> {
+        drop(self.sender);
+        self.thread
+            .join()
+            .map_err(|_| "the game service panicked".to_owned())
+    }
+
+    fn request<T, F: FnOnce(Sender<T>) -> Message>(&self, message: F) -> Result<T, String> {
+        let (reply, receiver) = mpsc::channel();
+        self.sender
+            .send(message(reply))
+            .map_err(|_| STOPPED.to_owned())?;
+        receiver.recv().map_err(|_| STOPPED.to_owned())
+    }
+}
+
+const STOPPED: &str = "the game service stopped";
+
+fn serve(mut game: Game, receiver: &Receiver<Message>) -> Game {
+    let mut subscribers: Vec<Sender<Event>> = Vec::new();
+    for message in receiver {
+        let event = match message {
+            Message::Play(mov, reply) => {
+                let result = game.play(&mov);
+                let played = result.is_ok();
+                // A caller that stopped waiting for the reply doesn't stop the move.
+                let _ = reply.send(result);
+                played.then(|| Event::Played(mov, game.board().clone()))
+            }
+            Message::Undo(reply) => {
+                let result = game.undo();
+                let undone = result.is_ok();
+                let _ = reply.send(result);
+                undone.then(|| Event::Undone(game.board().clone()))
+            }
+            Message::Query(reply) => {
+                let _ = reply.send(game.board().clone());
+                None
+            }
+            Message::Genmove(player, reply) => {
+                let mov = game.genmove_random(player);
+                let _ = reply.send(mov);
+                Some(Event::Played(mov, game.board().clone()))
+            }
+            Message::Subscribe(sender) => {
+                subscribers.push(sender);
+                None
+            }
+        };
+        if let Some(event) = event {
+            subscribers.retain(|sender| sender.send(event.clone()).is_ok());
+        }
+    }
+    game
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::vertex::Vertex;
+
+    #[test]
+    fn game_service() {
+        let service = GameService::spawn(Game::with_board_size(9).unwrap());
+        let events = service.subscribe().unwrap();
+
+        let mov = Move {
+            player: Player::Black,
+            vertex: Some(Vertex { x: 2, y: 2 }),
+        };
+        service.play(&mov).unwrap();
+        assert!(service.play(&mov).is_err());
+        let reply = service.genmove(Player::White).unwrap();
+        assert_eq!(reply.player, Player::White);
+        assert_eq!(service.board().unwrap().stones(Player::White).len(), 1);
+
+        let sender = service.sender();
+        thread::spawn(move || {
+            let (reply, receiver) = mpsc::channel();
+            sender.send(Message::Undo(reply)).unwrap();
+            receiver.recv().unwrap()
+        })
+        .join()
+        .unwrap()
+        .unwrap();
+
+        let game = service.stop().unwrap();
+        assert_eq!(game.board().stones(Player::White).len(), 0);
+
+        let events: Vec<_> = events.iter().collect();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(&events[0], Event::Played(played, _) if *played == mov));
+        assert!(
+            matches!(&events[2], Event::Undone(board) if board.stones(Player::Black).len() == 1)
+        );
+    }
+}
@@ -0,0 +1,616 @@
+//! A parser and writer for the [Smart Game Format](https://www.red-bean.com/sgf/) (SGF).
+
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::game::annotation::Annotation;
+use crate::game::board::Move;
+use crate::game::clock::Clock;
+use crate::game::encoding::GameRecord;
+use crate::game::numeric;
+use crate::game::player::Player;
+use crate::game::vertex::Vertex;
+use crate::game::{Game, RuleSet};
+
+/// The board size of a Go game when SZ is not given.
+const DEFAULT_BOARD_SIZE: usize = 19;
+
+/// A sequence of nodes followed by the variations that branch from the last of them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GameTree {
+    /// The main line of nodes.
+    pub nodes: Vec<Node>,
+    /// The variations following the last node.
+    pub variations: Vec<GameTree>,
+}
+
+/// A node holding a list of properties.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Node {
+    /// The properties in the order they appear.
+    pub properties: Vec<Property>,
+}
+
+/// A property identifier and its values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Property {
+    /// The identifier, e.g. `B` or `AB`.
+    pub id: String,
+    /// The unescaped values.
+    pub values: Vec<String>,
+}
+
+impl Property {
+    /// Returns a property with a single value.
+    #[must_use]
+    pub fn new<S: Into<String>>(id: &str, value: S) -> Self {
+        Property {
+            id: id.to_owned(),
+            values: vec![value.into()],
+        }
+    }
+}
+
+impl GameTree {
+    /// Returns the board size given by the SZ property of the root node.
+    ///
+    /// # Errors
+    ///
+    /// Fails if SZ is not a number.
+    pub fn board_size(&self) -> Result<usize, String> {
+        match self.nodes.first().and_then(|root| root.value("SZ")) {
+            Some(size) => size
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid board size {size:?}")),
+            None => Ok(DEFAULT_BOARD_SIZE),
+        }
+    }
+
+    /// Reads the main line of the tree as a game record: the board size, the komi from KM (or
+    /// 0), the rules from RU (or Chinese if it names rules we don't know), the setup stones from
+    /// AB and AW, the moves and the player to move after them, if PL gives one after the last
+    /// move. The moves aren't checked for legality.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the board size or komi isn't a number, a point isn't on the board, or PL isn't B
+    /// or W.
+    pub fn record(&self) -> Result<GameRecord, String> {
+        let board_size = self.board_size()?;
+        let root = self.nodes.first().ok_or("empty game tree")?;
+        let komi = match root.value("KM") {
+            Some(komi) => {
+                numeric::parse_points(komi.trim()).map_err(|_| format!("invalid komi {komi:?}"))?
+            }
+            None => 0.0,
+        };
+        let rule_set = match root.value("RU").map(str::to_lowercase).as_deref() {
+            Some("aga") => RuleSet::AGA,
+            Some("japanese" | "jp") => RuleSet::Japanese,
+            Some("tromp-taylor" | "tromp taylor") => RuleSet::TrompTaylor,
+            _ => RuleSet::Chinese,
+        };
+
+        let mut setup = Vec::new();
+        for (id, player) in [("AB", Player::Black), ("AW", Player::White)] {
+            for vertex in root.points(id, board_size)? {
+                setup.push((player, vertex));
+            }
+        }
+        let mut moves = Vec::new();
+        let mut to_move = None;
+        for node in &self.nodes {
+            if let Some(mov) = node.mov(board_size)? {
+                moves.push(mov);
+                to_move = None;
+            }
+            if let Some(player) = node.player_to_move()? {
+                to_move = Some(player);
+            }
+        }
+        Ok(GameRecord {
+            board_size,
+            rule_set,
+            komi,
+            setup,
+            moves,
+            to_move,
+        })
+    }
+}
+
+impl Node {
+    /// Returns the values of a property, or an empty slice if the node doesn't have it.
+    #[must_use]
+    pub fn values(&self, id: &str) -> &[String] {
+        self.properties
+            .iter()
+            .find(|property| property.id == id)
+            .map_or(&[], |property| &property.values)
+    }
+
+    /// Returns the first value of a property.
+    #[must_use]
+    pub fn value(&self, id: &str) -> Option<&str> {
+        self.values(id).first().map(String::as_str)
+    }
+
+    /// Returns the move stored in the node, if any.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the move is not a point on a board of the given size.
+    pub fn mov(&self, board_size: usize) -> Result<Option<Move>, String> {
+        for (id, player) in [("B", Player::Black), ("W", Player::White)] {
+            if let Some(point) = self.value(id) {
+                let vertex = Vertex::from_sgf(point, board_size)?;
+                return Ok(Some(Move { player, vertex }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the stones placed by a setup property such as AB or AW.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the points are not on a board of the given size.
+    pub fn points(&self, id: &str, board_size: usize) -> Result<Vec<Vertex>, String> {
+        let mut points = Vec::new();
+        for value in self.values(id) {
+            let (from, to) = value.split_once(':').unwrap_or((value, value));
+            let from = Vertex::from_sgf(from, board_size)?.ok_or("pass in a list of points")?;
+            let to = Vertex::from_sgf(to, board_size)?.ok_or("pass in a list of points")?;
+            // Compressed lists give a rectangle by two opposite corners.
+            for x in from.x.min(to.x)..=from.x.max(to.x) {
+                for y in from.y.min(to.y)..=from.y.max(to.y) {
+                    points.push(Vertex { x, y });
+                }
+            }
+        }
+        Ok(points)
+    }
+
+    /// Returns the player given by the PL property.
+    ///
+    /// # Errors
+    ///
+    /// Fails if PL is not B or W.
+    pub fn player_to_move(&self) -> Result<Option<Player>, String> {
+        match self.value("PL") {
+            None => Ok(None),
+            Some("B" | "b") => Ok(Some(Player::Black)),
+            Some("W" | "w") => Ok(Some(Player::White)),
+            Some(player) => Err(format!("invalid player {player:?}")),
+        }
+    }
+}
+
+/// The value of a property such as PL that names a player.
+fn player_value(player: Player) -> &'static str {
+    match player {
+        Player::Black => "B",
+        Player::White => "W",
+    }
+}
+
+/// Writes a property value, escaping `]` and `\`.
+fn write_property(sgf: &mut String, id: &str, values: &[String]) {
+    sgf.push_str(id);
+    for value in values {
+        sgf.push('[');
+        for c in value.chars() {
+            if c == ']' || c == '\\' {
+                sgf.push('\\');
+            }
+            sgf.push(c);
+        }
+        sgf.push(']');
+    }
+}
+
+/// Writes a game as an SGF game tree. The root node records the board size, komi, rules, time
+/// settings and setup stones, followed by the `properties` given, such as the players' names. The
+/// result is added as RE unless `properties` has one. A turn set with `Game::set_to_move` after the
+/// last move is written as PL, in the root or a node of its own after the moves. Each move records
+/// the time the player had left after it, if it was timed, and its annotations: comments as C,
+/// joined by blank lines if there are several, labels as LB, triangles as TR, squares as SQ and the
+/// last evaluation as V.
+#[must_use]
+pub fn write(game: &Game, properties: &[Property]) -> String {
+    let size = game.board().size();
+    let clock = game.time_settings();
+    let mut root = vec![
+        Property::new("FF", "4"),
+        Property::new("GM", "1"),
+        Property::new("SZ", size.to_string()),
+        Property::new("KM", numeric::format_points(game.komi)),
+        Property::new(
+            "RU",
+            match game.rule_set {
+                RuleSet::Chinese => "Chinese",
+                RuleSet::AGA => "AGA",
+                RuleSet::Japanese => "Japanese",
+                RuleSet::TrompTaylor => "Tromp-Taylor",
+            },
+        ),
+    ];
+    match clock {
+        Clock::Unlimited => {}
+        Clock::Absolute { main_time } => {
+            root.push(Property::new("TM", main_time.as_secs().to_string()));
+        }
+        Clock::ByoYomi {
+            main_time,
+            period_time,
+            periods,
+        } => {
+            root.push(Property::new("TM", main_time.as_secs().to_string()));
+            root.push(Property::new(
+                "OT",
+                format!("{periods}x{} byo-yomi", period_time.as_secs()),
+            ));
+        }
+        Clock::Canadian {
+            main_time,
+            period_time,
+            stones,
+        } => {
+            root.push(Property::new("TM", main_time.as_secs().to_string()));
+            root.push(Property::new(
+                "OT",
+                format!("{stones}/{} Canadian", period_time.as_secs()),
+            ));
+        }
+    }
+    let initial = game.initial_board();
+    for (id, player) in [("AB", Player::Black), ("AW", Player::White)] {
+        let stones = initial.stones(player);
+        if !stones.is_empty() {
+            root.push(Property {
+                id: id.to_owned(),
+                values: stones
+                    .into_iter()
+                    .map(|vertex| vertex.to_sgf(size))
+                    .collect(),
+            });
+        }
+    }
+    let to_move = game.turn_set();
+    if let (Some(player), true) = (to_move, game.move_history.is_empty()) {
+        root.push(Property::new("PL", player_value(player)));
+    }
+    root.extend_from_slice(properties);
+    if let (Some(result), false) = (
+        game.result(),
+        properties.iter().any(|property| property.id == "RE"),
+    ) {
+        root.push(Property::new("RE", result.to_string()));
+    }
+
+    let mut sgf = "(;".to_owned();
+    for property in &root {
+        write_property(&mut sgf, &property.id, &property.values);
+    }
+    for (number, record) in game.move_history.iter().enumerate() {
+        let (id, time_id, periods_id) = match record.mov.player {
+            Player::Black => ("B", "BL", "OB"),
+            Player::White => ("W", "WL", "OW"),
+        };
+        sgf.push_str("\n;");
+        let point = record
+            .mov
+            .vertex
+            .map_or_else(String::new, |vertex| vertex.to_sgf(size));
+        write_property(&mut sgf, id, &[point]);
+        if let Some(time_left) = game.recorded_time_left(number + 1) {
+            let (time, periods) = clock.gtp_time_left(&time_left);
+            let _ = write!(sgf, "{time_id}[{}]", numeric::format_seconds(time));
+            if periods > 0 {
+                let _ = write!(sgf, "{periods_id}[{periods}]");
+            }
+        }
+        write_annotations(&mut sgf, &record.annotations, size);
+    }
+    if let (Some(player), false) = (to_move, game.move_history.is_empty()) {
+        let _ = write!(sgf, "\n;PL[{}]", player_value(player));
+    }
+    sgf.push_str(")\n");
+    sgf
+}
+
+/// Writes the properties of a move's annotations.
+fn write_annotations(sgf: &mut String, annotations: &[Annotation], board_size: usize) {
+    let point = |vertex: &Vertex| vertex.to_sgf(board_size);
+    let mut comments = Vec::new();
+    let mut labels = Vec::new();
+    let mut triangles = Vec::new();
+    let mut squares = Vec::new();
+    let mut evaluation = None;
+    for annotation in annotations {
+        match annotation {
+            Annotation::Comment(comment) => comments.push(comment.as_str()),
+            Annotation::Label(vertex, label) => labels.push(format!("{}:{label}", point(vertex))),
+            Annotation::Triangle(vertex) => triangles.push(point(vertex)),
+            Annotation::Square(vertex) => squares.push(point(vertex)),
+            Annotation::Evaluation(value) => evaluation = Some(value.to_string()),
+        }
+    }
+    if !comments.is_empty() {
+        write_property(sgf, "C", &[comments.join("\n\n")]);
+    }
+    for (id, values) in [("LB", labels), ("TR", triangles), ("SQ", squares)] {
+        if !values.is_empty() {
+            write_property(sgf, id, &values);
+        }
+    }
+    if let Some(evaluation) = evaluation {
+        write_property(sgf, "V", &[evaluation]);
+    }
+}
+
+/// Parses a collection of game trees.
+///
+/// # Errors
+///
+/// Fails if the text is not valid SGF or contains no game trees.
+pub fn parse(text: &str) -> Result<Vec<GameTree>, String> {
+    let mut chars = text.chars().peekable();
+    let mut trees = Vec::new();
+
+    loop {
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some('(') => trees.push(parse_tree(&mut chars)?),
+            None => break,
+            // Text between game trees is ignored.
+            Some(_) => {}
+        }
+    }
+
+    if trees.is_empty() {
+        Err("no game tree found".to_owned())
+    } else {
+        Ok(trees)
+    }
+}
+
+/// Parses a collection of game trees from raw bytes, replacing invalid UTF-8 with U+FFFD.
+///
+/// # Errors
+///
+/// Fails if the text is not valid SGF or contains no game trees.
+pub fn parse_bytes(bytes: &[u8]) -> Result<Vec<GameTree>, String> {
+    parse(&String::from_utf8_lossy(bytes))
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+/// Parses a game tree after its opening parenthesis.
+fn parse_tree(chars: &mut Peekable<Chars>) -> Result<GameTree, String> {
+    // Nested variations are parsed with an explicit stack so deeply nested input can't overflow
+    // the call stack.
+    let mut stack = vec![GameTree::default()];
+
+    loop {
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(';') => {
+                let tree = stack.last_mut().ok_or("unbalanced parentheses")?;
+                if !tree.variations.is_empty() {
+                    return Err("node after a variation".to_owned());
+                }
+                tree.nodes.push(parse_node(chars)?);
+            }
+            Some('(') => stack.push(GameTree::default()),
+            Some(')') => {
+                let tree = stack.pop().ok_or("unbalanced parentheses")?;
+                if tree.nodes.is_empty() {
+                    return Err("empty game tree".to_owned());
+                }
+                match stack.last_mut() {
+                    Some(parent) => parent.variations.push(tree),
+                    None => return Ok(tree),
+                }
+            }
+            Some(c) => return Err(format!("unexpected character {c:?}")),
+            None => return Err("unexpected end of input".to_owned()),
+        }
+    }
+}
+
+/// Parses the properties of a node after its semicolon.
+fn parse_node(chars: &mut Peekable<Chars>) -> Result<Node, String> {
+    let mut node = Node::default();
+
+    loop {
+        skip_whitespace(chars);
+        let mut id = String::new();
+        while let Some(c) = chars.next_if(char::is_ascii_alphabetic) {
+            // Old versions of SGF allowed lower case letters in identifiers, which are ignored.
+            if c.is_ascii_uppercase() {
+                id.push(c);
+            }
+        }
+        if id.is_empty() {
+            return Ok(node);
+        }
+
+        let mut values = Vec::new();
+        skip_whitespace(chars);
+        while chars.next_if_eq(&'[').is_some() {
+            values.push(parse_value(chars)?);
+            skip_whitespace(chars);
+        }
+        if values.is_empty() {
+            return Err(format!("property {id} has no value"));
+        }
+        node.properties.push(Property { id, values });
+    }
+}
+
+/// Parses a property value after its opening bracket.
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            Some(']') => return Ok(value),
+            Some('\\') => match chars.next() {
+                // An escaped line break is removed.
+                Some('\n') => {
+                    chars.next_if_eq(&'\r');
+                }
+                Some('\r') => {
+                    chars.next_if_eq(&'\n');
+                }
+                Some(c) => value.push(c),
+                None => break,
+            },
+            Some(c) => value.push(c),
+            None => break,
+        }
+    }
+    Err("unterminated property value".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn parse_() {
+        let trees =
+            parse("(;FF[4]SZ[9]AB[aa][bb:cc];B[ee](;W[\\]x]C[a\\\ncomment])(;W[]))").unwrap();
+        assert_eq!(trees.len(), 1);
+
+        let tree = &trees[0];
+        assert_eq!(tree.board_size(), Ok(9));
+        assert_eq!(tree.nodes.len(), 2);
+        assert_eq!(tree.nodes[0].points("AB", 9).unwrap().len(), 5);
+        assert_eq!(
+            tree.nodes[1].mov(9),
+            Ok(Some(Move {
+                player: Player::Black,
+                vertex: Some(Vertex { x: 4, y: 4 }),
+            }))
+        );
+        assert_eq!(tree.variations.len(), 2);
+        assert_eq!(tree.variations[0].nodes[0].value("W"), Some("]x"));
+        assert_eq!(tree.variations[0].nodes[0].value("C"), Some("acomment"));
+        assert_eq!(
+            tree.variations[1].nodes[0].mov(9),
+            Ok(Some(Move {
+                player: Player::White,
+                vertex: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("").is_err());
+        assert!(parse("(;B[aa]").is_err());
+        assert!(parse("(;B[aa)").is_err());
+        assert!(parse("(;B)").is_err());
+        assert!(parse("()").is_err());
+        assert!(parse("(;SZ[9];B[zz])").unwrap()[0].nodes[1].mov(9).is_err());
+        assert!(parse(&"(;".repeat(1 << 16)).is_err());
+        assert!(parse_bytes(b"(;C[\xff])").is_ok());
+    }
+
+    #[test]
+    fn write_() {
+        let mut game = Game::with_board_size(9).unwrap();
+        game.set_time_settings(Clock::ByoYomi {
+            main_time: Duration::from_secs(60),
+            period_time: Duration::from_secs(10),
+            periods: 3,
+        });
+        let moves = [Some(Vertex { x: 2, y: 6 }), None];
+        for (vertex, player) in moves.into_iter().zip([Player::Black, Player::White]) {
+            game.play_timed(&Move { player, vertex }, Duration::from_secs(61))
+                .unwrap();
+        }
+
+        let sgf = write(&game, &[Property::new("PB", "Black]\\")]);
+        assert!(sgf.contains("TM[60]OT[3x10 byo-yomi]"), "{sgf}");
+        assert!(sgf.contains(";B[cc]BL[10.000]OB[3]"), "{sgf}");
+        let tree = &parse(&sgf).unwrap()[0];
+        assert_eq!(tree.nodes[0].value("PB"), Some("Black]\\"));
+        let played: Vec<_> = tree.nodes[1..]
+            .iter()
+            .map(|node| node.mov(9).unwrap().unwrap().vertex)
+            .collect();
+        assert_eq!(played, moves);
+    }
+
+    #[test]
+    fn player_to_move() {
+        let mut game = Game::with_board_size(9).unwrap();
+        game.set_to_move(Player::White);
+        let round_trip = |game: &Game| {
+            let sgf = write(game, &[]);
+            parse(&sgf).unwrap()[0].record().unwrap()
+        };
+        assert_eq!(round_trip(&game).to_move, Some(Player::White));
+        assert_eq!(
+            round_trip(&game).to_game().unwrap().player_turn(),
+            Player::White
+        );
+
+        game.play(&Move {
+            player: Player::White,
+            vertex: Some(Vertex { x: 2, y: 6 }),
+        })
+        .unwrap();
+        game.set_to_move(Player::White);
+        let record = round_trip(&game);
+        assert_eq!(record.to_move, Some(Player::White));
+        assert_eq!(record.to_game().unwrap().player_turn(), Player::White);
+        assert_eq!(record, GameRecord::from(&game));
+    }
+
+    #[test]
+    fn annotations() {
+        let mut game = Game::with_board_size(9).unwrap();
+        assert!(game
+            .annotate(1, Annotation::Comment("too early".to_owned()))
+            .is_err());
+        game.play(&Move {
+            player: Player::Black,
+            vertex: Some(Vertex { x: 2, y: 6 }),
+        })
+        .unwrap();
+        let annotations = [
+            Annotation::Comment("A good start.".to_owned()),
+            Annotation::Label(Vertex { x: 0, y: 8 }, "a".to_owned()),
+            Annotation::Triangle(Vertex { x: 2, y: 6 }),
+            Annotation::Square(Vertex { x: 6, y: 2 }),
+            Annotation::Square(Vertex { x: 6, y: 6 }),
+            Annotation::Evaluation(-1.5),
+            Annotation::Comment("Maybe [tengen]?".to_owned()),
+        ];
+        for annotation in annotations.clone() {
+            game.annotate(1, annotation).unwrap();
+        }
+        assert_eq!(game.annotations(1), annotations);
+        assert!(game.annotations(2).is_empty());
+        assert!(game
+            .annotate(1, Annotation::Triangle(Vertex { x: 9, y: 0 }))
+            .is_err());
+
+        let sgf = write(&game, &[]);
+        let node = &parse(&sgf).unwrap()[0].nodes[1];
+        assert_eq!(node.value("C"), Some("A good start.\n\nMaybe [tengen]?"));
+        assert_eq!(node.values("LB"), ["aa:a"]);
+        assert_eq!(node.values("TR"), ["cc"]);
+        assert_eq!(node.values("SQ"), ["gg", "gc"]);
+        assert_eq!(node.value("V"), Some("-1.5"));
+    }
+}
@@ -0,0 +1,163 @@
+//! A game shared between threads.
+//!
+//! `SharedGame` is a cheaply cloned handle to one `Game` behind a read-write lock, so for example
+//! a UI thread, a network thread and a search thread can each hold a handle. Any number of
+//! readers may look at the game at once and a writer has it to itself, so every change, such as a
+//! move with its captures, is seen whole or not at all. Each change bumps a version number that
+//! observers can wait on instead of polling.
+//!
+//! The closures passed to `read` and `write` run with the lock held, so keep them short: a search
+//! should take a `snapshot` and work on its own copy rather than read the game for its whole
+//! duration, and only `write` the move it settles on. A panic inside `write` doesn't make the
+//! handle unusable; later calls see the game as the panicking closure left it.
+
+use std::sync::{Arc, Condvar, Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::game::board::{Board, Move};
+use crate::game::Game;
+
+/// A handle to a game shared between threads. Clones refer to the same game.
+#[derive(Clone, Debug, Default)]
+pub struct SharedGame {
+    game: Arc<RwLock<Game>>,
+    version: Arc<(Mutex<u64>, Condvar)>,
+}
+
+impl SharedGame {
+    /// Shares the game.
+    #[must_use]
+    pub fn new(game: Game) -> Self {
+        SharedGame {
+            game: Arc::new(RwLock::new(game)),
+            version: Arc::default(),
+        }
+    }
+
+    /// Calls `f` with the game, alongside any other readers.
+    pub fn read<R, F: FnOnce(&Game) -> R>(&self, f: F) -> R {
+        f(&self.game.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Calls `f` with the game while no one else can see it, then wakes the threads waiting for
+    /// a change.
+    pub fn write<R, F: FnOnce(&mut Game) -> R>(&self, f: F) -> R {
+        let result = f(&mut self.game.write().unwrap_or_else(PoisonError::into_inner));
+        let (version, changed) = &*self.version;
+        *version.lock().unwrap_or_else(PoisonError::into_inner) += 1;
+        changed.notify_all();
+        result
+    }
+
+    /// Returns a copy of the game as it is now.
+    #[must_use]
+    pub fn snapshot(&self) -> Game {
+        self.read(Clone::clone)
+    }
+
+    /// Returns a copy of the board as it is now.
+    #[must_use]
+    pub fn board(&self) -> Board {
+        self.read(|game| game.board().clone())
+    }
+
+    /// Attempts to play a move.
+    ///
+    /// # Errors
+    ///
+    /// The move is illegal.
+    pub fn play(&self, mov: &Move) -> Result<(), String> {
+        self.write(|game| game.play(mov))
+    }
+
+    /// Undoes the last move.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there are no moves to undo.
+    pub fn undo(&self) -> Result<(), String> {
+        self.write(Game::undo)
+    }
+
+    /// Returns the number of times the game has been written to.
+    #[must_use]
+    pub fn version(&self) -> u64 {
+        *self
+            .version
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Waits until the version is past `seen` or the timeout passes, and returns the version.
+    #[must_use]
+    pub fn wait_for_change(&self, seen: u64, timeout: Duration) -> u64 {
+        let deadline = Instant::now() + timeout;
+        let (version, changed) = &*self.version;
+        let mut current = version.lock().unwrap_or_else(PoisonError::into_inner);
+        while *current <= seen {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            current = changed
+                .wait_timeout(current, left)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+        *current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::player::Player;
+    use crate::game::vertex::Vertex;
+    use std::thread;
+
+    #[test]
+    fn shared_game() {
+        let shared = SharedGame::new(Game::with_board_size(9).unwrap());
+        let observer = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                let mut seen = 0;
+                let stones = |game: &Game| game.board().stones(Player::Black).len();
+                while shared.read(stones) < 2 {
+                    seen = shared.wait_for_change(seen, Duration::from_secs(10));
+                }
+                shared.read(stones)
+            })
+        };
+
+        let players: Vec<_> = (0..4)
+            .map(|x| {
+                let shared = shared.clone();
+                thread::spawn(move || loop {
+                    // Each thread plays its own column when it's the player's turn.
+                    let player = shared.read(Game::player_turn);
+                    let mov = Move {
+                        player,
+                        vertex: Some(Vertex { x, y: 0 }),
+                    };
+                    if shared.write(|game| game.player_turn() == player && game.play(&mov).is_ok())
+                    {
+                        return;
+                    }
+                    thread::yield_now();
+                })
+            })
+            .collect();
+        for player in players {
+            player.join().unwrap();
+        }
+
+        assert_eq!(observer.join().unwrap(), 2);
+        assert_eq!(shared.board().stones(Player::White).len(), 2);
+        let version = shared.version();
+        shared.undo().unwrap();
+        assert_eq!(shared.snapshot().board().stones(Player::White).len(), 1);
+        assert_eq!(shared.wait_for_change(version, Duration::ZERO), version + 1);
+    }
+}
@@ -1,12 +1,91 @@
 use std::fmt;
 use std::str::FromStr;
 
-const GOBAN_LETTERS: &str = "ABCDEFGHJKLMNOPQRST";
+/// The letters of the columns of a board in GTP coordinates, from the left. `I` is skipped, so
+/// there are enough for boards up to 25x25.
+pub const GOBAN_LETTERS: &str = "ABCDEFGHJKLMNOPQRSTUVWXYZ";
+
+/// Returns the letter of the column x in GTP coordinates, or None if there isn't one.
+#[must_use]
+pub fn column_letter(x: usize) -> Option<char> {
+    GOBAN_LETTERS.chars().nth(x)
+}
+
+/// Returns the column x of the letter in GTP coordinates, or None if it isn't one.
+#[must_use]
+pub fn letter_column(letter: char) -> Option<usize> {
+    GOBAN_LETTERS.find(letter)
+}
+
+/// Returns the index of the vertex in a list of the points of a board row by row from the
+/// bottom left, `y * board_size + x`, as in `Matrix` and `encoding`.
+#[must_use]
+pub fn index(vertex: Vertex, board_size: usize) -> usize {
+    vertex.y * board_size + vertex.x
+}
+
+/// Returns the vertex at an index of a list of the points of a board, the inverse of `index`.
+#[must_use]
+pub fn from_index(index: usize, board_size: usize) -> Vertex {
+    Vertex {
+        x: index % board_size,
+        y: index / board_size,
+    }
+}
+
+/// One of the eight symmetries of a square board: the rotations and reflections that map the
+/// board onto itself. Rotations are clockwise as the board is usually drawn, with row 1 at the
+/// bottom.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Symmetry {
+    /// Leaves every vertex where it is.
+    Identity,
+    /// Rotates the board a quarter turn.
+    Rotate90,
+    /// Rotates the board a half turn.
+    Rotate180,
+    /// Rotates the board three quarters of a turn.
+    Rotate270,
+    /// Reflects the board left to right.
+    FlipHorizontal,
+    /// Reflects the board top to bottom.
+    FlipVertical,
+    /// Reflects the board in the diagonal from the bottom left to the top right corner.
+    Transpose,
+    /// Reflects the board in the diagonal from the top left to the bottom right corner.
+    AntiTranspose,
+}
+
+impl Symmetry {
+    /// All eight symmetries, starting with the identity.
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipHorizontal,
+        Symmetry::FlipVertical,
+        Symmetry::Transpose,
+        Symmetry::AntiTranspose,
+    ];
+
+    /// Returns the symmetry that undoes this one.
+    #[must_use]
+    pub fn inverse(self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            symmetry => symmetry,
+        }
+    }
+}
 
 /// A structure for storing the x and y coordinates of a board cell.
 ///
 /// (0, 0) is the bottom left corner of the board.
 #[derive(Clone, Copy, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vertex {
     /// The x coordinate.
     pub x: usize,
@@ -14,6 +93,87 @@ pub struct Vertex {
     pub y: usize,
 }
 
+impl Vertex {
+    /// Returns the vertex the symmetry moves this one to on a board of the given size.
+    ///
+    /// # Panics
+    ///
+    /// If the vertex isn't on the board.
+    #[must_use]
+    pub fn transform(self, symmetry: Symmetry, board_size: usize) -> Vertex {
+        assert!(
+            self.x < board_size && self.y < board_size,
+            "{self} is not on a board of size {board_size}"
+        );
+        let last = board_size - 1;
+        let Vertex { x, y } = self;
+        let (x, y) = match symmetry {
+            Symmetry::Identity => (x, y),
+            Symmetry::Rotate90 => (y, last - x),
+            Symmetry::Rotate180 => (last - x, last - y),
+            Symmetry::Rotate270 => (last - y, x),
+            Symmetry::FlipHorizontal => (last - x, y),
+            Symmetry::FlipVertical => (x, last - y),
+            Symmetry::Transpose => (y, x),
+            Symmetry::AntiTranspose => (last - y, last - x),
+        };
+        Vertex { x, y }
+    }
+
+    /// Reads an SGF point such as `dd` on a board of the given size. SGF letters the columns from
+    /// the left and the rows from the top, `a` to `z` and then `A` to `Z`. An empty point, or `tt`
+    /// on boards up to 19x19, is a pass and returns None.
+    ///
+    /// # Errors
+    ///
+    /// The point isn't two letters or isn't on the board.
+    pub fn from_sgf(point: &str, board_size: usize) -> Result<Option<Vertex>, String> {
+        if point.is_empty() || (point == "tt" && board_size <= 19) {
+            return Ok(None);
+        }
+
+        let coordinate = |c: u8| match c {
+            b'a'..=b'z' => Some(usize::from(c - b'a')),
+            b'A'..=b'Z' => Some(usize::from(c - b'A') + 26),
+            _ => None,
+        };
+        match point.as_bytes() {
+            &[x, y] => match (coordinate(x), coordinate(y)) {
+                (Some(x), Some(y)) if x < board_size && y < board_size => Ok(Some(Vertex {
+                    x,
+                    y: board_size - 1 - y,
+                })),
+                _ => Err(format!("point {point:?} is not on the board")),
+            },
+            _ => Err(format!("invalid point {point:?}")),
+        }
+    }
+
+    /// Writes the vertex as an SGF point such as `dd` on a board of the given size. Passes,
+    /// which have no vertex, are written as an empty point.
+    ///
+    /// # Panics
+    ///
+    /// If the vertex isn't on the board.
+    #[must_use]
+    pub fn to_sgf(self, board_size: usize) -> String {
+        assert!(
+            self.x < board_size && self.y < board_size,
+            "{self} is not on a board of size {board_size}"
+        );
+        let coordinate = |c: usize| {
+            // Boards are at most 52x52 in SGF, so the casts can't truncate.
+            #[allow(clippy::cast_possible_truncation)]
+            let c = c as u8;
+            char::from(if c < 26 { b'a' + c } else { b'A' + c - 26 })
+        };
+        [self.x, board_size - 1 - self.y]
+            .into_iter()
+            .map(coordinate)
+            .collect()
+    }
+}
+
 impl fmt::Debug for Vertex {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{self}")
@@ -22,10 +182,7 @@ impl fmt::Debug for Vertex {
 
 impl fmt::Display for Vertex {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let letter = GOBAN_LETTERS
-            .chars()
-            .nth(self.x)
-            .expect("expected char to be in GOBAN_LETTERS");
+        let letter = column_letter(self.x).expect("expected char to be in GOBAN_LETTERS");
         let number = (self.y + 1).to_owned();
         write!(f, "{letter}{number}")
     }
@@ -44,7 +201,7 @@ impl FromStr for Vertex {
             .next()
             .expect("expected vertex to contain a letter");
 
-        let Some(x) = GOBAN_LETTERS.find(letter) else {
+        let Some(x) = letter_column(letter) else {
             return Err(format!("invalid coordinate letter {letter:?}"));
         };
 
@@ -77,3 +234,50 @@ impl fmt::Display for Vertices {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let vertex: Vertex = "J3".parse().unwrap();
+        assert_eq!(vertex, Vertex { x: 8, y: 2 });
+        assert_eq!(column_letter(8), Some('J'));
+        assert_eq!(letter_column('I'), None);
+        assert_eq!(column_letter(25), None);
+
+        assert_eq!(index(vertex, 19), 46);
+        assert_eq!(from_index(46, 19), vertex);
+    }
+
+    #[test]
+    fn symmetries() {
+        let vertex: Vertex = "C4".parse().unwrap();
+        let images: Vec<String> = Symmetry::ALL
+            .iter()
+            .map(|&symmetry| vertex.transform(symmetry, 9).to_string())
+            .collect();
+        assert_eq!(images, ["C4", "D7", "G6", "F3", "G4", "C6", "D3", "F7"]);
+        for symmetry in Symmetry::ALL {
+            let image = vertex.transform(symmetry, 9);
+            assert_eq!(image.transform(symmetry.inverse(), 9), vertex);
+        }
+    }
+
+    #[test]
+    fn sgf() {
+        let vertex = Vertex { x: 8, y: 2 };
+        assert_eq!(vertex.to_sgf(19), "iq");
+        assert_eq!(Vertex::from_sgf("iq", 19), Ok(Some(vertex)));
+        assert_eq!(Vertex::from_sgf("", 19), Ok(None));
+        assert_eq!(Vertex::from_sgf("tt", 19), Ok(None));
+        assert!(Vertex::from_sgf("jj", 9).is_err());
+        assert!(Vertex::from_sgf("d", 9).is_err());
+        assert_eq!(Vertex::from_sgf("tt", 21), Ok(Some(Vertex { x: 19, y: 1 })));
+        let corner = Vertex { x: 0, y: 51 };
+        assert_eq!(corner.to_sgf(52), "aa");
+        assert_eq!(Vertex { x: 51, y: 0 }.to_sgf(52), "ZZ");
+        assert_eq!(Vertex::from_sgf("aa", 52), Ok(Some(corner)));
+    }
+}
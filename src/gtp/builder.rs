@@ -0,0 +1,220 @@
+use std::fmt;
+use std::io::Write;
+
+use crate::gtp::engine::Engine;
+use crate::gtp::options::{EngineOptions, ServerProfile};
+
+/// A group of commands registered together.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Group {
+    Extra,
+    Tournament,
+    Kgs,
+    Dlc,
+}
+
+/// Configures an engine in one call: the groups of commands it registers, the name and version it
+/// reports, the options of its searching `genmove` and where it logs the commands it runs. The
+/// presets are starting points that can be changed further:
+///
+/// ```
+/// use libgo::gtp::builder::EngineBuilder;
+///
+/// let engine = EngineBuilder::kgs().name("mybot").version("1.0").build();
+/// assert_eq!(engine.options().playouts, 1000);
+/// ```
+#[must_use]
+pub struct EngineBuilder {
+    name: Option<String>,
+    version: Option<String>,
+    groups: Vec<Group>,
+    options: Option<EngineOptions>,
+    log: Option<Box<dyn Write>>,
+}
+
+impl fmt::Debug for EngineBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EngineBuilder")
+            .field("name", &self.name)
+            .field("version", &self.version)
+            .field("groups", &self.groups)
+            .field("options", &self.options)
+            .field("log", &self.log.is_some())
+            .finish()
+    }
+}
+
+impl EngineBuilder {
+    /// Only the commands GTP requires, with `genmove` playing at random.
+    pub fn minimal() -> Self {
+        EngineBuilder {
+            name: None,
+            version: None,
+            groups: Vec::new(),
+            options: None,
+            log: None,
+        }
+    }
+
+    /// The commands for tournament play, with a searching `genmove`.
+    pub fn tournament() -> Self {
+        EngineBuilder::minimal()
+            .extra_commands()
+            .tournament_commands()
+            .options(EngineOptions::default())
+    }
+
+    /// The tournament commands and the KGS commands, with a searching `genmove` that follows the
+    /// KGS etiquette.
+    pub fn kgs() -> Self {
+        EngineBuilder::tournament()
+            .kgs_commands()
+            .options(EngineOptions {
+                profile: ServerProfile::Kgs,
+                ..EngineOptions::default()
+            })
+    }
+
+    /// Every command, including the `dlc-` commands for inspecting searches, with a searching
+    /// `genmove` that never resigns.
+    pub fn analysis() -> Self {
+        EngineBuilder::tournament()
+            .dlc_commands()
+            .options(EngineOptions {
+                resign_below: None,
+                ..EngineOptions::default()
+            })
+    }
+
+    /// Sets the name the engine replies to `name` with.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    /// Sets the version the engine replies to `version` with.
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = Some(version.to_owned());
+        self
+    }
+
+    /// Registers the commands of `Engine::register_extra_commands`.
+    pub fn extra_commands(self) -> Self {
+        self.add(Group::Extra)
+    }
+
+    /// Registers the commands of `Engine::register_tournament_commands`.
+    pub fn tournament_commands(self) -> Self {
+        self.add(Group::Tournament)
+    }
+
+    /// Registers the commands of `Engine::register_kgs_commands`.
+    pub fn kgs_commands(self) -> Self {
+        self.add(Group::Kgs)
+    }
+
+    /// Registers the commands of `Engine::register_dlc_commands`.
+    pub fn dlc_commands(self) -> Self {
+        self.add(Group::Dlc)
+    }
+
+    fn add(mut self, group: Group) -> Self {
+        if !self.groups.contains(&group) {
+            self.groups.push(group);
+        }
+        self
+    }
+
+    /// Makes `genmove` search with the options, as `Engine::set_options` does.
+    pub fn options(mut self, options: EngineOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Logs each command run and its response to the writer, as `Engine::set_log` does.
+    pub fn log<W: Write + 'static>(mut self, log: W) -> Self {
+        self.log = Some(Box::new(log));
+        self
+    }
+
+    /// Builds the engine.
+    #[must_use]
+    pub fn build(self) -> Engine {
+        let mut engine = Engine::new();
+        for group in self.groups {
+            match group {
+                Group::Extra => engine.register_extra_commands(),
+                Group::Tournament => engine.register_tournament_commands(),
+                Group::Kgs => engine.register_kgs_commands(),
+                Group::Dlc => engine.register_dlc_commands(),
+            }
+        }
+        // After the command groups, so the search replaces `kgs-genmove_cleanup` too.
+        if let Some(options) = self.options {
+            engine.set_options(options);
+        }
+        if let Some(name) = self.name {
+            engine.insert("name", move |_args, _game| Ok(Some(name.clone())));
+        }
+        if let Some(version) = self.version {
+            engine.insert("version", move |_args, _game| Ok(Some(version.clone())));
+        }
+        if let Some(log) = self.log {
+            engine.set_log(log);
+        }
+        engine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::game::Game;
+    use crate::gtp::command::Command;
+
+    #[derive(Clone, Default)]
+    struct Log(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Log {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn presets() {
+        let known = |engine: &Engine, name: &str| {
+            let command = Command::from_line(&format!("known_command {name}")).unwrap();
+            engine.exec(&mut Game::new(), &command).result == Ok(Some("true".to_owned()))
+        };
+        let minimal = EngineBuilder::minimal().build();
+        assert!(known(&minimal, "genmove"));
+        assert!(!known(&minimal, "undo"));
+
+        let log = Log::default();
+        let kgs = EngineBuilder::kgs().name("mybot").log(log.clone()).build();
+        assert!(known(&kgs, "kgs-genmove_cleanup"));
+        assert!(known(&kgs, "final_score"));
+        assert!(!known(&kgs, "dlc-pv"));
+        assert_eq!(kgs.options().profile, ServerProfile::Kgs);
+        let name = Command::from_line("1 name").unwrap();
+        assert_eq!(
+            kgs.exec(&mut Game::new(), &name).result,
+            Ok(Some("mybot".to_owned()))
+        );
+        let log = String::from_utf8(log.0.borrow().clone()).unwrap();
+        assert!(log.ends_with("1 name\n=1 mybot\r\n\r\n"));
+
+        let analysis = EngineBuilder::analysis().build();
+        assert!(known(&analysis, "dlc-pv"));
+        assert_eq!(analysis.options().resign_below, None);
+    }
+}
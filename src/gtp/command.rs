@@ -1,9 +1,16 @@
-use std::io::{self, BufRead, Lines};
+use std::fmt;
+use std::io::{self, BufRead};
 
 /// An Iterator that returns GTP commands.
+///
+/// Lines may end with LF, CR or CRLF, and each command is returned as soon as its line ends.
+/// Invalid UTF-8 is replaced with U+FFFD rather than ending the iteration with an error.
 #[derive(Debug)]
 pub struct CommandsIter<B> {
-    lines: Lines<B>,
+    reader: B,
+    /// Whether the last line ended with CR, so a following LF belongs to the same line break.
+    skip_lf: bool,
+    max_line_length: usize,
 }
 
 /// A trait extending `BufRead` to allow reading GTP commands from any type implementing `BufRead`.
@@ -14,7 +21,9 @@ pub trait Commands: BufRead {
         Self: Sized,
     {
         CommandsIter {
-            lines: self.lines(),
+            reader: self,
+            skip_lf: false,
+            max_line_length: usize::MAX,
         }
     }
 }
@@ -23,17 +32,84 @@ impl<C: Commands> Iterator for CommandsIter<C> {
     type Item = io::Result<Command>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        for result_line in self.lines.by_ref() {
-            match result_line {
-                Ok(line) => {
-                    if let Some(command) = Command::from_line(&line) {
+        loop {
+            match self.read_line() {
+                Ok(Some(line)) => {
+                    if let Some(command) = Command::from_bytes(&line) {
                         return Some(Ok(command));
                     }
                 }
+                Ok(None) => return None,
                 Err(err) => return Some(Err(err)),
             }
         }
-        None
+    }
+}
+
+impl<B> CommandsIter<B> {
+    /// Limits the length of a line in bytes. The rest of a longer line is skipped without being
+    /// stored and an `InvalidData` error is returned in place of its command.
+    #[must_use]
+    pub fn max_line_length(mut self, max: usize) -> Self {
+        self.max_line_length = max;
+        self
+    }
+}
+
+impl<B: BufRead> CommandsIter<B> {
+    /// Reads the bytes up to the next line break, or returns None at the end of the input.
+    fn read_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut line = Vec::new();
+        let mut too_long = false;
+        let mut push = |line: &mut Vec<u8>, bytes: &[u8]| {
+            if line.len() + bytes.len() > self.max_line_length {
+                too_long = true;
+            }
+            if !too_long {
+                line.extend_from_slice(bytes);
+            }
+        };
+        let line_too_long = |max| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line longer than {max} bytes"),
+            )
+        };
+
+        loop {
+            let buf = match self.reader.fill_buf() {
+                Ok(buf) => buf,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            if buf.is_empty() {
+                if too_long {
+                    return Err(line_too_long(self.max_line_length));
+                }
+                // The last line may not have a line break.
+                return Ok(if line.is_empty() { None } else { Some(line) });
+            }
+            if self.skip_lf {
+                self.skip_lf = false;
+                if buf[0] == b'\n' {
+                    self.reader.consume(1);
+                    continue;
+                }
+            }
+
+            if let Some(end) = buf.iter().position(|&byte| byte == b'\n' || byte == b'\r') {
+                push(&mut line, &buf[..end]);
+                self.skip_lf = buf[end] == b'\r';
+                self.reader.consume(end + 1);
+                if too_long {
+                    return Err(line_too_long(self.max_line_length));
+                }
+                return Ok(Some(line));
+            }
+            let read = buf.len();
+            push(&mut line, buf);
+            self.reader.consume(read);
+        }
     }
 }
 
@@ -120,6 +196,27 @@ impl Command {
             Command { id, name, args }
         })
     }
+
+    /// Converts a line of raw input into a Command, replacing invalid UTF-8 with U+FFFD. Returns
+    /// None if there was no command.
+    #[must_use]
+    pub fn from_bytes(line: &[u8]) -> Option<Self> {
+        Self::from_line(&String::from_utf8_lossy(line))
+    }
+}
+
+impl fmt::Display for Command {
+    /// Formats the command as a line to send to an engine, without the newline.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(id) = self.id {
+            write!(f, "{id} ")?;
+        }
+        write!(f, "{}", self.name)?;
+        for arg in &self.args {
+            write!(f, " {arg}")?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -196,6 +293,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn from_bytes() {
+        assert_eq!(
+            Command::from_bytes(b"1 play b \xff\xfe"),
+            Some(Command {
+                id: Some(1),
+                name: "play".to_string(),
+                args: vec!["b".to_string(), "\u{fffd}\u{fffd}".to_string()],
+            })
+        );
+        let huge = "x ".repeat(1 << 20);
+        assert_eq!(
+            Command::from_bytes(huge.as_bytes()).unwrap().args.len(),
+            (1 << 20) - 1
+        );
+    }
+
     #[test]
     fn commands_() {
         let mut commands = b"one\n2 two\n".commands();
@@ -215,5 +329,43 @@ mod tests {
                 args: Vec::new(),
             }
         );
+
+        let names: Vec<_> = b"one\rtwo\r\n\r\nthree\n\xff four"
+            .commands()
+            .map(|command| command.unwrap().name)
+            .collect();
+        assert_eq!(names, ["one", "two", "three", "\u{fffd}"]);
+    }
+
+    #[test]
+    fn max_line_length() {
+        let mut commands = b"one\ntwo two two\nthree\nfour four four"
+            .commands()
+            .max_line_length(5);
+        assert_eq!(commands.next().unwrap().unwrap().name, "one");
+        let err = commands.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(commands.next().unwrap().unwrap().name, "three");
+        assert!(commands.next().unwrap().is_err());
+        assert!(commands.next().is_none());
+    }
+
+    /// A reader that fails if it is read past its data, like a connection with nothing more sent.
+    struct Waiting(&'static [u8]);
+
+    impl io::Read for Waiting {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            assert!(!self.0.is_empty(), "read past the data");
+            let read = self.0.len().min(buf.len());
+            buf[..read].copy_from_slice(&self.0[..read]);
+            self.0 = &self.0[read..];
+            Ok(read)
+        }
+    }
+
+    #[test]
+    fn commands_returned_at_line_break() {
+        let mut commands = io::BufReader::new(Waiting(b"one\r")).commands();
+        assert_eq!(commands.next().unwrap().unwrap().name, "one");
     }
 }
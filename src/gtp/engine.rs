@@ -1,12 +1,29 @@
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::rc::Rc;
 use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::game::board::Move;
+use crate::game::benchmark;
+use crate::game::board::{Board, Move, Safety, Status};
+use crate::game::estimate;
+use crate::game::numeric;
 use crate::game::player::Player;
+use crate::game::search::{self, CancelToken, SearchNode, SearchResult};
+use crate::game::sgf;
 use crate::game::vertex::{Vertex, Vertices};
-use crate::game::{Game, Handicap};
+use crate::game::{Clock, Game, GameResult, Handicap};
+use crate::gtp::builder::EngineBuilder;
 use crate::gtp::command::Command;
+use crate::gtp::opponents::OpponentStore;
+use crate::gtp::options::{EngineOptions, PassPolicy, ServerProfile};
 use crate::gtp::response::{CommandResult, Response};
 
 /// The library version.
@@ -18,6 +35,9 @@ const GTP_PROTOCOL_VERSION: &str = "2";
 /// The official name of the agent.
 const PROGRAM_NAME: &str = env!("CARGO_PKG_NAME");
 
+/// The number of games `dlc-benchmark` plays out unless told otherwise.
+const BENCHMARK_GAMES: usize = 100;
+
 fn gtp_boardsize(args: &[String], game: &mut Game) -> CommandResult {
     if args.is_empty() {
         return Err("boardsize not given".to_owned());
@@ -41,11 +61,197 @@ fn gtp_genmove(args: &[String], game: &mut Game) -> CommandResult {
     }
     let player = parse_color(&args[0])?;
     let move_ = game.genmove_random(player);
-    let move_str = match move_.vertex {
+    Ok(Some(move_to_string(&move_)))
+}
+
+/// Generates a move with `genmove`, but where it passes plays the player's `Game::cleanup_move`
+/// instead, if they have one, so the game is counted exactly by area.
+fn gtp_genmove_cleanup<F>(args: &[String], game: &mut Game, genmove: F) -> CommandResult
+where
+    F: Fn(&[String], &mut Game) -> CommandResult,
+{
+    let reply = genmove(args, game)?;
+    match game.last_move() {
+        Some(&Move {
+            player,
+            vertex: None,
+        }) if reply.as_deref() == Some("pass") => match game.cleanup_move(player) {
+            Some(vertex) => {
+                game.undo()?;
+                game.play(&Move {
+                    player,
+                    vertex: Some(vertex),
+                })?;
+                Ok(Some(vertex.to_string()))
+            }
+            None => Ok(reply),
+        },
+        _ => Ok(reply),
+    }
+}
+
+/// A search run on its own thread while the opponent thinks, from the position after the reply
+/// the last search expects. Dropping it stops the search.
+struct Ponder {
+    /// The game after the expected reply.
+    game: Game,
+    /// The player the search is for.
+    player: Player,
+    cancel: CancelToken,
+    search: Option<thread::JoinHandle<SearchResult>>,
+}
+
+impl Ponder {
+    /// Starts searching for the player from the game.
+    fn start(game: Game, player: Player, playouts: usize) -> Self {
+        let cancel = CancelToken::new();
+        let search = {
+            let (game, cancel) = (game.clone(), cancel.clone());
+            thread::spawn(move || search::search(&game, player, playouts, &cancel))
+        };
+        Ponder {
+            game,
+            player,
+            cancel,
+            search: Some(search),
+        }
+    }
+
+    /// Whether the search is for the player from the game as it is now, i.e. the opponent made
+    /// the expected reply and the komi and rules haven't changed since.
+    fn is_for(&self, game: &Game, player: Player) -> bool {
+        self.player == player
+            && self.game.board() == game.board()
+            && self.game.moves().eq(game.moves())
+            && self.game.komi.to_bits() == game.komi.to_bits()
+            && self.game.rule_set == game.rule_set
+            && self.game.ko_rule() == game.ko_rule()
+    }
+
+    /// Waits for the search to finish and returns what it found.
+    fn finish(mut self) -> Option<SearchResult> {
+        self.search.take().and_then(|search| search.join().ok())
+    }
+}
+
+impl Drop for Ponder {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+fn gtp_genmove_search(
+    args: &[String],
+    game: &mut Game,
+    options: &EngineOptions,
+    principal_variation: &RefCell<Vec<Move>>,
+    ponder: &RefCell<Option<Ponder>>,
+) -> CommandResult {
+    if args.is_empty() {
+        return Err("too few arguments, expected: genmove <color>".to_owned());
+    }
+    let player = parse_color(&args[0])?;
+    // A search on another position is stopped as it's dropped.
+    let pondered = ponder
+        .borrow_mut()
+        .take()
+        .filter(|ponder| ponder.is_for(game, player));
+    let pass = Move {
+        player,
+        vertex: None,
+    };
+    if options.pass == PassPolicy::WhenWinning && options.profile.allows_pass(game) {
+        let mut passed = game.clone();
+        passed.play(&pass)?;
+        if passed.is_over() && passed.result().and_then(|result| result.winner()) == Some(player) {
+            game.play(&pass)?;
+            *principal_variation.borrow_mut() = vec![pass];
+            return Ok(Some(move_to_string(&pass)));
+        }
+    }
+
+    if let Some(mov) = search::anti_mirror_move(game, player).filter(|_| options.anti_mirror) {
+        game.play(&mov)?;
+        *principal_variation.borrow_mut() = vec![mov];
+        return Ok(Some(move_to_string(&mov)));
+    }
+
+    // The search stops early when the player's time for the move is up. A search started on the
+    // opponent's time carries on until then.
+    let cancel = pondered
+        .as_ref()
+        .map_or_else(CancelToken::new, |ponder| ponder.cancel.clone());
+    if let Some(move_time) = game.move_time(player) {
+        cancel.cancel_at(Instant::now() + move_time);
+    }
+    let search = match pondered.and_then(Ponder::finish) {
+        Some(search) => search,
+        None => search::search(game, player, options.playouts, &cancel),
+    };
+    *principal_variation.borrow_mut() = search.principal_variation();
+    let losing = match (options.resign_below, search.win_rate) {
+        (Some(resign_below), Some(rate)) => rate < resign_below,
+        _ => false,
+    };
+    if losing && game.moves().count() >= options.resign_after_moves {
+        game.resign(player);
+        return Ok(Some("resign".to_owned()));
+    }
+    let mov = allowed_move(game, player, &search, options.profile);
+    game.play(&mov)?;
+    if options.ponder {
+        if let [chosen, reply, ..] = principal_variation.borrow().as_slice() {
+            let mut expected = game.clone();
+            if *chosen == mov && expected.play(reply).is_ok() && !expected.is_over() {
+                *ponder.borrow_mut() = Some(Ponder::start(expected, player, options.playouts));
+            }
+        }
+    }
+    Ok(Some(move_to_string(&mov)))
+}
+
+/// Returns the searched move, or if the server profile doesn't allow it the most searched move it
+/// does allow, falling back on a pass.
+fn allowed_move(
+    game: &Game,
+    player: Player,
+    search: &SearchResult,
+    profile: ServerProfile,
+) -> Move {
+    let owners = estimate::owners(&game.territory_estimate());
+    let allowed = |mov: &Move| match mov.vertex {
+        Some(vertex) => profile.allows_move(game, &owners, player, vertex),
+        None => profile.allows_pass(game),
+    };
+    if allowed(&search.mov) {
+        return search.mov;
+    }
+    let mut children: Vec<_> = search.children.iter().collect();
+    children.sort_by_key(|child| Reverse(child.visits()));
+    children
+        .into_iter()
+        .map(SearchNode::mov)
+        .find(allowed)
+        .unwrap_or(Move {
+            player,
+            vertex: None,
+        })
+}
+
+/// Returns the result of a game decided other than by the score, e.g. by resignation, or else
+/// the score with the stones `final_status_list dead` gives taken off the board.
+fn final_score(game: &Game) -> GameResult {
+    match game.result() {
+        Some(result) if !matches!(result, GameResult::Score(..) | GameResult::Draw) => result,
+        _ => game.score_result_removing(&game.stones_with_status(Status::Dead)),
+    }
+}
+
+fn move_to_string(mov: &Move) -> String {
+    match mov.vertex {
         Some(vertex) => vertex.to_string(),
         None => "pass".to_owned(),
-    };
-    Ok(Some(move_str))
+    }
 }
 
 fn gtp_place_handicap(args: &[String], game: &mut Game, handicap: Handicap) -> CommandResult {
@@ -90,6 +296,261 @@ fn gtp_play(args: &[String], game: &mut Game) -> CommandResult {
     game.play(&mov).map(|_ok| None)
 }
 
+fn gtp_time_left(args: &[String], game: &mut Game) -> CommandResult {
+    if args.len() < 3 {
+        return Err("too few arguments, expected: <color> <time> <stones>".to_owned());
+    }
+
+    let player = parse_color(&args[0])?;
+    let Ok(time) = args[1].parse::<u32>() else {
+        return Err("time is not a u32".to_owned());
+    };
+    let Ok(stones) = args[2].parse::<u32>() else {
+        return Err("stones is not a u32".to_owned());
+    };
+    game.set_time_left(player, Duration::from_secs(u64::from(time)), stones);
+    Ok(None)
+}
+
+/// Sets the clock from the main time, byo-yomi time and byo-yomi stones, as `Clock::from_gtp`
+/// reads them, and gives both players the full time.
+fn gtp_time_settings(args: &[String], game: &mut Game) -> CommandResult {
+    let [main_time, byo_yomi_time, byo_yomi_stones] =
+        parse_numbers(args, ["main_time", "byo_yomi_time", "byo_yomi_stones"])?;
+    game.set_time_settings(Clock::from_gtp(
+        seconds(main_time),
+        seconds(byo_yomi_time),
+        byo_yomi_stones,
+    ));
+    Ok(None)
+}
+
+/// Replaces the game with the main line of the first game in an SGF file, replayed up to but not
+/// including the move number if one is given. The clock keeps its settings, which SGF files
+/// record only for information.
+fn gtp_loadsgf(args: &[String], game: &mut Game) -> CommandResult {
+    let (path, move_number) = match args {
+        [path] => (path, None),
+        [path, move_number] => match move_number.parse::<usize>() {
+            Ok(move_number) if move_number > 0 => (path, Some(move_number)),
+            _ => return Err("move_number is not a positive integer".to_owned()),
+        },
+        _ => return Err("expected: loadsgf <filename> [move_number]".to_owned()),
+    };
+    let load = || -> Result<Game, String> {
+        let bytes = fs::read(path).map_err(|err| err.to_string())?;
+        let tree = sgf::parse_bytes(&bytes)?
+            .into_iter()
+            .next()
+            .ok_or("no game in the file")?;
+        let mut record = tree.record()?;
+        if let Some(move_number) = move_number {
+            // The position is the one before the move, with its player to move.
+            if let Some(mov) = record.moves.get(move_number - 1) {
+                record.to_move = Some(mov.player);
+            }
+            record.moves.truncate(move_number - 1);
+        }
+        record.to_game()
+    };
+    // GTP gives one message for every way loading can fail.
+    let mut loaded = load().map_err(|_| "cannot load file".to_owned())?;
+    loaded.set_time_settings(game.time_settings());
+    *game = loaded;
+    Ok(None)
+}
+
+/// Sets the clock from the KGS extension of `time_settings`, whose first argument is the kind of
+/// clock: `none`, `absolute <main_time>`, `byoyomi <main_time> <period_time> <periods>` or
+/// `canadian <main_time> <period_time> <stones>`.
+fn gtp_kgs_time_settings(args: &[String], game: &mut Game) -> CommandResult {
+    let Some((kind, args)) = args.split_first() else {
+        return Err("too few arguments, expected: <none|absolute|byoyomi|canadian> ...".to_owned());
+    };
+    let clock = match kind.to_lowercase().as_str() {
+        "none" => Clock::Unlimited,
+        "absolute" => {
+            let [main_time] = parse_numbers(args, ["main_time"])?;
+            Clock::Absolute {
+                main_time: seconds(main_time),
+            }
+        }
+        "byoyomi" => {
+            let [main_time, period_time, periods] =
+                parse_numbers(args, ["main_time", "period_time", "periods"])?;
+            Clock::ByoYomi {
+                main_time: seconds(main_time),
+                period_time: seconds(period_time),
+                periods,
+            }
+        }
+        "canadian" => {
+            let [main_time, period_time, stones] =
+                parse_numbers(args, ["main_time", "period_time", "stones"])?;
+            if stones == 0 {
+                return Err("stones must be greater than zero".to_owned());
+            }
+            Clock::Canadian {
+                main_time: seconds(main_time),
+                period_time: seconds(period_time),
+                stones,
+            }
+        }
+        _ => return Err(format!("unknown time system {kind:?}")),
+    };
+    game.set_time_settings(clock);
+    Ok(None)
+}
+
+/// Parses the leading arguments as the named whole numbers.
+fn parse_numbers<const N: usize>(args: &[String], names: [&str; N]) -> Result<[u32; N], String> {
+    if args.len() < N {
+        let expected: Vec<_> = names.iter().map(|name| format!("<{name}>")).collect();
+        return Err(format!(
+            "too few arguments, expected: {}",
+            expected.join(" ")
+        ));
+    }
+
+    let mut numbers = [0; N];
+    for ((number, arg), name) in numbers.iter_mut().zip(args).zip(names) {
+        *number = arg
+            .parse::<u32>()
+            .map_err(|_| format!("{name} is not a u32"))?;
+    }
+    Ok(numbers)
+}
+
+fn seconds(seconds: u32) -> Duration {
+    Duration::from_secs(u64::from(seconds))
+}
+
+/// Reports the safety of the chain on the vertex or, without a vertex, a line for each safety
+/// listing the stones with it, leaving out those no stone has.
+fn gtp_group_status(args: &[String], game: &mut Game) -> CommandResult {
+    let safeties = game.board().safeties();
+    if let Some(vertex) = args.first() {
+        let vertex = Vertex::from_str(&vertex.to_uppercase())?;
+        return safeties
+            .get(&vertex)
+            .map(|safety| Some(safety.to_string()))
+            .ok_or_else(|| format!("no stone on {vertex}"));
+    }
+
+    let lines: Vec<_> = [
+        Safety::Alive,
+        Safety::ProbablyAlive,
+        Safety::Unsettled,
+        Safety::ProbablyDead,
+        Safety::Dead,
+    ]
+    .into_iter()
+    .filter_map(|safety| {
+        let mut stones: Vec<Vertex> = safeties
+            .iter()
+            .filter(|&(_, &stone_safety)| stone_safety == safety)
+            .map(|(&vertex, _)| vertex)
+            .collect();
+        stones.sort_by_key(|vertex| (vertex.x, vertex.y));
+        (!stones.is_empty()).then(|| format!("{safety} {}", Vertices(stones)))
+    })
+    .collect();
+    Ok(Some(lines.join("\n")))
+}
+
+/// Describes the build: the library version, the compiler, the enabled cargo features and the git
+/// commit, when it was built from a git checkout.
+fn build_info() -> String {
+    let features = env!("LIBGO_FEATURES");
+    format!(
+        "version {AGENT_VERSION}\nrustc {}\nfeatures {}\ngit {}",
+        option_env!("LIBGO_RUSTC_VERSION").unwrap_or("unknown"),
+        if features.is_empty() {
+            "none"
+        } else {
+            features
+        },
+        option_env!("LIBGO_GIT_HASH").unwrap_or("unknown"),
+    )
+}
+
+/// Runs quick checks of the rules, position hashing and scoring, returning the name of each check
+/// with whether it passed.
+fn self_test() -> Vec<(&'static str, Result<(), String>)> {
+    vec![
+        ("rules", self_test_rules()),
+        ("hashing", self_test_hashing()),
+        ("scoring", self_test_scoring()),
+    ]
+}
+
+fn self_test_game(moves: &[(Player, usize, usize)]) -> Result<Game, String> {
+    let mut game = Game::with_board_size(5)?;
+    for &(player, x, y) in moves {
+        game.play(&Move {
+            player,
+            vertex: Some(Vertex { x, y }),
+        })?;
+    }
+    Ok(game)
+}
+
+fn self_test_rules() -> Result<(), String> {
+    let game = self_test_game(&[
+        (Player::Black, 1, 0),
+        (Player::White, 0, 0),
+        (Player::Black, 0, 1),
+    ])?;
+    if !game.board().stones(Player::White).is_empty() || game.prisoners(Player::Black) != 1 {
+        return Err("a stone without liberties wasn't captured".to_owned());
+    }
+    let suicide = Move {
+        player: Player::White,
+        vertex: Some(Vertex { x: 0, y: 0 }),
+    };
+    if game.clone().play(&suicide).is_ok() {
+        return Err("suicide was allowed".to_owned());
+    }
+    Ok(())
+}
+
+fn self_test_hashing() -> Result<(), String> {
+    let hash = |board: &Board| {
+        let mut hasher = DefaultHasher::new();
+        board.hash(&mut hasher);
+        hasher.finish()
+    };
+    let mut game = self_test_game(&[
+        (Player::Black, 1, 0),
+        (Player::White, 0, 0),
+        (Player::Black, 0, 1),
+        (Player::White, 2, 2),
+    ])?;
+    let fork = game.fork_at(2);
+    game.undo()?;
+    game.undo()?;
+    if game.board() != fork.board() || hash(game.board()) != hash(fork.board()) {
+        return Err("undoing moves didn't restore the position".to_owned());
+    }
+    Ok(())
+}
+
+fn self_test_scoring() -> Result<(), String> {
+    let mut game = self_test_game(&[])?;
+    game.komi = 0.5;
+    if game.score_result() != GameResult::Score(Player::White, 0.5) {
+        return Err(format!("the empty board scored {}", game.score_result()));
+    }
+    let game = self_test_game(&[(Player::Black, 2, 2)])?;
+    if game.board().score_area() != 25 {
+        return Err(format!(
+            "one stone scored {} points",
+            game.board().score_area()
+        ));
+    }
+    Ok(())
+}
+
 fn parse_color(color: &str) -> Result<Player, String> {
     match color.to_lowercase().as_ref() {
         "b" | "black" => Ok(Player::Black),
@@ -98,12 +559,64 @@ fn parse_color(color: &str) -> Result<Player, String> {
     }
 }
 
+/// Something that executes GTP commands, such as an engine playing a game in this process or a
+/// connection to another program.
+pub trait GtpEngine {
+    /// Runs the command and returns the response.
+    fn exec(&mut self, command: &Command) -> Response;
+}
+
+/// An engine together with the game it is playing.
+#[derive(Debug, Default)]
+pub struct Session {
+    /// The commands the engine understands.
+    pub engine: Engine,
+    /// The game the commands are run on.
+    pub game: Game,
+}
+
+impl GtpEngine for Session {
+    fn exec(&mut self, command: &Command) -> Response {
+        self.engine.exec(&mut self.game, command)
+    }
+}
+
 type Arguments = Vec<String>;
 type CommandInputOutput = Box<dyn Fn(&Arguments, &mut Game) -> CommandResult>;
+type GameEventHook = Box<dyn Fn(GameEvent, &Game)>;
+type BeforeCommand = Box<dyn Fn(&Command, &Game) -> Result<(), String>>;
+type AfterCommand = Box<dyn Fn(&Command, &CommandResult, &Game)>;
+
+/// A change in the life of the games an engine plays, which its hooks are told about.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GameEvent {
+    /// KGS said the game is over with `kgs-game_over`. The hooks get the finished game.
+    GameOver,
+    /// The board was cleared with `clear_board`. The hooks get the game as it was before.
+    ClearBoard,
+    /// The board size was changed with `boardsize`. The hooks get the game as it was before.
+    BoardSize,
+    /// A game was loaded with `loadsgf`. The hooks get the game as it was before.
+    LoadSgf,
+}
 
 /// A structure holding a map of commands to their fns.
 pub struct Engine {
     inner: HashMap<String, CommandInputOutput>,
+    /// The moves expected by the last search, starting with the move it chose.
+    principal_variation: Rc<RefCell<Vec<Move>>>,
+    /// How the searching `genmove` plays.
+    options: Rc<RefCell<EngineOptions>>,
+    /// Where the commands run and their responses are logged, if anywhere.
+    log: Option<RefCell<Box<dyn Write>>>,
+    /// What to do when a game is over or replaced by a new one.
+    hooks: Vec<GameEventHook>,
+    /// The checks every command must pass before it's run.
+    before: Vec<BeforeCommand>,
+    /// What to do with every command run and its result.
+    after: Vec<AfterCommand>,
+    /// The search running on the opponent's time, if the options say to ponder.
+    ponder: Rc<RefCell<Option<Ponder>>>,
 }
 
 impl Default for Engine {
@@ -125,9 +638,70 @@ impl Engine {
 
     /// Runs the given command with the given game and returns the result.
     pub fn exec(&self, game: &mut Game, command: &Command) -> Response {
+        let result = self
+            .before
+            .iter()
+            .try_for_each(|before| before(command, game))
+            .and_then(|()| self.run(game, command));
+        for after in &self.after {
+            after(command, &result, game);
+        }
+        let response = Response {
+            id: command.id,
+            result,
+        };
+        if let Some(log) = &self.log {
+            // A log that can't be written to shouldn't stop the engine.
+            let _ = write!(log.borrow_mut(), "{command}\n{response}");
+        }
+        response
+    }
+
+    fn run(&self, game: &mut Game, command: &Command) -> CommandResult {
+        // Pondering on a game that is over or about to change would only waste the CPU.
+        if matches!(
+            command.name.as_str(),
+            "quit"
+                | "clear_board"
+                | "boardsize"
+                | "loadsgf"
+                | "undo"
+                | "kgs-game_over"
+                | "dlc-set_option"
+        ) {
+            self.ponder.borrow_mut().take();
+        }
+        let event = match command.name.as_ref() {
+            "kgs-game_over" => Some(GameEvent::GameOver),
+            "clear_board" => Some(GameEvent::ClearBoard),
+            "boardsize" => Some(GameEvent::BoardSize),
+            "loadsgf" => Some(GameEvent::LoadSgf),
+            _ => None,
+        }
+        .filter(|_| !self.hooks.is_empty());
+        // The game is kept for the hooks in case the command replaces it.
+        let before = event
+            .filter(|&event| event != GameEvent::GameOver)
+            .map(|_| game.clone());
         let result = match command.name.as_ref() {
             "list_commands" => Ok(Some(self.to_string())),
             "known_command" => Ok(Some(self.contains(command).to_string())),
+            // The move is generated by whichever `genmove` is registered, on a copy of the game
+            // so it isn't played. The search's principal variation and ponder are set aside, as
+            // the move is never played, and put back afterwards.
+            "reg_genmove" if self.inner.contains_key("reg_genmove") => {
+                let principal_variation = self.principal_variation.take();
+                let ponder = self.ponder.take();
+                let result = self
+                    .inner
+                    .get("genmove")
+                    .map_or(Err("unknown command".to_owned()), |genmove| {
+                        genmove(&command.args, &mut game.clone())
+                    });
+                self.principal_variation.replace(principal_variation);
+                self.ponder.replace(ponder);
+                result
+            }
             _ => self
                 .inner
                 .get(&command.name)
@@ -135,10 +709,50 @@ impl Engine {
                     f(&command.args, game)
                 }),
         };
-        Response {
-            id: command.id,
-            result,
+        if let (Some(event), Ok(_)) = (event, &result) {
+            for hook in &self.hooks {
+                hook(event, before.as_ref().unwrap_or(game));
+            }
         }
+        result
+    }
+
+    /// Returns a builder for an engine with only the commands GTP requires.
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::minimal()
+    }
+
+    /// Logs each command run and its response to the writer, in the form they're sent over GTP.
+    pub fn set_log<W: Write + 'static>(&mut self, log: W) {
+        self.log = Some(RefCell::new(Box::new(log)));
+    }
+
+    /// Calls the hook whenever a game is over or about to be replaced, so the engine can e.g.
+    /// record the result or clear its caches. Hooks are only called when the command succeeds.
+    pub fn on_game_event<F>(&mut self, hook: F)
+    where
+        F: 'static + Fn(GameEvent, &Game),
+    {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Checks every command before it's run, e.g. to refuse moves once a player has run out of
+    /// time. A command that fails a check isn't run and fails with the check's error. Checks are
+    /// made in the order they were added.
+    pub fn before_command<F>(&mut self, check: F)
+    where
+        F: 'static + Fn(&Command, &Game) -> Result<(), String>,
+    {
+        self.before.push(Box::new(check));
+    }
+
+    /// Calls the function with every command run, or refused by a check, its result and the game
+    /// after it, e.g. to record every move played.
+    pub fn after_command<F>(&mut self, f: F)
+    where
+        F: 'static + Fn(&Command, &CommandResult, &Game),
+    {
+        self.after.push(Box::new(f));
     }
 
     /// Adds a command to the command map.
@@ -154,6 +768,13 @@ impl Engine {
     pub fn new() -> Self {
         let mut commands = Engine {
             inner: HashMap::new(),
+            principal_variation: Rc::default(),
+            options: Rc::default(),
+            log: None,
+            hooks: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+            ponder: Rc::default(),
         };
 
         commands.insert("boardsize", |args, game| gtp_boardsize(args, game));
@@ -169,13 +790,13 @@ impl Engine {
             if args.is_empty() {
                 return Err("expected komi value".to_owned());
             }
-            args[0]
-                .parse::<f64>()
-                .ok()
-                .map_or(Err("komi is not a float".to_owned()), |komi| {
+            numeric::parse_points(&args[0]).ok().map_or(
+                Err("komi is not a float".to_owned()),
+                |komi| {
                     game.komi = komi;
                     Ok(None)
-                })
+                },
+            )
         });
         commands.insert("list_commands", |_args, _game| {
             unreachable!();
@@ -193,6 +814,52 @@ impl Engine {
         commands
     }
 
+    /// Makes `genmove` and `kgs-genmove_cleanup` choose moves by searching instead of playing at
+    /// random, resigning and passing as the options say. Also registers `dlc-option <name>` and
+    /// `dlc-set_option <name> <value>` for reading and changing the options over GTP.
+    pub fn set_options(&mut self, options: EngineOptions) {
+        *self.options.borrow_mut() = options;
+        // A search already pondering went by the old options.
+        self.ponder.borrow_mut().take();
+
+        let (options, principal_variation, ponder) = (
+            Rc::clone(&self.options),
+            Rc::clone(&self.principal_variation),
+            Rc::clone(&self.ponder),
+        );
+        let genmove = move |args: &Vec<String>, game: &mut Game| {
+            gtp_genmove_search(args, game, &options.borrow(), &principal_variation, &ponder)
+        };
+        if self.inner.contains_key("kgs-genmove_cleanup") {
+            let genmove = genmove.clone();
+            self.insert("kgs-genmove_cleanup", move |args, game| {
+                gtp_genmove_cleanup(args, game, |args, game| genmove(&args.to_vec(), game))
+            });
+        }
+        self.insert("genmove", genmove);
+
+        let options = Rc::clone(&self.options);
+        self.insert("dlc-option", move |args, _game| match args.first() {
+            Some(name) => options
+                .borrow()
+                .get(name)
+                .map(Some)
+                .ok_or_else(|| format!("unknown option: {name}")),
+            None => Err("expected: dlc-option <name>".to_owned()),
+        });
+        let options = Rc::clone(&self.options);
+        self.insert("dlc-set_option", move |args, _game| match args.as_slice() {
+            [name, value] => options.borrow_mut().set(name, value).map(|()| None),
+            _ => Err("expected: dlc-set_option <name> <value>".to_owned()),
+        });
+    }
+
+    /// Returns the options used by the searching `genmove`.
+    #[must_use]
+    pub fn options(&self) -> EngineOptions {
+        *self.options.borrow()
+    }
+
     /// Registers all known standard GTP commands.
     pub fn register_all_commands(&mut self) {
         self.register_extra_commands();
@@ -204,9 +871,47 @@ impl Engine {
         self.insert("dlc-debug_game", |_args, game| {
             Ok(Some(format!("{game:#?}")))
         });
+        self.insert("dlc-selftest", |_args, _game| {
+            let results = self_test();
+            let report: Vec<_> = results
+                .iter()
+                .map(|(name, result)| match result {
+                    Ok(()) => format!("{name}: ok"),
+                    Err(err) => format!("{name}: failed: {err}"),
+                })
+                .collect();
+            if results.iter().all(|(_, result)| result.is_ok()) {
+                Ok(Some(report.join("\n")))
+            } else {
+                Err(report.join("\n"))
+            }
+        });
+        self.insert("dlc-build_info", |_args, _game| Ok(Some(build_info())));
+        self.insert("dlc-benchmark", |args, game| {
+            let games = match args.first() {
+                Some(games) => games
+                    .parse()
+                    .map_err(|_| "games is not a usize".to_owned())?,
+                None => BENCHMARK_GAMES,
+            };
+            let result = benchmark::playouts(game.board().size(), games)?;
+            Ok(Some(result.to_json()))
+        });
+        self.insert("dlc-group_status", |args, game| {
+            gtp_group_status(args, game)
+        });
         self.insert("dlc-game_value", |_args, game| {
             Ok(Some(game.value().to_string()))
         });
+        let principal_variation = Rc::clone(&self.principal_variation);
+        self.insert("dlc-pv", move |_args, _game| {
+            let moves: Vec<_> = principal_variation
+                .borrow()
+                .iter()
+                .map(move_to_string)
+                .collect();
+            Ok(Some(moves.join(" ")))
+        });
     }
 
     /// Register additional GTP commands that are not required.
@@ -222,29 +927,79 @@ impl Engine {
         });
 
         // Tournament Commands
-        // final_score
-        // final_status_list
-        // time_left
-        // time_settings
+        self.insert("time_left", |args, game| gtp_time_left(args, game));
+        self.insert("time_settings", |args, game| gtp_time_settings(args, game));
     }
 
     /// Registers commands specific to playing on KGS.
     pub fn register_kgs_commands(&mut self) {
         // kgs-chat
         self.insert("kgs-game_over", |_args, game| {
-            game.kgs_game_over = true;
+            game.finish();
             Ok(None)
         });
-        self.insert("kgs-genmove_cleanup", |args, game| gtp_genmove(args, game));
+        self.insert("kgs-genmove_cleanup", |args, game| {
+            gtp_genmove_cleanup(args, game, gtp_genmove)
+        });
         // kgs-rules
-        // kgs-time_settings
+        self.insert("kgs-time_settings", |args, game| {
+            gtp_kgs_time_settings(args, game)
+        });
     }
 
-    /// Not Supported! Registers commands useful for GTP regression testing.
+    /// Registers commands useful for GTP regression testing: `loadsgf`, and `reg_genmove`, which
+    /// answers like `genmove` but leaves the board as it is.
+    ///
+    /// `loadsgf` reads files on the engine's machine, so these are best left out of an engine
+    /// that strangers can connect to.
     pub fn register_regression_commands(&mut self) {
-        unimplemented!();
-        // loadsgf
-        // reg_genmove
+        self.insert("loadsgf", |args, game| gtp_loadsgf(args, game));
+        self.insert("reg_genmove", |_args, _game| {
+            unreachable!();
+        });
+    }
+
+    /// Records the results and openings of the games the engine plays in the store, by the
+    /// opponent named with `dlc-opponent <name>`, which answers with the engine's wins, losses
+    /// and draws against them so far. A game is recorded once it has a result and is over or
+    /// replaced, as the player the engine last generated a move for.
+    pub fn record_opponents(&mut self, store: OpponentStore) {
+        let store = Rc::new(RefCell::new(store));
+        let opponent = Rc::new(RefCell::new(None));
+        let player = Rc::new(Cell::new(None));
+
+        let (records, current) = (Rc::clone(&store), Rc::clone(&opponent));
+        self.insert("dlc-opponent", move |args, _game| {
+            if args.is_empty() {
+                return Err("expected: dlc-opponent <name>".to_owned());
+            }
+            let name = args.join(" ");
+            let record = records.borrow().get(&name).cloned().unwrap_or_default();
+            *current.borrow_mut() = Some(name);
+            Ok(Some(format!(
+                "{} {} {}",
+                record.wins, record.losses, record.draws
+            )))
+        });
+        let engine_player = Rc::clone(&player);
+        self.after_command(move |command, result, _game| {
+            let genmove = matches!(command.name.as_str(), "genmove" | "kgs-genmove_cleanup");
+            if let (true, Ok(_), Some(color)) = (genmove, result, command.args.first()) {
+                if let Ok(color) = parse_color(color) {
+                    engine_player.set(Some(color));
+                }
+            }
+        });
+        self.on_game_event(move |_event, game| {
+            // The game is done with whatever the event, so it's recorded once at most.
+            let Some(player) = player.take() else {
+                return;
+            };
+            if let (Some(opponent), Some(_)) = (opponent.borrow().as_deref(), game.result()) {
+                // A store that can't be written to shouldn't stop the engine.
+                let _ = store.borrow_mut().record_game(opponent, player, game);
+            }
+        });
     }
 
     /// Registers the commands required by GTP for tournament play.
@@ -266,6 +1021,18 @@ impl Engine {
 
             game.set_free_handicap(&verts).map(|_ok| None)
         });
+        self.insert("final_score", |_args, game| {
+            Ok(Some(final_score(game).to_string()))
+        });
+        self.insert("final_status_list", |args, game| {
+            let status = match args.first().map(String::as_str) {
+                Some("alive") => Status::Alive,
+                Some("dead") => Status::Dead,
+                Some("seki") => Status::Seki,
+                _ => return Err("expected alive, dead or seki".to_owned()),
+            };
+            Ok(Some(Vertices(game.stones_with_status(status)).to_string()))
+        });
     }
 }
 
@@ -282,3 +1049,522 @@ impl fmt::Display for Engine {
         write!(f, "\r\n{}", &commands.join("\r\n"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Lifecycle;
+
+    #[test]
+    fn genmove_resigns() {
+        let mut engine = Engine::new();
+        engine.set_options(EngineOptions {
+            playouts: 50,
+            resign_after_moves: 0,
+            ..EngineOptions::default()
+        });
+        let genmove = Command::from_line("genmove b").unwrap();
+
+        let mut game = Game::with_board_size(5).unwrap();
+        let reply = engine.exec(&mut game, &genmove).result.unwrap().unwrap();
+        assert_ne!(reply, "resign");
+        assert_eq!(game.board().stones(Player::Black).len(), 1);
+
+        let dlc_pv = Command::from_line("dlc-pv").unwrap();
+        engine.register_dlc_commands();
+        let pv = engine.exec(&mut game, &dlc_pv).result.unwrap().unwrap();
+        assert_eq!(pv.split(' ').next(), Some(reply.as_str()));
+        assert!(pv.split(' ').count() > 1);
+
+        // With this much komi Black can't win any of the games played out.
+        game.komi = 100.0;
+        let reply = engine.exec(&mut game, &genmove).result.unwrap().unwrap();
+        assert_eq!(reply, "resign");
+        assert_eq!(game.result(), Some(GameResult::Resignation(Player::White)));
+    }
+
+    #[test]
+    fn benchmark() {
+        let mut engine = Engine::new();
+        engine.register_dlc_commands();
+        let mut game = Game::with_board_size(5).unwrap();
+        let benchmark = Command::from_line("dlc-benchmark 3").unwrap();
+        let json = engine.exec(&mut game, &benchmark).result.unwrap().unwrap();
+        assert!(json.contains(r#""board_size":5,"games":3,"#));
+        let benchmark = Command::from_line("dlc-benchmark many").unwrap();
+        assert!(engine.exec(&mut game, &benchmark).result.is_err());
+    }
+
+    #[test]
+    fn genmove_cleanup() {
+        let mut game = Game::with_board_size(5).unwrap();
+        for y in 0..5 {
+            for (player, x) in [(Player::Black, 1), (Player::White, 3)] {
+                game.play(&Move {
+                    player,
+                    vertex: Some(Vertex { x, y }),
+                })
+                .unwrap();
+            }
+        }
+        let pass = |args: &[String], game: &mut Game| {
+            let player = parse_color(&args[0])?;
+            game.play(&Move {
+                player,
+                vertex: None,
+            })?;
+            Ok(Some("pass".to_owned()))
+        };
+        let args = ["b".to_owned()];
+        let reply = gtp_genmove_cleanup(&args, &mut game, pass).unwrap();
+        assert_eq!(reply, Some("C1".to_owned()));
+        assert_eq!(
+            game.board().stone(Vertex { x: 2, y: 0 }),
+            Some(Player::Black)
+        );
+        assert_eq!(game.moves().count(), 11);
+    }
+
+    #[test]
+    fn ponder() {
+        let mut engine = Engine::new();
+        engine.set_options(EngineOptions {
+            playouts: 200,
+            resign_below: None,
+            ponder: true,
+            ..EngineOptions::default()
+        });
+        let mut game = Game::with_board_size(5).unwrap();
+        let exec = |engine: &Engine, game: &mut Game, line: &str| {
+            engine
+                .exec(game, &Command::from_line(line).unwrap())
+                .result
+                .unwrap()
+        };
+
+        exec(&engine, &mut game, "genmove b");
+        let expected = engine.principal_variation.borrow()[1];
+        assert!(engine.ponder.borrow().is_some());
+        let reply = move_to_string(&expected);
+        exec(&engine, &mut game, &format!("play w {reply}"));
+        assert!(engine
+            .ponder
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .is_for(&game, Player::Black));
+        let mut changed = game.clone();
+        changed.komi += 1.0;
+        assert!(!engine
+            .ponder
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .is_for(&changed, Player::Black));
+        exec(&engine, &mut game, "genmove b");
+        assert_eq!(game.moves().count(), 3);
+
+        assert!(engine.ponder.borrow().is_some());
+        exec(&engine, &mut game, "dlc-set_option playouts 100");
+        assert!(engine.ponder.borrow().is_none());
+        exec(&engine, &mut game, "genmove b");
+
+        assert!(engine.ponder.borrow().is_some());
+        exec(&engine, &mut game, "clear_board");
+        assert!(engine.ponder.borrow().is_none());
+    }
+
+    #[test]
+    fn time_settings() {
+        let mut engine = Engine::new();
+        engine.register_extra_commands();
+        engine.set_options(EngineOptions {
+            playouts: usize::MAX,
+            ..EngineOptions::default()
+        });
+        let mut game = Game::with_board_size(5).unwrap();
+        let exec = |game: &mut Game, line: &str| {
+            engine.exec(game, &Command::from_line(line).unwrap()).result
+        };
+
+        assert!(exec(&mut game, "time_settings 600 30").is_err());
+        assert_eq!(exec(&mut game, "time_settings 600 30 25"), Ok(None));
+        assert_eq!(
+            game.time_settings(),
+            Clock::Canadian {
+                main_time: Duration::from_secs(600),
+                period_time: Duration::from_secs(30),
+                stones: 25,
+            }
+        );
+
+        assert_eq!(exec(&mut game, "time_left b 20 4"), Ok(None));
+        assert_eq!(game.move_time(Player::Black), Some(Duration::from_secs(5)));
+        assert_eq!(game.move_time(Player::White), Some(Duration::from_secs(20)));
+        assert!(exec(&mut game, "time_left b 20").is_err());
+
+        // Without a clock the search would never stop.
+        assert_eq!(exec(&mut game, "time_settings 1 0 0"), Ok(None));
+        assert!(exec(&mut game, "genmove b").is_ok());
+        assert_eq!(game.moves().count(), 1);
+    }
+
+    #[test]
+    fn kgs_time_settings() {
+        let mut engine = Engine::new();
+        engine.register_kgs_commands();
+        let mut game = Game::with_board_size(9).unwrap();
+        let mut exec = |line: &str| {
+            engine
+                .exec(&mut game, &Command::from_line(line).unwrap())
+                .result
+                .map(|_| game.time_settings())
+        };
+
+        let minute = Duration::from_secs(60);
+        assert_eq!(
+            exec("kgs-time_settings byoyomi 600 60 5"),
+            Ok(Clock::ByoYomi {
+                main_time: minute * 10,
+                period_time: minute,
+                periods: 5,
+            })
+        );
+        assert_eq!(
+            exec("kgs-time_settings canadian 600 60 25"),
+            Ok(Clock::Canadian {
+                main_time: minute * 10,
+                period_time: minute,
+                stones: 25,
+            })
+        );
+        assert_eq!(
+            exec("kgs-time_settings absolute 60"),
+            Ok(Clock::Absolute { main_time: minute })
+        );
+        assert_eq!(exec("kgs-time_settings none"), Ok(Clock::Unlimited));
+        assert!(exec("kgs-time_settings canadian 600 60 0").is_err());
+        assert!(exec("kgs-time_settings byoyomi 600 60").is_err());
+        assert!(exec("kgs-time_settings hourglass 60").is_err());
+    }
+
+    #[test]
+    fn game_events() {
+        let mut engine = Engine::new();
+        engine.register_kgs_commands();
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let seen = Rc::clone(&events);
+        engine.on_game_event(move |event, game| {
+            seen.borrow_mut()
+                .push((event, game.lifecycle(), game.moves().count()));
+        });
+        let mut game = Game::with_board_size(9).unwrap();
+        for line in [
+            "play b e5",
+            "play w pass",
+            "play b pass",
+            "kgs-game_over",
+            "clear_board",
+            "boardsize 0",
+            "boardsize 13",
+        ] {
+            engine.exec(&mut game, &Command::from_line(line).unwrap());
+        }
+        assert_eq!(
+            *events.borrow(),
+            [
+                (GameEvent::GameOver, Lifecycle::Finished, 3),
+                (GameEvent::ClearBoard, Lifecycle::Finished, 3),
+                (GameEvent::BoardSize, Lifecycle::InProgress, 0),
+            ]
+        );
+        assert_eq!(game.board().size(), 13);
+    }
+
+    #[test]
+    fn interceptors() {
+        let mut engine = Engine::new();
+        engine.register_extra_commands();
+        engine.before_command(|command, game| match command.name.as_str() {
+            "play" | "genmove" if matches!(game.result(), Some(GameResult::Time(_))) => {
+                Err("the game is over".to_owned())
+            }
+            _ => Ok(()),
+        });
+        let played = Rc::new(RefCell::new(Vec::new()));
+        let journal = Rc::clone(&played);
+        engine.after_command(move |command, result, _game| {
+            if command.name == "play" && result.is_ok() {
+                journal.borrow_mut().push(command.args.join(" "));
+            }
+        });
+
+        let mut game = Game::with_board_size(9).unwrap();
+        for line in [
+            "play b e5",
+            "play w e5",
+            "time_settings 60 0 0",
+            "play w c3",
+            "time_left b 0 0",
+        ] {
+            engine.exec(&mut game, &Command::from_line(line).unwrap());
+        }
+        let play = Command::from_line("play b g7").unwrap();
+        assert_eq!(
+            engine.exec(&mut game, &play).result,
+            Err("the game is over".to_owned())
+        );
+        assert_eq!(game.moves().count(), 2);
+        assert_eq!(*played.borrow(), ["b e5", "w c3"]);
+    }
+
+    #[test]
+    fn loadsgf() {
+        let mut engine = Engine::new();
+        engine.register_regression_commands();
+        let path = std::env::temp_dir().join(format!("libgo-loadsgf-{}.sgf", std::process::id()));
+        fs::write(&path, "(;SZ[9]KM[0.5]HA[2]AB[cc][gg];W[ee];B[ec];W[ce])").unwrap();
+        let mut game = Game::with_board_size(19).unwrap();
+        game.set_time_settings(Clock::Absolute {
+            main_time: Duration::from_secs(60),
+        });
+        let loadsgf = |game: &mut Game, args: &str| {
+            let line = format!("loadsgf {} {args}", path.display());
+            engine
+                .exec(game, &Command::from_line(&line).unwrap())
+                .result
+        };
+
+        assert_eq!(loadsgf(&mut game, "3"), Ok(None));
+        assert_eq!(game.board().size(), 9);
+        assert!((game.komi - 0.5).abs() < f64::EPSILON);
+        assert_eq!(game.handicap(), 2);
+        assert_eq!(game.moves().count(), 2);
+        assert!(matches!(game.time_settings(), Clock::Absolute { .. }));
+        assert_eq!(loadsgf(&mut game, ""), Ok(None));
+        assert_eq!(game.moves().count(), 3);
+        assert!(loadsgf(&mut game, "0").is_err());
+
+        fs::write(&path, "(;SZ[9];B[ee];W[ee])").unwrap();
+        assert_eq!(loadsgf(&mut game, ""), Err("cannot load file".to_owned()));
+        fs::write(&path, "(;SZ[9]").unwrap();
+        assert_eq!(loadsgf(&mut game, ""), Err("cannot load file".to_owned()));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loadsgf(&mut game, ""), Err("cannot load file".to_owned()));
+        assert_eq!(game.moves().count(), 3);
+
+        fs::write(&path, "(;SZ[9];B[ee];PL[B])").unwrap();
+        assert_eq!(loadsgf(&mut game, ""), Ok(None));
+        assert_eq!(game.player_turn(), Player::Black);
+        fs::write(&path, "(;SZ[9];B[ee];B[cc];W[gg])").unwrap();
+        assert_eq!(loadsgf(&mut game, "2"), Ok(None));
+        assert_eq!(game.player_turn(), Player::Black);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn final_score() {
+        let mut engine = Engine::new();
+        engine.register_tournament_commands();
+        let mut game = Game::with_board_size(9).unwrap();
+        // Black's wall on the D line and White's on the E line, with a dead white stone on B5.
+        for row in 1..=9 {
+            for (player, column) in [(Player::Black, 'D'), (Player::White, 'E')] {
+                game.play(&Move {
+                    player,
+                    vertex: Some(format!("{column}{row}").parse().unwrap()),
+                })
+                .unwrap();
+            }
+        }
+        game.play(&Move {
+            player: Player::Black,
+            vertex: Some("A1".parse().unwrap()),
+        })
+        .unwrap();
+        game.play(&Move {
+            player: Player::White,
+            vertex: Some("B5".parse().unwrap()),
+        })
+        .unwrap();
+        let exec = |game: &mut Game, line: &str| {
+            engine
+                .exec(game, &Command::from_line(line).unwrap())
+                .result
+                .unwrap()
+                .unwrap()
+        };
+
+        assert_eq!(exec(&mut game, "final_status_list dead"), "B5");
+        assert_eq!(exec(&mut game, "final_score"), "W+16.5");
+        game.resign(Player::White);
+        assert_eq!(exec(&mut game, "final_score"), "B+R");
+    }
+
+    #[test]
+    fn record_opponents() {
+        let mut engine = Engine::new();
+        engine.register_kgs_commands();
+        engine.record_opponents(OpponentStore::new());
+        let mut game = Game::with_board_size(5).unwrap();
+        let mut exec = |line: &str| {
+            engine
+                .exec(&mut game, &Command::from_line(line).unwrap())
+                .result
+                .unwrap()
+        };
+        let games = |record: Option<String>| {
+            record
+                .unwrap()
+                .split(' ')
+                .map(|count| count.parse::<usize>().unwrap())
+                .sum::<usize>()
+        };
+
+        assert_eq!(games(exec("dlc-opponent mirror bot")), 0);
+        exec("play b c3");
+        exec("genmove w");
+        exec("play b pass");
+        exec("play w pass");
+        exec("kgs-game_over");
+        exec("clear_board");
+        assert_eq!(games(exec("dlc-opponent mirror bot")), 1);
+        exec("play b c3");
+        exec("clear_board");
+        assert_eq!(games(exec("dlc-opponent mirror bot")), 1);
+    }
+
+    #[test]
+    fn reg_genmove() {
+        let mut engine = Engine::new();
+        engine.set_options(EngineOptions {
+            playouts: 50,
+            ..EngineOptions::default()
+        });
+        let reg_genmove = Command::from_line("reg_genmove w").unwrap();
+        let mut game = Game::with_board_size(5).unwrap();
+        assert!(engine.exec(&mut game, &reg_genmove).result.is_err());
+
+        engine.register_regression_commands();
+        let reply = engine
+            .exec(&mut game, &reg_genmove)
+            .result
+            .unwrap()
+            .unwrap();
+        assert!(reply.parse::<Vertex>().is_ok() || reply == "pass");
+        assert_eq!(game.moves().count(), 0);
+        assert!(engine
+            .exec(&mut game, &Command::from_line("reg_genmove").unwrap())
+            .result
+            .is_err());
+
+        engine.set_options(EngineOptions {
+            playouts: 50,
+            resign_below: None,
+            ponder: true,
+            ..EngineOptions::default()
+        });
+        engine
+            .exec(&mut game, &Command::from_line("genmove b").unwrap())
+            .result
+            .unwrap();
+        let principal_variation = engine.principal_variation.borrow().clone();
+        let (ponder_game, ponder_player) = {
+            let ponder = engine.ponder.borrow();
+            let ponder = ponder.as_ref().unwrap();
+            (ponder.game.clone(), ponder.player)
+        };
+        engine.exec(&mut game, &reg_genmove).result.unwrap();
+        assert_eq!(*engine.principal_variation.borrow(), principal_variation);
+        let ponder = engine.ponder.borrow();
+        let ponder = ponder.as_ref().unwrap();
+        assert!(ponder.is_for(&ponder_game, ponder_player));
+        assert!(!ponder.cancel.is_cancelled());
+    }
+
+    #[test]
+    fn group_status() {
+        let mut engine = Engine::new();
+        engine.register_dlc_commands();
+        let mut game = Game::with_board_size(9).unwrap();
+        let group_status = |game: &mut Game, args: &str| {
+            let command = Command::from_line(&format!("dlc-group_status {args}")).unwrap();
+            engine.exec(game, &command).result
+        };
+        assert_eq!(group_status(&mut game, ""), Ok(Some(String::new())));
+        game.play(&Move {
+            player: Player::Black,
+            vertex: Some("E5".parse().unwrap()),
+        })
+        .unwrap();
+        assert_eq!(
+            group_status(&mut game, "e5"),
+            Ok(Some("unsettled".to_owned()))
+        );
+        assert_eq!(
+            group_status(&mut game, ""),
+            Ok(Some("unsettled E5".to_owned()))
+        );
+        assert!(group_status(&mut game, "A1").is_err());
+        assert!(group_status(&mut game, "Z99").is_err());
+    }
+
+    #[test]
+    fn build_info() {
+        let mut engine = Engine::new();
+        engine.register_dlc_commands();
+        let build_info = Command::from_line("dlc-build_info").unwrap();
+        let info = engine
+            .exec(&mut Game::new(), &build_info)
+            .result
+            .unwrap()
+            .unwrap();
+        let lines: Vec<_> = info.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], format!("version {AGENT_VERSION}"));
+        assert!(lines[1].starts_with("rustc "));
+    }
+
+    #[test]
+    fn self_test() {
+        let mut engine = Engine::new();
+        engine.register_dlc_commands();
+        let selftest = Command::from_line("dlc-selftest").unwrap();
+        let report = engine.exec(&mut Game::new(), &selftest).result;
+        assert_eq!(
+            report,
+            Ok(Some("rules: ok\nhashing: ok\nscoring: ok".to_owned()))
+        );
+    }
+
+    #[test]
+    fn options() {
+        let mut engine = Engine::new();
+        engine.set_options(EngineOptions {
+            playouts: 10,
+            pass: PassPolicy::WhenWinning,
+            ..EngineOptions::default()
+        });
+        let exec = |game: &mut Game, line: &str| {
+            engine.exec(game, &Command::from_line(line).unwrap()).result
+        };
+
+        // Black owns the whole board, so it ends the game once White passes.
+        let mut game = Game::with_board_size(5).unwrap();
+        exec(&mut game, "play b c3").unwrap();
+        exec(&mut game, "play w pass").unwrap();
+        assert_eq!(exec(&mut game, "genmove b"), Ok(Some("pass".to_owned())));
+        assert!(game.is_over());
+
+        assert_eq!(
+            exec(&mut game, "dlc-set_option pass no_moves_left"),
+            Ok(None)
+        );
+        assert_eq!(
+            exec(&mut game, "dlc-option pass"),
+            Ok(Some("no_moves_left".to_owned()))
+        );
+        assert!(exec(&mut game, "dlc-set_option playouts many").is_err());
+        assert_eq!(engine.options().pass, PassPolicy::NoMovesLeft);
+    }
+}
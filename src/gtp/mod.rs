@@ -1,8 +1,22 @@
 //! This module implements the [Go Text Protocol](http://www.lysator.liu.se/~gunnar/gtp/) with [KGS](http://www.gokgs.com) support.
 
+/// A builder for engines, with presets for common uses.
+pub mod builder;
 /// A Go Text Protocol Command.
 pub mod command;
 /// A GTP engine that accepts commands and returns reponses.
 pub mod engine;
+/// Playing GTP engines against each other over TCP and broadcasting their games to spectators.
+pub mod net;
+/// Remembering the results and openings of games by opponent, so a bot can vary its play.
+pub mod opponents;
+/// Options for how a searching engine resigns and passes.
+pub mod options;
+/// A proxy between a controller and an engine that logs, rewrites and injects commands.
+pub mod proxy;
 /// The result of executing a Go Text Protocol Command.
 pub mod response;
+/// Checking that engines follow the Go Text Protocol.
+pub mod testing;
+/// Tournaments between GTP engines that can be stopped and resumed.
+pub mod tournament;
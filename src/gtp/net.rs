@@ -0,0 +1,1301 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::game::board::{Board, Move, Status};
+use crate::game::clock::Clock;
+use crate::game::numeric;
+use crate::game::player::Player;
+use crate::game::sgf::{self, Property};
+use crate::game::vertex::{Vertex, Vertices};
+use crate::game::{Game, GameResult};
+use crate::gtp::command::{Command, Commands};
+use crate::gtp::engine::GtpEngine;
+use crate::gtp::response::{CommandResult, Response};
+
+/// A GTP engine connected over TCP, driven by sending it commands.
+#[derive(Debug)]
+pub struct Connection {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl Connection {
+    /// Wraps a connected stream.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the stream can't be cloned for reading.
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        let reader = BufReader::new(stream.try_clone()?);
+        Ok(Connection { stream, reader })
+    }
+
+    /// Returns the address of the engine's end of the connection.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the stream isn't connected.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Sends a command and waits for the engine's response.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the connection is lost or the engine replies with something other than a GTP
+    /// response.
+    pub fn send(&mut self, command: &str) -> io::Result<CommandResult> {
+        self.exchange(command).map(|response| response.result)
+    }
+
+    fn exchange(&mut self, command: &str) -> io::Result<Response> {
+        // One write, so the line isn't split into two packets.
+        self.stream.write_all(format!("{command}\n").as_bytes())?;
+
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                if lines.is_empty() {
+                    continue;
+                }
+                break;
+            }
+            lines.push(line.to_owned());
+        }
+
+        Response::from_str(&lines.join("\n"))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Asks the engine for its board with `showboard`, so its internal state can be checked
+    /// against the game.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the connection is lost. The inner error is set if the engine rejects the command
+    /// or its board can't be read.
+    pub fn showboard(&mut self) -> io::Result<Result<Board, String>> {
+        Ok(self
+            .send("showboard")?
+            .and_then(|board| Board::from_ascii(&board.unwrap_or_default())))
+    }
+
+    /// Closes the connection.
+    pub fn shutdown(&self) {
+        // The engine may already have hung up.
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+impl GtpEngine for Connection {
+    /// Sends the command to the engine. A lost connection or an invalid response is returned as
+    /// a failed command.
+    fn exec(&mut self, command: &Command) -> Response {
+        self.exchange(&command.to_string())
+            .unwrap_or_else(|err| Response {
+                id: command.id,
+                result: Err(format!("connection failed: {err}")),
+            })
+    }
+}
+
+/// Limits on what a client may send an engine, so a hostile client can't exhaust the memory or
+/// CPU of a hosted engine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The longest line accepted in bytes.
+    pub max_line_length: usize,
+    /// The most arguments a command may have.
+    pub max_args: usize,
+    /// The most commands run per second. Reading from the client pauses when it is reached.
+    pub commands_per_second: Option<u32>,
+}
+
+impl Default for Limits {
+    /// Limits that any honest controller stays within, without a rate limit.
+    fn default() -> Self {
+        Limits {
+            max_line_length: 4096,
+            max_args: 512,
+            commands_per_second: None,
+        }
+    }
+}
+
+/// The upper bounds of the buckets of a `LatencyHistogram`, after which comes one for slower
+/// replies.
+pub const LATENCY_BUCKETS: [Duration; 4] = [
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+    Duration::from_secs(10),
+];
+
+/// How long a client took to reply with its moves, counted in the buckets of `LATENCY_BUCKETS`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LatencyHistogram {
+    counts: [usize; LATENCY_BUCKETS.len() + 1],
+}
+
+impl LatencyHistogram {
+    /// Counts a reply that took `latency`.
+    pub fn record(&mut self, latency: Duration) {
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| latency < bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.counts[bucket] += 1;
+    }
+
+    /// Returns the number of replies in each bucket, fastest first.
+    #[must_use]
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// Returns the number of replies counted.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Adds the counts of another histogram to this one.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count += other;
+        }
+    }
+}
+
+/// How a client behaved in its matches, for operators of a public server to spot clients that
+/// stall, drop games they are losing or try illegal moves.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ClientStats {
+    /// The matches played.
+    pub matches: usize,
+    /// How long the client took to reply to each `genmove`.
+    pub latency: LatencyHistogram,
+    /// The matches the client disconnected from before they were over.
+    pub disconnects: usize,
+    /// The moves the client tried that were illegal or not moves at all.
+    pub illegal_moves: usize,
+}
+
+impl ClientStats {
+    /// Adds another client's stats, or those of another match, to these.
+    pub fn merge(&mut self, other: &ClientStats) {
+        self.matches += other.matches;
+        self.latency.merge(&other.latency);
+        self.disconnects += other.disconnects;
+        self.illegal_moves += other.illegal_moves;
+    }
+}
+
+/// Runs the commands a client sends to an engine and writes back the responses within the limits,
+/// until the client sends `quit` or hangs up. A line that is too long or a command with too many
+/// arguments gets an error response without being run.
+///
+/// # Errors
+///
+/// Fails if reading from or writing to the client fails.
+pub fn serve<R: BufRead, W: Write, E: GtpEngine>(
+    reader: R,
+    mut writer: W,
+    engine: &mut E,
+    limits: &Limits,
+) -> io::Result<()> {
+    let mut second = Instant::now();
+    let mut commands_this_second = 0;
+
+    for command in reader.commands().max_line_length(limits.max_line_length) {
+        if let Some(rate) = limits.commands_per_second {
+            let elapsed = second.elapsed();
+            if elapsed >= Duration::from_secs(1) {
+                second = Instant::now();
+                commands_this_second = 0;
+            } else if commands_this_second >= rate {
+                thread::sleep(Duration::from_secs(1).saturating_sub(elapsed));
+                second = Instant::now();
+                commands_this_second = 0;
+            }
+            commands_this_second += 1;
+        }
+
+        let (response, quit) = match command {
+            Ok(command) if command.args.len() > limits.max_args => (
+                Response {
+                    id: command.id,
+                    result: Err(format!("more than {} arguments", limits.max_args)),
+                },
+                false,
+            ),
+            Ok(command) => (engine.exec(&command), command.name == "quit"),
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => (
+                Response {
+                    id: None,
+                    result: Err(err.to_string()),
+                },
+                false,
+            ),
+            Err(err) => return Err(err),
+        };
+        writer.write_all(response.to_string().as_bytes())?;
+        writer.flush()?;
+        if quit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Something that happened in a game being broadcast.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// The current position, sent to spectators when they subscribe.
+    Position(Board),
+    /// A move and the position after it.
+    Move(Move, Board),
+    /// The game is over.
+    End,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Position(board) => write!(f, "position\r\n{board}"),
+            Event::Move(mov, board) => {
+                let vertex = mov.vertex.map_or("pass".to_owned(), |v| v.to_string());
+                write!(f, "play {} {vertex}\r\n{board}", mov.player)
+            }
+            Event::End => write!(f, "end\r\n"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Subscribers {
+    position: Board,
+    senders: Vec<Sender<Event>>,
+    ended: bool,
+}
+
+/// Sends the moves of a live game to any number of read-only subscribers. Clones share the same
+/// subscribers.
+#[derive(Clone, Debug)]
+pub struct Broadcast {
+    inner: Arc<Mutex<Subscribers>>,
+}
+
+impl Broadcast {
+    /// Returns a broadcast of a game starting from the given position.
+    #[must_use]
+    pub fn new(position: Board) -> Self {
+        Broadcast {
+            inner: Arc::new(Mutex::new(Subscribers {
+                position,
+                senders: Vec::new(),
+                ended: false,
+            })),
+        }
+    }
+
+    /// Subscribes to the game. The first event received is the current position. Once the game
+    /// has ended, the subscription ends after it with `End`.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while publishing.
+    #[must_use]
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (sender, receiver) = mpsc::channel();
+        let mut inner = self.inner.lock().unwrap();
+        // The receiver is still in scope, so this can't fail.
+        let _ = sender.send(Event::Position(inner.position.clone()));
+        if inner.ended {
+            let _ = sender.send(Event::End);
+        } else {
+            inner.senders.push(sender);
+        }
+        receiver
+    }
+
+    /// Sends an event to every subscriber, dropping those that went away. After `End` every
+    /// subscription is closed.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while publishing.
+    pub fn publish(&self, event: &Event) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Event::Position(board) | Event::Move(_, board) = event {
+            inner.position = board.clone();
+        }
+        inner
+            .senders
+            .retain(|sender| sender.send(event.clone()).is_ok());
+        if let Event::End = event {
+            inner.ended = true;
+            inner.senders.clear();
+        }
+    }
+
+    /// Writes the events of the game as text to each spectator accepted on the listener, until the
+    /// game ends or the spectator hangs up. Anything the spectators send is ignored. This never
+    /// returns, so a server playing many games should accept spectators itself and hand each to
+    /// the current game's broadcast with `spectate`.
+    pub fn serve(&self, listener: &TcpListener) {
+        for stream in listener.incoming().flatten() {
+            self.spectate(stream);
+        }
+    }
+
+    /// Writes the events of the game as text to the spectator on a thread of its own, until the
+    /// game ends or the spectator hangs up.
+    pub fn spectate(&self, stream: TcpStream) {
+        let receiver = self.subscribe();
+        thread::spawn(move || spectate(stream, &receiver));
+    }
+}
+
+fn spectate(mut stream: TcpStream, receiver: &Receiver<Event>) {
+    for event in receiver {
+        if stream.write_all(event.to_string().as_bytes()).is_err() {
+            return;
+        }
+        if let Event::End = event {
+            break;
+        }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+/// How a match ended.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum End {
+    /// The game was played until it was over.
+    Finished,
+    /// The player resigned.
+    Resigned(Player),
+    /// The player lost by playing an illegal move or breaking the protocol.
+    Forfeited(Player, String),
+    /// The player's engine disconnected, losing the game.
+    Disconnected(Player),
+    /// The player's engine disconnected and the game was adjourned.
+    Adjourned(Player),
+}
+
+/// An engine's answer to a scoring command that differs from the referee's.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Disagreement {
+    /// The player whose engine disagreed.
+    pub player: Player,
+    /// The command sent, e.g. `final_score`.
+    pub command: String,
+    /// The engine's answer.
+    pub engine: String,
+    /// The referee's answer.
+    pub referee: String,
+}
+
+/// The game played in a match and how it ended.
+#[derive(Clone, Debug)]
+pub struct Outcome {
+    /// The game as it was when the match ended.
+    pub game: Game,
+    /// How the match ended.
+    pub end: End,
+    /// Where the engines' scoring of a finished game differed from the referee's. Engines that
+    /// don't support `final_score` or `final_status_list` are not checked.
+    pub disagreements: Vec<Disagreement>,
+    /// Whether play was resumed to capture the dead stones because the engines disagreed about
+    /// which stones were dead.
+    pub cleaned_up: bool,
+    /// The names and versions the engines playing Black and White gave, empty if they gave none.
+    pub engines: [String; 2],
+    /// How the engines playing Black and White behaved in the match.
+    pub stats: [ClientStats; 2],
+    /// Why the game record couldn't be written, if it couldn't.
+    pub record_error: Option<String>,
+}
+
+impl Outcome {
+    /// Returns the result of the match, or None if it was adjourned or the game has no result.
+    #[must_use]
+    pub fn result(&self) -> Option<GameResult> {
+        match self.end {
+            End::Finished => self.game.result(),
+            End::Resigned(player) => Some(GameResult::Resignation(player.enemy())),
+            End::Forfeited(player, _) | End::Disconnected(player) => {
+                Some(GameResult::Forfeit(player.enemy()))
+            }
+            End::Adjourned(_) => None,
+        }
+    }
+
+    /// Returns the game record as SGF, with the engines as the players and the result of the
+    /// match.
+    #[must_use]
+    pub fn to_sgf(&self) -> String {
+        let mut properties = Vec::new();
+        for (id, engine) in ["PB", "PW"].into_iter().zip(&self.engines) {
+            if !engine.is_empty() {
+                properties.push(Property::new(id, engine.as_str()));
+            }
+        }
+        let result = self
+            .result()
+            .map_or("?".to_owned(), |result| result.to_string());
+        properties.push(Property::new("RE", result));
+        sgf::write(&self.game, &properties)
+    }
+}
+
+/// A game between two connected engines. The match keeps its own copy of the game and checks
+/// every move against it, so the engines don't have to be trusted.
+#[derive(Debug)]
+pub struct Match {
+    black: Connection,
+    white: Connection,
+    game: Game,
+    broadcast: Broadcast,
+    adjourn_on_disconnect: bool,
+    cleaned_up: bool,
+    engines: [String; 2],
+    stats: [ClientStats; 2],
+    record: Option<PathBuf>,
+}
+
+impl Match {
+    /// Returns a match between two engines played on `game`. Handicap stones and any moves
+    /// already played in `game` are sent to both engines before play continues.
+    #[must_use]
+    pub fn new(black: Connection, white: Connection, game: Game) -> Self {
+        let broadcast = Broadcast::new(game.board().clone());
+        Match {
+            black,
+            white,
+            game,
+            broadcast,
+            adjourn_on_disconnect: false,
+            cleaned_up: false,
+            engines: Default::default(),
+            stats: [ClientStats {
+                matches: 1,
+                ..ClientStats::default()
+            }; 2],
+            record: None,
+        }
+    }
+
+    /// Returns the broadcast of the match's moves.
+    #[must_use]
+    pub fn broadcast(&self) -> &Broadcast {
+        &self.broadcast
+    }
+
+    /// Sets whether an engine disconnecting adjourns the game instead of losing it.
+    pub fn set_adjourn_on_disconnect(&mut self, adjourn: bool) {
+        self.adjourn_on_disconnect = adjourn;
+    }
+
+    /// Sets a file to write the game record to as SGF when the match ends.
+    pub fn set_record<P: Into<PathBuf>>(&mut self, path: P) {
+        self.record = Some(path.into());
+    }
+
+    /// Sets up both engines and plays the match until the game is over, a player resigns or an
+    /// engine fails, then closes both connections.
+    ///
+    /// If the engines disagree about which stones are dead when the game is over, play resumes as
+    /// it does on KGS: the engines are asked for moves with `kgs-genmove_cleanup`, or `genmove` if
+    /// they don't know it, until both pass again before the game is scored. A game counted by
+    /// area then has its dame filled and chains in atari connected, see `Game::fill_dame`.
+    ///
+    /// If the game has time settings, the engines are sent them and the time they have left
+    /// before each move, and a player taking too long loses on time.
+    #[must_use]
+    pub fn play(mut self) -> Outcome {
+        let end = self.run();
+        if let End::Disconnected(player) | End::Adjourned(player) = end {
+            self.stats_mut(player).disconnects += 1;
+        }
+        let disagreements = if end == End::Finished {
+            self.verify_score()
+        } else {
+            Vec::new()
+        };
+        self.broadcast.publish(&Event::End);
+        self.black.shutdown();
+        self.white.shutdown();
+        let mut outcome = Outcome {
+            game: self.game,
+            end,
+            disagreements,
+            cleaned_up: self.cleaned_up,
+            engines: self.engines,
+            stats: self.stats,
+            record_error: None,
+        };
+        if let Some(path) = self.record {
+            if let Err(err) = fs::write(&path, outcome.to_sgf()) {
+                outcome.record_error = Some(format!("{}: {err}", path.display()));
+            }
+        }
+        outcome
+    }
+
+    /// Asks both engines for the score and dead stones of the finished game and compares them
+    /// with the referee's. Dead stones the engines agree on are taken as they are, as the referee
+    /// can only estimate them with `Game::stones_with_status`, and the score is counted with
+    /// them removed.
+    fn verify_score(&mut self) -> Vec<Disagreement> {
+        let Some(result) = self.game.result() else {
+            return Vec::new();
+        };
+        let commands = ["final_score", "final_status_list dead"];
+        let replies = [&mut self.black, &mut self.white].map(|connection| {
+            commands.map(|command| match connection.send(command) {
+                Ok(Ok(reply)) => Some(reply.unwrap_or_default()),
+                _ => None,
+            })
+        });
+
+        let listed = match [&replies[0][1], &replies[1][1]] {
+            [Some(black), Some(white)] => {
+                parse_vertices(black).filter(|black| parse_vertices(white).as_ref() == Some(black))
+            }
+            _ => None,
+        };
+        let mut dead_stones = listed.map_or_else(
+            || self.game.stones_with_status(Status::Dead),
+            |agreed| agreed.into_iter().collect(),
+        );
+        dead_stones.sort_by_key(|vertex| (vertex.x, vertex.y));
+        let result = if self.scored() {
+            self.game.score_result_removing(&dead_stones)
+        } else {
+            result
+        };
+        let referees = [result.to_string(), Vertices(dead_stones).to_string()];
+
+        let mut disagreements = Vec::new();
+        for (player, replies) in [Player::Black, Player::White].into_iter().zip(replies) {
+            for ((command, referee), reply) in commands.into_iter().zip(&referees).zip(replies) {
+                let Some(reply) = reply else {
+                    continue;
+                };
+                let agrees = if command == "final_score" {
+                    same_score(&reply, referee)
+                } else {
+                    same_vertices(&reply, referee)
+                };
+                if !agrees {
+                    disagreements.push(Disagreement {
+                        player,
+                        command: command.to_owned(),
+                        engine: reply,
+                        referee: referee.clone(),
+                    });
+                }
+            }
+        }
+        disagreements
+    }
+
+    fn run(&mut self) -> End {
+        if let Err(end) = self.set_up() {
+            return end;
+        }
+        let end = match self.play_until_over(["genmove", "genmove"]) {
+            End::Finished if self.scored() && self.dead_stones_disputed() => {
+                self.cleaned_up = true;
+                let cleanup = [Player::Black, Player::White].map(|player| {
+                    match self.send(player, "known_command kgs-genmove_cleanup") {
+                        Ok(Some(known)) if known.trim() == "true" => "kgs-genmove_cleanup",
+                        _ => "genmove",
+                    }
+                });
+                self.play_until_over(cleanup)
+            }
+            end => end,
+        };
+        if end == End::Finished && self.scored() && self.game.rule_set.counts_area() {
+            self.fill_dame()
+        } else {
+            end
+        }
+    }
+
+    /// Fills the dame and connects the chains left in atari at the end of a game counted by
+    /// area, with `Game::fill_dame`, and tells both engines the moves, so the count is exact.
+    fn fill_dame(&mut self) -> End {
+        for mov in self.game.clone().fill_dame() {
+            self.game.play(&mov).expect("cleanup moves are legal");
+            self.broadcast
+                .publish(&Event::Move(mov, self.game.board().clone()));
+            let vertex = mov
+                .vertex
+                .map_or("pass".to_owned(), |vertex| vertex.to_string());
+            for player in [Player::Black, Player::White] {
+                if let Err(end) = self.send(player, &format!("play {} {vertex}", mov.player)) {
+                    return end;
+                }
+            }
+        }
+        End::Finished
+    }
+
+    fn set_up(&mut self) -> Result<(), End> {
+        for (index, player) in [Player::Black, Player::White].into_iter().enumerate() {
+            let mut engine = Vec::new();
+            for command in ["name", "version"] {
+                if let Some(reply) = self.send_optional(player, command)? {
+                    if !reply.trim().is_empty() {
+                        engine.push(reply.trim().to_owned());
+                    }
+                }
+            }
+            self.engines[index] = engine.join(" ");
+        }
+
+        let mut setup = vec![
+            format!("boardsize {}", self.game.board().size()),
+            "clear_board".to_owned(),
+            format!("komi {}", numeric::format_points(self.game.komi)),
+        ];
+        if self.game.handicap() >= 2 {
+            let mut stones = self.game.fork_at(0).board().stones(Player::Black);
+            stones.sort_by_key(|vertex| (vertex.x, vertex.y));
+            setup.push(format!("set_free_handicap {}", Vertices(stones)));
+        }
+        for command in &setup {
+            for player in [Player::Black, Player::White] {
+                self.send(player, command)?;
+            }
+        }
+
+        match self.game.time_settings() {
+            Clock::Unlimited => {}
+            Clock::ByoYomi {
+                main_time,
+                period_time,
+                periods,
+            } => {
+                let (main_time, period_time) = (main_time.as_secs(), period_time.as_secs());
+                for player in [Player::Black, Player::White] {
+                    // Standard GTP only has Canadian byo-yomi, so engines that don't know the KGS
+                    // command are told about a single period.
+                    let kgs =
+                        format!("kgs-time_settings byoyomi {main_time} {period_time} {periods}");
+                    if self.send_optional(player, &kgs)?.is_none() {
+                        let canadian = format!("time_settings {main_time} {period_time} 1");
+                        self.send_optional(player, &canadian)?;
+                    }
+                }
+            }
+            Clock::Absolute { main_time } => {
+                // Byo-yomi of no time means absolute time in GTP.
+                let absolute = format!("time_settings {} 0 0", main_time.as_secs());
+                for player in [Player::Black, Player::White] {
+                    self.send_optional(player, &absolute)?;
+                }
+            }
+            Clock::Canadian {
+                main_time,
+                period_time,
+                stones,
+            } => {
+                let canadian = format!(
+                    "time_settings {} {} {stones}",
+                    main_time.as_secs(),
+                    period_time.as_secs()
+                );
+                for player in [Player::Black, Player::White] {
+                    self.send_optional(player, &canadian)?;
+                }
+            }
+        }
+
+        // Moves already in the game, e.g. of an opening or an interrupted game, are replayed.
+        let moves: Vec<Move> = self.game.moves().copied().collect();
+        for mov in moves {
+            let vertex = mov
+                .vertex
+                .map_or("pass".to_owned(), |vertex| vertex.to_string());
+            for player in [Player::Black, Player::White] {
+                self.send(player, &format!("play {} {vertex}", mov.player))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Plays until the game is over, asking each player's engine for moves with the given
+    /// command, e.g. `genmove`.
+    fn play_until_over(&mut self, genmove: [&str; 2]) -> End {
+        // Play resumed after the game is over needs two new moves to end again.
+        let min_moves = if self.game.is_over() { 2 } else { 0 };
+        let mut moves = 0;
+        while moves < min_moves || !self.game.is_over() {
+            moves += 1;
+            let player = self.game.player_turn();
+            let genmove = match player {
+                Player::Black => genmove[0],
+                Player::White => genmove[1],
+            };
+            let clock = self.game.time_settings();
+            if clock != Clock::Unlimited {
+                let (time, stones) = clock.gtp_time_left(&self.game.time_left(player));
+                let time_left = format!("time_left {player} {} {stones}", time.as_secs());
+                if let Err(end) = self.send_optional(player, &time_left) {
+                    return end;
+                }
+            }
+            let started = Instant::now();
+            let reply = match self.send(player, &format!("{genmove} {player}")) {
+                Ok(reply) => reply.unwrap_or_default(),
+                Err(end) => return end,
+            };
+            let elapsed = started.elapsed();
+            self.stats_mut(player).latency.record(elapsed);
+            let vertex = match reply.to_uppercase().as_str() {
+                "RESIGN" => return End::Resigned(player),
+                "PASS" => None,
+                vertex => match Vertex::from_str(vertex) {
+                    Ok(vertex) => Some(vertex),
+                    Err(err) => {
+                        self.stats_mut(player).illegal_moves += 1;
+                        return End::Forfeited(player, err);
+                    }
+                },
+            };
+            let mov = Move { player, vertex };
+            let legal = if clock == Clock::Unlimited {
+                self.game.play(&mov)
+            } else {
+                self.game.play_timed(&mov, elapsed)
+            };
+            if let Err(err) = legal {
+                if let Some(GameResult::Time(_)) = self.game.result() {
+                    return End::Finished;
+                }
+                self.stats_mut(player).illegal_moves += 1;
+                return End::Forfeited(player, format!("{reply}: {err}"));
+            }
+            self.broadcast
+                .publish(&Event::Move(mov, self.game.board().clone()));
+
+            let vertex = vertex.map_or("pass".to_owned(), |vertex| vertex.to_string());
+            if let Err(end) = self.send(player.enemy(), &format!("play {player} {vertex}")) {
+                return end;
+            }
+        }
+        End::Finished
+    }
+
+    fn stats_mut(&mut self, player: Player) -> &mut ClientStats {
+        match player {
+            Player::Black => &mut self.stats[0],
+            Player::White => &mut self.stats[1],
+        }
+    }
+
+    /// Whether the game ended by passing and is decided by the score.
+    fn scored(&self) -> bool {
+        matches!(
+            self.game.result(),
+            Some(GameResult::Score(..) | GameResult::Draw)
+        )
+    }
+
+    /// Whether the engines both list the dead stones and the lists differ. Engines that don't
+    /// support `final_status_list` can't dispute.
+    fn dead_stones_disputed(&mut self) -> bool {
+        let lists = [Player::Black, Player::White].map(|player| {
+            self.send(player, "final_status_list dead")
+                .ok()
+                .and_then(|list| parse_vertices(&list.unwrap_or_default()))
+        });
+        matches!(&lists, [Some(black), Some(white)] if black != white)
+    }
+
+    /// Sends a command to a player's engine, turning failures into the end of the match.
+    fn send(&mut self, player: Player, command: &str) -> Result<Option<String>, End> {
+        match self.send_command(player, command) {
+            Ok(Ok(reply)) => Ok(reply),
+            Ok(Err(err)) => Err(End::Forfeited(player, format!("{command}: {err}"))),
+            Err(end) => Err(end),
+        }
+    }
+
+    /// Sends a command that engines need not support, returning None if the engine rejects it.
+    fn send_optional(&mut self, player: Player, command: &str) -> Result<Option<String>, End> {
+        self.send_command(player, command)
+            .map(|result| result.ok().map(Option::unwrap_or_default))
+    }
+
+    /// Sends a command to a player's engine, turning a broken connection into the end of the
+    /// match.
+    fn send_command(&mut self, player: Player, command: &str) -> Result<CommandResult, End> {
+        let connection = match player {
+            Player::Black => &mut self.black,
+            Player::White => &mut self.white,
+        };
+        match connection.send(command) {
+            Ok(result) => Ok(result),
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                Err(End::Forfeited(player, err.to_string()))
+            }
+            Err(_) if self.adjourn_on_disconnect => Err(End::Adjourned(player)),
+            Err(_) => Err(End::Disconnected(player)),
+        }
+    }
+}
+
+/// Compares scores such as `W+7.5`, ignoring case and how the points are written.
+fn same_score(a: &str, b: &str) -> bool {
+    let (a, b) = (a.trim().to_uppercase(), b.trim().to_uppercase());
+    match (a.split_once('+'), b.split_once('+')) {
+        (Some((a_winner, a_points)), Some((b_winner, b_points))) => {
+            a_winner == b_winner
+                && match (
+                    numeric::parse_points(a_points),
+                    numeric::parse_points(b_points),
+                ) {
+                    (Ok(a_points), Ok(b_points)) => (a_points - b_points).abs() < f64::EPSILON,
+                    _ => a_points == b_points,
+                }
+        }
+        _ => a == b,
+    }
+}
+
+/// Reads a list of vertices separated by whitespace.
+fn parse_vertices(list: &str) -> Option<HashSet<Vertex>> {
+    list.split_whitespace()
+        .map(|vertex| Vertex::from_str(&vertex.to_uppercase()).ok())
+        .collect()
+}
+
+/// Compares lists of vertices, ignoring order and case.
+fn same_vertices(a: &str, b: &str) -> bool {
+    parse_vertices(a).map_or(false, |a| Some(a) == parse_vertices(b))
+}
+
+/// A server that listens for GTP engines to connect and plays them against each other in pairs.
+#[derive(Debug)]
+pub struct Server {
+    listener: TcpListener,
+    game: Game,
+    adjourn_on_disconnect: bool,
+    record_dir: Option<PathBuf>,
+    /// How the clients from each address behaved in their finished matches.
+    stats: Arc<Mutex<HashMap<IpAddr, ClientStats>>>,
+}
+
+impl Server {
+    /// Listens for engines on the address. Each match is played on its own copy of `game`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the address can't be bound.
+    pub fn bind<A: ToSocketAddrs>(address: A, game: Game) -> io::Result<Self> {
+        Ok(Server {
+            listener: TcpListener::bind(address)?,
+            game,
+            adjourn_on_disconnect: false,
+            record_dir: None,
+            stats: Arc::default(),
+        })
+    }
+
+    /// Returns the listener the server accepts engines on.
+    #[must_use]
+    pub fn listener(&self) -> &TcpListener {
+        &self.listener
+    }
+
+    /// Sets whether an engine disconnecting adjourns its game instead of losing it.
+    pub fn set_adjourn_on_disconnect(&mut self, adjourn: bool) {
+        self.adjourn_on_disconnect = adjourn;
+    }
+
+    /// Returns how the clients from each address behaved in the matches they finished, so abusive
+    /// clients can be spotted and banned.
+    #[must_use]
+    pub fn client_stats(&self) -> HashMap<IpAddr, ClientStats> {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Sets a directory to write the record of each match to, as `match-<number>.sgf`.
+    pub fn set_record_dir<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.record_dir = Some(dir.into());
+    }
+
+    /// Accepts engines forever, pairing each with the next to connect. The first of a pair plays
+    /// Black. Matches are numbered from 0 and each is played on its own thread after `on_match`
+    /// is called with it, so the caller can subscribe to its broadcast. The outcome of each
+    /// match is sent with its number to `outcomes`.
+    pub fn run<F: FnMut(usize, &Match)>(
+        &self,
+        mut on_match: F,
+        outcomes: &Sender<(usize, Outcome)>,
+    ) {
+        let mut waiting = None;
+        let mut matches = 0..;
+        for stream in self.listener.incoming() {
+            let Ok(connection) = stream.and_then(Connection::new) else {
+                continue;
+            };
+            let Ok(address) = connection.peer_addr() else {
+                continue;
+            };
+            match waiting.take() {
+                None => waiting = Some((connection, address.ip())),
+                Some((black, black_address)) => {
+                    let mut game_match = Match::new(black, connection, self.game.clone());
+                    game_match.set_adjourn_on_disconnect(self.adjourn_on_disconnect);
+                    let number = matches.next().unwrap_or_default();
+                    if let Some(dir) = &self.record_dir {
+                        game_match.set_record(dir.join(format!("match-{number}.sgf")));
+                    }
+                    on_match(number, &game_match);
+                    let outcomes = outcomes.clone();
+                    let stats = Arc::clone(&self.stats);
+                    let addresses = [black_address, address.ip()];
+                    thread::spawn(move || {
+                        let outcome = game_match.play();
+                        let mut stats = stats.lock().unwrap_or_else(PoisonError::into_inner);
+                        for (address, match_stats) in addresses.into_iter().zip(&outcome.stats) {
+                            stats.entry(address).or_default().merge(match_stats);
+                        }
+                        drop(stats);
+                        // The caller may have stopped listening for outcomes.
+                        let _ = outcomes.send((number, outcome));
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtp::engine::{Engine, Session};
+
+    /// How a test engine plays and scores.
+    #[derive(Clone, Copy)]
+    struct Script {
+        /// The reply to every genmove.
+        genmove: &'static str,
+        /// The score to claim instead of scoring honestly.
+        final_score: Option<&'static str>,
+        /// The stones the engine lists as dead.
+        dead: &'static str,
+    }
+
+    impl Script {
+        fn new(genmove: &'static str) -> Self {
+            Script {
+                genmove,
+                final_score: None,
+                dead: "",
+            }
+        }
+    }
+
+    /// Connects an engine that follows the script.
+    fn engine(address: SocketAddr, script: Script) -> thread::JoinHandle<()> {
+        let Script {
+            genmove,
+            final_score,
+            dead,
+        } = script;
+        thread::spawn(move || {
+            let mut engine = Engine::new();
+            engine.insert("genmove", move |_args, game| {
+                let vertex = Vertex::from_str(genmove).ok();
+                let _ = game.play(&Move {
+                    player: game.player_turn(),
+                    vertex,
+                });
+                Ok(Some(genmove.to_owned()))
+            });
+            engine.register_tournament_commands();
+            if let Some(final_score) = final_score {
+                engine.insert("final_score", move |_args, _game| {
+                    Ok(Some(final_score.to_owned()))
+                });
+            }
+            engine.insert("final_status_list", move |_args, _game| {
+                Ok(Some(dead.to_owned()))
+            });
+            let mut game = Game::new();
+            let stream = TcpStream::connect(address).unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else { return };
+                if let Some(command) = Command::from_line(&line) {
+                    let response = engine.exec(&mut game, &command).to_string();
+                    if writer.write_all(response.as_bytes()).is_err() {
+                        return;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Plays a match between engines that always make the given moves.
+    fn play(black: &'static str, white: &'static str) -> (Outcome, Vec<Event>) {
+        play_scripts(Script::new(black), Script::new(white))
+    }
+
+    /// Plays a match between engines that follow the scripts.
+    fn play_scripts(black: Script, white: Script) -> (Outcome, Vec<Event>) {
+        play_configured(black, white, |_game_match| {})
+    }
+
+    /// Plays a match between engines that follow the scripts, after configuring the match.
+    fn play_configured<F: FnOnce(&mut Match)>(
+        black: Script,
+        white: Script,
+        configure: F,
+    ) -> (Outcome, Vec<Event>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let black = engine(address, black);
+        let (black_stream, _) = listener.accept().unwrap();
+        let white = engine(address, white);
+        let (white_stream, _) = listener.accept().unwrap();
+
+        let mut game_match = Match::new(
+            Connection::new(black_stream).unwrap(),
+            Connection::new(white_stream).unwrap(),
+            Game::with_board_size(9).unwrap(),
+        );
+        configure(&mut game_match);
+        let spectator = game_match.broadcast().subscribe();
+        let outcome = game_match.play();
+
+        black.join().unwrap();
+        white.join().unwrap();
+        (outcome, spectator.iter().collect())
+    }
+
+    #[test]
+    fn broadcast() {
+        let (outcome, events) = play("pass", "pass");
+        assert_eq!(outcome.end, End::Finished);
+        assert!(outcome.game.is_over());
+        assert!(outcome.disagreements.is_empty());
+        assert!(!outcome.cleaned_up);
+
+        assert_eq!(events.len(), 4);
+        assert!(matches!(events[0], Event::Position(_)));
+        assert!(matches!(
+            events[1],
+            Event::Move(
+                Move {
+                    player: Player::Black,
+                    vertex: None
+                },
+                _
+            )
+        ));
+        assert!(matches!(events[3], Event::End));
+
+        let broadcast = Broadcast::new(Game::with_board_size(9).unwrap().board().clone());
+        let before = broadcast.subscribe();
+        broadcast.publish(&Event::End);
+        let after = broadcast.subscribe();
+        for receiver in [before, after] {
+            let events: Vec<_> = receiver.iter().collect();
+            assert!(matches!(events[..], [Event::Position(_), Event::End]));
+        }
+    }
+
+    #[test]
+    fn score_disagreement() {
+        let (outcome, _) = play_scripts(
+            Script {
+                final_score: Some("w+7.50"),
+                ..Script::new("pass")
+            },
+            Script {
+                final_score: Some("B+1"),
+                ..Script::new("pass")
+            },
+        );
+        assert!(!outcome.cleaned_up);
+        assert_eq!(
+            outcome.disagreements,
+            vec![Disagreement {
+                player: Player::White,
+                command: "final_score".to_owned(),
+                engine: "B+1".to_owned(),
+                referee: "W+7.5".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn agreed_dead_stones_are_removed() {
+        // Black's wall on the D line and White's on the E line, with a dead white stone on B5.
+        let mut game = Game::with_board_size(9).unwrap();
+        let black = (1..=9)
+            .map(|row| format!("D{row}"))
+            .chain(["A1".to_owned()]);
+        let white = ["B5".to_owned()]
+            .into_iter()
+            .chain((1..=9).map(|row| format!("E{row}")));
+        for (black, white) in black.zip(white) {
+            for (player, vertex) in [(Player::Black, black), (Player::White, white)] {
+                game.play(&Move {
+                    player,
+                    vertex: Some(vertex.parse().unwrap()),
+                })
+                .unwrap();
+            }
+        }
+        let script = Script {
+            final_score: Some("W+16.5"),
+            dead: "b5",
+            ..Script::new("pass")
+        };
+        let (outcome, _) = play_configured(script, script, |game_match| game_match.game = game);
+        assert_eq!(outcome.end, End::Finished);
+        assert!(!outcome.cleaned_up);
+        assert_eq!(outcome.disagreements, Vec::new());
+    }
+
+    #[test]
+    fn disputed_dead_stones_are_cleaned_up() {
+        let (outcome, events) = play_scripts(
+            Script {
+                dead: "c3",
+                ..Script::new("pass")
+            },
+            Script::new("pass"),
+        );
+        assert_eq!(outcome.end, End::Finished);
+        assert!(outcome.cleaned_up);
+        // The initial position, two passes, two more passes after resuming and the end.
+        assert_eq!(events.len(), 6);
+        assert_eq!(outcome.disagreements.len(), 1);
+    }
+
+    #[test]
+    fn record() {
+        let path = std::env::temp_dir().join(format!("libgo-record-{}.sgf", std::process::id()));
+        let (outcome, _) = play_configured(Script::new("C3"), Script::new("pass"), |game_match| {
+            game_match.set_record(&path);
+        });
+        assert_eq!(outcome.record_error, None);
+        let engine = format!("libgo {}", crate::gtp::engine::AGENT_VERSION);
+        assert_eq!(outcome.engines, [engine.clone(), engine.clone()]);
+
+        let sgf = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let root = &sgf::parse(&sgf).unwrap()[0].nodes[0];
+        assert_eq!(root.value("PB"), Some(engine.as_str()));
+        assert_eq!(root.value("RE"), Some("W+F"));
+        assert_eq!(root.value("SZ"), Some("9"));
+    }
+
+    #[test]
+    fn showboard() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let engine = thread::spawn({
+            let address = listener.local_addr().unwrap();
+            move || {
+                let stream = TcpStream::connect(address).unwrap();
+                let mut writer = stream.try_clone().unwrap();
+                let mut engine = Engine::new();
+                engine.register_extra_commands();
+                let mut game = Game::with_board_size(5).unwrap();
+                for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                    if let Some(command) = Command::from_line(&line) {
+                        let response = engine.exec(&mut game, &command).to_string();
+                        writer.write_all(response.as_bytes()).unwrap();
+                    }
+                }
+            }
+        });
+
+        let mut connection = Connection::new(listener.accept().unwrap().0).unwrap();
+        connection.send("play b c3").unwrap().unwrap();
+        let board = connection.showboard().unwrap().unwrap();
+        assert_eq!(board.stones(Player::Black), vec![Vertex { x: 2, y: 2 }]);
+        connection.shutdown();
+        engine.join().unwrap();
+    }
+
+    #[test]
+    fn serve_() {
+        let mut session = Session::default();
+        let limits = Limits {
+            max_line_length: 16,
+            max_args: 1,
+            commands_per_second: Some(1000),
+        };
+        let mut output = Vec::new();
+        let input = format!("1 name\n2 play b c3\n3 {}\nquit\nname\n", "x".repeat(20));
+        serve(input.as_bytes(), &mut output, &mut session, &limits).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let responses: Vec<_> = output.split("\r\n\r\n").collect();
+        assert_eq!(responses.len(), 5);
+        assert!(responses[0].starts_with("=1 "));
+        assert_eq!(responses[1], "?2 more than 1 arguments");
+        assert_eq!(responses[2], "? line longer than 16 bytes");
+        assert_eq!(responses[3], "= ");
+    }
+
+    #[test]
+    fn illegal_move_forfeits() {
+        let (outcome, _) = play("A1", "pass");
+        assert!(matches!(outcome.end, End::Forfeited(Player::Black, _)));
+        assert_eq!(outcome.result(), Some(GameResult::Forfeit(Player::White)));
+        assert_eq!(outcome.game.board().stones(Player::Black).len(), 1);
+        let [black, white] = outcome.stats;
+        assert_eq!((black.matches, black.illegal_moves), (1, 1));
+        assert_eq!(black.latency.total(), 2);
+        assert_eq!((white.illegal_moves, white.latency.total()), (0, 1));
+    }
+
+    #[test]
+    fn client_stats() {
+        let mut latency = LatencyHistogram::default();
+        for millis in [5, 50, 60, 20_000] {
+            latency.record(Duration::from_millis(millis));
+        }
+        assert_eq!(latency.counts(), [1, 2, 0, 0, 1]);
+        let mut stats = ClientStats {
+            matches: 1,
+            latency,
+            disconnects: 1,
+            illegal_moves: 0,
+        };
+        stats.merge(&stats.clone());
+        assert_eq!(stats.latency.total(), 8);
+        assert_eq!((stats.matches, stats.disconnects), (2, 2));
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::game::player::Player;
+use crate::game::vertex::Vertex;
+use crate::game::Game;
+
+/// The number of the engine's own first stones kept as the opening it chose.
+const OPENING_STONES: usize = 4;
+
+/// How a game against an opponent ended for the engine.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Outcome {
+    Won,
+    Lost,
+    Drew,
+}
+
+/// What the engine remembers about its games against one opponent.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OpponentRecord {
+    /// The games the engine won.
+    pub wins: usize,
+    /// The games the engine lost.
+    pub losses: usize,
+    /// The games that were drawn or voided.
+    pub draws: usize,
+    /// The openings the engine chose, as its first few stones, oldest game first.
+    pub openings: Vec<Vec<Vertex>>,
+}
+
+impl OpponentRecord {
+    /// Returns the number of games played against the opponent.
+    #[must_use]
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+
+    fn add(&mut self, outcome: Outcome, opening: Vec<Vertex>) {
+        match outcome {
+            Outcome::Won => self.wins += 1,
+            Outcome::Lost => self.losses += 1,
+            Outcome::Drew => self.draws += 1,
+        }
+        self.openings.push(opening);
+    }
+}
+
+/// The results and openings of an engine's games, kept by opponent name so a bot that meets the
+/// same players again and again can vary its play against them. A store opened on a file keeps
+/// it up to date, one `won D4,Q16 name` line per game, with `-` for no opening.
+#[derive(Debug, Default)]
+pub struct OpponentStore {
+    path: Option<PathBuf>,
+    opponents: HashMap<String, OpponentRecord>,
+}
+
+impl OpponentStore {
+    /// Returns an empty store kept only in memory.
+    #[must_use]
+    pub fn new() -> Self {
+        OpponentStore::default()
+    }
+
+    /// Opens the store kept in the file, which is created when the first game is recorded. A
+    /// partial last line, left by a crash while a game was recorded, is cut off the file.
+    ///
+    /// # Errors
+    ///
+    /// The file couldn't be read or has a line that isn't a game.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut store = OpponentStore {
+            path: Some(path.to_owned()),
+            opponents: HashMap::new(),
+        };
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        // Cut off so the next game recorded isn't appended onto it.
+        let complete = text.rfind('\n').map_or(0, |end| end + 1);
+        if complete < text.len() {
+            OpenOptions::new()
+                .write(true)
+                .open(path)?
+                .set_len(complete as u64)?;
+        }
+        for line in text[..complete].lines() {
+            let (outcome, opening, name) = parse_line(line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: invalid game {line:?}", path.display()),
+                )
+            })?;
+            store
+                .opponents
+                .entry(name.to_owned())
+                .or_default()
+                .add(outcome, opening);
+        }
+        Ok(store)
+    }
+
+    /// Returns what is remembered about the opponent, if the engine has played them.
+    #[must_use]
+    pub fn get(&self, opponent: &str) -> Option<&OpponentRecord> {
+        self.opponents.get(opponent)
+    }
+
+    /// Records the finished game the engine played as the player against the opponent, with
+    /// the engine's first stones as its opening.
+    ///
+    /// # Errors
+    ///
+    /// The game has no result, the name is empty or has a line break, or the file couldn't be
+    /// written.
+    pub fn record_game(&mut self, opponent: &str, player: Player, game: &Game) -> io::Result<()> {
+        let invalid_input = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, message);
+        if opponent.is_empty() || opponent.contains(['\n', '\r']) {
+            return Err(invalid_input("invalid opponent name"));
+        }
+        let result = game
+            .result()
+            .ok_or_else(|| invalid_input("the game has no result"))?;
+        let outcome = match result.winner() {
+            Some(winner) if winner == player => Outcome::Won,
+            Some(_) => Outcome::Lost,
+            None => Outcome::Drew,
+        };
+        let opening: Vec<_> = game
+            .moves()
+            .filter(|mov| mov.player == player)
+            .filter_map(|mov| mov.vertex)
+            .take(OPENING_STONES)
+            .collect();
+
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            file.write_all(format_line(outcome, &opening, opponent).as_bytes())?;
+        }
+        self.opponents
+            .entry(opponent.to_owned())
+            .or_default()
+            .add(outcome, opening);
+        Ok(())
+    }
+
+    /// Returns the candidate first move the engine has opened with least often against the
+    /// opponent, the earliest candidate on a tie, or None if there are no candidates.
+    #[must_use]
+    pub fn least_played_opening(&self, opponent: &str, candidates: &[Vertex]) -> Option<Vertex> {
+        let openings = self
+            .get(opponent)
+            .map_or(&[][..], |record| &record.openings);
+        candidates.iter().copied().min_by_key(|&candidate| {
+            openings
+                .iter()
+                .filter(|opening| opening.first() == Some(&candidate))
+                .count()
+        })
+    }
+}
+
+fn format_line(outcome: Outcome, opening: &[Vertex], name: &str) -> String {
+    let outcome = match outcome {
+        Outcome::Won => "won",
+        Outcome::Lost => "lost",
+        Outcome::Drew => "drew",
+    };
+    let opening: Vec<_> = opening.iter().map(ToString::to_string).collect();
+    let opening = if opening.is_empty() {
+        "-".to_owned()
+    } else {
+        opening.join(",")
+    };
+    format!("{outcome} {opening} {name}\n")
+}
+
+fn parse_line(line: &str) -> Option<(Outcome, Vec<Vertex>, &str)> {
+    let mut fields = line.splitn(3, ' ');
+    let outcome = match fields.next()? {
+        "won" => Outcome::Won,
+        "lost" => Outcome::Lost,
+        "drew" => Outcome::Drew,
+        _ => return None,
+    };
+    let opening = match fields.next()? {
+        "-" => Vec::new(),
+        opening => opening
+            .split(',')
+            .map(|vertex| Vertex::from_str(vertex).ok())
+            .collect::<Option<_>>()?,
+    };
+    let name = fields.next().filter(|name| !name.is_empty())?;
+    Some((outcome, opening, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::board::Move;
+
+    fn game(opening: &str, loser: Player) -> Game {
+        let mut game = Game::with_board_size(9).unwrap();
+        game.play(&Move {
+            player: Player::Black,
+            vertex: Some(opening.parse().unwrap()),
+        })
+        .unwrap();
+        game.resign(loser);
+        game
+    }
+
+    #[test]
+    fn store() {
+        let path = std::env::temp_dir().join(format!("libgo-opponents-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut store = OpponentStore::open(&path).unwrap();
+        let name = "mirror bot";
+        store
+            .record_game(name, Player::Black, &game("E5", Player::White))
+            .unwrap();
+        store
+            .record_game(name, Player::Black, &game("C3", Player::Black))
+            .unwrap();
+        assert!(store
+            .record_game(name, Player::Black, &Game::with_board_size(9).unwrap())
+            .is_err());
+
+        // A crash while a game was recorded.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"won C3,D").unwrap();
+        drop(file);
+
+        let mut store = OpponentStore::open(&path).unwrap();
+        store
+            .record_game("other bot", Player::White, &game("E5", Player::Black))
+            .unwrap();
+        let store = OpponentStore::open(&path).unwrap();
+        assert_eq!(store.get("other bot").unwrap().wins, 1);
+        let record = store.get(name).unwrap();
+        assert_eq!((record.wins, record.losses, record.games()), (1, 1, 2));
+        let candidates = ["C3", "E5", "G7"].map(|vertex| vertex.parse().unwrap());
+        assert_eq!(
+            store.least_played_opening(name, &candidates),
+            Some(candidates[2])
+        );
+        assert_eq!(
+            store.least_played_opening("someone else", &candidates),
+            Some(candidates[0])
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
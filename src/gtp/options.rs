@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::str::FromStr;
+
+use crate::game::player::Player;
+use crate::game::vertex::Vertex;
+use crate::game::Game;
+
+/// When a searching engine passes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PassPolicy {
+    /// Only when it has no move left that doesn't fill one of its own eyes.
+    NoMovesLeft,
+    /// Also right after the opponent passes, if ending the game there wins it on the score as the
+    /// board stands. Servers that expect dead stones to be captured before the game ends shouldn't
+    /// use this.
+    WhenWinning,
+}
+
+impl fmt::Display for PassPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PassPolicy::NoMovesLeft => write!(f, "no_moves_left"),
+            PassPolicy::WhenWinning => write!(f, "when_winning"),
+        }
+    }
+}
+
+impl FromStr for PassPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "no_moves_left" => Ok(PassPolicy::NoMovesLeft),
+            "when_winning" => Ok(PassPolicy::WhenWinning),
+            _ => Err(format!("unknown pass policy: {s}")),
+        }
+    }
+}
+
+/// The etiquette of a server, which limits the moves a searching engine chooses.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ServerProfile {
+    /// Any legal move.
+    Raw,
+    /// KGS: don't pass before half the board has been played, and don't fill in the engine's own
+    /// territory.
+    Kgs,
+    /// OGS: don't fill in the engine's own territory.
+    Ogs,
+}
+
+impl fmt::Display for ServerProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerProfile::Raw => write!(f, "raw"),
+            ServerProfile::Kgs => write!(f, "kgs"),
+            ServerProfile::Ogs => write!(f, "ogs"),
+        }
+    }
+}
+
+impl FromStr for ServerProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(ServerProfile::Raw),
+            "kgs" => Ok(ServerProfile::Kgs),
+            "ogs" => Ok(ServerProfile::Ogs),
+            _ => Err(format!("unknown server profile: {s}")),
+        }
+    }
+}
+
+impl ServerProfile {
+    /// Whether the profile lets a player pass in the game as it stands.
+    #[must_use]
+    pub fn allows_pass(self, game: &Game) -> bool {
+        match self {
+            ServerProfile::Raw | ServerProfile::Ogs => true,
+            ServerProfile::Kgs => 2 * game.moves().count() >= game.board().size().pow(2),
+        }
+    }
+
+    /// Whether the profile lets the player play on the vertex, given the owners of the points
+    /// estimated with `estimate::owners`, which can be worked out once for every move checked in
+    /// a position. Filling in territory means playing on an empty point that it and every point
+    /// next to it are estimated to belong to the player.
+    #[must_use]
+    pub fn allows_move<S: BuildHasher>(
+        self,
+        game: &Game,
+        owners: &HashMap<Vertex, Player, S>,
+        player: Player,
+        vertex: Vertex,
+    ) -> bool {
+        match self {
+            ServerProfile::Raw => true,
+            ServerProfile::Kgs | ServerProfile::Ogs => {
+                let territory = |vertex: &Vertex| owners.get(vertex) == Some(&player);
+                !(territory(&vertex) && game.board().neighbors(vertex).iter().all(territory))
+            }
+        }
+    }
+}
+
+/// How a searching engine plays, e.g. to follow the etiquette of the server it plays on. The
+/// options can be read from a config file of `name = value` lines, where `#` starts a comment,
+/// and written back out the same way.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EngineOptions {
+    /// The number of random games played out to choose each move.
+    pub playouts: usize,
+    /// Resign when the best move wins less than this fraction of its playouts, or never if None.
+    pub resign_below: Option<f64>,
+    /// The number of moves that must have been played before resigning.
+    pub resign_after_moves: usize,
+    /// When to pass.
+    pub pass: PassPolicy,
+    /// Whether to break the mirror when the opponent plays mirror Go.
+    pub anti_mirror: bool,
+    /// The etiquette of the server played on.
+    pub profile: ServerProfile,
+    /// Whether to keep searching on the opponent's time, from the reply the last search expects.
+    pub ponder: bool,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        EngineOptions {
+            playouts: 1000,
+            resign_below: Some(0.1),
+            resign_after_moves: 20,
+            pass: PassPolicy::NoMovesLeft,
+            anti_mirror: true,
+            profile: ServerProfile::Raw,
+            ponder: false,
+        }
+    }
+}
+
+impl EngineOptions {
+    /// The names of the options.
+    pub const NAMES: [&'static str; 7] = [
+        "playouts",
+        "resign_below",
+        "resign_after_moves",
+        "pass",
+        "anti_mirror",
+        "profile",
+        "ponder",
+    ];
+
+    /// Sets the named option from its value written as text. `resign_below` can be `never`.
+    ///
+    /// # Errors
+    ///
+    /// There is no option with the name or the value is invalid.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("invalid value for {name}: {value}");
+        match name {
+            "playouts" => self.playouts = value.parse().map_err(|_| invalid())?,
+            "resign_below" => {
+                self.resign_below = match value {
+                    "never" => None,
+                    _ => match value.parse() {
+                        Ok(rate) if (0.0..=1.0).contains(&rate) => Some(rate),
+                        _ => return Err(invalid()),
+                    },
+                };
+            }
+            "resign_after_moves" => {
+                self.resign_after_moves = value.parse().map_err(|_| invalid())?;
+            }
+            "pass" => self.pass = value.parse()?,
+            "anti_mirror" => self.anti_mirror = value.parse().map_err(|_| invalid())?,
+            "profile" => self.profile = value.parse()?,
+            "ponder" => self.ponder = value.parse().map_err(|_| invalid())?,
+            _ => return Err(format!("unknown option: {name}")),
+        }
+        Ok(())
+    }
+
+    /// Returns the named option's value written as text, or None if there is no such option.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<String> {
+        match name {
+            "playouts" => Some(self.playouts.to_string()),
+            "resign_below" => Some(
+                self.resign_below
+                    .map_or_else(|| "never".to_owned(), |rate| rate.to_string()),
+            ),
+            "resign_after_moves" => Some(self.resign_after_moves.to_string()),
+            "pass" => Some(self.pass.to_string()),
+            "anti_mirror" => Some(self.anti_mirror.to_string()),
+            "profile" => Some(self.profile.to_string()),
+            "ponder" => Some(self.ponder.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for EngineOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in EngineOptions::NAMES {
+            writeln!(f, "{name} = {}", self.get(name).unwrap_or_default())?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for EngineOptions {
+    type Err = String;
+
+    /// Reads options from the lines of a config file. Options that aren't given keep their
+    /// defaults.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut options = EngineOptions::default();
+        for (number, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected name = value", number + 1))?;
+            options
+                .set(name.trim(), value.trim())
+                .map_err(|err| format!("line {}: {err}", number + 1))?;
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::board::Move;
+    use crate::game::estimate;
+
+    #[test]
+    fn server_profile() {
+        let mut game = Game::with_board_size(5).unwrap();
+        for x in 0..5 {
+            game.play(&Move {
+                player: Player::Black,
+                vertex: Some(Vertex { x, y: 2 }),
+            })
+            .unwrap();
+            game.play(&Move {
+                player: Player::White,
+                vertex: Some(Vertex { x, y: 3 }),
+            })
+            .unwrap();
+        }
+        let corner = Vertex { x: 0, y: 0 };
+        let owners = estimate::owners(&game.territory_estimate());
+        assert!(ServerProfile::Raw.allows_move(&game, &owners, Player::Black, corner));
+        assert!(!ServerProfile::Ogs.allows_move(&game, &owners, Player::Black, corner));
+        assert!(ServerProfile::Ogs.allows_move(&game, &owners, Player::White, corner));
+
+        assert!(!ServerProfile::Kgs.allows_pass(&game));
+        assert!(ServerProfile::Ogs.allows_pass(&game));
+        for (player, y) in [(Player::Black, 0), (Player::White, 4), (Player::Black, 1)] {
+            game.play(&Move {
+                player,
+                vertex: Some(Vertex { x: 1, y }),
+            })
+            .unwrap();
+        }
+        assert!(ServerProfile::Kgs.allows_pass(&game));
+    }
+
+    #[test]
+    fn engine_options() {
+        let options: EngineOptions = "# Etiquette for a server that wants games played out.
+            playouts = 200
+            resign_below = never
+            pass = no_moves_left
+            anti_mirror = false
+            profile = kgs
+            ponder = true"
+            .parse()
+            .unwrap();
+        assert_eq!(options.playouts, 200);
+        assert_eq!(options.resign_below, None);
+        assert_eq!(options.resign_after_moves, 20);
+        assert!(!options.anti_mirror);
+        assert_eq!(options.profile, ServerProfile::Kgs);
+        assert!(options.ponder);
+        assert_eq!(options.to_string().parse(), Ok(options));
+
+        let mut options = EngineOptions::default();
+        options.set("resign_below", "0.05").unwrap();
+        assert_eq!(options.get("resign_below").as_deref(), Some("0.05"));
+        assert!(options.set("resign_below", "2").is_err());
+        assert!(options.set("pass", "sometimes").is_err());
+        assert!(options.set("komi", "7.5").is_err());
+        assert!(options.set("profile", "igs").is_err());
+        assert_eq!(
+            "playouts: 10".parse::<EngineOptions>(),
+            Err("line 1: expected name = value".to_owned())
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::Write;
+
+use crate::gtp::command::Command;
+use crate::gtp::engine::GtpEngine;
+use crate::gtp::response::Response;
+
+type Rewrite = Box<dyn FnMut(Command) -> Command>;
+
+/// Sits between a controller and an engine, passing the controller's commands through to the
+/// engine and its responses back. On the way the commands can be logged, rewritten, e.g. with
+/// `kgs_time_settings_to_standard` for an engine that only knows standard GTP, or preceded by
+/// commands of the proxy's own. A proxy is itself an engine, so it can be served with
+/// `net::serve` and proxies can be stacked.
+pub struct Proxy<E> {
+    engine: E,
+    rewrites: Vec<Rewrite>,
+    injected: VecDeque<Command>,
+    log: Option<Box<dyn Write>>,
+}
+
+impl<E: GtpEngine> Proxy<E> {
+    /// Returns a proxy that passes commands through to the engine unchanged.
+    #[must_use]
+    pub fn new(engine: E) -> Self {
+        Proxy {
+            engine,
+            rewrites: Vec::new(),
+            injected: VecDeque::new(),
+            log: None,
+        }
+    }
+
+    /// Returns the engine behind the proxy.
+    #[must_use]
+    pub fn engine(&self) -> &E {
+        &self.engine
+    }
+
+    /// Returns the engine behind the proxy, dropping the proxy.
+    #[must_use]
+    pub fn into_inner(self) -> E {
+        self.engine
+    }
+
+    /// Logs each command sent to the engine and its response, in the form they're sent over GTP.
+    pub fn set_log<W: Write + 'static>(&mut self, log: W) {
+        self.log = Some(Box::new(log));
+    }
+
+    /// Rewrites every command before it's sent to the engine, after the rewrites added before.
+    /// The controller gets the response with the id of the command it sent.
+    pub fn rewrite<F>(&mut self, rewrite: F)
+    where
+        F: 'static + FnMut(Command) -> Command,
+    {
+        self.rewrites.push(Box::new(rewrite));
+    }
+
+    /// Sends the command to the engine before the next command from the controller. Its response
+    /// is only logged.
+    pub fn inject(&mut self, command: Command) {
+        self.injected.push_back(command);
+    }
+
+    fn send(&mut self, command: &Command) -> Response {
+        let response = self.engine.exec(command);
+        if let Some(log) = &mut self.log {
+            // A log that can't be written to shouldn't stop the proxy.
+            let _ = write!(log, "{command}\n{response}");
+        }
+        response
+    }
+}
+
+impl<E: GtpEngine> GtpEngine for Proxy<E> {
+    fn exec(&mut self, command: &Command) -> Response {
+        while let Some(injected) = self.injected.pop_front() {
+            self.send(&injected);
+        }
+        let mut rewritten = command.clone();
+        for rewrite in &mut self.rewrites {
+            rewritten = rewrite(rewritten);
+        }
+        Response {
+            id: command.id,
+            ..self.send(&rewritten)
+        }
+    }
+}
+
+impl<E: fmt::Debug> fmt::Debug for Proxy<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Proxy")
+            .field("engine", &self.engine)
+            .field("rewrites", &self.rewrites.len())
+            .field("injected", &self.injected)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Rewrites `kgs-time_settings` as the standard `time_settings`, leaving other commands as they
+/// are. Byo-yomi is sent as Canadian overtime of one stone a period, as `time_settings` has no
+/// periods, and no time limit as byo-yomi with time but no stones.
+#[must_use]
+pub fn kgs_time_settings_to_standard(command: Command) -> Command {
+    if command.name != "kgs-time_settings" {
+        return command;
+    }
+    let args = match command.args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["none", ..] => ["0", "1", "0"].map(ToOwned::to_owned),
+        ["absolute", main_time, ..] => [main_time, "0", "0"].map(ToOwned::to_owned),
+        ["byoyomi", main_time, period_time, _, ..] => {
+            [main_time, period_time, "1"].map(ToOwned::to_owned)
+        }
+        ["canadian", main_time, period_time, stones, ..] => {
+            [main_time, period_time, stones].map(ToOwned::to_owned)
+        }
+        // The engine may know what to make of it.
+        _ => return command,
+    };
+    Command {
+        id: command.id,
+        name: "time_settings".to_owned(),
+        args: args.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Clock;
+    use crate::gtp::engine::Session;
+    use std::time::Duration;
+
+    fn command(line: &str) -> Command {
+        Command::from_line(line).unwrap()
+    }
+
+    #[test]
+    fn proxy() {
+        let mut session = Session::default();
+        session.engine.register_extra_commands();
+        let mut proxy = Proxy::new(session);
+        proxy.rewrite(kgs_time_settings_to_standard);
+        proxy.inject(command("play b c3"));
+
+        let response = proxy.exec(&command("7 kgs-time_settings byoyomi 600 30 5"));
+        assert_eq!(response.id, Some(7));
+        assert_eq!(response.result, Ok(None));
+        let session = proxy.engine();
+        assert_eq!(session.game.moves().count(), 1);
+        assert_eq!(
+            session.game.time_settings(),
+            Clock::Canadian {
+                main_time: Duration::from_secs(600),
+                period_time: Duration::from_secs(30),
+                stones: 1,
+            }
+        );
+        assert!(proxy
+            .exec(&command("kgs-time_settings hourglass"))
+            .result
+            .is_err());
+    }
+
+    #[test]
+    fn kgs_time_settings() {
+        for (kgs, standard) in [
+            ("kgs-time_settings none", "time_settings 0 1 0"),
+            ("kgs-time_settings absolute 300", "time_settings 300 0 0"),
+            (
+                "kgs-time_settings canadian 60 300 25",
+                "time_settings 60 300 25",
+            ),
+            (
+                "1 kgs-time_settings byoyomi 60 30 5",
+                "1 time_settings 60 30 1",
+            ),
+            (
+                "kgs-time_settings byoyomi 60",
+                "kgs-time_settings byoyomi 60",
+            ),
+            ("play b c3", "play b c3"),
+        ] {
+            let rewritten = kgs_time_settings_to_standard(command(kgs));
+            assert_eq!(rewritten.to_string(), standard);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::str::FromStr;
 
 const EOL: &str = "\r\n";
 
@@ -6,7 +7,7 @@ const EOL: &str = "\r\n";
 pub type CommandResult = Result<Option<String>, String>;
 
 /// A Go Text Protocol response.
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct Response {
     /// A sequence id.
     pub id: Option<u32>,
@@ -25,3 +26,92 @@ impl fmt::Display for Response {
         }
     }
 }
+
+impl Response {
+    /// Parses a response from raw bytes, replacing invalid UTF-8 with U+FFFD.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the bytes are not a GTP response.
+    pub fn from_bytes(response: &[u8]) -> Result<Self, String> {
+        Self::from_str(&String::from_utf8_lossy(response))
+    }
+}
+
+impl FromStr for Response {
+    type Err = String;
+
+    /// Parses a response sent by an engine, such as `=1 C3`, ignoring the empty line that ends it.
+    fn from_str(response: &str) -> Result<Self, Self::Err> {
+        // A controller removes control characters other than HT and LF, then converts HT to SPACE.
+        let response: String = response
+            .chars()
+            .filter(|&c| c == '\t' || c == '\n' || !c.is_control())
+            .map(|c| if c == '\t' { ' ' } else { c })
+            .collect();
+        let response = response.trim_start_matches('\n').trim_end();
+
+        let success = match response.chars().next() {
+            Some('=') => true,
+            Some('?') => false,
+            _ => return Err(format!("invalid response {response:?}")),
+        };
+        let rest = &response[1..];
+        let text = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+        let id = match &rest[..rest.len() - text.len()] {
+            "" => None,
+            id => Some(id.parse().map_err(|_| format!("invalid id {id:?}"))?),
+        };
+        if !text.is_empty() && !text.starts_with([' ', '\n']) {
+            return Err(format!("invalid response {response:?}"));
+        }
+
+        let text = text.strip_prefix(' ').unwrap_or(text);
+        let result = match (success, text.trim().is_empty()) {
+            (true, true) => Ok(None),
+            (true, false) => Ok(Some(text.to_owned())),
+            (false, _) => Err(text.to_owned()),
+        };
+        Ok(Response { id, result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str() {
+        for response in [
+            Response {
+                id: Some(3),
+                result: Ok(Some("C3".to_owned())),
+            },
+            Response {
+                id: None,
+                result: Ok(None),
+            },
+            Response {
+                id: None,
+                result: Err("illegal move".to_owned()),
+            },
+        ] {
+            assert_eq!(response.to_string().parse(), Ok(response));
+        }
+        assert_eq!(
+            "=\tpass\n\n".parse::<Response>().map(|r| r.result),
+            Ok(Ok(Some("pass".to_owned())))
+        );
+        assert!("C3".parse::<Response>().is_err());
+        assert!("=1x".parse::<Response>().is_err());
+        assert!("".parse::<Response>().is_err());
+        assert!("=99999999999 x".parse::<Response>().is_err());
+        assert_eq!(
+            Response::from_bytes(b"? \xff"),
+            Ok(Response {
+                id: None,
+                result: Err("\u{fffd}".to_owned()),
+            })
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt;
+
+use crate::gtp::command::Command;
+use crate::gtp::engine::GtpEngine;
+use crate::gtp::response::CommandResult;
+
+/// The commands every GTP engine is required to implement.
+const REQUIRED_COMMANDS: [&str; 11] = [
+    "protocol_version",
+    "name",
+    "version",
+    "known_command",
+    "list_commands",
+    "quit",
+    "boardsize",
+    "clear_board",
+    "komi",
+    "play",
+    "genmove",
+];
+
+/// A command that didn't behave as the GTP specification requires.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    /// The command sent.
+    pub command: String,
+    /// What was wrong with the response.
+    pub problem: String,
+}
+
+/// The results of running the conformance suite against an engine.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// The number of commands sent.
+    pub checks: usize,
+    /// The commands whose responses broke the specification.
+    pub violations: Vec<Violation>,
+}
+
+impl Report {
+    /// Returns whether the engine passed every check.
+    #[must_use]
+    pub fn is_conformant(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} of {} checks failed",
+            self.violations.len(),
+            self.checks
+        )?;
+        for violation in &self.violations {
+            writeln!(f, "{}: {}", violation.command, violation.problem)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sends commands to an engine and records the violations.
+struct Suite<'a, E> {
+    engine: &'a mut E,
+    next_id: u32,
+    report: Report,
+}
+
+impl<E: GtpEngine> Suite<'_, E> {
+    /// Sends a command with a fresh id, checking that the id is echoed back.
+    fn send(&mut self, line: &str) -> CommandResult {
+        self.next_id += 1;
+        let command = Command::from_line(&format!("{} {line}", self.next_id))
+            .expect("suite commands are not empty");
+        let response = self.engine.exec(&command);
+        self.report.checks += 1;
+        if response.id != command.id {
+            self.violate(line, format!("id {:?} was not echoed", command.id));
+        }
+        response.result
+    }
+
+    fn violate(&mut self, command: &str, problem: String) {
+        self.report.violations.push(Violation {
+            command: command.to_owned(),
+            problem,
+        });
+    }
+
+    fn expect_success(&mut self, line: &str) -> Option<String> {
+        match self.send(line) {
+            Ok(reply) => reply,
+            Err(err) => {
+                self.violate(line, format!("failed: {err}"));
+                None
+            }
+        }
+    }
+
+    fn expect_failure(&mut self, line: &str) {
+        if self.send(line).is_ok() {
+            self.violate(line, "succeeded but should have failed".to_owned());
+        }
+    }
+
+    fn expect_reply(&mut self, line: &str, expected: &str) {
+        if let Some(reply) = self.expect_success(line) {
+            if reply.trim() != expected {
+                self.violate(line, format!("replied {reply:?}, expected {expected:?}"));
+            }
+        }
+    }
+
+    fn run(&mut self) {
+        self.expect_reply("protocol_version", "2");
+        self.expect_success("name");
+        self.expect_success("version");
+
+        let commands = self.expect_success("list_commands").unwrap_or_default();
+        let commands: Vec<_> = commands.split_whitespace().collect();
+        for required in REQUIRED_COMMANDS {
+            if !commands.contains(&required) {
+                self.violate("list_commands", format!("{required} is missing"));
+            }
+            self.expect_reply(&format!("known_command {required}"), "true");
+        }
+        self.expect_reply("known_command dlc-no_such_command", "false");
+        self.expect_failure("dlc-no_such_command");
+
+        // Without an id, none may be echoed.
+        let response = self.engine.exec(&Command {
+            id: None,
+            name: "name".to_owned(),
+            args: Vec::new(),
+        });
+        self.report.checks += 1;
+        if let Some(id) = response.id {
+            self.violate("name", format!("echoed id {id} that was not sent"));
+        }
+
+        self.expect_failure("boardsize 0");
+        self.expect_failure("boardsize nineteen");
+        self.expect_success("boardsize 19");
+        self.expect_success("boardsize 9");
+        self.expect_success("clear_board");
+
+        self.expect_success("komi 6.5");
+        self.expect_success("komi -3");
+        self.expect_failure("komi seven");
+
+        self.expect_success("play b C3");
+        self.expect_failure("play w C3");
+        self.expect_failure("play b Z99");
+        self.expect_failure("play purple D4");
+        self.expect_success("play w pass");
+
+        if commands.contains(&"undo") {
+            self.expect_success("undo");
+            self.expect_success("undo");
+            self.expect_success("play w C3");
+            self.expect_success("clear_board");
+            self.expect_failure("undo");
+        }
+
+        self.expect_success("clear_board");
+        if let Some(reply) = self.expect_success("genmove b") {
+            let reply = reply.trim().to_lowercase();
+            if reply != "pass" && reply != "resign" && self.send(&format!("play w {reply}")).is_ok()
+            {
+                self.violate("genmove b", format!("{reply} was not played on the board"));
+            }
+        }
+    }
+}
+
+/// Runs the required GTP commands against an engine and reports how its responses break the
+/// specification: the bounds of `boardsize`, parsing `komi`, the semantics of `play` and `undo`
+/// and echoing command ids. The engine is left in an arbitrary state, but is not sent `quit`.
+pub fn conformance<E: GtpEngine>(engine: &mut E) -> Report {
+    let mut suite = Suite {
+        engine,
+        next_id: 0,
+        report: Report::default(),
+    };
+    suite.run();
+    suite.report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtp::engine::Session;
+    use crate::gtp::response::Response;
+
+    /// An engine that forgets ids and accepts any komi.
+    struct Sloppy(Session);
+
+    impl GtpEngine for Sloppy {
+        fn exec(&mut self, command: &Command) -> Response {
+            let mut response = self.0.exec(command);
+            response.id = None;
+            if command.name == "komi" {
+                response.result = Ok(None);
+            }
+            response
+        }
+    }
+
+    #[test]
+    fn conformance_() {
+        let mut session = Session::default();
+        session.engine.register_all_commands();
+        let report = conformance(&mut session);
+        assert!(report.is_conformant(), "{report}");
+
+        let report = conformance(&mut Sloppy(Session::default()));
+        assert!(report
+            .violations
+            .iter()
+            .any(|violation| violation.command == "komi seven"));
+        assert!(report
+            .violations
+            .iter()
+            .any(|violation| violation.problem.contains("not echoed")));
+    }
+}
@@ -0,0 +1,1102 @@
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::game::board::Move;
+use crate::game::numeric;
+use crate::game::player::Player;
+use crate::game::sgf::{self, GameTree};
+use crate::game::vertex::Vertex;
+use crate::game::{Game, GameResult};
+use crate::gtp::net::{Connection, Event, Match};
+
+/// The file in a state directory listing the pairings, one `black white configuration opening`
+/// line each, with `-` for no opening.
+const PAIRINGS_FILE: &str = "pairings";
+/// The file in a state directory listing the finished games, one `game result` line each.
+const RESULTS_FILE: &str = "results";
+/// How long a client asking for the status has to send its request.
+const STATUS_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A game between two entrants, given by their index in the tournament's entrants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pairing {
+    /// The entrant playing Black.
+    pub black: usize,
+    /// The entrant playing White.
+    pub white: usize,
+    /// The index of the configuration the game is played with.
+    pub configuration: usize,
+    /// The index of the opening the game starts from, if any.
+    pub opening: Option<usize>,
+}
+
+/// The results of the finished games of one configuration.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Standings {
+    /// The number of games finished.
+    pub games: usize,
+    /// The number of games each entrant won.
+    pub wins: Vec<usize>,
+    /// The number of games Black won.
+    pub black_wins: usize,
+    /// The number of games White won.
+    pub white_wins: usize,
+}
+
+/// The results of the finished pairs of games between two entrants that played the same
+/// configuration and opening once with each color.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PairedStandings {
+    /// The entrant listed first.
+    pub first: usize,
+    /// The entrant listed second.
+    pub second: usize,
+    /// The number of pairs with both games finished.
+    pub pairs: usize,
+    /// The pairs where the first entrant won both games.
+    pub first_won_both: usize,
+    /// The pairs where the second entrant won both games.
+    pub second_won_both: usize,
+    /// The pairs where each entrant won one game, e.g. the one with the better color.
+    pub split: usize,
+}
+
+/// How far a running tournament has got, sent after each game finishes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Progress {
+    /// The names of the entrants.
+    pub entrants: Vec<String>,
+    /// The number of finished games.
+    pub finished: usize,
+    /// The number of games in the tournament.
+    pub total: usize,
+    /// The number of games each entrant won against each other, by the index of the winner and
+    /// then the loser.
+    pub crosstable: Vec<Vec<usize>>,
+    /// The time the rest of the games are expected to take, going by the games played so far.
+    pub eta: Option<Duration>,
+}
+
+impl Progress {
+    /// Returns the progress as a JSON object, with the ETA in seconds or null.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let entrants: Vec<String> = self.entrants.iter().map(|name| json_string(name)).collect();
+        let rows: Vec<String> = self
+            .crosstable
+            .iter()
+            .map(|row| {
+                let wins: Vec<String> = row.iter().map(ToString::to_string).collect();
+                format!("[{}]", wins.join(","))
+            })
+            .collect();
+        let eta = self.eta.map_or("null".to_owned(), numeric::format_seconds);
+        format!(
+            concat!(
+                r#"{{"entrants":[{}],"finished":{},"total":{},"#,
+                r#""crosstable":[{}],"eta":{}}}"#
+            ),
+            entrants.join(","),
+            self.finished,
+            self.total,
+            rows.join(","),
+            eta,
+        )
+    }
+}
+
+/// Quotes a string for JSON.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", u32::from(c));
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Serves the latest progress received as JSON to anything that connects over HTTP, e.g. a
+/// browser or `curl`, so a long tournament can be watched from elsewhere. Each client is answered
+/// on its own thread, so one slow to send its request doesn't hold up the others. Runs forever,
+/// until accepting a connection fails.
+///
+/// # Errors
+///
+/// If accepting a connection fails.
+pub fn serve_status(listener: &TcpListener, progress: &Receiver<Progress>) -> io::Result<()> {
+    let mut latest = Progress::default();
+    loop {
+        let (stream, _) = listener.accept()?;
+        if let Some(progress) = progress.try_iter().last() {
+            latest = progress;
+        }
+        let body = latest.to_json();
+        // The client may have gone already.
+        thread::spawn(move || send_status(stream, &body));
+    }
+}
+
+/// Reads the request head, whatever it asks for, and sends the body as the response.
+fn send_status(mut stream: TcpStream, body: &str) -> io::Result<()> {
+    stream.set_read_timeout(Some(STATUS_READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    while reader.read_line(&mut line).map_or(false, |read| read > 0) {
+        if line.trim().is_empty() {
+            break;
+        }
+        line.clear();
+    }
+    write!(
+        stream,
+        "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Which hypothesis a sequential probability ratio test accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hypothesis {
+    /// The first entrant is at most `elo0` stronger than the second.
+    Null,
+    /// The first entrant is at least `elo1` stronger than the second.
+    Alternative,
+}
+
+/// A sequential probability ratio test (SPRT) of whether one entrant is stronger than another,
+/// which can be checked after every game so a comparison stops as soon as the results are
+/// significant. Draws count as half a win and half a loss.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprt {
+    elo0: f64,
+    elo1: f64,
+    lower: f64,
+    upper: f64,
+}
+
+impl Sprt {
+    /// Returns a test of the hypothesis that the difference in strength is `elo0` against the
+    /// hypothesis that it is `elo1`, with `alpha` the chance of wrongly accepting the alternative
+    /// and `beta` the chance of wrongly accepting the null hypothesis.
+    ///
+    /// # Errors
+    ///
+    /// If `elo0` is not less than `elo1` or either chance is not between 0 and 1.
+    pub fn new(elo0: f64, elo1: f64, alpha: f64, beta: f64) -> Result<Self, String> {
+        if elo0.is_nan() || elo1.is_nan() || elo0 >= elo1 {
+            return Err(format!("elo0 {elo0} is not less than elo1 {elo1}"));
+        }
+        for chance in [alpha, beta] {
+            if !(chance > 0.0 && chance < 1.0) {
+                return Err(format!("error rate {chance} is not between 0 and 1"));
+            }
+        }
+        Ok(Sprt {
+            elo0,
+            elo1,
+            lower: (beta / (1.0 - alpha)).ln(),
+            upper: ((1.0 - beta) / alpha).ln(),
+        })
+    }
+
+    /// Returns the log likelihood ratio of the alternative to the null hypothesis given the first
+    /// entrant's results.
+    #[must_use]
+    pub fn llr(&self, wins: usize, draws: usize, losses: usize) -> f64 {
+        let score = |elo: f64| 1.0 / (1.0 + 10_f64.powf(-elo / 400.0));
+        let (score0, score1) = (score(self.elo0), score(self.elo1));
+        let half_draws = count(draws) / 2.0;
+        (count(wins) + half_draws) * (score1 / score0).ln()
+            + (count(losses) + half_draws) * ((1.0 - score1) / (1.0 - score0)).ln()
+    }
+
+    /// Returns the hypothesis accepted given the first entrant's results, or None if the test
+    /// needs more games.
+    #[must_use]
+    pub fn test(&self, wins: usize, draws: usize, losses: usize) -> Option<Hypothesis> {
+        let llr = self.llr(wins, draws, losses);
+        if llr >= self.upper {
+            Some(Hypothesis::Alternative)
+        } else if llr <= self.lower {
+            Some(Hypothesis::Null)
+        } else {
+            None
+        }
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn count(games: usize) -> f64 {
+    games as f64
+}
+
+/// Returns a configuration for every combination of the board sizes, komis and handicaps, with the
+/// other settings of `base`. A handicap of 0 or 1 places no stones.
+///
+/// # Errors
+///
+/// Fails if a board size or a handicap is not valid.
+pub fn configurations(
+    base: &Game,
+    board_sizes: &[usize],
+    komis: &[f64],
+    handicaps: &[usize],
+) -> Result<Vec<Game>, String> {
+    let mut configurations = Vec::new();
+    for &board_size in board_sizes {
+        for &komi in komis {
+            for &handicap in handicaps {
+                let mut game = Game::builder()
+                    .board_size(board_size)
+                    .komi(komi)
+                    .rules(base.rule_set)
+                    .clock(base.time_settings())
+                    .handicap(if handicap >= 2 { handicap } else { 0 })
+                    .build()?;
+                game.ko_rule = base.ko_rule;
+                game.variant = base.variant;
+                configurations.push(game);
+            }
+        }
+    }
+    Ok(configurations)
+}
+
+/// Reads a pool of openings from SGF: every line of play from the start of a game tree to the end
+/// of one of its variations, cut to at most `moves` moves. Repeated openings are left out.
+///
+/// # Errors
+///
+/// Fails if the SGF can't be parsed or holds an invalid move.
+pub fn openings_from_sgf(text: &str, moves: usize) -> Result<Vec<Vec<Move>>, String> {
+    fn lines(
+        tree: &GameTree,
+        size: usize,
+        mut line: Vec<Move>,
+        openings: &mut Vec<Vec<Move>>,
+    ) -> Result<(), String> {
+        for node in &tree.nodes {
+            line.extend(node.mov(size)?);
+        }
+        if tree.variations.is_empty() {
+            openings.push(line);
+            return Ok(());
+        }
+        for variation in &tree.variations {
+            lines(variation, size, line.clone(), openings)?;
+        }
+        Ok(())
+    }
+
+    let mut openings = Vec::new();
+    for tree in sgf::parse(text)? {
+        lines(&tree, tree.board_size()?, Vec::new(), &mut openings)?;
+    }
+    let mut pool: Vec<Vec<Move>> = Vec::new();
+    for mut opening in openings {
+        opening.truncate(moves);
+        if !pool.contains(&opening) {
+            pool.push(opening);
+        }
+    }
+    Ok(pool)
+}
+
+/// A series of games between GTP engines, played one at a time. Each game is played with one of
+/// the tournament's configurations, a game with the board size, komi, handicap and rules to play
+/// with, so the settings can be swept across batches of games. Games may also start from an
+/// opening, so engines that always play the same moves don't play the same game every time.
+///
+/// Given a state directory, the tournament keeps its pairings, the result of each finished game,
+/// the SGF record of each game and a journal of the moves of the game in progress there. A
+/// tournament that stopped, whether it crashed or was stopped on purpose, can then be resumed
+/// without playing the finished games again, and the game that was in progress continues from its
+/// last move.
+#[derive(Debug)]
+pub struct Tournament {
+    entrants: Vec<String>,
+    pairings: Vec<Pairing>,
+    results: Vec<Option<GameResult>>,
+    configurations: Vec<Game>,
+    openings: Vec<Vec<Move>>,
+    /// Whether the pairings come in pairs of games with the colors swapped.
+    color_paired: bool,
+    /// A test that stops the tournament once it decides between the first two entrants.
+    sprt: Option<Sprt>,
+    /// Where to send the progress after each game.
+    progress: Option<Sender<Progress>>,
+    state_dir: Option<PathBuf>,
+}
+
+impl Tournament {
+    /// Returns a tournament playing the pairings between the entrants, each game on its own copy
+    /// of its configuration.
+    ///
+    /// # Panics
+    ///
+    /// If a pairing refers to an entrant or configuration that doesn't exist.
+    #[must_use]
+    pub fn new(entrants: Vec<String>, pairings: Vec<Pairing>, configurations: Vec<Game>) -> Self {
+        assert!(
+            pairings.iter().all(|pairing| pairing.black < entrants.len()
+                && pairing.white < entrants.len()
+                && pairing.configuration < configurations.len()),
+            "a pairing refers to an unknown entrant or configuration"
+        );
+        Tournament {
+            entrants,
+            results: vec![None; pairings.len()],
+            pairings,
+            configurations,
+            openings: Vec::new(),
+            color_paired: false,
+            sprt: None,
+            progress: None,
+            state_dir: None,
+        }
+    }
+
+    /// Returns a tournament where every entrant plays every other `rounds` times on `game`,
+    /// swapping colors each round.
+    #[must_use]
+    pub fn round_robin(entrants: Vec<String>, rounds: usize, game: Game) -> Self {
+        Self::sweep(entrants, rounds, vec![game])
+    }
+
+    /// Returns a tournament playing a round robin of `rounds` rounds with each configuration in
+    /// turn, e.g. to find the komi or handicap that makes games between unequal engines fair.
+    #[must_use]
+    pub fn sweep(entrants: Vec<String>, rounds: usize, configurations: Vec<Game>) -> Self {
+        let pairings = schedule(entrants.len(), rounds, configurations.len(), false);
+        Self::new(entrants, pairings, configurations)
+    }
+
+    /// Returns a tournament like `sweep`, but where every entrant plays every other twice in each
+    /// round, once with each color, with the same configuration and opening. The pairs of games
+    /// are reported by `paired_standings`, which cancels out the advantage of either color.
+    #[must_use]
+    pub fn color_paired(entrants: Vec<String>, rounds: usize, configurations: Vec<Game>) -> Self {
+        let pairings = schedule(entrants.len(), rounds, configurations.len(), true);
+        let mut tournament = Self::new(entrants, pairings, configurations);
+        tournament.color_paired = true;
+        tournament
+    }
+
+    /// Returns the names of the entrants.
+    #[must_use]
+    pub fn entrants(&self) -> &[String] {
+        &self.entrants
+    }
+
+    /// Returns the games to be played, in order.
+    #[must_use]
+    pub fn pairings(&self) -> &[Pairing] {
+        &self.pairings
+    }
+
+    /// Returns the configurations games are played with.
+    #[must_use]
+    pub fn configurations(&self) -> &[Game] {
+        &self.configurations
+    }
+
+    /// Starts each game from an opening drawn from the pool. The openings are drawn without
+    /// replacement until the pool is used up, in an order given by the seed, so the same seed gives
+    /// the same pairings when the tournament is set up again to be resumed. Call this before
+    /// `set_state_dir`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if an opening can't be played in one of the configurations.
+    pub fn set_openings(&mut self, openings: Vec<Vec<Move>>, seed: u64) -> Result<(), String> {
+        for (index, opening) in openings.iter().enumerate() {
+            for configuration in &self.configurations {
+                if let Err((number, err)) = configuration.validate_sequence(opening) {
+                    return Err(format!("opening {index}, move {}: {err}", number + 1));
+                }
+            }
+        }
+
+        // Both games of a color paired tournament's pairs start from the same opening.
+        let games_per_opening = if self.color_paired { 2 } else { 1 };
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut drawn = Vec::new();
+        for pairings in self.pairings.chunks_mut(games_per_opening) {
+            if drawn.is_empty() {
+                drawn = (0..openings.len()).collect();
+                drawn.shuffle(&mut rng);
+            }
+            let opening = drawn.pop();
+            for pairing in pairings {
+                pairing.opening = opening;
+            }
+        }
+        self.openings = openings;
+        Ok(())
+    }
+
+    /// Returns the pool of openings games start from.
+    #[must_use]
+    pub fn openings(&self) -> &[Vec<Move>] {
+        &self.openings
+    }
+
+    /// Returns the result of each pairing, or None for games that haven't finished.
+    #[must_use]
+    pub fn results(&self) -> &[Option<GameResult>] {
+        &self.results
+    }
+
+    /// Returns the standings of the finished games of each configuration.
+    #[must_use]
+    pub fn standings(&self) -> Vec<Standings> {
+        let mut standings = vec![
+            Standings {
+                wins: vec![0; self.entrants.len()],
+                ..Standings::default()
+            };
+            self.configurations.len()
+        ];
+        for (pairing, result) in self.pairings.iter().zip(&self.results) {
+            let Some(result) = result else {
+                continue;
+            };
+            let standings = &mut standings[pairing.configuration];
+            standings.games += 1;
+            match result.winner() {
+                Some(Player::Black) => {
+                    standings.black_wins += 1;
+                    standings.wins[pairing.black] += 1;
+                }
+                Some(Player::White) => {
+                    standings.white_wins += 1;
+                    standings.wins[pairing.white] += 1;
+                }
+                None => {}
+            }
+        }
+        standings
+    }
+
+    /// Stops running the tournament as soon as the test accepts a hypothesis about the games
+    /// between the first two entrants, e.g. a new version of an engine and the old one.
+    pub fn set_sprt(&mut self, sprt: Sprt) {
+        self.sprt = Some(sprt);
+    }
+
+    /// Returns the hypothesis the tournament's SPRT accepted from the finished games between the
+    /// first two entrants, or None if there is no test or it needs more games.
+    #[must_use]
+    pub fn sprt_result(&self) -> Option<Hypothesis> {
+        let sprt = self.sprt?;
+        let (mut wins, mut draws, mut losses) = (0, 0, 0);
+        for (pairing, result) in self.pairings.iter().zip(&self.results) {
+            let Some(result) = result else {
+                continue;
+            };
+            let first = match (pairing.black, pairing.white) {
+                (0, 1) => Player::Black,
+                (1, 0) => Player::White,
+                _ => continue,
+            };
+            match result.winner() {
+                Some(winner) if winner == first => wins += 1,
+                Some(_) => losses += 1,
+                None => draws += 1,
+            }
+        }
+        sprt.test(wins, draws, losses)
+    }
+
+    /// Sends the progress of the tournament to `progress` each time `run` finishes a game.
+    pub fn set_progress(&mut self, progress: Sender<Progress>) {
+        self.progress = Some(progress);
+    }
+
+    /// Returns the number of games each entrant won against each other, by the index of the
+    /// winner and then the loser.
+    #[must_use]
+    pub fn crosstable(&self) -> Vec<Vec<usize>> {
+        let mut crosstable = vec![vec![0; self.entrants.len()]; self.entrants.len()];
+        for (pairing, result) in self.pairings.iter().zip(&self.results) {
+            match result.and_then(|result| result.winner()) {
+                Some(Player::Black) => crosstable[pairing.black][pairing.white] += 1,
+                Some(Player::White) => crosstable[pairing.white][pairing.black] += 1,
+                None => {}
+            }
+        }
+        crosstable
+    }
+
+    /// Returns the standings of the pairs of games between each two entrants of a color paired
+    /// tournament, or nothing if the tournament isn't color paired.
+    #[must_use]
+    pub fn paired_standings(&self) -> Vec<PairedStandings> {
+        let mut standings: Vec<PairedStandings> = Vec::new();
+        if !self.color_paired {
+            return standings;
+        }
+        for (pairings, results) in self.pairings.chunks(2).zip(self.results.chunks(2)) {
+            let (first, second) = (pairings[0].black, pairings[0].white);
+            let found = standings
+                .iter()
+                .position(|s| s.first == first && s.second == second);
+            let index = found.unwrap_or_else(|| {
+                standings.push(PairedStandings {
+                    first,
+                    second,
+                    ..PairedStandings::default()
+                });
+                standings.len() - 1
+            });
+            let [Some(with_black), Some(with_white)] = results else {
+                continue;
+            };
+            let standings = &mut standings[index];
+            standings.pairs += 1;
+            match (with_black.winner(), with_white.winner()) {
+                (Some(Player::Black), Some(Player::White)) => standings.first_won_both += 1,
+                (Some(Player::White), Some(Player::Black)) => standings.second_won_both += 1,
+                (Some(first), Some(second)) if first == second => standings.split += 1,
+                _ => {}
+            }
+        }
+        standings
+    }
+
+    /// Keeps the state of the tournament in the directory, creating it if needed. If the
+    /// directory already holds the state of this tournament, the results of its finished games are
+    /// loaded.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the directory can't be read or written, or holds the state of a tournament with
+    /// other pairings.
+    pub fn set_state_dir<P: Into<PathBuf>>(&mut self, dir: P) -> io::Result<()> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut pairings = String::new();
+        for pairing in &self.pairings {
+            let opening = pairing
+                .opening
+                .map_or("-".to_owned(), |opening| opening.to_string());
+            let _ = writeln!(
+                pairings,
+                "{} {} {} {opening}",
+                pairing.black, pairing.white, pairing.configuration
+            );
+        }
+        match fs::read_to_string(dir.join(PAIRINGS_FILE)) {
+            Ok(saved) if saved == pairings => {}
+            Ok(_) => {
+                return Err(invalid_data(format!(
+                    "{} holds a tournament with other pairings",
+                    dir.display()
+                )))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                fs::write(dir.join(PAIRINGS_FILE), pairings)?;
+            }
+            Err(err) => return Err(err),
+        }
+
+        for line in read_lines(&dir.join(RESULTS_FILE))? {
+            let result = line.split_once(' ').and_then(|(number, result)| {
+                let number = number.parse::<usize>().ok()?;
+                let result = GameResult::from_str(result).ok()?;
+                Some((number, result))
+            });
+            match result {
+                Some((number, result)) if number < self.results.len() => {
+                    self.results[number] = Some(result);
+                }
+                _ => return Err(invalid_data(format!("invalid result line {line:?}"))),
+            }
+        }
+
+        self.state_dir = Some(dir);
+        Ok(())
+    }
+
+    /// Plays the games that haven't finished, in order. For each game `connect` is called with the
+    /// names of the entrants playing Black and then White to connect to their engines. A game that
+    /// ends without a result, e.g. because it was adjourned, is left unfinished. With an SPRT set,
+    /// the rest of the games are left unplayed once the test accepts a hypothesis.
+    ///
+    /// # Errors
+    ///
+    /// Fails if connecting to an engine fails or the state can't be read or written.
+    pub fn run<F>(&mut self, mut connect: F) -> io::Result<()>
+    where
+        F: FnMut(&str) -> io::Result<Connection>,
+    {
+        let started = Instant::now();
+        let mut played = 0;
+        for number in 0..self.pairings.len() {
+            if self.sprt_result().is_some() {
+                break;
+            }
+            if self.results[number].is_some() {
+                continue;
+            }
+            let pairing = self.pairings[number];
+            let black = connect(&self.entrants[pairing.black])?;
+            let white = connect(&self.entrants[pairing.white])?;
+
+            let mut game = self.configurations[pairing.configuration].clone();
+            if let Some(opening) = pairing.opening {
+                for mov in &self.openings[opening] {
+                    game.play(mov).map_err(invalid_data)?;
+                }
+            }
+            let journal = self
+                .state_dir
+                .as_ref()
+                .map(|dir| dir.join(format!("game-{number}.journal")));
+            if let Some(journal) = &journal {
+                replay_journal(&mut game, journal)?;
+            }
+            let mut game_match = Match::new(black, white, game);
+            if let Some(dir) = &self.state_dir {
+                game_match.set_record(dir.join(format!("game-{number}.sgf")));
+            }
+            let writer = journal.clone().map(|journal| {
+                let events = game_match.broadcast().subscribe();
+                thread::spawn(move || write_journal(&journal, events.iter()))
+            });
+
+            let outcome = game_match.play();
+            if let Some(writer) = writer {
+                writer
+                    .join()
+                    .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "panicked")))?;
+            }
+
+            let Some(result) = outcome.result() else {
+                continue;
+            };
+            self.results[number] = Some(result);
+            if let (Some(dir), Some(journal)) = (&self.state_dir, &journal) {
+                let mut results = open_lines_for_append(&dir.join(RESULTS_FILE))?;
+                results.write_all(format!("{number} {result}\n").as_bytes())?;
+                results.sync_data()?;
+                match fs::remove_file(journal) {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                    _ => {}
+                }
+            }
+
+            played += 1;
+            if let Some(progress) = &self.progress {
+                let finished = self.results.iter().flatten().count();
+                let left = self.pairings.len() - finished;
+                // Nobody may be listening any more.
+                let _ = progress.send(Progress {
+                    entrants: self.entrants.clone(),
+                    finished,
+                    total: self.pairings.len(),
+                    crosstable: self.crosstable(),
+                    eta: Some(started.elapsed().mul_f64(count(left) / count(played))),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the pairings of `rounds` round robins between the entrants with each configuration in
+/// turn. Colors are swapped each round, or within each round if `color_paired`.
+fn schedule(
+    entrants: usize,
+    rounds: usize,
+    configurations: usize,
+    color_paired: bool,
+) -> Vec<Pairing> {
+    let mut pairings = Vec::new();
+    for configuration in 0..configurations {
+        for round in 0..rounds {
+            for first in 0..entrants {
+                for second in first + 1..entrants {
+                    let mut games = vec![(first, second)];
+                    if color_paired {
+                        games.push((second, first));
+                    } else if round % 2 == 1 {
+                        games[0] = (second, first);
+                    }
+                    for (black, white) in games {
+                        pairings.push(Pairing {
+                            black,
+                            white,
+                            configuration,
+                            opening: None,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    pairings
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads the complete lines of a file, or none if it doesn't exist. A last line without a line
+/// break was cut short, e.g. by a crash, and is left out.
+fn read_lines(path: &Path) -> io::Result<Vec<String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(text
+            .split_inclusive('\n')
+            .filter_map(|line| line.strip_suffix('\n'))
+            .map(ToOwned::to_owned)
+            .collect()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Opens the file for appending lines, first cutting off a partial last line left by a crash while
+/// it was written, which readers skip, so the next line doesn't run on from it.
+fn open_lines_for_append(path: &Path) -> io::Result<File> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(path)?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let complete = bytes
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |end| end + 1);
+    if complete < bytes.len() {
+        file.set_len(complete as u64)?;
+    }
+    file.seek(SeekFrom::Start(complete as u64))?;
+    Ok(file)
+}
+
+/// Plays the moves of a journal, written as `black C3` or `white pass`, on the game.
+fn replay_journal(game: &mut Game, path: &Path) -> io::Result<()> {
+    for line in read_lines(path)? {
+        let mov = line.split_once(' ').and_then(|(player, vertex)| {
+            let player = match player {
+                "black" => Player::Black,
+                "white" => Player::White,
+                _ => return None,
+            };
+            let vertex = match vertex {
+                "pass" => None,
+                vertex => Some(Vertex::from_str(vertex).ok()?),
+            };
+            Some(Move { player, vertex })
+        });
+        let played = mov.map_or(Err("invalid move".to_owned()), |mov| game.play(&mov));
+        if let Err(err) = played {
+            return Err(invalid_data(format!("{}: {line:?}: {err}", path.display())));
+        }
+    }
+    Ok(())
+}
+
+/// Appends each move of a game to its journal as it is played, until the game ends.
+fn write_journal<I: Iterator<Item = Event>>(path: &Path, events: I) -> io::Result<()> {
+    let mut journal = open_lines_for_append(path)?;
+    for event in events {
+        match event {
+            Event::Move(mov, _) => {
+                let vertex = mov
+                    .vertex
+                    .map_or("pass".to_owned(), |vertex| vertex.to_string());
+                journal.write_all(format!("{} {vertex}\n", mov.player).as_bytes())?;
+            }
+            Event::Position(_) => {}
+            Event::End => break,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+    use std::net::TcpStream;
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::gtp::engine::Session;
+    use crate::gtp::net::{self, Limits};
+
+    /// Connects to an engine that always passes.
+    fn connect_passer() -> io::Result<Connection> {
+        connect_engine("pass")
+    }
+
+    /// Connects to an engine that always replies to genmove with `reply`, `pass` or `resign`.
+    fn connect_engine(reply: &'static str) -> io::Result<Connection> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+        thread::spawn(move || {
+            let stream = TcpStream::connect(address).unwrap();
+            let reader = BufReader::new(stream.try_clone().unwrap());
+            let mut session = Session::default();
+            session.engine.register_tournament_commands();
+            session.engine.insert("genmove", move |_args, game| {
+                if reply == "pass" {
+                    let player = game.player_turn();
+                    game.play(&Move {
+                        player,
+                        vertex: None,
+                    })?;
+                }
+                Ok(Some(reply.to_owned()))
+            });
+            // The match hangs up when it's over.
+            let _ = net::serve(reader, stream, &mut session, &Limits::default());
+        });
+        Connection::new(listener.accept()?.0)
+    }
+
+    fn tournament() -> Tournament {
+        let entrants = vec!["a".to_owned(), "b".to_owned()];
+        Tournament::round_robin(entrants, 2, Game::with_board_size(9).unwrap())
+    }
+
+    #[test]
+    fn progress() {
+        let (sender, receiver) = mpsc::channel();
+        let mut tournament = tournament();
+        tournament.set_progress(sender);
+        tournament.run(|_| connect_passer()).unwrap();
+        let progress: Vec<Progress> = receiver.try_iter().collect();
+        assert_eq!(progress.len(), 2);
+        assert_eq!((progress[0].finished, progress[0].total), (1, 2));
+        assert_eq!(progress[0].crosstable, [[0, 0], [1, 0]]);
+        assert_eq!(progress[1].crosstable, [[0, 1], [1, 0]]);
+        assert_eq!(progress[1].eta, Some(Duration::ZERO));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        sender.send(progress[1].clone()).unwrap();
+        thread::spawn(move || serve_status(&listener, &receiver));
+        // A client that never sends its request doesn't hold up the next.
+        let _idle = TcpStream::connect(address).unwrap();
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.0 200 OK"));
+        assert!(response.ends_with(
+            r#"{"entrants":["a","b"],"finished":2,"total":2,"crosstable":[[0,1],[1,0]],"eta":0.000}"#
+        ));
+    }
+
+    #[test]
+    fn resume() {
+        let dir = std::env::temp_dir().join(format!("libgo-tournament-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut first = tournament();
+        assert_eq!(
+            first.pairings(),
+            [
+                Pairing {
+                    black: 0,
+                    white: 1,
+                    configuration: 0,
+                    opening: None,
+                },
+                Pairing {
+                    black: 1,
+                    white: 0,
+                    configuration: 0,
+                    opening: None,
+                }
+            ]
+        );
+        first.set_state_dir(&dir).unwrap();
+        // The second game finished and the first was interrupted while a move was written.
+        fs::write(dir.join(RESULTS_FILE), "1 W+R\n").unwrap();
+        fs::write(dir.join("game-0.journal"), "black C3\nwhite D").unwrap();
+
+        let mut resumed = tournament();
+        resumed.set_state_dir(&dir).unwrap();
+        assert_eq!(
+            resumed.results(),
+            [None, Some(GameResult::Resignation(Player::White))]
+        );
+        let mut connections = 0;
+        resumed
+            .run(|_name| {
+                connections += 1;
+                connect_passer()
+            })
+            .unwrap();
+        assert_eq!(connections, 2);
+        assert!(resumed.results().iter().all(Option::is_some));
+        assert!(!dir.join("game-0.journal").exists());
+        let sgf = fs::read_to_string(dir.join("game-0.sgf")).unwrap();
+        assert!(sgf.contains(";B[cg]\n;W[]\n;B[]"), "{sgf}");
+
+        // A journal resumed after a crash mid-line goes on from the last complete move.
+        let journal = dir.join("game-0.journal");
+        fs::write(&journal, "black C3\nwhite D").unwrap();
+        let white = Move {
+            player: Player::White,
+            vertex: Some("D4".parse().unwrap()),
+        };
+        let board = Game::with_board_size(9).unwrap().board().clone();
+        write_journal(
+            &journal,
+            [Event::Move(white, board), Event::End].into_iter(),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(&journal).unwrap(),
+            "black C3\nwhite D4\n"
+        );
+        let mut game = Game::with_board_size(9).unwrap();
+        replay_journal(&mut game, &journal).unwrap();
+        assert_eq!(game.moves().count(), 2);
+        fs::remove_file(&journal).unwrap();
+
+        let mut finished = tournament();
+        finished.set_state_dir(&dir).unwrap();
+        finished
+            .run(|_name| panic!("a finished game was played again"))
+            .unwrap();
+        assert_eq!(finished.results(), resumed.results());
+
+        let entrants = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+        let mut other = Tournament::round_robin(entrants, 1, Game::new());
+        assert!(other.set_state_dir(&dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn openings() {
+        let sgf = "(;SZ[9];B[ee](;W[gc];B[cg])(;W[cc]))(;SZ[9];B[ee];W[gc])(;SZ[9];W[zz])";
+        assert!(openings_from_sgf(sgf, 2).is_err());
+        let sgf = sgf.trim_end_matches("(;SZ[9];W[zz])");
+        let pool = openings_from_sgf(sgf, 2).unwrap();
+        assert_eq!(pool.len(), 2);
+        assert_eq!(pool[1][1].vertex, Some(Vertex { x: 2, y: 6 }));
+
+        let entrants = vec!["a".to_owned(), "b".to_owned()];
+        let mut tournament =
+            Tournament::round_robin(entrants, 3, Game::with_board_size(9).unwrap());
+        assert!(tournament
+            .set_openings(vec![vec![pool[0][0], pool[0][0]]], 0)
+            .is_err());
+        tournament.set_openings(pool.clone(), 7).unwrap();
+        let openings: Vec<_> = tournament
+            .pairings()
+            .iter()
+            .map(|pairing| pairing.opening.unwrap())
+            .collect();
+        assert_ne!(openings[0], openings[1]);
+
+        tournament.run(|_name| connect_passer()).unwrap();
+        assert!(tournament.results().iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn color_paired() {
+        let entrants = vec![
+            "passer".to_owned(),
+            "resigner".to_owned(),
+            "other".to_owned(),
+        ];
+        let configurations = vec![Game::with_board_size(9).unwrap()];
+        let mut tournament = Tournament::color_paired(entrants, 1, configurations);
+        assert_eq!(tournament.pairings().len(), 6);
+        let pool = vec![
+            Vec::new(),
+            vec![Move {
+                player: Player::Black,
+                vertex: Some(Vertex { x: 4, y: 4 }),
+            }],
+        ];
+        tournament.set_openings(pool, 1).unwrap();
+        for pair in tournament.pairings().chunks(2) {
+            assert_eq!(pair[0].black, pair[1].white);
+            assert_eq!(pair[0].opening, pair[1].opening);
+        }
+
+        tournament
+            .run(|name| connect_engine(if name == "resigner" { "resign" } else { "pass" }))
+            .unwrap();
+        let standings = tournament.paired_standings();
+        assert_eq!(standings.len(), 3);
+        // The resigner loses both games, and White wins both games between the passers by komi.
+        assert_eq!((standings[0].pairs, standings[0].first_won_both), (1, 1));
+        assert_eq!(standings[1].split, 1);
+        assert_eq!(standings[2].second_won_both, 1);
+    }
+
+    #[test]
+    fn sprt() {
+        assert!(Sprt::new(5.0, 0.0, 0.05, 0.05).is_err());
+        assert!(Sprt::new(0.0, 5.0, 0.0, 0.05).is_err());
+        let sprt = Sprt::new(0.0, 50.0, 0.05, 0.05).unwrap();
+        assert!(sprt.llr(10, 0, 10) < 0.0);
+        assert!((sprt.llr(3, 2, 1) - sprt.llr(4, 0, 2)).abs() < 1e-9);
+        assert_eq!(sprt.test(5, 0, 5), None);
+        assert_eq!(sprt.test(40, 0, 0), Some(Hypothesis::Alternative));
+        assert_eq!(sprt.test(200, 0, 200), Some(Hypothesis::Null));
+
+        let entrants = vec!["passer".to_owned(), "resigner".to_owned()];
+        let configurations = vec![Game::with_board_size(9).unwrap()];
+        let mut tournament = Tournament::sweep(entrants, 100, configurations);
+        tournament.set_sprt(Sprt::new(0.0, 200.0, 0.05, 0.05).unwrap());
+        tournament
+            .run(|name| connect_engine(if name == "resigner" { "resign" } else { "pass" }))
+            .unwrap();
+        assert_eq!(tournament.sprt_result(), Some(Hypothesis::Alternative));
+        let played = tournament.results().iter().flatten().count();
+        assert!(played > 0 && played < 20, "{played} games played");
+    }
+
+    #[test]
+    fn sweep() {
+        let base = Game::with_board_size(9).unwrap();
+        let all = configurations(&base, &[9, 13], &[0.5, 7.5], &[0, 2, 3]).unwrap();
+        assert_eq!(all.len(), 12);
+        assert_eq!(all[11].board().size(), 13);
+        assert_eq!(all[11].handicap(), 3);
+        assert!(configurations(&base, &[9], &[0.5], &[100]).is_err());
+
+        let configurations = configurations(&base, &[9], &[0.5, 7.5], &[0, 2]).unwrap();
+        let entrants = vec!["a".to_owned(), "b".to_owned()];
+        let mut tournament = Tournament::sweep(entrants, 2, configurations);
+        tournament.run(|_name| connect_passer()).unwrap();
+        let standings = tournament.standings();
+        // Only Black's handicap stones are points on a board where both players pass.
+        let black_wins: Vec<_> = standings.iter().map(|s| s.black_wins).collect();
+        assert_eq!(black_wins, [0, 2, 0, 2]);
+        assert_eq!(standings[2].wins, [1, 1]);
+    }
+}
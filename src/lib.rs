@@ -20,3 +20,4 @@ extern crate rand;
 
 pub mod game;
 pub mod gtp;
+pub mod prelude;
@@ -0,0 +1,21 @@
+//! The types nearly every program using libgo needs, to import with a single `use`:
+//!
+//! ```
+//! use libgo::prelude::*;
+//!
+//! let mut engine = Engine::new();
+//! engine.register_all_commands();
+//! let mut game = Game::new();
+//! let command = Command::from_line("play black D4").unwrap();
+//! let response: Response = engine.exec(&mut game, &command);
+//! assert!(response.result.is_ok());
+//! assert_eq!(game.board().stone(Vertex { x: 3, y: 3 }), Some(Player::Black));
+//! ```
+
+pub use crate::game::board::{Board, Move};
+pub use crate::game::player::Player;
+pub use crate::game::vertex::Vertex;
+pub use crate::game::Game;
+pub use crate::gtp::command::{Command, Commands};
+pub use crate::gtp::engine::Engine;
+pub use crate::gtp::response::Response;